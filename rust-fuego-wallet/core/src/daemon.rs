@@ -1,10 +1,53 @@
+use crate::replay::{Recorder, ReplayProvider, Reply, Request};
+use fuego_sdk::alias::AliasTransport;
+use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+use fuego_sdk::{FailoverProvider, SdkError};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
+/// Client for one or more fuegod nodes. Requests go to the healthiest node
+/// as ranked by the SDK's [`FailoverProvider`]; on a connection failure or
+/// 5xx answer the next one is tried. Clones share the node health.
 #[derive(Clone)]
 pub struct DaemonClient {
-    pub base_url: String,
+    nodes: Arc<FailoverProvider<String>>,
     client: Client,
+    transport: Transport,
+}
+
+/// Where requests go: the nodes, the nodes with every exchange written to
+/// a fixture, or a fixture alone.
+#[derive(Clone)]
+enum Transport {
+    Live,
+    Record(Arc<Recorder>),
+    Replay(Arc<ReplayProvider>),
+}
+
+/// One attempt at `request` on `node`. Unreachable nodes and 5xx answers
+/// are network failures, so the failover moves on.
+async fn call_node(client: &Client, node: &str, path: &str, request: &Request<'_>) -> fuego_sdk::Result<Reply> {
+    let url = format!("{}{}", node, path);
+    let builder = match request {
+        Request::Get => client.get(url),
+        Request::Json(body) => client.post(url).json(body),
+        Request::Bin(body) => client
+            .post(url)
+            .header("Content-Type", "application/octet-stream")
+            .body(body.to_vec()),
+    };
+    let failed = |e: String| {
+        log::warn!("fuegod node {} failed: {}", node, e);
+        SdkError::Network(e)
+    };
+    let resp = builder.send().await.map_err(|e| failed(format!("HTTP: {}", e)))?;
+    if resp.status().is_server_error() {
+        return Err(failed(format!("HTTP {} from {}{}", resp.status(), node, path)));
+    }
+    let status = resp.status().as_u16();
+    let body = resp.bytes().await.map_err(|e| failed(format!("body: {}", e)))?;
+    Ok(Reply { status, body: body.to_vec() })
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,22 +88,120 @@ struct JsonRpcError {
 
 impl DaemonClient {
     pub fn new(base_url: &str) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(60))
-            .build()
-            .expect("build reqwest client");
+        Self::with_nodes(&[base_url])
+    }
+
+    /// Client over several nodes, the first one active initially.
+    pub fn with_nodes<S: AsRef<str>>(base_urls: &[S]) -> Self {
+        let client = Self::default_http().client(DEST_DAEMON).expect("build reqwest client");
         Self {
-            base_url: base_url.trim_end_matches('/').to_string(),
+            nodes: Arc::new(FailoverProvider::new(
+                base_urls
+                    .iter()
+                    .map(|url| url.as_ref().trim_end_matches('/').to_string())
+                    .collect(),
+            )),
             client,
+            transport: Transport::Live,
+        }
+    }
+
+    /// Default settings: direct connections, 60 s per request.
+    pub fn default_http() -> HttpConfig {
+        HttpConfig::default().with_timeout(std::time::Duration::from_secs(60))
+    }
+
+    /// Same nodes, reached through `http` (proxy, TLS, auth, timeouts).
+    pub fn with_http(self, http: &HttpConfig) -> Result<Self, String> {
+        let client = http.client(DEST_DAEMON).map_err(|e| e.to_string())?;
+        Ok(Self { client, ..self })
+    }
+
+    /// Client answered from recorded exchanges only; no node is contacted.
+    pub fn replay(provider: Arc<ReplayProvider>) -> Self {
+        Self {
+            transport: Transport::Replay(provider),
+            ..Self::new("replay:")
+        }
+    }
+
+    /// Keep talking to the same nodes, recording every exchange.
+    pub fn recording(self, recorder: Arc<Recorder>) -> Self {
+        Self { transport: Transport::Record(recorder), ..self }
+    }
+
+    /// URL of the node requests currently go to.
+    pub fn base_url(&self) -> &str {
+        self.nodes.preferred().map(String::as_str).unwrap_or_default()
+    }
+
+    async fn send(&self, path: &str, request: Request<'_>) -> Result<Reply, String> {
+        self.exchange(path, &request, self.send_live(path, &request)).await
+    }
+
+    /// Answer `request` from the fixture when replaying; otherwise await
+    /// `live`, recording the exchange if asked to.
+    async fn exchange(
+        &self,
+        path: &str,
+        request: &Request<'_>,
+        live: impl std::future::Future<Output = Result<Reply, String>>,
+    ) -> Result<Reply, String> {
+        if let Transport::Replay(replay) = &self.transport {
+            return replay.reply(path, request);
+        }
+        let reply = live.await?;
+        if let Transport::Record(recorder) = &self.transport {
+            recorder.record(path, request, &reply)?;
+        }
+        Ok(reply)
+    }
+
+    /// Send `request` to `path`, healthiest node first, moving on while
+    /// nodes are unreachable or failing.
+    async fn send_live(&self, path: &str, request: &Request<'_>) -> Result<Reply, String> {
+        self.nodes
+            .first_ok(|node| async move { call_node(&self.client, &node, path, request).await })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// `/getinfo` from every node at once. Each node's height goes into the
+    /// failover stats, so nodes that fall behind rank last; the answer of
+    /// the highest node is returned.
+    async fn poll_info(&self) -> Result<Reply, String> {
+        let client = self.client.clone();
+        let polled = self
+            .nodes
+            .poll_heights(
+                |node| {
+                    let client = client.clone();
+                    async move {
+                        let reply = call_node(&client, &node, "/getinfo", &Request::Get).await?;
+                        let info: DaemonInfo = serde_json::from_slice(&reply.body)
+                            .map_err(|e| SdkError::Serialization(format!("getinfo from {}: {}", node, e)))?;
+                        Ok((info.height, reply))
+                    }
+                },
+                |(height, _)| *height,
+            )
+            .await;
+        let mut best: Option<(u64, Reply)> = None;
+        let mut last_error = "no fuegod nodes configured".to_string();
+        for (_, result) in polled {
+            match result {
+                Ok((height, reply)) if best.as_ref().is_none_or(|(h, _)| height > *h) => {
+                    best = Some((height, reply))
+                }
+                Ok(_) => {}
+                Err(e) => last_error = e.to_string(),
+            }
         }
+        best.map(|(_, reply)| reply).ok_or(last_error)
     }
 
     pub async fn get_info(&self) -> Result<DaemonInfo, String> {
-        let url = format!("{}/getinfo", self.base_url);
-        let resp = self.client.get(&url).send().await
-            .map_err(|e| format!("HTTP: {}", e))?;
-        resp.json::<DaemonInfo>().await
-            .map_err(|e| format!("JSON: {}", e))
+        self.exchange("/getinfo", &Request::Get, self.poll_info()).await?.json::<DaemonInfo>()
     }
 
     pub async fn get_height(&self) -> Result<u64, String> {
@@ -74,28 +215,19 @@ impl DaemonClient {
     }
 
     pub async fn send_raw_tx(&self, tx_hex: &str) -> Result<String, String> {
-        let url = format!("{}/sendrawtransaction", self.base_url);
-        let resp = self.client.post(&url)
-            .json(&serde_json::json!({"tx_as_hex": tx_hex}))
-            .send().await.map_err(|e| format!("HTTP: {}", e))?;
-        let val: serde_json::Value = resp.json().await
-            .map_err(|e| format!("JSON: {}", e))?;
+        let body = serde_json::json!({"tx_as_hex": tx_hex});
+        let val: serde_json::Value = self.send("/sendrawtransaction", Request::Json(&body)).await?.json()?;
         val["status"].as_str().map(|s| s.to_string())
             .ok_or("missing status".into())
     }
 
     /// Binary POST helper for the .bin endpoints.
     async fn post_bin(&self, path: &str, body: Vec<u8>) -> Result<Vec<u8>, String> {
-        let url = format!("{}{}", self.base_url, path);
-        let resp = self.client.post(&url)
-            .header("Content-Type", "application/octet-stream")
-            .body(body)
-            .send().await
-            .map_err(|e| format!("HTTP: {}", e))?;
-        if !resp.status().is_success() {
-            return Err(format!("HTTP {} from {}", resp.status(), path));
+        let reply = self.send(path, Request::Bin(&body)).await?;
+        if !(200..300).contains(&reply.status) {
+            return Err(format!("HTTP {} from {}", reply.status, path));
         }
-        resp.bytes().await.map(|b| b.to_vec()).map_err(|e| format!("body: {}", e))
+        Ok(reply.body)
     }
 
     /// /queryblockslite.bin — incremental block + tx-prefix sync.
@@ -110,6 +242,19 @@ impl DaemonClient {
         parse_query_blocks_lite_response(&resp).map_err(|e| e.to_string())
     }
 
+    /// /get_pool_changes_lite.bin — pool transactions added since
+    /// `known_tx_ids`, and which of those left the pool.
+    pub async fn get_pool_changes_lite(
+        &self,
+        tail_block_id: &[u8; 32],
+        known_tx_ids: &[[u8; 32]],
+    ) -> Result<fuego_sdk::serialization::PoolChangesLiteResponse, String> {
+        use fuego_sdk::serialization::{parse_pool_changes_lite_response, pool_changes_lite_request};
+        let body = pool_changes_lite_request(tail_block_id, known_tx_ids);
+        let resp = self.post_bin("/get_pool_changes_lite.bin", body).await?;
+        parse_pool_changes_lite_response(&resp).map_err(|e| e.to_string())
+    }
+
     /// /getrandom_outs.bin — decoy outputs for the given amounts.
     pub async fn get_random_outs(
         &self,
@@ -232,18 +377,14 @@ impl DaemonClient {
     async fn json_rpc<T: serde::de::DeserializeOwned>(
         &self, method: &str, params: serde_json::Value,
     ) -> Result<T, String> {
-        let url = format!("{}/json_rpc", self.base_url);
         let req = JsonRpcRequest {
             jsonrpc: "2.0".into(),
             id: "1".into(),
             method: method.into(),
             params,
         };
-        let resp: JsonRpcResponse<T> = self.client.post(&url)
-            .json(&req).send().await
-            .map_err(|e| format!("HTTP: {}", e))?
-            .json().await
-            .map_err(|e| format!("JSON: {}", e))?;
+        let req = serde_json::to_value(&req).map_err(|e| format!("JSON: {}", e))?;
+        let resp: JsonRpcResponse<T> = self.send("/json_rpc", Request::Json(&req)).await?.json()?;
         if let Some(err) = resp.error {
             Err(format!("RPC: {}", err.message))
         } else {
@@ -251,3 +392,16 @@ impl DaemonClient {
         }
     }
 }
+
+/// Alias lookups take the same path as every other daemon call, so they
+/// fail over, and are recorded and replayed, with the rest.
+#[async_trait::async_trait]
+impl AliasTransport for DaemonClient {
+    async fn post(&self, path: &str, body: serde_json::Value) -> fuego_sdk::Result<serde_json::Value> {
+        let reply = self.send(path, Request::Json(&body)).await.map_err(SdkError::Network)?;
+        if !(200..300).contains(&reply.status) {
+            return Err(SdkError::Network(format!("RPC {} returned {}", path, reply.status)));
+        }
+        reply.json().map_err(SdkError::Serialization)
+    }
+}
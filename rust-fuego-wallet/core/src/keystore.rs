@@ -1,3 +1,4 @@
+use fuego_sdk::store::StateKey;
 use chacha20poly1305::{
     aead::{Aead, KeyInit},
     ChaCha20Poly1305,
@@ -107,6 +108,12 @@ impl Keystore {
             .map_err(|e| format!("decrypt: {}", e))
     }
 
+    /// Key for the encrypted wallet state database: the keystore key,
+    /// created on first use.
+    pub fn state_key(&self) -> Result<StateKey, String> {
+        self.ensure_key().map(StateKey::Key)
+    }
+
     pub fn save(&self, secrets: &WalletSecrets) -> Result<(), String> {
         let plaintext = serde_json::to_vec(secrets).map_err(|e| format!("json: {}", e))?;
         let key = self.ensure_key()?;
@@ -6,17 +6,24 @@ mod daemon;
 mod fuegod;
 mod keystore;
 mod release;
+mod replay;
 mod scanner;
 mod server;
 mod swapd;
+#[cfg(test)]
+mod test_daemon;
 mod wallet_service;
+mod wallet_store;
 mod walletd;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use crate::wallet_service::WalletService;
+use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+use fuego_sdk::store::StateKey;
+use fuego_sdk::wallet::Wallet;
 
 fn default_wallet_dir() -> PathBuf {
     directories::ProjectDirs::from("org", "usexfg", "fuego-wallet")
@@ -42,29 +49,96 @@ port: u16,
 
 #[derive(Subcommand)]
 enum Commands {
-    Serve {
-        #[arg(long, default_value = "207.244.247.64")]
-        daemon_host: String,
+    Serve(Box<ServeArgs>),
+    Status,
+}
 
-        #[arg(long, default_value_t = 18180)]
-        daemon_port: u16,
+#[derive(Args)]
+struct ServeArgs {
+    #[arg(long, default_value = "207.244.247.64")]
+    daemon_host: String,
 
-        #[arg(long)]
-        testnet: bool,
+    #[arg(long, default_value_t = 18180)]
+    daemon_port: u16,
 
-        #[arg(long)]
-        local: bool,
+    /// fuegod node to use, repeatable; later nodes are fallbacks for
+    /// earlier ones. Replaces --daemon-host/--daemon-port (with --local,
+    /// the embedded daemon stays first).
+    #[arg(long = "node", value_name = "HOST:PORT")]
+    nodes: Vec<String>,
 
-        /// Launch xfg-swapd alongside fuegod (uses <wallet_dir>/swap_config.json
-        /// unless overridden).
-        #[arg(long)]
-        swapd_config: Option<PathBuf>,
+    #[arg(long)]
+    testnet: bool,
 
-        /// Skip the xfg-swapd auto-launch even if a config is found.
-        #[arg(long)]
-        no_swapd: bool,
-    },
-    Status,
+    #[arg(long)]
+    local: bool,
+
+    /// Launch xfg-swapd alongside fuegod (uses <wallet_dir>/swap_config.json
+    /// unless overridden).
+    #[arg(long)]
+    swapd_config: Option<PathBuf>,
+
+    /// Skip the xfg-swapd auto-launch even if a config is found.
+    #[arg(long)]
+    no_swapd: bool,
+
+    /// Watch this address without its spend key: balance and history
+    /// only, every spend method fails. Requires --view-key.
+    #[arg(long, value_name = "ADDRESS", requires = "view_key")]
+    view_only: Option<String>,
+
+    /// Private view key (hex) of the --view-only address.
+    #[arg(long, value_name = "HEX", requires = "view_only")]
+    view_key: Option<String>,
+
+    /// Record every fuegod request and response to this fixture file,
+    /// for replaying sync and send paths in tests.
+    #[arg(long, value_name = "FILE")]
+    record_daemon: Option<PathBuf>,
+
+    /// Reach fuegod through this proxy, e.g. socks5h://127.0.0.1:9050.
+    /// Loopback addresses always connect directly.
+    #[arg(long, value_name = "URL", conflicts_with = "tor")]
+    proxy: Option<String>,
+
+    /// Reach fuegod through the local Tor daemon (127.0.0.1:9050) on a
+    /// circuit of its own.
+    #[arg(long)]
+    tor: bool,
+
+    /// Per-request timeout for fuegod calls, in seconds.
+    #[arg(long, value_name = "SECS", default_value_t = 60)]
+    http_timeout: u64,
+
+    /// Extra trusted CA certificate (PEM) for fuegod's TLS, repeatable.
+    #[arg(long = "ca-cert", value_name = "FILE")]
+    ca_certs: Vec<PathBuf>,
+
+    /// Talk to fuegod over HTTPS only. The embedded daemon (--local)
+    /// serves plain HTTP.
+    #[arg(long, conflicts_with = "local")]
+    https: bool,
+
+    /// HTTP basic auth credentials for fuegod.
+    #[arg(long, value_name = "USER:PASS")]
+    daemon_login: Option<String>,
+
+    /// Seconds between sync polls once the wallet is caught up.
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    sync_interval: u64,
+
+    /// Seconds between transaction pool polls for unconfirmed payments.
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    pool_interval: u64,
+
+    /// Encrypt the wallet state database with the keystore key (OS
+    /// keyring, or a key file next to the keystore).
+    #[arg(long)]
+    encrypt_state: bool,
+
+    /// Encrypt the wallet state database with the passphrase in FILE.
+    #[arg(long, value_name = "FILE", conflicts_with = "encrypt_state")]
+    state_passphrase_file: Option<PathBuf>,
 }
 
 fn load_or_create_seed(wallet_dir: &PathBuf) -> Result<[u8; 32], Box<dyn std::error::Error>> {
@@ -85,6 +159,67 @@ fn load_or_create_seed(wallet_dir: &PathBuf) -> Result<[u8; 32], Box<dyn std::er
     Ok(seed)
 }
 
+/// Passphrase for the state database from its file, without the trailing
+/// newline editors add.
+fn read_state_passphrase(path: &PathBuf) -> Result<StateKey, String> {
+    let mut passphrase = std::fs::read(path)
+        .map_err(|e| format!("state passphrase file {}: {}", path.display(), e))?;
+    while matches!(passphrase.last(), Some(b'\n' | b'\r')) {
+        passphrase.pop();
+    }
+    if passphrase.is_empty() {
+        return Err(format!("state passphrase file {} is empty", path.display()));
+    }
+    Ok(StateKey::Passphrase(passphrase))
+}
+
+/// HTTP settings for fuegod traffic from the `serve` flags.
+fn daemon_http_config(
+    proxy: Option<String>,
+    tor: bool,
+    timeout_secs: u64,
+    ca_certs: &[PathBuf],
+    https: bool,
+    login: Option<&str>,
+) -> Result<HttpConfig, String> {
+    let mut http = if tor { HttpConfig::tor() } else { HttpConfig::default() };
+    if let Some(proxy) = proxy {
+        http = http.with_proxy(proxy);
+    }
+    http = http
+        .with_timeout(std::time::Duration::from_secs(timeout_secs))
+        .https_only(https);
+    for path in ca_certs {
+        let pem = std::fs::read(path).map_err(|e| format!("CA certificate {}: {}", path.display(), e))?;
+        http = http.with_ca_certificate(pem);
+    }
+    if let Some(login) = login {
+        let (user, password) = login
+            .split_once(':')
+            .ok_or_else(|| "--daemon-login must be USER:PASS".to_string())?;
+        http = http.with_basic_auth(user, password);
+    }
+    Ok(http)
+}
+
+/// Decode a watch-only address and its private view key, checking that the
+/// key belongs to the address. Returns (spend public key, view secret).
+fn parse_view_only(address: &str, view_key: &str) -> Result<([u8; 32], [u8; 32]), String> {
+    let (spend_public, view_public) =
+        fuego_crypto::parse_address(address).ok_or_else(|| format!("invalid address: {}", address))?;
+    let bytes = hex::decode(view_key.trim_start_matches("0x"))
+        .map_err(|e| format!("invalid view key hex: {}", e))?;
+    let view_secret: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "view key must be 32 bytes".to_string())?;
+    if !fuego_crypto::ref10::sc_check(&view_secret)
+        || fuego_crypto::ring::secret_key_to_public_key(&view_secret) != view_public
+    {
+        return Err(format!("view key does not match address {}", address));
+    }
+    Ok((spend_public, view_secret))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -94,8 +229,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::create_dir_all(&wallet_dir)?;
 
     match cli.command.unwrap_or(Commands::Status) {
-        Commands::Serve { daemon_host, daemon_port, testnet, local, swapd_config, no_swapd } => {
-            let (actual_host, actual_port, _daemon_guard) = if local {
+        Commands::Serve(args) => {
+            let ServeArgs {
+                daemon_host,
+                daemon_port,
+                nodes,
+                testnet,
+                local,
+                swapd_config,
+                no_swapd,
+                view_only,
+                view_key,
+                record_daemon,
+                proxy,
+                tor,
+                http_timeout,
+                ca_certs,
+                https,
+                daemon_login,
+                sync_interval,
+                pool_interval,
+                encrypt_state,
+                state_passphrase_file,
+            } = *args;
+            let http = daemon_http_config(proxy, tor, http_timeout, &ca_certs, https, daemon_login.as_deref())?;
+            let scheme = http.scheme();
+            // The embedded daemon only serves plain HTTP on loopback.
+            let (actual_host, actual_port, daemon_scheme, _daemon_guard) = if local {
                 log::info!("--local: starting embedded fuegod...");
                 let data_dir = wallet_dir.join("fuegod");
                 let mut daemon = fuegod::DaemonProcess::new(daemon_port);
@@ -103,30 +263,87 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .await
                     .map_err(|e| format!("Failed to start fuegod: {}", e))?;
                 log::info!("Embedded fuegod ready at {}", url);
-                ("127.0.0.1".to_string(), daemon_port, Some(daemon))
+                ("127.0.0.1".to_string(), daemon_port, "http", Some(daemon))
+            } else if let Some(node) = nodes.first() {
+                let (node_scheme, address) = node.split_once("://").unwrap_or((scheme, node));
+                let (host, port) = address
+                    .rsplit_once(':')
+                    .ok_or_else(|| format!("node {} has no port", node))?;
+                let port = port.parse().map_err(|_| format!("invalid node port: {}", port))?;
+                (host.to_string(), port, node_scheme, None)
             } else {
-                (daemon_host.clone(), daemon_port, None)
+                (daemon_host.clone(), daemon_port, scheme, None)
             };
 
-            let daemon_url = format!("http://{}:{}", actual_host, actual_port);
+            let daemon_url = format!("{}://{}:{}", daemon_scheme, actual_host, actual_port);
+            let mut daemon_urls = vec![daemon_url.clone()];
+            for node in &nodes {
+                let url = if node.contains("://") {
+                    node.clone()
+                } else {
+                    format!("{}://{}", scheme, node)
+                };
+                if !daemon_urls.contains(&url) {
+                    daemon_urls.push(url);
+                }
+            }
+            if daemon_urls.len() > 1 {
+                log::info!("fuegod nodes: {}", daemon_urls.join(", "));
+            }
 
             // 2. Initialize SDK wallet
-            let seed = match &cli.seed {
-                Some(s) => {
-                    let bytes = hex::decode(s.trim_start_matches("0x"))
-                        .map_err(|e| format!("invalid seed hex: {}", e))?;
-                    if bytes.len() != 32 {
-                        return Err(format!("seed must be 32 bytes").into());
+            let state_key = match &state_passphrase_file {
+                Some(path) => Some(read_state_passphrase(path)?),
+                None if encrypt_state => {
+                    Some(keystore::Keystore::new(wallet_dir.join("wallet.keystore")).state_key()?)
+                }
+                None => None,
+            };
+            let wallet_service = match (&view_only, &view_key) {
+                (Some(address), Some(view_key)) => {
+                    if cli.seed.is_some() {
+                        return Err("--seed cannot be combined with --view-only".into());
                     }
-                    let mut seed = [0u8; 32];
-                    seed.copy_from_slice(&bytes);
-                    seed
+                    let (spend_public, view_secret) = parse_view_only(address, view_key)?;
+                    // Watch wallets keep their own scan state so they never
+                    // mix with the seeded wallet's.
+                    let state_dir = wallet_dir.join("watch").join(address);
+                    std::fs::create_dir_all(&state_dir)?;
+                    log::info!("View-only mode: spending is disabled");
+                    Wallet::view_only(spend_public, view_secret)
+                        .and_then(|wallet| {
+                            WalletService::with_wallet(wallet, &daemon_url, state_dir, testnet, state_key.as_ref())
+                        })
+                        .map_err(|e| format!("Failed to initialize view-only wallet: {}", e))?
+                }
+                _ => {
+                    let seed = match &cli.seed {
+                        Some(s) => {
+                            let bytes = hex::decode(s.trim_start_matches("0x"))
+                                .map_err(|e| format!("invalid seed hex: {}", e))?;
+                            if bytes.len() != 32 {
+                                return Err("seed must be 32 bytes".to_string().into());
+                            }
+                            let mut seed = [0u8; 32];
+                            seed.copy_from_slice(&bytes);
+                            seed
+                        }
+                        None => load_or_create_seed(&wallet_dir)?,
+                    };
+                    Wallet::from_seed(seed)
+                        .and_then(|wallet| {
+                            WalletService::with_wallet(wallet, &daemon_url, wallet_dir.clone(), testnet, state_key.as_ref())
+                        })
+                        .map_err(|e| format!("Failed to initialize SDK wallet: {}", e))?
                 }
-                None => load_or_create_seed(&wallet_dir)?,
             };
-
-            let wallet_service = WalletService::new(seed, &daemon_url, wallet_dir.clone(), testnet)
-                .map_err(|e| format!("Failed to initialize SDK wallet: {}", e))?;
+            let mut wallet_service = wallet_service.with_daemon_nodes(&daemon_urls).with_http(&http)?;
+            if let Some(path) = &record_daemon {
+                log::info!("Recording fuegod traffic to {}", path.display());
+                let recorder = Arc::new(replay::Recorder::create(path)?);
+                let daemon = wallet_service.daemon.clone().recording(recorder);
+                wallet_service = wallet_service.with_daemon(daemon);
+            }
             let wallet_addr = wallet_service.address().await;
             let wallet = Arc::new(Mutex::new(wallet_service));
 
@@ -165,20 +382,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
-            // 3. Start background sync (detached engine: never blocks the
+            // 3. Start background sync and pool watch (detached engine: never blocks the
             // JSON-RPC handlers)
+            log::info!("Starting background wallet sync...");
             {
-                let service = wallet.clone();
-                tokio::spawn(async move {
-                    log::info!("Starting background wallet sync...");
-                    let engine = service.lock().await.sync_engine();
-                    engine.sync_loop().await;
-                });
+                let mut wallet = wallet.lock().await;
+                wallet.start_sync(std::time::Duration::from_secs(sync_interval.max(1)));
+                wallet.start_pool_watch(std::time::Duration::from_secs(pool_interval.max(1)));
             }
 
             // 4. Start Axum server
             let bind = format!("{}:{}", cli.host, cli.port);
-            server::run_server(wallet, &daemon_url, &bind).await?;
+            let daemon_client = http.client(DEST_DAEMON).map_err(|e| e.to_string())?;
+            server::run_server(wallet, &daemon_url, &bind, daemon_client).await?;
         }
 
         Commands::Status => {
@@ -191,3 +407,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn https_conflicts_with_local() {
+        assert!(Cli::try_parse_from(["fuego-wallet", "serve", "--local", "--https"]).is_err());
+        assert!(Cli::try_parse_from(["fuego-wallet", "serve", "--https"]).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+//! Record and replay fuegod traffic. A [`Recorder`] attached to a
+//! `DaemonClient` appends every request/response pair to a fixture file;
+//! a [`ReplayProvider`] answers a `DaemonClient` from such a fixture, so
+//! sync and send paths run without a live daemon.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const FIXTURE_VERSION: u32 = 1;
+
+/// A request as `DaemonClient` hands it to the transport.
+pub enum Request<'a> {
+    Get,
+    Json(&'a serde_json::Value),
+    Bin(&'a [u8]),
+}
+
+/// An HTTP answer: status code and raw body.
+pub struct Reply {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("JSON: {}", e))
+    }
+}
+
+/// Request or response body as stored in a fixture. Binary bodies (the
+/// .bin endpoints) are kept as hex.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Payload {
+    Empty,
+    Json(serde_json::Value),
+    Binary(String),
+}
+
+/// One recorded request/response pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub path: String,
+    /// JSON-RPC method of a /json_rpc call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// JSON-RPC params, the JSON body, or the binary body.
+    pub request: Payload,
+    pub status: u16,
+    pub response: Payload,
+}
+
+impl Exchange {
+    /// Matching key of a request. JSON-RPC calls are keyed by method and
+    /// params, so the envelope's `id` never causes a mismatch.
+    fn key(path: &str, request: &Request) -> (Option<String>, Payload) {
+        match request {
+            Request::Get => (None, Payload::Empty),
+            Request::Bin(body) => (None, Payload::Binary(hex::encode(body))),
+            Request::Json(body) if path == "/json_rpc" => (
+                body.get("method").and_then(|m| m.as_str()).map(str::to_string),
+                Payload::Json(body.get("params").cloned().unwrap_or_default()),
+            ),
+            Request::Json(body) => (None, Payload::Json((*body).clone())),
+        }
+    }
+
+    fn new(path: &str, request: &Request, reply: &Reply) -> Self {
+        let (method, request_payload) = Self::key(path, request);
+        let json = match request {
+            Request::Bin(_) => None,
+            _ => serde_json::from_slice(&reply.body).ok(),
+        };
+        let response = match json {
+            Some(value) => Payload::Json(value),
+            None if reply.body.is_empty() => Payload::Empty,
+            None => Payload::Binary(hex::encode(&reply.body)),
+        };
+        Self { path: path.to_string(), method, request: request_payload, status: reply.status, response }
+    }
+
+    fn reply(&self) -> Result<Reply, String> {
+        let body = match &self.response {
+            Payload::Empty => Vec::new(),
+            Payload::Json(value) => serde_json::to_vec(value).map_err(|e| format!("fixture: {}", e))?,
+            Payload::Binary(data) => hex::decode(data).map_err(|e| format!("fixture: bad hex: {}", e))?,
+        };
+        Ok(Reply { status: self.status, body })
+    }
+
+    fn describe(&self) -> String {
+        match &self.method {
+            Some(method) => format!("{} {}", self.path, method),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// First line of a fixture file.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// The exchanges in the order they happened. On disk: JSON lines, a
+/// header with the format version followed by one exchange per line.
+#[derive(Debug, Clone, Default)]
+pub struct Fixture {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let fail = |e: &dyn std::fmt::Display| format!("fixture {}: {}", path.display(), e);
+        let data = std::fs::read_to_string(path).map_err(|e| fail(&e))?;
+        let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+        let header: Header = serde_json::from_str(lines.next().unwrap_or_default()).map_err(|e| fail(&e))?;
+        if header.version != FIXTURE_VERSION {
+            return Err(fail(&format!("unsupported version {}", header.version)));
+        }
+        let exchanges = lines
+            .map(|line| serde_json::from_str(line).map_err(|e| fail(&e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { exchanges })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let recorder = Recorder::create(path)?;
+        for exchange in &self.exchanges {
+            recorder.append(exchange.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// Appends every exchange to a fixture file as it happens, so a crash
+/// loses at most the exchange being written.
+pub struct Recorder {
+    file: Mutex<File>,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl Recorder {
+    /// Start a fixture at `path`, replacing any file there.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let mut file = File::create(path).map_err(|e| format!("fixture {}: {}", path.display(), e))?;
+        let header = serde_json::to_string(&Header { version: FIXTURE_VERSION }).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", header).map_err(|e| format!("fixture {}: {}", path.display(), e))?;
+        Ok(Self { file: Mutex::new(file), exchanges: Mutex::new(Vec::new()) })
+    }
+
+    pub fn record(&self, path: &str, request: &Request<'_>, reply: &Reply) -> Result<(), String> {
+        self.append(Exchange::new(path, request, reply))
+    }
+
+    fn append(&self, exchange: Exchange) -> Result<(), String> {
+        let line = serde_json::to_string(&exchange).map_err(|e| format!("fixture: {}", e))?;
+        let mut exchanges = self.exchanges.lock().unwrap();
+        writeln!(self.file.lock().unwrap(), "{}", line).map_err(|e| format!("fixture: {}", e))?;
+        exchanges.push(exchange);
+        Ok(())
+    }
+
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+}
+
+/// Serves recorded exchanges back. A request takes the first unused
+/// exchange with the same path, method and params; once those run out the
+/// last one is repeated. Outside strict mode a request whose params were
+/// never recorded falls back to the same path and method (sent
+/// transactions carry fresh randomness on every run). Strict mode fails
+/// on anything not recorded, and on calls beyond the recorded count.
+pub struct ReplayProvider {
+    exchanges: Vec<Exchange>,
+    used: Mutex<Vec<bool>>,
+    strict: bool,
+}
+
+impl ReplayProvider {
+    pub fn new(fixture: Fixture) -> Self {
+        Self {
+            used: Mutex::new(vec![false; fixture.exchanges.len()]),
+            exchanges: fixture.exchanges,
+            strict: false,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Fixture::load(path).map(Self::new)
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Recorded exchanges no request has consumed yet.
+    pub fn unused(&self) -> Vec<Exchange> {
+        let used = self.used.lock().unwrap();
+        self.exchanges
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, used)| !**used)
+            .map(|(exchange, _)| exchange.clone())
+            .collect()
+    }
+
+    pub fn reply(&self, path: &str, request: &Request<'_>) -> Result<Reply, String> {
+        let (method, payload) = Exchange::key(path, request);
+        let same_call = |e: &Exchange| e.path == path && e.method == method;
+        let exact: Vec<usize> = (0..self.exchanges.len())
+            .filter(|&i| same_call(&self.exchanges[i]) && self.exchanges[i].request == payload)
+            .collect();
+        let candidates = if !exact.is_empty() || self.strict {
+            exact
+        } else {
+            (0..self.exchanges.len()).filter(|&i| same_call(&self.exchanges[i])).collect()
+        };
+
+        let mut used = self.used.lock().unwrap();
+        let index = match candidates.iter().find(|&&i| !used[i]) {
+            Some(&i) => i,
+            None => match candidates.last() {
+                Some(&i) if !self.strict => i,
+                Some(&i) => {
+                    return Err(format!(
+                        "replay: {} called more often than recorded",
+                        self.exchanges[i].describe()
+                    ))
+                }
+                None => {
+                    let call = match &method {
+                        Some(method) => format!("{} {}", path, method),
+                        None => path.to_string(),
+                    };
+                    return Err(format!("replay: unexpected call {}", call));
+                }
+            },
+        };
+        used[index] = true;
+        self.exchanges[index].reply()
+    }
+}
@@ -1,19 +1,25 @@
 use axum::{
     extract::State,
     http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 
 use crate::wallet_service::WalletService;
+use fuego_sdk::scanner::SignedKeyImage;
+use fuego_sdk::store::StateKey;
 
 pub struct AppState {
     pub wallet: Arc<Mutex<WalletService>>,
     pub fuegod_url: String,
+    /// Client for proxied daemon calls, carrying the daemon HTTP settings.
+    pub http: reqwest::Client,
 }
 
 #[derive(Serialize)]
@@ -63,10 +69,13 @@ fn is_fuegod_method(method: &str) -> bool {
 
 fn is_wallet_method(method: &str) -> bool {
     matches!(method,
-        "getBalance" | "getAddresses" | "getAddress" | "getTransactions" |
-        "sendTransaction" | "getStatus" | "register_alias" | "create_cd" | "claim_cd" |
+        "getBalance" | "getAddresses" | "getAddress" | "createAddress" | "getTransactions" |
+        "getUnconfirmedTransactionHashes" | "getUnconfirmedTransactions" |
+        "sendTransaction" | "getStatus" | "lock" | "unlock" | "register_alias" | "create_cd" | "claim_cd" |
         "create_integrated" | "list_cds" | "cd::list" | "cd::create" | "cd::claim" |
-        "mint_heat" | "swap" | "add_liq" | "remove_liq" | "place_limit_order"
+        "mint_heat" | "swap" | "add_liq" | "remove_liq" | "place_limit_order" |
+        "export_key_images" | "import_key_images" | "create_unsigned_transaction" |
+        "sign_unsigned_transaction" | "submit_signed_transaction"
     )
 }
 
@@ -84,8 +93,128 @@ fn sanitize_error(msg: &str) -> String {
     sanitized
 }
 
-async fn proxy_to_fuegod(fuegod_url: &str, body: &serde_json::Value) -> Result<serde_json::Value, String> {
-    let client = reqwest::Client::new();
+/// Decode one `signed_key_images` entry: hex `output_key`, `key_image`
+/// and 64-byte `signature`.
+fn parse_signed_key_image(value: &serde_json::Value) -> Result<SignedKeyImage, String> {
+    fn field<const N: usize>(value: &serde_json::Value, name: &str) -> Result<[u8; N], String> {
+        let hex_str = value.get(name)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("missing {}", name))?;
+        hex::decode(hex_str)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| format!("{} must be {} hex bytes", name, N))
+    }
+    Ok(SignedKeyImage {
+        output_key: field(value, "output_key")?,
+        key_image: field(value, "key_image")?,
+        signature: field(value, "signature")?,
+    })
+}
+
+/// Destinations, fee and anonymity of a transfer request.
+type Transfer = (Vec<(String, u64)>, u64, u32);
+
+/// Decode the `destinations` / `fee` / `anonymity` params shared by
+/// sendTransaction and create_unsigned_transaction.
+fn parse_transfer(params: &serde_json::Value) -> Result<Transfer, String> {
+    let destinations = params.get("destinations")
+        .and_then(|d| d.as_array())
+        .ok_or("missing destinations")?;
+    let mut dests: Vec<(String, u64)> = Vec::with_capacity(destinations.len());
+    for dest in destinations {
+        let address = dest.get("address")
+            .and_then(|a| a.as_str())
+            .ok_or("missing address")?
+            .to_string();
+        let amount = dest.get("amount")
+            .and_then(|a| a.as_u64())
+            .ok_or("missing amount")?;
+        dests.push((address, amount));
+    }
+    if dests.is_empty() {
+        return Err("empty destinations".into());
+    }
+    let fee = params.get("fee")
+        .and_then(|f| f.as_u64())
+        .unwrap_or(0);
+    let anonymity = params.get("anonymity")
+        .and_then(|a| a.as_u64())
+        .unwrap_or(0) as u32;
+    Ok((dests, fee, anonymity))
+}
+
+/// Decode a hex-encoded parameter (cold signing file, payment id) from
+/// `params[name]`.
+fn hex_param(params: &serde_json::Value, name: &str) -> Result<Vec<u8>, String> {
+    let hex_str = params.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("missing {}", name))?;
+    hex::decode(hex_str).map_err(|e| format!("invalid {} hex: {}", name, e))
+}
+
+/// Rows `getTransactions` returns when the caller does not page.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// `getTransactions` filters: `firstBlockIndex`/`blockCount`, `paymentId`,
+/// one of `addresses`, plus `direction` ("in"/"out"), `minAmount`,
+/// `maxAmount` and `offset`/`limit` paging. Without `offset` or `limit`
+/// the answer is the latest `DEFAULT_HISTORY_LIMIT` rows, newest first.
+fn parse_history_query(
+    params: &serde_json::Value,
+    addresses: &[String],
+) -> Result<fuego_sdk::HistoryQuery, String> {
+    use fuego_sdk::scanner::HistoryDirection;
+
+    let uint = |name: &str| params.get(name).and_then(|v| v.as_u64());
+    let mut query = fuego_sdk::HistoryQuery {
+        first_height: uint("firstBlockIndex").unwrap_or(0),
+        block_count: uint("blockCount"),
+        min_amount: uint("minAmount"),
+        max_amount: uint("maxAmount"),
+        offset: uint("offset").unwrap_or(0) as usize,
+        limit: uint("limit").map(|l| l as usize),
+        ..Default::default()
+    };
+    if params.get("offset").is_none() && params.get("limit").is_none() {
+        query.limit = Some(DEFAULT_HISTORY_LIMIT);
+        query.newest_first = true;
+    }
+    if params.get("paymentId").is_some() {
+        let id = hex_param(params, "paymentId")?;
+        query.payment_id = Some(
+            fuego_sdk::serialization::PaymentId::from_bytes(&id)
+                .ok_or("paymentId must be 8 or 32 bytes")?,
+        );
+    }
+    query.direction = match params.get("direction").and_then(|d| d.as_str()) {
+        None => None,
+        Some("in") => Some(HistoryDirection::Incoming),
+        Some("out") => Some(HistoryDirection::Outgoing),
+        Some(other) => return Err(format!("unknown direction: {other}")),
+    };
+    if let Some(filter) = params.get("addresses").and_then(|a| a.as_array()) {
+        match filter.as_slice() {
+            [] => {}
+            [address] => {
+                let address = address.as_str().ok_or("addresses must be strings")?;
+                let index = addresses
+                    .iter()
+                    .position(|a| a == address)
+                    .ok_or_else(|| format!("address not in wallet: {address}"))?;
+                query.subaddress = Some(index as u32);
+            }
+            _ => return Err("filtering by more than one address is not supported".into()),
+        }
+    }
+    Ok(query)
+}
+
+async fn proxy_to_fuegod(
+    client: &reqwest::Client,
+    fuegod_url: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
     let method = body.get("method").and_then(|v| v.as_str()).unwrap_or("");
     let params = body.get("params").cloned().unwrap_or(serde_json::json!({}));
 
@@ -237,6 +366,16 @@ async fn handle_wallet_method(
     match method {
         "getBalance" | "getbalance" => {
             let wallet = wallet.lock().await;
+            if let Some(address) = params.get("address").and_then(|a| a.as_str()) {
+                let index = wallet.subaddress_index(address).await
+                    .ok_or("address does not belong to this wallet")?;
+                let balances = wallet.subaddress_balances().await;
+                return Ok(serde_json::json!({
+                    "availableBalance": balances[index as usize].1,
+                    "lockedAmount": 0,
+                    "blockCount": wallet.height().await,
+                }));
+            }
             let balance = wallet.balance_full().await;
             Ok(serde_json::json!({
                 "availableBalance": balance.confirmed,
@@ -244,12 +383,52 @@ async fn handle_wallet_method(
                 "blockCount": wallet.height().await,
             }))
         }
-        "getAddress" | "getAddresses" | "get_address" => {
+        "getAddress" | "get_address" => {
             let wallet = wallet.lock().await;
             Ok(serde_json::json!({
                 "address": wallet.address().await,
             }))
         }
+        "getAddresses" => {
+            let wallet = wallet.lock().await;
+            let balances: Vec<serde_json::Value> = wallet.subaddress_balances().await
+                .into_iter()
+                .map(|(address, balance)| serde_json::json!({
+                    "address": address,
+                    "availableBalance": balance,
+                }))
+                .collect();
+            Ok(serde_json::json!({
+                "address": wallet.address().await,
+                "addresses": wallet.addresses().await,
+                "balances": balances,
+            }))
+        }
+        "lock" => {
+            let wallet = wallet.lock().await;
+            wallet.lock()?;
+            Ok(serde_json::json!({ "locked": true }))
+        }
+        "unlock" => {
+            let state_key = match (params.get("passphrase").and_then(|p| p.as_str()), params.get("key")) {
+                (Some(passphrase), _) => StateKey::Passphrase(passphrase.as_bytes().to_vec()),
+                (None, Some(_)) => {
+                    let key: [u8; 32] = hex_param(params, "key")?
+                        .try_into()
+                        .map_err(|_| "key must be 32 bytes".to_string())?;
+                    StateKey::Key(key)
+                }
+                (None, None) => return Err("missing passphrase or key".into()),
+            };
+            let wallet = wallet.lock().await;
+            wallet.unlock(&state_key)?;
+            Ok(serde_json::json!({ "locked": false }))
+        }
+        "createAddress" => {
+            let wallet = wallet.lock().await;
+            let address = wallet.create_address().await?;
+            Ok(serde_json::json!({ "address": address }))
+        }
         "getHealth" => {
             let wallet = wallet.lock().await;
             let status = wallet.sync_status();
@@ -258,6 +437,7 @@ async fn handle_wallet_method(
                     "ok": true,
                     "height": wallet.height().await,
                     "syncing": status.is_syncing,
+                    "locked": wallet.is_locked(),
                 },
                 "swap": {
                     "ok": crate::swapd::swapd_healthy(crate::swapd::SWAPD_RPC_PORT).await,
@@ -275,10 +455,14 @@ async fn handle_wallet_method(
         }
         "getTransactions" | "get_transfers" => {
             let wallet = wallet.lock().await;
-            let txs = wallet.get_transactions(100).await;
-            let items: Vec<serde_json::Value> = txs.iter().map(|tx| {
+            let addresses = wallet.addresses().await;
+            let query = parse_history_query(params, &addresses)?;
+            let page = wallet.query_transactions(&query).await;
+            let items: Vec<serde_json::Value> = page.entries.iter().map(|tx| {
                 serde_json::json!({
                     "transactionHash": hex::encode(tx.tx_hash),
+                    "address": addresses.get(tx.subaddress as usize),
+                    "paymentId": tx.payment_id.map(|id| id.to_hex()),
                     "fee": tx.fee,
                     "blockIndex": tx.block_height,
                     "amount": match tx.direction {
@@ -288,32 +472,39 @@ async fn handle_wallet_method(
                     "transfers": [],
                 })
             }).collect();
-            Ok(serde_json::json!({ "items": items, "transactions": txs.len() }))
+            Ok(serde_json::json!({
+                "items": items,
+                "transactions": page.entries.len(),
+                "total": page.total,
+            }))
+        }
+        "getUnconfirmedTransactionHashes" => {
+            let wallet = wallet.lock().await;
+            let hashes: Vec<String> = wallet.pool_transactions().iter()
+                .map(|tx| hex::encode(tx.effect.tx_hash))
+                .collect();
+            Ok(serde_json::json!({ "transactionHashes": hashes }))
+        }
+        "getUnconfirmedTransactions" => {
+            let wallet = wallet.lock().await;
+            let addresses = wallet.addresses().await;
+            let items: Vec<serde_json::Value> = wallet.pool_transactions().iter().map(|tx| {
+                serde_json::json!({
+                    "transactionHash": hex::encode(tx.effect.tx_hash),
+                    "address": tx.effect.received_by.first().and_then(|(i, _)| addresses.get(*i as usize)),
+                    "paymentId": tx.effect.payment_id.map(|id| id.to_hex()),
+                    "amount": tx.effect.received as i64 - tx.effect.spent as i64,
+                    "transfers": tx.effect.received_by.iter().map(|(i, amount)| serde_json::json!({
+                        "address": addresses.get(*i as usize),
+                        "amount": amount,
+                    })).collect::<Vec<_>>(),
+                    "conflict": tx.conflict,
+                })
+            }).collect();
+            Ok(serde_json::json!({ "items": items }))
         }
         "sendTransaction" | "transfer" => {
-            let destinations = params.get("destinations")
-                .and_then(|d| d.as_array())
-                .ok_or("missing destinations")?;
-            let mut dests: Vec<(String, u64)> = Vec::with_capacity(destinations.len());
-            for dest in destinations {
-                let address = dest.get("address")
-                    .and_then(|a| a.as_str())
-                    .ok_or("missing address")?
-                    .to_string();
-                let amount = dest.get("amount")
-                    .and_then(|a| a.as_u64())
-                    .ok_or("missing amount")?;
-                dests.push((address, amount));
-            }
-            if dests.is_empty() {
-                return Err("empty destinations".into());
-            }
-            let fee = params.get("fee")
-                .and_then(|f| f.as_u64())
-                .unwrap_or(0);
-            let anonymity = params.get("anonymity")
-                .and_then(|a| a.as_u64())
-                .unwrap_or(0) as u32;
+            let (dests, fee, anonymity) = parse_transfer(params)?;
 
             let wallet = wallet.lock().await;
             let tx_hash = wallet.send_transaction(&dests, fee, anonymity).await
@@ -339,10 +530,15 @@ async fn handle_wallet_method(
             }))
         }
         "create_integrated" => {
+            let payment_id = match params.get("payment_id") {
+                Some(_) => Some(hex_param(params, "payment_id")?),
+                None => None,
+            };
             let wallet = wallet.lock().await;
-            let addr = wallet.address().await;
+            let (addr, payment_id) = wallet.integrated_address(payment_id.as_deref()).await?;
             Ok(serde_json::json!({
                 "integratedAddress": addr,
+                "paymentId": payment_id,
             }))
         }
         "list_cds" | "cd::list" => {
@@ -556,6 +752,61 @@ async fn handle_wallet_method(
                 "txHash": tx_hash,
             }))
         }
+        "export_key_images" => {
+            let wallet = wallet.lock().await;
+            let images = wallet.export_key_images().await?;
+            let items: Vec<serde_json::Value> = images.iter().map(|i| {
+                serde_json::json!({
+                    "output_key": hex::encode(i.output_key),
+                    "key_image": hex::encode(i.key_image),
+                    "signature": hex::encode(i.signature),
+                })
+            }).collect();
+            Ok(serde_json::json!({ "signed_key_images": items }))
+        }
+        "import_key_images" => {
+            let images = params.get("signed_key_images")
+                .and_then(|v| v.as_array())
+                .ok_or("missing signed_key_images")?
+                .iter()
+                .map(parse_signed_key_image)
+                .collect::<Result<Vec<_>, String>>()?;
+            let wallet = wallet.lock().await;
+            let summary = wallet.import_key_images(&images).await?;
+            Ok(serde_json::json!({
+                "imported": summary.imported,
+                "spent": summary.spent,
+                "unspent": summary.unspent,
+                "height": wallet.height().await,
+            }))
+        }
+        "create_unsigned_transaction" => {
+            let (dests, fee, anonymity) = parse_transfer(params)?;
+            let wallet = wallet.lock().await;
+            let unsigned = wallet.create_unsigned(&dests, fee, anonymity).await?;
+            Ok(serde_json::json!({
+                "unsigned_transaction": hex::encode(unsigned.to_bytes()),
+                "inputs": unsigned.inputs.len(),
+                "amount": unsigned.input_amount(),
+                "fee": unsigned.fee,
+            }))
+        }
+        "sign_unsigned_transaction" => {
+            let unsigned = hex_param(params, "unsigned_transaction")?;
+            let wallet = wallet.lock().await;
+            let signed = wallet.sign_unsigned(&unsigned)?;
+            Ok(serde_json::json!({ "signed_transaction": hex::encode(signed) }))
+        }
+        "submit_signed_transaction" => {
+            let signed = hex_param(params, "signed_transaction")?;
+            let wallet = wallet.lock().await;
+            let tx_hash = wallet.submit_signed(&signed).await
+                .map_err(|e| format!("send failed: {}", e))?;
+            Ok(serde_json::json!({
+                "transactionHash": tx_hash,
+                "txHash": tx_hash,
+            }))
+        }
         _ => Err(format!("unknown wallet method: {}", method)),
     }
 }
@@ -594,7 +845,7 @@ async fn json_rpc_handler(
         let params = body.get("params").cloned().unwrap_or(serde_json::Value::Null);
         handle_wallet_method(&state.wallet, &state.fuegod_url, method, &params).await
     } else if is_fuegod_method(method) {
-        proxy_to_fuegod(&state.fuegod_url, &body).await
+        proxy_to_fuegod(&state.http, &state.fuegod_url, &body).await
     } else {
         Err(format!("unknown method: {}", method))
     };
@@ -625,7 +876,7 @@ async fn fuegod_get(
     }
     let fuegod_path = req.uri().path();
     let fuegod_query = req.uri().query().unwrap_or("");
-    let client = reqwest::Client::new();
+    let client = &state.http;
     let url = if fuegod_query.is_empty() {
         format!("{}{}", state.fuegod_url, fuegod_path)
     } else {
@@ -660,7 +911,7 @@ async fn fuegod_post(
     let _ = parts;
     let body_bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap_or_default();
     let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap_or(serde_json::json!({}));
-    let client = reqwest::Client::new();
+    let client = &state.http;
     let url = format!("{}{}", state.fuegod_url, fuegod_path);
     
     match client.post(&url).json(&body).send().await {
@@ -698,8 +949,29 @@ async fn status_handler(
     }))
 }
 
+/// Server-sent stream of wallet events, one JSON object per `data:` line
+/// with the event type as the SSE event name. A subscriber that falls
+/// behind gets a `lagged` event with the number of events it missed and
+/// should re-read balances.
+async fn events_handler(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let rx = state.wallet.lock().await.subscribe_events();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(event) => Event::default().event(event.kind()).data(event.to_json().to_string()),
+            Err(RecvError::Lagged(skipped)) => Event::default()
+                .event("lagged")
+                .data(serde_json::json!({ "skipped": skipped }).to_string()),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let client = reqwest::Client::new();
+    let client = &state.http;
     let fuegod_ok = client.get(format!("{}/getinfo", state.fuegod_url))
         .send().await
         .map(|r| r.status().is_success())
@@ -754,10 +1026,12 @@ pub async fn run_server(
     wallet: Arc<Mutex<WalletService>>,
     fuegod_url: &str,
     bind_addr: &str,
+    http: reqwest::Client,
 ) -> Result<(), String> {
     let state = Arc::new(AppState {
         wallet,
         fuegod_url: fuegod_url.to_string(),
+        http,
     });
 
     let cors = tower_http::cors::CorsLayer::new()
@@ -773,6 +1047,7 @@ pub async fn run_server(
         .route("/json_rpc", post(json_rpc_handler))
         .route("/health", get(health_check))
         .route("/status", get(status_handler))
+        .route("/events", get(events_handler))
         .route("/scan_balance", post(scan_balance_handler))
         // HEARTH AMM REST proxy
         .route("/amm_pool_info", get(fuegod_get))
@@ -816,3 +1091,89 @@ pub async fn run_server(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send `method` to /json_rpc and return its `result` or `error`.
+    async fn rpc(state: &Arc<AppState>, method: &str, params: serde_json::Value) -> serde_json::Value {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = json_rpc_handler(State(state.clone()), axum::http::HeaderMap::new(), Json(body))
+            .await
+            .into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let reply: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        reply.get("result").or(reply.get("error")).cloned().unwrap()
+    }
+
+    fn app_state(service: WalletService, url: &str) -> Arc<AppState> {
+        Arc::new(AppState {
+            wallet: Arc::new(Mutex::new(service)),
+            fuegod_url: url.to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn json_rpc_locks_and_unlocks_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain = crate::test_daemon::TestChain::default();
+        let url = chain.serve().await;
+        let wallet = fuego_sdk::Wallet::from_seed([207u8; 32]).unwrap();
+        let key = StateKey::Key([7u8; 32]);
+        let service = WalletService::with_wallet(wallet, &url, dir.path().to_path_buf(), true, Some(&key)).unwrap();
+        let state = app_state(service, &url);
+
+        assert_eq!(rpc(&state, "lock", serde_json::json!({})).await, serde_json::json!({ "locked": true }));
+        assert!(state.wallet.lock().await.is_locked());
+
+        let wrong = rpc(&state, "unlock", serde_json::json!({ "key": hex::encode([8u8; 32]) })).await;
+        assert!(wrong.get("message").is_some());
+        assert!(state.wallet.lock().await.is_locked());
+
+        let unlocked = rpc(&state, "unlock", serde_json::json!({ "key": hex::encode([7u8; 32]) })).await;
+        assert_eq!(unlocked, serde_json::json!({ "locked": false }));
+        assert!(!state.wallet.lock().await.is_locked());
+    }
+
+    #[tokio::test]
+    async fn json_rpc_reports_pool_transactions() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let service = WalletService::new([207u8; 32], &url, dir.path().to_path_buf(), true).unwrap();
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        chain.push(0, vec![]);
+        service.sync_once().await.unwrap();
+        let mut tx_secret = [0u8; 32];
+        tx_secret[0] = 4;
+        let incoming = chain.add_to_pool(pay_to(&keys, 250, tx_secret));
+        service.refresh_pool().await.unwrap();
+        let address = service.address().await;
+        let state = app_state(service, &url);
+
+        let hashes = rpc(&state, "getUnconfirmedTransactionHashes", serde_json::json!({})).await;
+        assert_eq!(hashes, serde_json::json!({ "transactionHashes": [hex::encode(incoming)] }));
+
+        let pool = rpc(&state, "getUnconfirmedTransactions", serde_json::json!({})).await;
+        let item = &pool["items"][0];
+        assert_eq!(item["transactionHash"], hex::encode(incoming));
+        assert_eq!(item["address"], address);
+        assert_eq!(item["amount"], 250);
+        assert_eq!(item["conflict"], false);
+    }
+
+    #[test]
+    fn unpaged_history_query_returns_latest_rows() {
+        let query = parse_history_query(&serde_json::json!({}), &[]).unwrap();
+        assert_eq!(query.limit, Some(DEFAULT_HISTORY_LIMIT));
+        assert!(query.newest_first);
+
+        let paged = parse_history_query(&serde_json::json!({ "offset": 5 }), &[]).unwrap();
+        assert_eq!((paged.offset, paged.limit), (5, None));
+        assert!(!paged.newest_first);
+    }
+}
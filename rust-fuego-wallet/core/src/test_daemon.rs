@@ -0,0 +1,642 @@
+//! Local stand-in for fuegod used by the sync tests: serves /getinfo,
+//! /queryblockslite.bin, /get_o_indexes.bin, /getrandom_outs.bin,
+//! /gettransactions, /sendrawtransaction, /get_alias, /get_alias_by_address,
+//! /get_pool_changes_lite.bin and the getblockheaderbyheight, on_getblockhash, f_block_json and is_key_image_spent
+//! JSON-RPC methods over a chain the test can extend or reorganize at will.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use fuego_sdk::scanner::WalletKeys;
+use fuego_sdk::alias::AliasInfo;
+use fuego_sdk::serialization::{
+    build_extra_with_pubkey, parse_extra_alias, parse_kv_document, parse_tx, read_varint,
+    serialize_tx, write_varint, KeyInput, KvValue, OutputTarget, Transaction, TransactionPrefix,
+    TxInput, TxOutput,
+};
+
+/// A transaction as (hash, prefix).
+pub type TestTx = ([u8; 32], TransactionPrefix);
+
+pub struct TestBlock {
+    pub id: [u8; 32],
+    pub txs: Vec<TestTx>,
+}
+
+#[derive(Clone, Default)]
+pub struct TestChain {
+    blocks: Arc<Mutex<Vec<TestBlock>>>,
+    /// Transactions received on /sendrawtransaction, not yet mined.
+    submitted: Arc<Mutex<Vec<Transaction>>>,
+    /// Most blocks per /queryblockslite.bin answer; 0 for no limit.
+    batch_limit: Arc<Mutex<usize>>,
+    /// Latency of /queryblockslite.bin, in milliseconds.
+    batch_delay: Arc<AtomicU64>,
+    batches_in_flight: Arc<AtomicUsize>,
+    max_batches_in_flight: Arc<AtomicUsize>,
+    /// Transactions in the pool, served on /get_pool_changes_lite.bin.
+    pool: Arc<Mutex<Vec<TestTx>>>,
+}
+
+impl TestChain {
+    /// Append a block; `branch` salts the block id so competing branches
+    /// get distinct ids at the same height.
+    pub fn push(&self, branch: u8, txs: Vec<TransactionPrefix>) {
+        let txs = txs
+            .into_iter()
+            .map(|prefix| {
+                let hash = fuego_sdk::serialization::tx_prefix_hash(&prefix);
+                (hash, prefix)
+            })
+            .collect();
+        self.push_block(branch, txs);
+    }
+
+    /// Mine the transactions submitted since the last call into a new
+    /// block, under their full transaction hashes like fuegod. Returns
+    /// those hashes.
+    pub fn mine_submitted(&self, branch: u8) -> Vec<[u8; 32]> {
+        let txs: Vec<TestTx> = self
+            .take_submitted()
+            .into_iter()
+            .map(|tx| (fuego_crypto::cn_fast_hash(&serialize_tx(&tx)), tx.prefix))
+            .collect();
+        let hashes = txs.iter().map(|(hash, _)| *hash).collect();
+        self.push_block(branch, txs);
+        hashes
+    }
+
+    fn push_block(&self, branch: u8, txs: Vec<TestTx>) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let height = blocks.len() as u64;
+        let mut seed = height.to_le_bytes().to_vec();
+        seed.push(branch);
+        let id = fuego_crypto::cn_fast_hash(&seed);
+        self.pool.lock().unwrap().retain(|(hash, _)| !txs.iter().any(|(mined, _)| mined == hash));
+        blocks.push(TestBlock { id, txs });
+    }
+
+    /// Put a transaction in the pool; it leaves when a block mines it.
+    pub fn add_to_pool(&self, prefix: TransactionPrefix) -> [u8; 32] {
+        let hash = fuego_sdk::serialization::tx_prefix_hash(&prefix);
+        self.pool.lock().unwrap().push((hash, prefix));
+        hash
+    }
+
+    /// Drop every transaction from the pool, as on expiry.
+    pub fn clear_pool(&self) {
+        self.pool.lock().unwrap().clear();
+    }
+
+    /// Orphan every block at `height` and above.
+    pub fn truncate(&self, height: u64) {
+        self.blocks.lock().unwrap().truncate(height as usize);
+    }
+
+    /// Answer /queryblockslite.bin with at most `blocks` blocks, like the
+    /// daemon's per-request cap.
+    pub fn limit_batches(&self, blocks: usize) {
+        *self.batch_limit.lock().unwrap() = blocks;
+    }
+
+    /// Answer /queryblockslite.bin only after `millis`.
+    pub fn delay_batches(&self, millis: u64) {
+        self.batch_delay.store(millis, Ordering::SeqCst);
+    }
+
+    /// Most /queryblockslite.bin requests served at once so far.
+    pub fn max_batches_in_flight(&self) -> usize {
+        self.max_batches_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Drain the transactions submitted since the last call.
+    pub fn take_submitted(&self) -> Vec<Transaction> {
+        std::mem::take(&mut *self.submitted.lock().unwrap())
+    }
+
+    /// Aliases registered by mined transactions, first registration wins.
+    fn aliases(&self) -> Vec<AliasInfo> {
+        let blocks = self.blocks.lock().unwrap();
+        let mut aliases: Vec<AliasInfo> = Vec::new();
+        for (_, prefix) in blocks.iter().flat_map(|b| b.txs.iter()) {
+            if let Some(info) = parse_extra_alias(&prefix.extra) {
+                if !aliases.iter().any(|a| a.alias == info.alias) {
+                    aliases.push(info);
+                }
+            }
+        }
+        aliases
+    }
+
+    /// Serve the chain on an ephemeral port; returns the daemon URL.
+    pub async fn serve(&self) -> String {
+        let app = Router::new()
+            .route("/getinfo", get(getinfo))
+            .route("/queryblockslite.bin", post(query_blocks_lite))
+            .route("/get_o_indexes.bin", post(get_o_indexes))
+            .route("/get_pool_changes_lite.bin", post(get_pool_changes_lite))
+            .route("/getrandom_outs.bin", post(get_random_outs))
+            .route("/gettransactions", post(get_transactions))
+            .route("/sendrawtransaction", post(send_raw_transaction))
+            .route("/get_alias", post(get_alias))
+            .route("/get_alias_by_address", post(get_alias_by_address))
+            .route("/json_rpc", post(json_rpc))
+            .with_state(self.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{addr}")
+    }
+}
+
+/// A transaction paying `amount` to the wallet with `keys` in output 0.
+/// `tx_secret` must be a canonical scalar.
+pub fn pay_to(keys: &WalletKeys, amount: u64, tx_secret: [u8; 32]) -> TransactionPrefix {
+    let r = fuego_crypto::Keypair::from_secret(tx_secret);
+    let derivation = fuego_crypto::generate_key_derivation(
+        &fuego_crypto::PublicKey(keys.view_public),
+        &r.secret,
+    )
+    .unwrap();
+    let output_key = fuego_crypto::derive_public_key(&derivation, 0, &keys.spend_public).unwrap();
+    TransactionPrefix {
+        version: 1,
+        unlock_time: 0,
+        inputs: Vec::new(),
+        outputs: vec![TxOutput {
+            amount,
+            target: OutputTarget::Key(output_key.0),
+        }],
+        extra: build_extra_with_pubkey(&r.public),
+    }
+}
+
+/// A transaction consuming the output with `key_image`, paying nothing
+/// back to the wallet.
+pub fn spend(key_image: [u8; 32], amount: u64) -> TransactionPrefix {
+    let mut tx_secret = [0u8; 32];
+    tx_secret[0] = 0x5a;
+    let r = fuego_crypto::Keypair::from_secret(tx_secret);
+    TransactionPrefix {
+        version: 1,
+        unlock_time: 0,
+        inputs: vec![TxInput::Key(KeyInput {
+            amount,
+            offsets: vec![0],
+            key_image,
+        })],
+        outputs: Vec::new(),
+        extra: build_extra_with_pubkey(&r.public),
+    }
+}
+
+async fn getinfo(State(chain): State<TestChain>) -> Json<serde_json::Value> {
+    let blocks = chain.blocks.lock().unwrap();
+    let top = blocks.last().map(|b| hex::encode(b.id)).unwrap_or_default();
+    Json(serde_json::json!({
+        "height": blocks.len() as u64,
+        "difficulty": 1,
+        "tx_count": 0,
+        "tx_pool_size": 0,
+        "incoming_connections_count": 0,
+        "outgoing_connections_count": 0,
+        "last_block_timestamp": 0,
+        "last_block_reward": 0,
+        "top_block_hash": top,
+        "status": "OK",
+        "version": "test",
+    }))
+}
+
+/// Reply for a request the stand-in can't encode: HTTP 501 with the reason,
+/// which the wallet sees as a failed daemon call.
+type Unsupported = (StatusCode, String);
+
+async fn query_blocks_lite(
+    State(chain): State<TestChain>,
+    body: Bytes,
+) -> Result<Vec<u8>, Unsupported> {
+    let in_flight = chain.batches_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+    chain.max_batches_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+    let delay = chain.batch_delay.load(Ordering::SeqCst);
+    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+    chain.batches_in_flight.fetch_sub(1, Ordering::SeqCst);
+
+    let locator = read_locator(&body);
+    let blocks = chain.blocks.lock().unwrap();
+    // First locator entry on our chain, like findBlockchainSupplement.
+    let start = locator
+        .iter()
+        .find_map(|id| blocks.iter().position(|b| &b.id == id))
+        .unwrap_or(0);
+
+    let limit = match *chain.batch_limit.lock().unwrap() {
+        0 => usize::MAX,
+        limit => limit,
+    };
+
+    let items: Vec<Vec<u8>> = blocks[start..]
+        .iter()
+        .take(limit)
+        .map(|block| {
+            let txs = block
+                .txs
+                .iter()
+                .map(|(hash, prefix)| {
+                    Ok(kv_object(&[
+                        ("txHash", 10, kv_blob(hash)),
+                        ("txPrefix", 12, kv_prefix(prefix)?),
+                    ]))
+                })
+                .collect::<Result<Vec<_>, Unsupported>>()?;
+            Ok(kv_object(&[
+                ("blockId", 10, kv_blob(&block.id)),
+                ("block", 10, kv_blob(&[])),
+                ("txPrefixes", 12 | 0x80, kv_array(&txs)),
+            ]))
+        })
+        .collect::<Result<Vec<_>, Unsupported>>()?;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0101_1101u32.to_le_bytes());
+    out.extend_from_slice(&0x0102_0101u32.to_le_bytes());
+    out.push(1);
+    out.extend(kv_object(&[
+        ("status", 10, kv_blob(b"OK")),
+        ("startHeight", 5, (start as u64).to_le_bytes().to_vec()),
+        ("currentHeight", 5, (blocks.len() as u64).to_le_bytes().to_vec()),
+        ("fullOffset", 5, 0u64.to_le_bytes().to_vec()),
+        ("items", 12 | 0x80, kv_array(&items)),
+    ]));
+    Ok(out)
+}
+
+async fn get_pool_changes_lite(
+    State(chain): State<TestChain>,
+    body: Bytes,
+) -> Result<Vec<u8>, Unsupported> {
+    let request = parse_kv_document(&body).unwrap();
+    let field = |name: &str| match request.get(name) {
+        Some(KvValue::Bytes(bytes)) => bytes.clone(),
+        _ => Vec::new(),
+    };
+    let tail = field("tailBlockId");
+    let known: Vec<[u8; 32]> = field("knownTxsIds")
+        .chunks_exact(32)
+        .map(|c| c.try_into().unwrap())
+        .collect();
+    let tail_actual = chain.blocks.lock().unwrap().last().is_some_and(|b| b.id.as_slice() == tail);
+
+    let pool = chain.pool.lock().unwrap();
+    let added = pool
+        .iter()
+        .filter(|(hash, _)| !known.contains(hash))
+        .map(|(hash, prefix)| {
+            Ok(kv_object(&[("txHash", 10, kv_blob(hash)), ("txPrefix", 12, kv_prefix(prefix)?)]))
+        })
+        .collect::<Result<Vec<_>, Unsupported>>()?;
+    let deleted: Vec<u8> = known
+        .iter()
+        .filter(|id| !pool.iter().any(|(hash, _)| hash == *id))
+        .flatten()
+        .copied()
+        .collect();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0101_1101u32.to_le_bytes());
+    out.extend_from_slice(&0x0102_0101u32.to_le_bytes());
+    out.push(1);
+    out.extend(kv_object(&[
+        ("status", 10, kv_blob(b"OK")),
+        ("isTailBlockActual", 11, vec![tail_actual as u8]),
+        ("addedTxs", 12 | 0x80, kv_array(&added)),
+        ("deletedTxsIds", 10, kv_blob(&deleted)),
+    ]));
+    Ok(out)
+}
+
+async fn get_o_indexes(State(chain): State<TestChain>, body: Bytes) -> Vec<u8> {
+    let blocks = chain.blocks.lock().unwrap();
+    let outputs = blocks
+        .iter()
+        .flat_map(|b| b.txs.iter())
+        .find(|(hash, _)| hash.as_slice() == body.as_ref())
+        .map(|(_, prefix)| prefix.outputs.len())
+        .unwrap_or(0);
+    let mut out = Vec::new();
+    write_varint(outputs as u64, &mut out);
+    for i in 0..outputs {
+        write_varint(i as u64 + 1, &mut out);
+    }
+    out
+}
+
+/// Answer every requested amount with `outs_count` synthetic outputs at
+/// global indices 100 and up; their keys are valid points so rings sign.
+async fn get_random_outs(body: Bytes) -> Vec<u8> {
+    let mut pos = 0;
+    let count = read_varint(&body, &mut pos).unwrap();
+    let amounts: Vec<u64> = (0..count).map(|_| read_varint(&body, &mut pos).unwrap()).collect();
+    let outs_count = read_varint(&body, &mut pos).unwrap();
+
+    let mut out = Vec::new();
+    write_varint(2, &mut out);
+    out.extend_from_slice(b"OK");
+    write_varint(amounts.len() as u64, &mut out);
+    for amount in amounts {
+        write_varint(amount, &mut out);
+        write_varint(outs_count, &mut out);
+        for i in 0..outs_count {
+            let mut secret = [0u8; 32];
+            secret[0] = 0x77;
+            secret[1] = i as u8;
+            out.extend_from_slice(&(100 + i).to_le_bytes());
+            out.extend_from_slice(&fuego_crypto::ring::secret_key_to_public_key(&secret));
+        }
+    }
+    out
+}
+
+async fn send_raw_transaction(
+    State(chain): State<TestChain>,
+    Json(req): Json<serde_json::Value>,
+) -> Json<serde_json::Value> {
+    let tx = hex::decode(req["tx_as_hex"].as_str().unwrap_or_default())
+        .ok()
+        .and_then(|bytes| parse_tx(&bytes).ok());
+    // Like fuegod, a double spend of a mined key image is a bare "Failed".
+    let spent: Vec<[u8; 32]> = chain
+        .blocks
+        .lock()
+        .unwrap()
+        .iter()
+        .flat_map(|b| b.txs.iter())
+        .flat_map(|(_, prefix)| prefix.inputs.iter())
+        .filter_map(|input| input.key_image().copied())
+        .collect();
+    let status = match tx {
+        Some(tx) if !tx.prefix.inputs.iter().any(|i| i.key_image().is_some_and(|k| spent.contains(k))) => {
+            chain.submitted.lock().unwrap().push(tx);
+            "OK"
+        }
+        _ => "Failed",
+    };
+    Json(serde_json::json!({ "status": status }))
+}
+
+/// Full transaction blob as /gettransactions serves it. Signatures are
+/// zero-filled: nothing downstream of the stand-in verifies them.
+pub fn tx_blob(prefix: &TransactionPrefix) -> Vec<u8> {
+    serialize_tx(&Transaction {
+        prefix: prefix.clone(),
+        signatures: prefix.inputs.iter().map(|i| vec![[0u8; 64]; i.ring_size()]).collect(),
+    })
+}
+
+async fn get_transactions(
+    State(chain): State<TestChain>,
+    Json(req): Json<serde_json::Value>,
+) -> Json<serde_json::Value> {
+    let blocks = chain.blocks.lock().unwrap();
+    let blobs: Vec<Vec<u8>> = blocks
+        .iter()
+        .flat_map(|b| b.txs.iter())
+        .map(|(_, prefix)| tx_blob(prefix))
+        .collect();
+    let mut txs_as_hex = Vec::new();
+    let mut missed_tx = Vec::new();
+    for hash in req["txs_hashes"].as_array().into_iter().flatten() {
+        let hash = hash.as_str().unwrap_or_default();
+        match blobs.iter().find(|b| hex::encode(fuego_crypto::cn_fast_hash(b)) == hash) {
+            Some(blob) => txs_as_hex.push(hex::encode(blob)),
+            None => missed_tx.push(hash.to_string()),
+        }
+    }
+    Json(serde_json::json!({
+        "txs_as_hex": txs_as_hex,
+        "missed_tx": missed_tx,
+        "status": "OK",
+    }))
+}
+
+async fn get_alias(
+    State(chain): State<TestChain>,
+    Json(req): Json<serde_json::Value>,
+) -> Json<serde_json::Value> {
+    let alias = req["alias"].as_str().unwrap_or_default();
+    Json(match chain.aliases().into_iter().find(|a| a.alias == alias) {
+        Some(info) => serde_json::json!({ "address": info.address.0, "status": "OK" }),
+        None => serde_json::json!({ "status": "alias not found" }),
+    })
+}
+
+async fn get_alias_by_address(
+    State(chain): State<TestChain>,
+    Json(req): Json<serde_json::Value>,
+) -> Json<serde_json::Value> {
+    let address = req["address"].as_str().unwrap_or_default();
+    Json(match chain.aliases().into_iter().find(|a| a.address.0 == address) {
+        Some(info) => serde_json::json!({ "alias": info.alias, "status": "OK" }),
+        None => serde_json::json!({ "status": "alias not found" }),
+    })
+}
+
+async fn json_rpc(
+    State(chain): State<TestChain>,
+    Json(req): Json<serde_json::Value>,
+) -> Json<serde_json::Value> {
+    let params = &req["params"];
+    let blocks = chain.blocks.lock().unwrap();
+    let result = match req["method"].as_str().unwrap_or_default() {
+        "getblockheaderbyheight" => {
+            let height = params["height"].as_u64().unwrap_or(u64::MAX) as usize;
+            match blocks.get(height) {
+                Some(block) => serde_json::json!({
+                    "block_header": {
+                        "height": height,
+                        "hash": hex::encode(block.id),
+                        "prev_hash": hex::encode(height.checked_sub(1).map(|h| blocks[h].id).unwrap_or_default()),
+                        "timestamp": 1_700_000_000 + height as u64,
+                    },
+                    "status": "OK",
+                }),
+                None => return rpc_error(&req, "height out of range"),
+            }
+        }
+        "on_getblockhash" => {
+            let height = params[0].as_u64().unwrap_or(u64::MAX) as usize;
+            match blocks.get(height) {
+                Some(block) => serde_json::json!(hex::encode(block.id)),
+                None => return rpc_error(&req, "height out of range"),
+            }
+        }
+        "f_block_json" => {
+            let hash = params["hash"].as_str().unwrap_or_default();
+            match blocks.iter().find(|b| hex::encode(b.id) == hash) {
+                Some(block) => {
+                    let txs: Vec<serde_json::Value> = block
+                        .txs
+                        .iter()
+                        .map(|(_, prefix)| {
+                            let hash = fuego_crypto::cn_fast_hash(&tx_blob(prefix));
+                            serde_json::json!({ "hash": hex::encode(hash) })
+                        })
+                        .collect();
+                    serde_json::json!({ "block": { "hash": hash, "transactions": txs }, "status": "OK" })
+                }
+                None => return rpc_error(&req, "block not found"),
+            }
+        }
+        // 1 XFG buys 1 HEAT.
+        "amm_pool_info" => serde_json::json!({
+            "reserve_xfg": 1_000_000_000u64,
+            "reserve_heat": 1_000_000_000u64,
+            "total_lp_shares": 1_000_000_000u64,
+            "spot_price": 10_000_000u64,
+        }),
+        _ => {
+            let image = params["key_image"].as_str().unwrap_or_default();
+            let spent = blocks
+                .iter()
+                .flat_map(|b| b.txs.iter())
+                .flat_map(|(_, prefix)| prefix.inputs.iter())
+                .any(|input| matches!(input, TxInput::Key(k) if hex::encode(k.key_image) == image));
+            serde_json::json!({ "spent": spent })
+        }
+    };
+    Json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": req["id"],
+        "result": result,
+    }))
+}
+
+fn rpc_error(req: &serde_json::Value, message: &str) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": req["id"],
+        "error": { "code": -1, "message": message },
+    }))
+}
+
+/// Extract `block_ids` from a query_blocks_lite_request body.
+fn read_locator(body: &[u8]) -> Vec<[u8; 32]> {
+    // header (9) + section count (1) + name "block_ids" (10) + type (1)
+    let mut pos = 21;
+    let first = body[pos];
+    let width = 1usize << (first & 0x03);
+    let mut raw = 0u64;
+    for (i, byte) in body[pos..pos + width].iter().enumerate() {
+        raw |= (*byte as u64) << (i * 8);
+    }
+    pos += width;
+    let len = (raw >> 2) as usize;
+    body[pos..pos + len]
+        .chunks_exact(32)
+        .map(|c| c.try_into().unwrap())
+        .collect()
+}
+
+fn kv_size(n: usize, out: &mut Vec<u8>) {
+    if n <= 63 {
+        out.push((n as u8) << 2);
+    } else {
+        out.extend_from_slice(&(((n as u32) << 2) | 2).to_le_bytes());
+    }
+}
+
+fn kv_blob(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    kv_size(bytes.len(), &mut out);
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn kv_array(values: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    kv_size(values.len(), &mut out);
+    for value in values {
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+fn kv_object(fields: &[(&str, u8, Vec<u8>)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    kv_size(fields.len(), &mut out);
+    for (name, ty, payload) in fields {
+        out.push(name.len() as u8);
+        out.extend_from_slice(name.as_bytes());
+        out.push(*ty);
+        out.extend_from_slice(payload);
+    }
+    out
+}
+
+fn kv_prefix(prefix: &TransactionPrefix) -> Result<Vec<u8>, Unsupported> {
+    let unsupported = |what: &str| {
+        (StatusCode::NOT_IMPLEMENTED, format!("stand-in daemon serves {what} only"))
+    };
+    let inputs = prefix
+        .inputs
+        .iter()
+        .map(|input| match input {
+            TxInput::Key(k) => {
+                let mut offsets = Vec::new();
+                let relative = fuego_sdk::serialization::absolute_offsets_to_relative(&k.offsets);
+                kv_size(relative.len(), &mut offsets);
+                for o in relative {
+                    offsets.extend_from_slice(&(o as u64).to_le_bytes());
+                }
+                Ok(kv_object(&[
+                    ("type", 10, kv_blob(&[0x02])),
+                    (
+                        "value",
+                        12,
+                        kv_object(&[
+                            ("amount", 5, k.amount.to_le_bytes().to_vec()),
+                            ("key_offsets", 5 | 0x80, offsets),
+                            ("k_image", 10, kv_blob(&k.key_image)),
+                        ]),
+                    ),
+                ]))
+            }
+            TxInput::Base(_) | TxInput::CommitmentSpend(_) => Err(unsupported("key inputs")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let outputs = prefix
+        .outputs
+        .iter()
+        .map(|output| {
+            let key = match &output.target {
+                OutputTarget::Key(key) => key,
+                OutputTarget::Commitment(_) => return Err(unsupported("key outputs")),
+            };
+            Ok(kv_object(&[
+                ("amount", 5, output.amount.to_le_bytes().to_vec()),
+                (
+                    "target",
+                    12,
+                    kv_object(&[
+                        ("type", 10, kv_blob(&[0x02])),
+                        ("data", 12, kv_object(&[("key", 10, kv_blob(key))])),
+                    ]),
+                ),
+            ]))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(kv_object(&[
+        ("version", 8, vec![prefix.version]),
+        ("unlock_time", 5, prefix.unlock_time.to_le_bytes().to_vec()),
+        ("vin", 12 | 0x80, kv_array(&inputs)),
+        ("vout", 12 | 0x80, kv_array(&outputs)),
+        ("extra", 10, kv_blob(&prefix.extra)),
+    ]))
+}
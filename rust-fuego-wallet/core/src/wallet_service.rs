@@ -1,15 +1,21 @@
 use crate::daemon::DaemonClient;
+use crate::wallet_store::{WalletStore, MAX_ROLLBACK_DEPTH};
 
 use fuego_sdk::*;
+use fuego_sdk::alias::{add_alias_info_to_extra, is_valid_alias, AliasInfo, AliasResolver};
 use fuego_sdk::serialization::{add_treasury_fund_extra, HEAT_TERM};
+use fuego_sdk::store::StateKey;
+use fuego_sdk::unsigned_tx::{SignedTransaction, UnsignedInput, UnsignedTransaction};
 use fuego_sdk::transaction_builder::{
     build_commitment_spend_transaction, decompose_change, BuildCommitmentDestination,
     BuildDestination, CommitmentDeposit, DecoyEntry, DEFAULT_DUST_THRESHOLD, MINIMUM_FEE,
 };
+use futures_util::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Default ring size when the caller does not specify one (C++ API default
 /// mixIn is 4).
@@ -25,6 +31,16 @@ const DEPOSIT_MAX_TERM: u32 = 64800;
 /// CryptoNoteConfig.h HEAT_MINT_MIN_HEAT (0.1 HEAT).
 const HEAT_MINT_MIN_HEAT: u64 = 1_000_000;
 
+/// Ring size for a caller's `anonymity` (0 → DEFAULT_MIXIN), capped at
+/// MAX_MIXIN.
+fn requested_mixin(anonymity: u32) -> usize {
+    if anonymity == 0 {
+        DEFAULT_MIXIN
+    } else {
+        (anonymity as usize).min(fuego_sdk::transaction_builder::MAX_MIXIN)
+    }
+}
+
 /// Integer square root (AmmPool.cpp isqrt128).
 fn isqrt128(n: u128) -> u64 {
     if n <= 1 {
@@ -74,14 +90,59 @@ struct PendingTx {
     created_height: u64,
 }
 
+/// An unconfirmed pool transaction that pays or spends the wallet.
+#[derive(Debug, Clone)]
+pub struct PoolTx {
+    pub effect: fuego_sdk::scanner::PoolTxEffect,
+    /// Spends the wallet's outputs but is none of its pending sends: a
+    /// double spend by another copy of the wallet.
+    pub conflict: bool,
+}
+
+/// The daemon's transaction pool as last polled.
+#[derive(Default)]
+struct PoolView {
+    /// Every pool transaction id seen, so each poll returns only changes.
+    known: HashSet<[u8; 32]>,
+    /// The ones touching the wallet, in arrival order.
+    relevant: Vec<PoolTx>,
+}
+
+impl PoolView {
+    fn remove(&mut self, tx_ids: &[[u8; 32]]) {
+        for id in tx_ids {
+            self.known.remove(id);
+        }
+        self.relevant.retain(|tx| !tx_ids.contains(&tx.effect.tx_hash));
+    }
+}
+
+/// Result of importing a full wallet's key images into this one.
+#[derive(Debug, Clone, Default)]
+pub struct KeyImageImportSummary {
+    /// Outputs that received their key image.
+    pub imported: usize,
+    /// Amount of those outputs found already spent.
+    pub spent: u64,
+    /// Amount of those outputs not known to be spent.
+    pub unspent: u64,
+}
+
 pub struct WalletService {
     pub wallet: Arc<Mutex<Wallet>>,
     pub daemon: DaemonClient,
-    db: sled::Db,
+    /// Turns `@alias` destinations into addresses via the daemon.
+    pub aliases: AliasResolver,
+    store: WalletStore,
     testnet: bool,
     /// AFK adaptor secrets, keyed by lock id. In-memory only (like the C++
     /// WalletLegacy m_afkLockSecrets) — never persisted plaintext to sled.
     afk_secrets: Arc<Mutex<HashMap<String, AfkLockSecret>>>,
+    /// Background sync, once `start_sync` ran.
+    sync: Option<SyncService>,
+    pool: Arc<Mutex<PoolView>>,
+    /// Background pool polling, once `start_pool_watch` ran.
+    pool_watch: Option<SyncService>,
 }
 
 /// Background sync runner. Shares the wallet and daemon handles with the
@@ -91,46 +152,175 @@ pub struct WalletService {
 pub struct SyncEngine {
     pub wallet: Arc<Mutex<Wallet>>,
     pub daemon: DaemonClient,
-    db: sled::Db,
+    store: WalletStore,
+    pool: Arc<Mutex<PoolView>>,
 }
 
-const KEY_HEIGHT: &[u8] = b"height";
-const KEY_TOP_HASH: &[u8] = b"top_hash";
-
-fn meta_tree(db: &sled::Db) -> sled::Tree {
-    db.open_tree("meta").expect("open meta tree")
+/// /queryblockslite.bin batches scanned per sync round.
+const SYNC_ROUND_BATCHES: usize = 16;
+/// /queryblockslite.bin batches downloading at once during a round.
+const SYNC_DOWNLOAD_BATCHES: usize = 4;
+/// get_o_indexes requests in flight at once.
+const O_INDEXES_PARALLELISM: usize = 8;
+
+/// Number of most recent block ids sent densely in a locator before the
+/// spacing starts doubling (Blockchain::buildSparseChain in the daemon).
+const LOCATOR_DENSE_IDS: usize = 10;
+
+/// Heights of a sparse block-id locator for a chain whose top is `top`:
+/// the last LOCATOR_DENSE_IDS heights, then exponentially spaced, always
+/// ending at genesis. Highest first, as the daemon expects.
+fn sparse_locator_heights(top: u64) -> Vec<u64> {
+    let mut heights = Vec::new();
+    let mut step = 1u64;
+    let mut height = top;
+    loop {
+        heights.push(height);
+        if height == 0 {
+            break;
+        }
+        if heights.len() >= LOCATOR_DENSE_IDS {
+            step = step.saturating_mul(2);
+        }
+        height = height.saturating_sub(step);
+    }
+    heights
 }
 
 impl WalletService {
     pub fn new(seed: [u8; 32], daemon_url: &str, wallet_dir: PathBuf, testnet: bool) -> Result<Self> {
-        let wallet = Arc::new(Mutex::new(Wallet::from_seed(seed)?));
+        Self::with_wallet(Wallet::from_seed(seed)?, daemon_url, wallet_dir, testnet, None)
+    }
+
+    /// A watch-only service for the wallet with `spend_public` / `view_secret`:
+    /// syncs balance and history; every spend path returns an error.
+    pub fn new_view_only(
+        spend_public: [u8; 32],
+        view_secret: [u8; 32],
+        daemon_url: &str,
+        wallet_dir: PathBuf,
+        testnet: bool,
+    ) -> Result<Self> {
+        Self::with_wallet(
+            Wallet::view_only(spend_public, view_secret)?,
+            daemon_url,
+            wallet_dir,
+            testnet,
+            None,
+        )
+    }
+
+    /// A service for `wallet` whose scan state lives in `wallet_dir`.
+    /// With `state_key` the state database is encrypted at rest (existing
+    /// plaintext state is encrypted on open); an encrypted database can't
+    /// be opened without it.
+    pub fn with_wallet(
+        mut wallet: Wallet,
+        daemon_url: &str,
+        wallet_dir: PathBuf,
+        testnet: bool,
+        state_key: Option<&StateKey>,
+    ) -> Result<Self> {
         let daemon = DaemonClient::new(daemon_url);
         let db = sled::open(wallet_dir.join("wallet_state.sled"))
             .map_err(|e| SdkError::Storage(format!("sled open: {e}")))?;
+        let store = WalletStore::open(&db, state_key).map_err(SdkError::Storage)?;
+        wallet.set_subaddress_count(store.subaddress_count());
+        let wallet = Arc::new(Mutex::new(wallet));
 
         let service = Self {
             wallet,
+            aliases: AliasResolver::with_transport(Arc::new(daemon.clone())),
             daemon,
-            db,
+            store,
             testnet,
             afk_secrets: Arc::new(Mutex::new(HashMap::new())),
+            sync: None,
+            pool: Arc::new(Mutex::new(PoolView::default())),
+            pool_watch: None,
         };
-        service.sync_engine().load_state();
+        service.sync_engine().load_state().map_err(SdkError::Storage)?;
         Ok(service)
     }
 
+    /// Talk to several fuegod nodes, `nodes[0]` first, falling over to the
+    /// next one whenever the active node stops answering.
+    pub fn with_daemon_nodes(mut self, nodes: &[String]) -> Self {
+        if !nodes.is_empty() {
+            self.daemon = DaemonClient::with_nodes(nodes);
+            self.aliases = AliasResolver::with_transport(Arc::new(self.daemon.clone()));
+        }
+        self
+    }
+
+    /// Reach the daemon (and alias lookups) through `http`.
+    pub fn with_http(mut self, http: &HttpConfig) -> std::result::Result<Self, String> {
+        self.daemon = self.daemon.with_http(http)?;
+        self.aliases = AliasResolver::with_transport(Arc::new(self.daemon.clone()));
+        Ok(self)
+    }
+
+    /// Use `daemon` for every daemon call, e.g. a recording or replaying
+    /// client.
+    pub fn with_daemon(mut self, daemon: DaemonClient) -> Self {
+        self.aliases = AliasResolver::with_transport(Arc::new(daemon.clone()));
+        self.daemon = daemon;
+        self
+    }
+
     /// The wallet's primary address for the configured network.
     pub fn primary_address_string(&self) -> String {
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        self.subaddress_string(0).unwrap_or_default()
+    }
+
+    /// Address of subaddress `index` (0 = primary) for the configured
+    /// network, if issued.
+    fn subaddress_string(&self, index: u32) -> Option<String> {
+        let wallet = self.wallet.lock().unwrap();
+        let spend_public = wallet.subaddress_spend_public(index)?;
+        let view_public = wallet.wallet_keys().view_public;
         let prefix = if self.testnet {
             fuego_crypto::TESTNET_ADDRESS_BASE58_PREFIX
         } else {
             fuego_crypto::ADDRESS_BASE58_PREFIX
         };
-        fuego_crypto::make_address_with_prefix(&keys.spend_public, &keys.view_public, prefix).0
+        Some(fuego_crypto::make_address_with_prefix(&spend_public, &view_public, prefix).0)
+    }
+
+    pub fn is_view_only(&self) -> bool {
+        self.wallet.lock().unwrap().is_view_only()
+    }
+
+    /// Wallet keys for a spend path; refuses on a view-only wallet, whose
+    /// spend secret is unknown.
+    fn spend_keys(&self) -> std::result::Result<fuego_sdk::scanner::WalletKeys, String> {
+        let wallet = self.wallet.lock().unwrap();
+        if wallet.is_view_only() {
+            return Err("view-only wallet cannot spend".into());
+        }
+        Ok(wallet.wallet_keys())
+    }
+
+    /// Builder inputs for `utxos`, their one-time secrets re-derived.
+    fn spendable_outputs(
+        &self,
+        utxos: &[fuego_sdk::scanner::UtxoEntry],
+    ) -> std::result::Result<Vec<fuego_sdk::transaction_builder::SpendableOutput>, String> {
+        self.wallet.lock().unwrap().spendable_outputs(utxos).map_err(|e| e.to_string())
     }
 
-    // ------------------------------------------------------------ state
+    /// Builder spends for `entries`, key scalars re-derived, no interest.
+    fn commitment_deposits(
+        &self,
+        entries: &[fuego_sdk::scanner::CommitmentEntry],
+    ) -> std::result::Result<Vec<CommitmentDeposit>, String> {
+        let wallet = self.wallet.lock().unwrap();
+        entries
+            .iter()
+            .map(|d| wallet.commitment_deposit(d, 0))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| e.to_string())
+    }
 
     // ------------------------------------------------------------ sync
 
@@ -139,196 +329,395 @@ impl WalletService {
     pub async fn sync_once(&self) -> std::result::Result<u64, String> {
         self.sync_engine().sync_once().await
     }
-}
 
-impl SyncEngine {
-    fn load_state(&self) {
-        let db = &self.db;
-        let wallet = self.wallet.lock().unwrap();
+    /// Run the sync engine in the background, polling every `interval`
+    /// once caught up. Replaces a previously started sync.
+    pub fn start_sync(&mut self, interval: Duration) -> SyncHandle {
+        let service = SyncService::start(Arc::new(self.sync_engine()), interval);
+        let handle = service.handle();
+        self.sync = Some(service);
+        handle
+    }
 
-        if let Ok(Some(bytes)) = db.get(b"utxos") {
-            if let Ok(utxos) = bincode::deserialize::<Vec<fuego_sdk::scanner::UtxoEntry>>(&bytes) {
-                let snapshot = fuego_sdk::scanner::ScannerStateSnapshot {
-                    height: db
-                        .get(KEY_HEIGHT)
-                        .ok()
-                        .flatten()
-                        .and_then(|b| bincode::deserialize::<u64>(&b).ok())
-                        .unwrap_or(0),
-                    utxos,
-                    commitments: db
-                        .get(b"commitments")
-                        .ok()
-                        .flatten()
-                        .and_then(|b| bincode::deserialize::<Vec<fuego_sdk::scanner::CommitmentEntry>>(&b).ok())
-                        .unwrap_or_default(),
-                    spent_images: db
-                        .get(b"spent")
-                        .ok()
-                        .flatten()
-                        .and_then(|b| bincode::deserialize::<Vec<[u8; 32]>>(&b).ok())
-                        .unwrap_or_default(),
-                    history: db
-                        .get(b"history")
-                        .ok()
-                        .flatten()
-                        .and_then(|b| bincode::deserialize::<Vec<fuego_sdk::scanner::HistoryEntry>>(&b).ok())
-                        .unwrap_or_default(),
-                };
-                wallet.restore_state(&snapshot);
-            }
+    /// Pause/resume/stop handle of the background sync, if started.
+    pub fn sync_handle(&self) -> Option<SyncHandle> {
+        self.sync.as_ref().map(SyncService::handle)
+    }
+
+    // ------------------------------------------------------------ pool
+
+    /// Poll the daemon's transaction pool once. Returns the number of pool
+    /// transactions added or dropped since the last poll.
+    pub async fn refresh_pool(&self) -> std::result::Result<u64, String> {
+        self.sync_engine().refresh_pool().await
+    }
+
+    /// Poll the pool in the background every `interval`, so incoming
+    /// payments show as pending within seconds of broadcast. Replaces a
+    /// previously started watch.
+    pub fn start_pool_watch(&mut self, interval: Duration) -> SyncHandle {
+        let service = SyncService::start(Arc::new(PoolWatcher(self.sync_engine())), interval);
+        let handle = service.handle();
+        self.pool_watch = Some(service);
+        handle
+    }
+
+    /// Unconfirmed transactions paying or spending the wallet.
+    pub fn pool_transactions(&self) -> Vec<PoolTx> {
+        self.pool.lock().unwrap().relevant.clone()
+    }
+
+    /// Wallet events from here on: payments, confirmations, spends,
+    /// unlocks, send outcomes and matured deposits.
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<WalletEvent> {
+        self.wallet.lock().unwrap().subscribe()
+    }
+
+    // ------------------------------------------------------------ lock
+
+    pub fn is_encrypted(&self) -> bool {
+        self.store.is_encrypted()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.store.is_locked()
+    }
+
+    /// Drop the state key from memory. Until `unlock`, nothing is written
+    /// to disk: sync and pool rounds fail, and so does every send, since a
+    /// send is persisted before it is broadcast. Balances already loaded
+    /// stay readable.
+    pub fn lock(&self) -> std::result::Result<(), String> {
+        if !self.store.is_encrypted() {
+            return Err("wallet state is not encrypted".into());
         }
+        self.store.lock();
+        Ok(())
+    }
 
-        // Re-reserve pending sends (persist-before-broadcast: never release
-        // these automatically).
-        if let Ok(Some(bytes)) = db.get(b"pending") {
-            if let Ok(pending) = bincode::deserialize::<Vec<PendingTx>>(&bytes) {
-                let images: Vec<[u8; 32]> = pending
-                    .iter()
-                    .flat_map(|p| p.key_images.clone())
-                    .collect();
-                wallet.reserve_pending(&images);
-            }
+    pub fn unlock(&self, state_key: &StateKey) -> std::result::Result<(), String> {
+        self.store.unlock(state_key)?;
+        if let Some(sync) = self.sync_handle() {
+            sync.sync_now();
         }
+        Ok(())
     }
+}
 
-    fn persist_state(&self) {
+impl SyncEngine {
+    fn load_state(&self) -> std::result::Result<(), String> {
+        let state = self.store.load()?;
         let wallet = self.wallet.lock().unwrap();
-        let snapshot = wallet.snapshot_state();
-        let db = &self.db;
-        let _ = bincode::serialize(&snapshot.height).ok().and_then(|b| db.insert(KEY_HEIGHT, b).ok());
-        let _ = bincode::serialize(&snapshot.utxos).ok().and_then(|b| db.insert(b"utxos", b).ok());
-        let _ = bincode::serialize(&snapshot.commitments).ok().and_then(|b| db.insert(b"commitments", b).ok());
-        let _ = bincode::serialize(&snapshot.spent_images).ok().and_then(|b| db.insert(b"spent", b).ok());
-        let _ = bincode::serialize(&snapshot.history).ok().and_then(|b| db.insert(b"history", b).ok());
-        if let Ok(Some(bytes)) = db.get(KEY_TOP_HASH) {
-            let _ = db.flush();
-            let _ = bytes;
-        } else {
-            let _ = db.flush();
+        wallet.restore_state(&state);
+
+        // Re-reserve pending sends (persist-before-broadcast: never release
+        // these automatically).
+        for p in &self.pending() {
+            wallet.track_send(&p.tx_hash, &p.key_images);
         }
+        Ok(())
     }
 
-    fn top_hash(&self) -> Option<[u8; 32]> {
-        self.db
-            .get(KEY_TOP_HASH)
-            .ok()
-            .flatten()
-            .and_then(|b| bincode::deserialize::<[u8; 32]>(&b).ok())
+    /// Sparse locator over the recorded block ids, highest first. Empty when
+    /// nothing has been scanned yet (the daemon then starts at genesis).
+    fn locator(&self, top: u64) -> Vec<[u8; 32]> {
+        sparse_locator_heights(top)
+            .into_iter()
+            .filter_map(|h| self.store.block_id(h))
+            .collect()
     }
 
-    fn set_top_hash(&self, hash: &[u8; 32]) {
-        let _ = bincode::serialize(hash).ok().and_then(|b| self.db.insert(KEY_TOP_HASH, b).ok());
+    /// Roll the wallet back to just below `height` after a reorganization
+    /// orphaned the block we scanned there: drop scan results from the
+    /// orphaned blocks and forget their ids so the new branch is rescanned.
+    fn detach(&self, height: u64) -> std::result::Result<(), String> {
+        log::warn!("chain reorganization: rolling back blocks from height {}", height);
+        self.store.detach(height)?;
+        let wallet = self.wallet.lock().unwrap();
+        wallet.detach(height);
+        // Sends still waiting in the mempool keep their inputs reserved.
+        for p in self.pending() {
+            wallet.track_send(&p.tx_hash, &p.key_images);
+        }
+        Ok(())
     }
 
     fn pending(&self) -> Vec<PendingTx> {
-        self.db
-            .get(b"pending")
+        self.store
+            .get_blob(b"pending")
             .ok()
             .flatten()
             .and_then(|b| bincode::deserialize::<Vec<PendingTx>>(&b).ok())
             .unwrap_or_default()
     }
 
-    fn store_pending(&self, list: &[PendingTx]) {
-        let _ = bincode::serialize(list).ok().and_then(|b| self.db.insert(b"pending", b).ok());
-        let _ = self.db.flush();
+    fn store_pending(&self, list: &[PendingTx]) -> std::result::Result<(), String> {
+        let bytes = bincode::serialize(list).map_err(|e| format!("encode pending: {e}"))?;
+        self.store.put_blob(b"pending", &bytes)?;
+        self.store.flush();
+        Ok(())
     }
 
+    /// Poll /get_pool_changes_lite.bin and preview the new pool
+    /// transactions against the wallet. Returns the number of pool
+    /// transactions added or dropped.
+    pub async fn refresh_pool(&self) -> std::result::Result<u64, String> {
+        if self.store.is_locked() {
+            return Err("wallet state is locked".into());
+        }
+        let tail = self.store.top_hash().unwrap_or([0u8; 32]);
+        let known: Vec<[u8; 32]> = self.pool.lock().unwrap().known.iter().copied().collect();
+        let changes = self.daemon.get_pool_changes_lite(&tail, &known).await?;
+        if !changes.is_tail_block_actual {
+            log::debug!("pool polled ahead of the scanned chain");
+        }
+
+        let our_sends: Vec<[u8; 32]> = self.pending().iter().map(|p| p.tx_hash).collect();
+        let added: Vec<PoolTx> = {
+            let wallet = self.wallet.lock().unwrap();
+            changes
+                .added_txs
+                .iter()
+                .map(|txi| wallet.preview_tx_prefix(&txi.tx_hash, &txi.parsed))
+                .filter(|effect| effect.received > 0 || effect.spent > 0)
+                .map(|effect| PoolTx {
+                    conflict: effect.spent > 0 && !our_sends.contains(&effect.tx_hash),
+                    effect,
+                })
+                .collect()
+        };
+        for tx in added.iter().filter(|tx| tx.conflict) {
+            log::warn!(
+                "pool transaction {} spends {} of this wallet's outputs but is not one of its sends",
+                hex::encode(tx.effect.tx_hash),
+                tx.effect.spent_key_images.len()
+            );
+        }
+
+        let mut pool = self.pool.lock().unwrap();
+        pool.remove(&changes.deleted_tx_ids);
+        pool.known.extend(changes.added_txs.iter().map(|txi| txi.tx_hash));
+        pool.relevant.extend(added);
+        Ok((changes.added_txs.len() + changes.deleted_tx_ids.len()) as u64)
+    }
 
     /// One incremental sync round over /queryblockslite.bin. Returns the
     /// number of blocks scanned.
     pub async fn sync_once(&self) -> std::result::Result<u64, String> {
-        let info = self.daemon.get_info().await?;
+        if self.store.is_locked() {
+            return Err("wallet state is locked".into());
+        }
+        self.sync_to(self.daemon.get_info().await?.height).await
+    }
+
+    /// Scan towards daemon height `target`, up to SYNC_ROUND_BATCHES
+    /// batches. The first batch shows how many blocks the daemon answers
+    /// with; the later ones are requested by height, SYNC_DOWNLOAD_BATCHES
+    /// at a time, and scanned in order as they arrive. A batch that no
+    /// longer starts on the scanned chain (a shorter answer, or a reorg
+    /// since it was requested) ends the round.
+    async fn sync_to(&self, target: u64) -> std::result::Result<u64, String> {
         let our_height = self.wallet.lock().unwrap().height();
 
-        if info.height <= our_height {
+        if target <= our_height {
             return Ok(0);
         }
 
-        let locator: Vec<[u8; 32]> = match self.top_hash() {
-            Some(h) if our_height > 0 => vec![h],
-            _ => Vec::new(),
-        };
+        let first = self.daemon.query_blocks_lite(&self.locator(our_height), 0).await?;
+        let mut scanned = self.apply_batch(&first).await?;
+
+        // Each answer starts with the block its locator named, so a batch
+        // of n items advances n - 1 blocks.
+        let step = (first.items.len() as u64).saturating_sub(1);
+        if scanned > 0 && step > 0 {
+            let tip = first.start_height + step;
+            let starts = (0..SYNC_ROUND_BATCHES as u64 - 1)
+                .map(|k| tip + k * step)
+                .take_while(|&height| height + 1 < target);
+            let mut batches = stream::iter(starts)
+                .map(|height| self.fetch_batch_from(height, our_height))
+                .buffered(SYNC_DOWNLOAD_BATCHES);
+            while let Some(batch) = batches.next().await {
+                let batch = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        log::warn!("downloading block batch failed: {}", e);
+                        break;
+                    }
+                };
+                let connects = batch
+                    .items
+                    .first()
+                    .is_some_and(|item| self.store.block_id(batch.start_height) == Some(item.block_id));
+                if !connects {
+                    break;
+                }
+                let applied = self.apply_batch(&batch).await?;
+                scanned += applied;
+                if applied == 0 {
+                    break;
+                }
+            }
+        }
 
-        let resp = self.daemon.query_blocks_lite(&locator, 0).await?;
+        if scanned > 0 {
+            // Reconcile deep-confirmed utxos against the daemon's key image
+            // index (covers outputs spent before a seed restore).
+            self.reconcile_spent(our_height).await;
+            self.prune_spent()?;
+            self.store.flush();
+        }
+
+        Ok(scanned)
+    }
+
+    /// Forget spend records older than MAX_ROLLBACK_DEPTH blocks.
+    fn prune_spent(&self) -> std::result::Result<(), String> {
+        let height = self.wallet.lock().unwrap().height();
+        if let Some(below) = height.checked_sub(MAX_ROLLBACK_DEPTH) {
+            self.store.prune_spent(below)?;
+            self.wallet.lock().unwrap().prune_spent(below);
+        }
+        Ok(())
+    }
+
+    /// /queryblockslite.bin batch starting at the daemon's block at
+    /// `height`, falling back to the locator of `our_height` if that block
+    /// left the main chain meanwhile.
+    async fn fetch_batch_from(
+        &self,
+        height: u64,
+        our_height: u64,
+    ) -> std::result::Result<fuego_sdk::serialization::QueryBlocksLiteResponse, String> {
+        let id: [u8; 32] = hex::decode(self.daemon.get_block_hash(height).await?)
+            .ok()
+            .and_then(|id| id.try_into().ok())
+            .ok_or_else(|| format!("bad block hash at height {}", height))?;
+        let mut locator = vec![id];
+        locator.extend(self.locator(our_height));
+        self.daemon.query_blocks_lite(&locator, 0).await
+    }
+
+    /// Scan and record one /queryblockslite.bin batch in height order.
+    /// Returns the number of blocks applied.
+    async fn apply_batch(
+        &self,
+        resp: &fuego_sdk::serialization::QueryBlocksLiteResponse,
+    ) -> std::result::Result<u64, String> {
         let mut scanned = 0u64;
 
         for (k, item) in resp.items.iter().enumerate() {
             let block_height = resp.start_height + k as u64;
 
+            // The daemon answers from the last locator entry still on its
+            // main chain. Blocks we already hold are skipped; a different id
+            // at a height we scanned marks the fork point.
+            match self.store.block_id(block_height) {
+                Some(id) if id == item.block_id => continue,
+                Some(_) => self.detach(block_height)?,
+                None if block_height <= self.wallet.lock().unwrap().height()
+                    && self.store.top_hash().is_some() =>
+                {
+                    self.detach(block_height)?
+                }
+                None => {}
+            }
+
+            let mut touched = false;
+            let mut received_txs = Vec::new();
             for txi in &item.tx_prefixes {
                 let prefix = &txi.parsed;
-                let received = {
+                let (received, spent) = {
                     let wallet = self.wallet.lock().unwrap();
                     wallet
-                        .scan_tx_prefix(&txi.tx_hash, &prefix, block_height)
+                        .scan_tx_prefix(&txi.tx_hash, prefix, block_height)
                         .map_err(|e| format!("scan: {e}"))?
-                        .0
                 };
-
+                touched |= received > 0 || spent > 0;
                 if received > 0 {
-                    match self.daemon.get_o_indexes(&txi.tx_hash).await {
-                        Ok(indices) => {
-                            let wallet = self.wallet.lock().unwrap();
-                            wallet.attach_global_indices(&txi.tx_hash, &indices);
-                        }
-                        Err(e) => {
-                            log::warn!("get_o_indexes failed for {}: {}", hex::encode(txi.tx_hash), e);
-                        }
+                    received_txs.push(txi.tx_hash);
+                }
+            }
+
+            // Global indices of received outputs, fetched concurrently.
+            let lookups: Vec<_> = stream::iter(received_txs)
+                .map(|tx_hash| async move { (tx_hash, self.daemon.get_o_indexes(&tx_hash).await) })
+                .buffered(O_INDEXES_PARALLELISM)
+                .collect()
+                .await;
+            for (tx_hash, lookup) in lookups {
+                match lookup {
+                    Ok(indices) => {
+                        let wallet = self.wallet.lock().unwrap();
+                        wallet.attach_global_indices(&tx_hash, &indices);
+                    }
+                    Err(e) => {
+                        log::warn!("get_o_indexes failed for {}: {}", hex::encode(tx_hash), e);
                     }
                 }
             }
 
             // Remove pending sends that confirmed in this block.
             self.confirm_pending(&item.tx_prefixes);
+            let mined: Vec<[u8; 32]> = item.tx_prefixes.iter().map(|txi| txi.tx_hash).collect();
+            self.pool.lock().unwrap().remove(&mined);
 
-            let wallet = self.wallet.lock().unwrap();
-            wallet.set_height(block_height);
-            drop(wallet);
-            self.set_top_hash(&item.block_id);
+            let delta = {
+                let wallet = self.wallet.lock().unwrap();
+                wallet.set_height(block_height);
+                if touched {
+                    wallet.block_delta(block_height)
+                } else {
+                    Default::default()
+                }
+            };
+            self.store.apply_block(block_height, &item.block_id, &delta)?;
             scanned += 1;
         }
 
-        if scanned > 0 {
-            // Reconcile deep-confirmed utxos against the daemon's key image
-            // index (covers outputs spent before a seed restore).
-            self.reconcile_spent(our_height).await;
-            self.persist_state();
-        }
-
         Ok(scanned)
     }
 
-    /// Background sync loop; never returns.
-    pub async fn sync_loop(&self) {
-        loop {
-            match self.sync_once().await {
-                Ok(0) => {
-                    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
-                }
-                Ok(n) => {
-                    log::info!("Synced {} blocks", n);
-                }
-                Err(e) => {
-                    log::error!("Sync error: {}", e);
-                    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
-                }
-            }
-        }
-    }
-
-    /// Remove pending entries whose transaction is now in a scanned block.
+    /// Remove pending entries whose transaction is now in a scanned block,
+    /// or whose inputs another transaction in it spent (the scanner
+    /// reports those as failed sends).
     fn confirm_pending(&self, prefixes: &[fuego_sdk::serialization::TxPrefixInfo]) {
         let mut pending = self.pending();
         if pending.is_empty() {
             return;
         }
-        let confirmed: Vec<[u8; 32]> = prefixes.iter().map(|p| p.tx_hash).collect();
+        let mut failed = Vec::new();
         let before = pending.len();
-        pending.retain(|p| !confirmed.contains(&p.tx_hash));
+        pending.retain(|p| {
+            if prefixes.iter().any(|txi| txi.tx_hash == p.tx_hash) {
+                return false;
+            }
+            let double_spend = prefixes.iter().find(|txi| {
+                txi.parsed
+                    .inputs
+                    .iter()
+                    .filter_map(|input| input.key_image())
+                    .any(|image| p.key_images.contains(image))
+            });
+            match double_spend {
+                Some(txi) => {
+                    failed.push((p.tx_hash, txi.tx_hash));
+                    false
+                }
+                None => true,
+            }
+        });
         if pending.len() != before {
-            self.store_pending(&pending);
+            if let Err(e) = self.store_pending(&pending) {
+                log::warn!("could not update pending sends: {}", e);
+            }
+        }
+        for (tx_hash, spent_by) in failed {
+            log::warn!(
+                "pending send {} failed: its inputs were spent by {}",
+                hex::encode(tx_hash),
+                hex::encode(spent_by)
+            );
         }
     }
 
@@ -337,7 +726,8 @@ impl SyncEngine {
     /// tracking remains in effect.
     async fn reconcile_spent(&self, _our_height: u64) {
         let utxos = self.wallet.lock().unwrap().utxos();
-        for utxo in utxos {
+        // View-only outputs have no key image until one is imported.
+        for utxo in utxos.into_iter().filter(|u| u.key_image != [0u8; 32]) {
             match self.daemon.is_key_image_spent(&utxo.key_image).await {
                 Ok(true) => {
                     log::info!(
@@ -347,6 +737,9 @@ impl SyncEngine {
                     );
                     let wallet = self.wallet.lock().unwrap();
                     wallet.reserve_pending(&[utxo.key_image]);
+                    if let Err(e) = self.store.mark_spent(&utxo.key_image) {
+                        log::warn!("could not record spent key image: {}", e);
+                    }
                 }
                 Ok(false) => {}
                 Err(_) => return, // endpoint missing: stop, rely on scans
@@ -355,13 +748,56 @@ impl SyncEngine {
     }
 }
 
+#[async_trait::async_trait]
+impl SyncTarget for SyncEngine {
+    async fn sync_round(&self) -> Result<SyncRound> {
+        let round = async {
+            let target = self.daemon.get_info().await?.height;
+            let synced = self.sync_to(target).await?;
+            // The daemon reports a block count; the wallet height, like the
+            // node's, is the index of the last scanned block.
+            let height = self.wallet.lock().unwrap().height();
+            Ok::<_, String>(SyncRound { synced, height, target: target.saturating_sub(1) })
+        };
+        match round.await {
+            Ok(round) => {
+                if round.synced > 0 {
+                    log::info!("Synced {} blocks", round.synced);
+                }
+                Ok(round)
+            }
+            Err(e) => {
+                log::error!("Sync error: {}", e);
+                Err(SdkError::Sync(e))
+            }
+        }
+    }
+}
+
+/// Pool polling as a sync target: each round is one `refresh_pool`, so
+/// the service always waits the full interval between polls.
+struct PoolWatcher(SyncEngine);
+
+#[async_trait::async_trait]
+impl SyncTarget for PoolWatcher {
+    async fn sync_round(&self) -> Result<SyncRound> {
+        let changed = self.0.refresh_pool().await.map_err(|e| {
+            log::warn!("Pool poll error: {}", e);
+            SdkError::Sync(e)
+        })?;
+        let height = self.0.wallet.lock().unwrap().height();
+        Ok(SyncRound { synced: changed, height, target: height })
+    }
+}
+
 impl WalletService {
     /// The background sync engine, detached from the service mutex.
     pub fn sync_engine(&self) -> SyncEngine {
         SyncEngine {
             wallet: self.wallet.clone(),
             daemon: self.daemon.clone(),
-            db: self.db.clone(),
+            store: self.store.clone(),
+            pool: self.pool.clone(),
         }
     }
 
@@ -375,12 +811,12 @@ impl WalletService {
         fee: u64,
         anonymity: u32,
     ) -> std::result::Result<String, String> {
+        if self.is_view_only() {
+            return Err("view-only wallet cannot spend".into());
+        }
         let fee = fee.max(MINIMUM_FEE);
-        let mixin = if anonymity == 0 {
-            DEFAULT_MIXIN
-        } else {
-            (anonymity as usize).min(fuego_sdk::transaction_builder::MAX_MIXIN)
-        };
+        let mixin = requested_mixin(anonymity);
+        let dests = self.resolve_destinations(destinations).await?;
 
         let total: u64 = destinations.iter().map(|(_, a)| *a).sum::<u64>() + fee;
 
@@ -391,7 +827,37 @@ impl WalletService {
                 .map_err(|e| format!("coin selection: {e}"))?
         };
 
-        // Fetch decoys per input amount.
+        let decoys = self.fetch_decoys(&selected, mixin).await?;
+
+        let built = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet
+                .build_with_selection(&selected, &dests, fee, mixin, &decoys, &mut rand::thread_rng())
+                .map_err(|e| format!("build: {e}"))?
+        };
+
+        let key_images: Vec<[u8; 32]> = selected.iter().map(|u| u.key_image).collect();
+        self.broadcast_built(built, key_images).await
+    }
+
+    /// Destinations with any `@alias` resolved to its registered address.
+    async fn resolve_destinations(
+        &self,
+        destinations: &[(String, u64)],
+    ) -> std::result::Result<Vec<(fuego_sdk::Address, u64)>, String> {
+        self.aliases
+            .resolve_destinations(destinations)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Fetch `mixin` decoys per selected output from /getrandom_outs.bin,
+    /// never using the output itself as a decoy.
+    async fn fetch_decoys(
+        &self,
+        selected: &[fuego_sdk::scanner::UtxoEntry],
+        mixin: usize,
+    ) -> std::result::Result<Vec<Vec<DecoyEntry>>, String> {
         let amounts: Vec<u64> = selected.iter().map(|u| u.amount).collect();
         let groups = self.daemon.get_random_outs(&amounts, (mixin + 1) as u64).await?;
 
@@ -424,21 +890,7 @@ impl WalletService {
             }
             decoys.push(entries);
         }
-
-        let dests: Vec<(fuego_sdk::Address, u64)> = destinations
-            .iter()
-            .map(|(addr, amount)| (fuego_sdk::Address(addr.clone()), *amount))
-            .collect();
-
-        let built = {
-            let wallet = self.wallet.lock().unwrap();
-            wallet
-                .build_with_selection(&selected, &dests, fee, mixin, &decoys, &mut rand::thread_rng())
-                .map_err(|e| format!("build: {e}"))?
-        };
-
-        let key_images: Vec<[u8; 32]> = selected.iter().map(|u| u.key_image).collect();
-        self.broadcast_built(built, key_images).await
+        Ok(decoys)
     }
 
     /// Persist-before-broadcast + reserve + submit, shared by all send paths.
@@ -451,10 +903,8 @@ impl WalletService {
     ) -> std::result::Result<String, String> {
         let tx_hash_hex = hex::encode(built.tx_hash);
         let serialized_hex = hex::encode(&built.serialized);
-        {
-            let key = format!("txs:{}", tx_hash_hex);
-            let _ = self.db.insert(key.as_bytes(), serialized_hex.as_bytes());
-        }
+        let key = format!("txs:{}", tx_hash_hex);
+        self.store.put_blob(key.as_bytes(), serialized_hex.as_bytes())?;
 
         let mut pending = self.sync_engine().pending();
         pending.push(PendingTx {
@@ -463,10 +913,10 @@ impl WalletService {
             serialized_hex: serialized_hex.clone(),
             created_height: self.wallet.lock().unwrap().height(),
         });
-        self.sync_engine().store_pending(&pending);
+        self.sync_engine().store_pending(&pending)?;
         {
             let wallet = self.wallet.lock().unwrap();
-            wallet.reserve_pending(&key_images);
+            wallet.track_send(&built.tx_hash, &key_images);
         }
 
         let status = self.daemon.send_raw_tx(&serialized_hex).await?;
@@ -477,8 +927,13 @@ impl WalletService {
             "Failed" => {
                 let mut pending = self.sync_engine().pending();
                 pending.retain(|p| p.tx_hash != built.tx_hash);
-                self.sync_engine().store_pending(&pending);
-                return Err(format!("daemon rejected transaction: {}", status));
+                let _ = self.sync_engine().store_pending(&pending);
+                let reason = format!("daemon rejected transaction: {}", status);
+                self.wallet.lock().unwrap().events().publish(WalletEvent::SendFailed {
+                    tx_hash: built.tx_hash,
+                    reason: reason.clone(),
+                });
+                return Err(reason);
             }
             other => {
                 log::warn!(
@@ -574,7 +1029,7 @@ impl WalletService {
         };
 
         // Adaptor pre-signature material.
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let zero_hash = [0u8; 32];
         let (secret, adaptor_point, pre_sig) = fuego_crypto::ring::generate_afk_lock_data(
             &zero_hash,
@@ -635,7 +1090,7 @@ impl WalletService {
         }
 
         let fee = MINIMUM_FEE;
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let selected = {
             let wallet = self.wallet.lock().unwrap();
             wallet
@@ -675,8 +1130,7 @@ impl WalletService {
             decoys.push(entries);
         }
 
-        let inputs: Vec<fuego_sdk::transaction_builder::SpendableOutput> =
-            selected.iter().map(|u| u.into()).collect();
+        let inputs = self.spendable_outputs(&selected)?;
         let built = fuego_sdk::transaction_builder::build_mint_transaction(
             &inputs,
             &decoys,
@@ -733,7 +1187,7 @@ impl WalletService {
                 ));
             }
 
-            let keys = self.wallet.lock().unwrap().wallet_keys();
+            let keys = self.spend_keys()?;
             let selected = {
                 let wallet = self.wallet.lock().unwrap();
                 wallet
@@ -770,8 +1224,7 @@ impl WalletService {
                 decoys.push(entries);
             }
 
-            let inputs: Vec<fuego_sdk::transaction_builder::SpendableOutput> =
-                selected.iter().map(|u| u.into()).collect();
+            let inputs = self.spendable_outputs(&selected)?;
             let built = fuego_sdk::transaction_builder::build_swap_xfg_to_heat_transaction(
                 &inputs,
                 &decoys,
@@ -803,7 +1256,7 @@ impl WalletService {
             ));
         }
 
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let heat: Vec<fuego_sdk::scanner::CommitmentEntry> = self
             .wallet
             .lock()
@@ -831,17 +1284,7 @@ impl WalletService {
         for deposit in &selected {
             decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
-        let spends: Vec<CommitmentDeposit> = selected
-            .iter()
-            .map(|d| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: 0,
-            })
-            .collect();
+        let spends = self.commitment_deposits(&selected)?;
 
         let built = fuego_sdk::transaction_builder::build_swap_heat_to_xfg_transaction(
             &spends,
@@ -895,7 +1338,7 @@ impl WalletService {
 
         let fee = MINIMUM_FEE;
         let mixin = DEFAULT_MIXIN;
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
 
         // XFG side: select key inputs for amount_xfg + fee.
         let selected_xfg = {
@@ -964,19 +1407,8 @@ impl WalletService {
             heat_decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
 
-        let xfg_inputs: Vec<fuego_sdk::transaction_builder::SpendableOutput> =
-            selected_xfg.iter().map(|u| u.into()).collect();
-        let heat_deposits: Vec<CommitmentDeposit> = selected_heat
-            .iter()
-            .map(|d| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: 0,
-            })
-            .collect();
+        let xfg_inputs = self.spendable_outputs(&selected_xfg)?;
+        let heat_deposits = self.commitment_deposits(&selected_heat)?;
 
         let built = fuego_sdk::transaction_builder::build_lp_add_transaction(
             &xfg_inputs,
@@ -1028,7 +1460,7 @@ impl WalletService {
 
         let fee = MINIMUM_FEE;
         let mixin = DEFAULT_MIXIN;
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
 
         let lp: Vec<fuego_sdk::scanner::CommitmentEntry> = self
             .wallet
@@ -1068,17 +1500,7 @@ impl WalletService {
         for deposit in &selected {
             decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
-        let spends: Vec<CommitmentDeposit> = selected
-            .iter()
-            .map(|d| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: 0,
-            })
-            .collect();
+        let spends = self.commitment_deposits(&selected)?;
 
         let built = fuego_sdk::transaction_builder::build_lp_remove_transaction(
             &spends,
@@ -1121,7 +1543,7 @@ impl WalletService {
 
         let fee = MINIMUM_FEE;
         let mixin = DEFAULT_MIXIN;
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
 
         let selected = {
             let wallet = self.wallet.lock().unwrap();
@@ -1175,8 +1597,7 @@ impl WalletService {
             decoys.push(entries);
         }
 
-        let inputs: Vec<fuego_sdk::transaction_builder::SpendableOutput> =
-            selected.iter().map(|u| u.into()).collect();
+        let inputs = self.spendable_outputs(&selected)?;
         let built = fuego_sdk::transaction_builder::build_place_order_transaction(
             &inputs,
             &decoys,
@@ -1256,7 +1677,7 @@ impl WalletService {
             decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
 
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let heat_change = found - amount - banking_fee - fee;
 
         let mut commitment_dests = vec![BuildCommitmentDestination {
@@ -1275,17 +1696,7 @@ impl WalletService {
         let mut extra_extra = Vec::new();
         add_treasury_fund_extra(&mut extra_extra, 1 /* HEAT */, banking_fee);
 
-        let spends: Vec<CommitmentDeposit> = selected
-            .iter()
-            .map(|d| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: 0,
-            })
-            .collect();
+        let spends = self.commitment_deposits(&selected)?;
         let built = build_commitment_spend_transaction(
             &spends,
             &decoys,
@@ -1386,7 +1797,7 @@ impl WalletService {
             decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
 
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let (chunks, dust) = decompose_change(payout, DEFAULT_DUST_THRESHOLD);
         let mut key_dests: Vec<BuildDestination> = Vec::with_capacity(chunks.len() + 1);
         for chunk in chunks {
@@ -1394,6 +1805,7 @@ impl WalletService {
                 amount: chunk,
                 spend_pub: keys.spend_public,
                 view_pub: keys.view_public,
+                payment_id: None,
             });
         }
         if dust > 0 {
@@ -1401,21 +1813,19 @@ impl WalletService {
                 amount: dust,
                 spend_pub: keys.spend_public,
                 view_pub: keys.view_public,
+                payment_id: None,
             });
         }
 
-        let spends: Vec<CommitmentDeposit> = deposits
-            .iter()
-            .zip(interests.iter())
-            .map(|(d, interest)| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: *interest,
-            })
-            .collect();
+        let spends = {
+            let wallet = self.wallet.lock().unwrap();
+            deposits
+                .iter()
+                .zip(interests.iter())
+                .map(|(d, interest)| wallet.commitment_deposit(d, *interest))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| e.to_string())?
+        };
         let built = build_commitment_spend_transaction(
             &spends,
             &decoys,
@@ -1444,8 +1854,13 @@ impl WalletService {
         if amount == 0 {
             return Err("amount must be > 0".into());
         }
-        let (recv_spend, recv_view) = fuego_crypto::parse_address(address)
-            .ok_or_else(|| format!("invalid destination address: {}", address))?;
+        let address = self
+            .aliases
+            .resolve_destination(address)
+            .await
+            .map_err(|e| e.to_string())?;
+        let (recv_spend, recv_view) = fuego_crypto::parse_address(&address.0)
+            .ok_or_else(|| format!("invalid destination address: {}", address.0))?;
 
         let heat: Vec<fuego_sdk::scanner::CommitmentEntry> = self
             .wallet
@@ -1479,7 +1894,7 @@ impl WalletService {
             decoys.push(self.commitment_decoys(deposit, mixin).await?);
         }
 
-        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let keys = self.spend_keys()?;
         let mut commitment_dests = vec![BuildCommitmentDestination {
             amount,
             term: HEAT_TERM,
@@ -1496,17 +1911,7 @@ impl WalletService {
         let mut extra = Vec::new();
         fuego_sdk::serialization::add_heat_send_auth_extra(&mut extra, amount);
 
-        let spends: Vec<CommitmentDeposit> = selected
-            .iter()
-            .map(|d| CommitmentDeposit {
-                amount: d.amount,
-                commit_key: d.commit_key,
-                key_scalar: d.key_scalar,
-                key_image: d.key_image,
-                global_index: d.global_index,
-                claimed_interest: 0,
-            })
-            .collect();
+        let spends = self.commitment_deposits(&selected)?;
         let built = build_commitment_spend_transaction(
             &spends,
             &decoys,
@@ -1539,10 +1944,8 @@ impl WalletService {
 
         let key = format!("txs:{}", tx_hash);
         let serialized_hex = self
-            .db
-            .get(key.as_bytes())
-            .ok()
-            .flatten()
+            .store
+            .get_blob(key.as_bytes())?
             .map(|b| String::from_utf8_lossy(&b).to_string())
             .ok_or_else(|| format!("transaction {} not found (only locally-sent txs are provable)", tx_hash))?;
         let serialized = hex::decode(&serialized_hex)
@@ -1580,6 +1983,149 @@ impl WalletService {
         Ok(out)
     }
 
+    // ------------------------------------------------------------ key images
+
+    /// Signed key images of every key output, for a view-only wallet.
+    pub async fn export_key_images(
+        &self,
+    ) -> std::result::Result<Vec<fuego_sdk::scanner::SignedKeyImage>, String> {
+        self.wallet
+            .lock()
+            .unwrap()
+            .export_key_images()
+            .map_err(|e| format!("key image export: {e}"))
+    }
+
+    /// Import key images exported by the full wallet, then ask the daemon
+    /// which of the newly identified outputs were spent before the import.
+    pub async fn import_key_images(
+        &self,
+        images: &[fuego_sdk::scanner::SignedKeyImage],
+    ) -> std::result::Result<KeyImageImportSummary, String> {
+        let import = self
+            .wallet
+            .lock()
+            .unwrap()
+            .import_key_images(images)
+            .map_err(|e| format!("key image import: {e}"))?;
+        self.store.put_utxos(&import.attached)?;
+
+        let imported = import.attached.len() + import.spent.len();
+        let mut spent = import.spent;
+        for utxo in &import.attached {
+            match self.daemon.is_key_image_spent(&utxo.key_image).await {
+                Ok(true) => {
+                    if let Some(record) = self.wallet.lock().unwrap().mark_spent(&utxo.key_image) {
+                        spent.push(record);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    // Scans still catch later spends; earlier ones surface
+                    // through reconcile_spent once the daemon answers.
+                    log::warn!("is_key_image_spent unavailable, skipping check: {e}");
+                    break;
+                }
+            }
+        }
+        for record in &spent {
+            self.store.record_spent(record)?;
+        }
+        self.store.flush();
+
+        let unspent = import
+            .attached
+            .iter()
+            .filter(|u| !spent.iter().any(|r| r.key_image() == u.key_image))
+            .map(|u| u.amount)
+            .sum();
+        Ok(KeyImageImportSummary {
+            imported,
+            spent: spent
+                .iter()
+                .map(|r| match &r.output {
+                    fuego_sdk::scanner::SpentOutput::Key(u) => u.amount,
+                    fuego_sdk::scanner::SpentOutput::Commitment(c) => c.amount,
+                })
+                .sum(),
+            unspent,
+        })
+    }
+
+    // ------------------------------------------------------------ cold signing
+
+    /// Select outputs and fetch their decoys into an unsigned transaction
+    /// for an air-gapped wallet to sign. Works on view-only wallets.
+    pub async fn create_unsigned(
+        &self,
+        destinations: &[(String, u64)],
+        fee: u64,
+        anonymity: u32,
+    ) -> std::result::Result<UnsignedTransaction, String> {
+        let fee = fee.max(MINIMUM_FEE);
+        let mixin = requested_mixin(anonymity);
+        let dests = self.resolve_destinations(destinations).await?;
+        let total: u64 = destinations.iter().map(|(_, a)| *a).sum::<u64>() + fee;
+
+        let selected = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet
+                .select_for_send(total, &mut rand::thread_rng())
+                .map_err(|e| format!("coin selection: {e}"))?
+        };
+        // Outputs migrated from before tx_public_key was stored cannot be
+        // re-derived by the signer.
+        if selected.iter().any(|u| u.tx_public_key == [0u8; 32]) {
+            return Err("selected outputs predate cold signing support; rescan the wallet".into());
+        }
+        let decoys = self.fetch_decoys(&selected, mixin).await?;
+
+        Ok(UnsignedTransaction {
+            inputs: selected
+                .iter()
+                .zip(&decoys)
+                .map(|(utxo, decoys)| UnsignedInput::new(utxo, decoys))
+                .collect(),
+            destinations: dests,
+            fee,
+            mixin,
+            unlock_time: 0,
+            extra: Vec::new(),
+        })
+    }
+
+    /// Sign an unsigned transaction file with this wallet's spend key.
+    /// Touches neither the daemon nor the store.
+    pub fn sign_unsigned(&self, unsigned: &[u8]) -> std::result::Result<Vec<u8>, String> {
+        let unsigned = UnsignedTransaction::from_bytes(unsigned)
+            .map_err(|e| format!("unsigned transaction: {e}"))?;
+        let signed = self
+            .wallet
+            .lock()
+            .unwrap()
+            .sign_unsigned(&unsigned)
+            .map_err(|e| format!("sign: {e}"))?;
+        Ok(signed.to_bytes())
+    }
+
+    /// Broadcast a signed transaction file. Its key images are imported
+    /// first so a watch wallet reserves the inputs and sees its own spend.
+    pub async fn submit_signed(&self, signed: &[u8]) -> std::result::Result<String, String> {
+        let signed = SignedTransaction::from_bytes(signed)
+            .map_err(|e| format!("signed transaction: {e}"))?;
+        let import = self
+            .wallet
+            .lock()
+            .unwrap()
+            .import_key_images(&signed.key_images)
+            .map_err(|e| format!("key image import: {e}"))?;
+        self.store.put_utxos(&import.attached)?;
+        self.store.flush();
+
+        let key_images = signed.input_key_images();
+        self.broadcast_built(signed.built, key_images).await
+    }
+
     // ------------------------------------------------------------ API
 
     pub async fn address(&self) -> String {
@@ -1594,16 +2140,86 @@ impl WalletService {
         self.wallet.lock().unwrap().height()
     }
 
+    /// The primary address followed by every issued subaddress.
+    pub async fn addresses(&self) -> Vec<String> {
+        let count = self.wallet.lock().unwrap().subaddress_count();
+        (0..=count).filter_map(|i| self.subaddress_string(i)).collect()
+    }
+
+    /// Issue a new subaddress and persist the count so it keeps being
+    /// scanned after a restart.
+    pub async fn create_address(&self) -> std::result::Result<String, String> {
+        let index = self
+            .wallet
+            .lock()
+            .unwrap()
+            .new_subaddress()
+            .map_err(|e| e.to_string())?;
+        self.store.set_subaddress_count(index)?;
+        self.store.flush();
+        self.subaddress_string(index)
+            .ok_or_else(|| format!("subaddress {index} not derivable"))
+    }
+
+    /// Integrated address for the primary address with `payment_id`
+    /// (8 or 32 bytes), or a fresh random 32-byte id when none is given.
+    /// Returns the address and the payment id in hex.
+    pub async fn integrated_address(
+        &self,
+        payment_id: Option<&[u8]>,
+    ) -> std::result::Result<(String, String), String> {
+        let payment_id = match payment_id {
+            Some(id) => id.to_vec(),
+            None => {
+                let mut id = vec![0u8; 32];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut id);
+                id
+            }
+        };
+        let keys = self.wallet.lock().unwrap().wallet_keys();
+        let prefix = if self.testnet {
+            fuego_crypto::TESTNET_ADDRESS_BASE58_PREFIX
+        } else {
+            fuego_crypto::ADDRESS_BASE58_PREFIX
+        };
+        let address = fuego_crypto::make_integrated_address_with_prefix(
+            &keys.spend_public,
+            &keys.view_public,
+            &payment_id,
+            prefix,
+        )
+        .ok_or("payment id must be 8 or 32 bytes")?;
+        Ok((address.0, hex::encode(payment_id)))
+    }
+
+    /// Subaddress index of one of this wallet's addresses.
+    pub async fn subaddress_index(&self, address: &str) -> Option<u32> {
+        self.addresses()
+            .await
+            .iter()
+            .position(|a| a == address)
+            .map(|i| i as u32)
+    }
+
+    /// Confirmed balance of each address, primary first.
+    pub async fn subaddress_balances(&self) -> Vec<(String, u64)> {
+        let balances = self.wallet.lock().unwrap().subaddress_balances();
+        self.addresses().await.into_iter().zip(balances).collect()
+    }
+
+    /// Scanned balance, with `pending` holding what unconfirmed pool
+    /// transactions pay the wallet.
     pub async fn balance_full(&self) -> Balance {
-        self.wallet.lock().unwrap().balance()
+        let mut balance = self.wallet.lock().unwrap().balance();
+        balance.pending = self.pool.lock().unwrap().relevant.iter().map(|tx| tx.effect.received).sum();
+        balance
     }
 
     pub fn sync_status(&self) -> SyncStatus {
-        SyncStatus {
-            current_height: self.wallet.lock().unwrap().height(),
-            target_height: 0,
-            is_syncing: false,
-            last_sync_time: None,
+        let current_height = self.wallet.lock().unwrap().height();
+        match self.sync_handle() {
+            Some(handle) => SyncStatus { current_height, ..handle.status() },
+            None => SyncStatus { current_height, target_height: 0, is_syncing: false, last_sync_time: None },
         }
     }
 
@@ -1611,14 +2227,68 @@ impl WalletService {
         self.wallet.lock().unwrap().get_transactions(limit)
     }
 
-    pub async fn get_keypair(&self, index: u32) -> Keypair {
-        self.wallet.lock().unwrap().get_keypair(index)
+    pub async fn query_transactions(&self, query: &fuego_sdk::HistoryQuery) -> fuego_sdk::HistoryPage {
+        self.wallet.lock().unwrap().query_transactions(query)
     }
 
-    pub async fn register_alias(&self, _alias: &str, _fee: u64) -> Result<[u8; 32]> {
-        Err(SdkError::Vault(
-            "alias registration is not part of the transaction builder path (Phase 7)".into(),
-        ))
+    pub async fn get_keypair(&self, index: u32) -> Result<Keypair> {
+        self.wallet.lock().unwrap().try_get_keypair(index)
+    }
+
+    /// Register `alias` for the primary address: a transaction paying `fee`
+    /// with no destinations besides change, carrying the 0xbf alias extra.
+    pub async fn register_alias(&self, alias: &str, fee: u64) -> Result<[u8; 32]> {
+        if !is_valid_alias(alias) {
+            return Err(SdkError::InvalidAddress(format!(
+                "alias must be 8 characters of [a-z0-9&]: {alias}"
+            )));
+        }
+        let keys = self.spend_keys().map_err(SdkError::Wallet)?;
+        // Only a definite "not registered" lets the fee be spent; a lookup
+        // that failed says nothing about whether the alias is free.
+        match self.aliases.resolve(alias).await {
+            Ok(owner) => {
+                return Err(SdkError::Wallet(format!("alias @{alias} is taken by {}", owner.0)))
+            }
+            Err(SdkError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        let fee = fee.max(MINIMUM_FEE);
+        let mut extra = Vec::new();
+        add_alias_info_to_extra(
+            &mut extra,
+            &AliasInfo {
+                alias: alias.to_string(),
+                address: fuego_sdk::Address(self.primary_address_string()),
+                view_key: fuego_crypto::PublicKey(keys.view_public),
+            },
+        );
+
+        let selected = self
+            .wallet
+            .lock()
+            .unwrap()
+            .select_for_send(fee, &mut rand::thread_rng())?;
+        let decoys = self
+            .fetch_decoys(&selected, DEFAULT_MIXIN)
+            .await
+            .map_err(SdkError::Network)?;
+        let built = self.wallet.lock().unwrap().build_with_selection_ext(
+            &selected,
+            &[],
+            fee,
+            DEFAULT_MIXIN,
+            &decoys,
+            0,
+            &extra,
+            &mut rand::thread_rng(),
+        )?;
+        let tx_hash = built.tx_hash;
+        let key_images: Vec<[u8; 32]> = selected.iter().map(|u| u.key_image).collect();
+        self.broadcast_built(built, key_images)
+            .await
+            .map_err(SdkError::Transaction)?;
+        Ok(tx_hash)
     }
 
     pub async fn claim_afk_swap(
@@ -1724,4 +2394,686 @@ mod tests {
         assert_ne!(lock_keccak, h_point_keccak);
         assert_ne!(lock_sha, lock_keccak);
     }
+
+    #[test]
+    fn sparse_locator_is_dense_then_exponential_down_to_genesis() {
+        assert_eq!(sparse_locator_heights(0), vec![0]);
+        assert_eq!(sparse_locator_heights(3), vec![3, 2, 1, 0]);
+        assert_eq!(
+            sparse_locator_heights(100),
+            vec![100, 99, 98, 97, 96, 95, 94, 93, 92, 91, 89, 85, 77, 61, 29, 0]
+        );
+    }
+
+    fn stand_in_service(url: &str, dir: &std::path::Path) -> WalletService {
+        // Seed whose derived spend and view secrets are canonical scalars,
+        // which the scanner's key derivations require.
+        WalletService::new([207u8; 32], url, dir.to_path_buf(), true).unwrap()
+    }
+
+    /// A stand-in wallet in a fresh directory, synced against its own
+    /// empty test chain.
+    async fn served_service() -> (tempfile::TempDir, crate::test_daemon::TestChain, String, WalletService) {
+        let dir = tempfile::tempdir().unwrap();
+        let chain = crate::test_daemon::TestChain::default();
+        let url = chain.serve().await;
+        let service = stand_in_service(&url, dir.path());
+        (dir, chain, url, service)
+    }
+
+    fn scalar(n: u8) -> [u8; 32] {
+        let mut s = [0u8; 32];
+        s[0] = n;
+        s
+    }
+
+    #[tokio::test]
+    async fn encrypted_state_survives_restart_and_locks() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let key = StateKey::Key([7u8; 32]);
+        let open = |state_key: Option<&StateKey>| {
+            let wallet = Wallet::from_seed([207u8; 32]).unwrap();
+            WalletService::with_wallet(wallet, &url, dir.path().to_path_buf(), true, state_key)
+        };
+
+        // Plaintext state from an earlier run is encrypted on open.
+        let service = stand_in_service(&url, dir.path());
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 3_000_000, scalar(1))]);
+        assert_eq!(service.sync_once().await.unwrap(), 2);
+        drop(service);
+        let service = open(Some(&key)).unwrap();
+        assert!(service.is_encrypted());
+        assert_eq!(service.balance().await, 3_000_000);
+
+        // Locked, nothing reaches the disk; unlocking resumes sync.
+        chain.push(0, vec![pay_to(&keys, 500, scalar(2))]);
+        service.lock().unwrap();
+        assert!(service.is_locked());
+        assert!(service.sync_once().await.is_err());
+        let address = service.address().await;
+        let err = service
+            .send_transaction(&[(address, 1_000_000)], MINIMUM_FEE, 2)
+            .await
+            .unwrap_err();
+        assert!(err.contains("locked"), "{err}");
+        assert!(chain.take_submitted().is_empty());
+        assert!(service.unlock(&StateKey::Key([8u8; 32])).is_err());
+        service.unlock(&key).unwrap();
+        assert_eq!(service.sync_once().await.unwrap(), 1);
+        drop(service);
+
+        assert!(open(None).is_err());
+        let service = open(Some(&key)).unwrap();
+        assert_eq!(service.balance().await, 3_000_500);
+    }
+
+    #[tokio::test]
+    async fn reorg_rolls_back_orphaned_receives_and_spends() {
+        use crate::test_daemon::{pay_to, spend};
+
+        let (dir, chain, url, service) = served_service().await;
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+
+        // Branch A: genesis, empty, receive 1000.
+        chain.push(0, vec![]);
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1))]);
+        assert_eq!(service.sync_once().await.unwrap(), 3);
+        assert_eq!(service.balance().await, 1000);
+        assert_eq!(service.height().await, 2);
+        // Already-held blocks are not rescanned.
+        assert_eq!(service.sync_once().await.unwrap(), 0);
+        assert_eq!(service.get_transactions(10).await.len(), 1);
+
+        // Branch A spends it at height 3.
+        let image = service.wallet.lock().unwrap().utxos()[0].key_image;
+        chain.push(0, vec![spend(image, 1000)]);
+        assert_eq!(service.sync_once().await.unwrap(), 1);
+        assert_eq!(service.balance().await, 0);
+
+        // Branch B replaces height 3: the spend is orphaned, a new receive
+        // lands at height 4.
+        chain.truncate(3);
+        chain.push(1, vec![]);
+        chain.push(1, vec![pay_to(&keys, 500, scalar(2))]);
+        assert_eq!(service.sync_once().await.unwrap(), 2);
+        assert_eq!(service.balance().await, 1500);
+        assert_eq!(service.height().await, 4);
+        let history = service.get_transactions(10).await;
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|h| h.direction == fuego_sdk::scanner::HistoryDirection::Incoming));
+
+        // Branch C forks below the first receive: everything is dropped.
+        chain.truncate(2);
+        for _ in 0..4 {
+            chain.push(2, vec![]);
+        }
+        assert_eq!(service.sync_once().await.unwrap(), 4);
+        assert_eq!(service.balance().await, 0);
+        assert_eq!(service.height().await, 5);
+        assert!(service.get_transactions(10).await.is_empty());
+
+        // The rollback survives a restart.
+        drop(service);
+        let service = stand_in_service(&url, dir.path());
+        assert_eq!(service.height().await, 5);
+        assert_eq!(service.balance().await, 0);
+        assert_eq!(service.sync_once().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn background_sync_follows_the_chain_until_paused() {
+        use crate::test_daemon::pay_to;
+
+        // Waits for the round that credited `balance` to finish, too.
+        async fn wait_for_balance(service: &WalletService, balance: u64) {
+            for _ in 0..500 {
+                let status = service.sync_status();
+                if service.balance().await == balance && !status.is_syncing && status.target_height > 0 {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("balance stuck at {}", service.balance().await);
+        }
+
+        let (_dir, chain, _url, mut service) = served_service().await;
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        assert!(service.sync_status().last_sync_time.is_none());
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1))]);
+        let sync = service.start_sync(Duration::from_secs(3600));
+        wait_for_balance(&service, 1000).await;
+        let status = service.sync_status();
+        assert!(status.target_height > 0);
+        assert!(status.current_height > 0);
+        assert!(status.last_sync_time.is_some());
+
+        // Paused: a wake-up does not scan the new block.
+        sync.pause();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        chain.push(0, vec![pay_to(&keys, 300, scalar(3))]);
+        sync.sync_now();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(service.balance().await, 1000);
+
+        sync.resume();
+        wait_for_balance(&service, 1300).await;
+        sync.stop();
+        assert!(service.sync_handle().unwrap().is_stopped());
+    }
+
+    #[tokio::test]
+    async fn sync_chains_capped_batches_in_one_round() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        chain.limit_batches(10);
+        chain.delay_batches(50);
+        let url = chain.serve().await;
+        let service = stand_in_service(&url, dir.path());
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+
+        for height in 0..25u8 {
+            let txs = match height {
+                3 => vec![pay_to(&keys, 100, scalar(1))],
+                12 => vec![pay_to(&keys, 200, scalar(2)), pay_to(&keys, 300, scalar(3))],
+                22 => vec![pay_to(&keys, 400, scalar(4))],
+                _ => vec![],
+            };
+            chain.push(0, txs);
+        }
+
+        // Three daemon batches (the later ones repeat the previous tip),
+        // the last two downloading together.
+        assert_eq!(service.sync_once().await.unwrap(), 25);
+        assert_eq!(chain.max_batches_in_flight(), 2);
+        assert_eq!(service.height().await, 24);
+        assert_eq!(service.balance().await, 1000);
+        let utxos = service.wallet.lock().unwrap().utxos();
+        assert_eq!(utxos.len(), 4);
+        assert!(utxos.iter().all(|u| u.global_index == 1));
+        assert_eq!(service.sync_once().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn wallet_events_report_payments_and_send_outcomes() {
+        use crate::test_daemon::{pay_to, spend};
+
+        fn drain(rx: &mut tokio::sync::broadcast::Receiver<WalletEvent>) -> Vec<WalletEvent> {
+            std::iter::from_fn(|| rx.try_recv().ok()).collect()
+        }
+
+        let (dir, chain, url, service) = served_service().await;
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        let recipient = WalletService::new([9u8; 32], &url, dir.path().join("to"), true)
+            .unwrap()
+            .address()
+            .await;
+        let mut events = service.subscribe_events();
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 3_000_000, scalar(1)), pay_to(&keys, 3_000_000, scalar(2))]);
+        service.sync_once().await.unwrap();
+        let seen = drain(&mut events);
+        assert!(seen.iter().any(|e| matches!(
+            e,
+            WalletEvent::PaymentReceived { amount: 3_000_000, height: 1, .. }
+        )));
+
+        // A send that gets mined.
+        let sent = service.send_transaction(&[(recipient.clone(), 1_000_000)], MINIMUM_FEE, 2).await.unwrap();
+        let mined = chain.mine_submitted(0);
+        assert_eq!(hex::encode(mined[0]), sent);
+        service.sync_once().await.unwrap();
+        assert!(drain(&mut events).contains(&WalletEvent::SendConfirmed { tx_hash: mined[0], height: 2 }));
+
+        // A send whose input another copy of the wallet spends first.
+        let sent = service.send_transaction(&[(recipient, 1_000_000)], MINIMUM_FEE, 2).await.unwrap();
+        let submitted = chain.take_submitted();
+        let image = submitted[0].prefix.inputs[0].key_image().copied().unwrap();
+        let amount = submitted[0].prefix.inputs[0].amount();
+        let rival = spend(image, amount);
+        let rival_hash = fuego_sdk::serialization::tx_prefix_hash(&rival);
+        chain.push(0, vec![rival]);
+        service.sync_once().await.unwrap();
+        let seen = drain(&mut events);
+        assert!(seen.iter().any(|e| matches!(e, WalletEvent::OutputSpent { key_image, .. } if *key_image == image)));
+        assert!(seen.contains(&WalletEvent::SendFailed {
+            tx_hash: hex::decode(&sent).unwrap().try_into().unwrap(),
+            reason: format!("inputs spent by {}", hex::encode(rival_hash)),
+        }));
+        assert!(!seen.iter().any(|e| matches!(e, WalletEvent::SendConfirmed { .. })));
+        assert!(service.sync_engine().pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pool_payments_show_as_pending_and_foreign_spends_conflict() {
+        use crate::test_daemon::{pay_to, spend};
+
+        let (_dir, chain, _url, service) = served_service().await;
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1))]);
+        service.sync_once().await.unwrap();
+        let image = service.wallet.lock().unwrap().utxos()[0].key_image;
+
+        // An incoming payment and an unrelated transaction enter the pool.
+        let incoming = chain.add_to_pool(pay_to(&keys, 250, scalar(4)));
+        let stranger = Wallet::from_seed([208u8; 32]).unwrap().wallet_keys();
+        chain.add_to_pool(pay_to(&stranger, 99, scalar(5)));
+        assert_eq!(service.refresh_pool().await.unwrap(), 2);
+        let balance = service.balance_full().await;
+        assert_eq!((balance.confirmed, balance.pending), (1000, 250));
+        let pool = service.pool_transactions();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool[0].effect.tx_hash, incoming);
+        assert_eq!(pool[0].effect.received_by, vec![(0, 250)]);
+        assert!(!pool[0].conflict);
+        // Nothing new on the next poll.
+        assert_eq!(service.refresh_pool().await.unwrap(), 0);
+
+        // Someone else spends our output: a conflict, not a pending payment.
+        chain.add_to_pool(spend(image, 1000));
+        assert_eq!(service.refresh_pool().await.unwrap(), 1);
+        let conflict = service.pool_transactions().into_iter().find(|tx| tx.conflict).unwrap();
+        assert_eq!(conflict.effect.spent, 1000);
+        assert_eq!(conflict.effect.spent_key_images, vec![image]);
+        assert_eq!(service.balance_full().await.pending, 250);
+
+        // Mining the payment moves it from pending to confirmed.
+        chain.push(0, vec![pay_to(&keys, 250, scalar(4))]);
+        service.sync_once().await.unwrap();
+        let balance = service.balance_full().await;
+        assert_eq!((balance.confirmed, balance.pending), (1250, 0));
+        assert_eq!(service.pool_transactions().len(), 1);
+
+        // The conflict leaves the pool.
+        chain.clear_pool();
+        service.refresh_pool().await.unwrap();
+        assert!(service.pool_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn view_only_service_tracks_receives_but_cannot_spend() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let keys = stand_in_service(&url, &dir.path().join("full")).wallet.lock().unwrap().wallet_keys();
+        let watch = WalletService::new_view_only(
+            keys.spend_public,
+            keys.view_secret,
+            &url,
+            dir.path().join("watch"),
+            true,
+        )
+        .unwrap();
+        assert!(watch.is_view_only());
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1)), pay_to(&keys, 300, scalar(3))]);
+        assert_eq!(watch.sync_once().await.unwrap(), 2);
+        assert_eq!(watch.balance().await, 1300);
+        assert_eq!(watch.get_transactions(10).await.len(), 2);
+
+        let err = watch
+            .send_transaction(&[(watch.address().await, 100)], MINIMUM_FEE, 0)
+            .await
+            .unwrap_err();
+        assert!(err.contains("view-only"));
+        assert!(watch.mint_heat(HEAT_MINT_MIN_HEAT).await.is_err());
+        assert!(watch.get_keypair(0).await.is_err());
+
+        // Both zero-key-image outputs survive a restart.
+        drop(watch);
+        let watch = WalletService::new_view_only(
+            keys.spend_public,
+            keys.view_secret,
+            &url,
+            dir.path().join("watch"),
+            true,
+        )
+        .unwrap();
+        assert_eq!(watch.balance().await, 1300);
+    }
+
+    #[tokio::test]
+    async fn imported_key_images_reveal_earlier_spends() {
+        use crate::test_daemon::{pay_to, spend, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let full = stand_in_service(&url, &dir.path().join("full"));
+        let keys = full.wallet.lock().unwrap().wallet_keys();
+        let open_watch = || {
+            WalletService::new_view_only(
+                keys.spend_public,
+                keys.view_secret,
+                &url,
+                dir.path().join("watch"),
+                true,
+            )
+            .unwrap()
+        };
+        let watch = open_watch();
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1)), pay_to(&keys, 300, scalar(3))]);
+        full.sync_once().await.unwrap();
+        let spent_image = full
+            .wallet
+            .lock()
+            .unwrap()
+            .utxos()
+            .iter()
+            .find(|u| u.amount == 1000)
+            .unwrap()
+            .key_image;
+        chain.push(0, vec![spend(spent_image, 1000)]);
+        full.sync_once().await.unwrap();
+        assert_eq!(watch.sync_once().await.unwrap(), 3);
+        assert_eq!(watch.balance().await, 1300);
+
+        assert!(watch.export_key_images().await.is_err());
+        let images = full.export_key_images().await.unwrap();
+        assert_eq!(images.len(), 2);
+        let summary = watch.import_key_images(&images).await.unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.spent, 1000);
+        assert_eq!(summary.unspent, 300);
+        assert_eq!(watch.balance().await, 300);
+
+        // The import survives a restart, and later spends are scanned.
+        drop(watch);
+        let watch = open_watch();
+        assert_eq!(watch.balance().await, 300);
+        let remaining = images.iter().find(|i| i.key_image != spent_image).unwrap().key_image;
+        chain.push(0, vec![spend(remaining, 300)]);
+        assert_eq!(watch.sync_once().await.unwrap(), 1);
+        assert_eq!(watch.balance().await, 0);
+    }
+
+    #[tokio::test]
+    async fn cold_signed_spend_from_watch_wallet() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        // The signer never syncs: it only holds the keys.
+        let cold = stand_in_service(&url, &dir.path().join("cold"));
+        let keys = cold.wallet.lock().unwrap().wallet_keys();
+        let watch = WalletService::new_view_only(
+            keys.spend_public,
+            keys.view_secret,
+            &url,
+            dir.path().join("watch"),
+            true,
+        )
+        .unwrap();
+
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 100_000, scalar(1))]);
+        watch.sync_once().await.unwrap();
+
+        let recipient = WalletService::new([9u8; 32], &url, dir.path().join("to"), true)
+            .unwrap()
+            .address()
+            .await;
+        let unsigned = watch
+            .create_unsigned(&[(recipient, 60_000)], MINIMUM_FEE, 2)
+            .await
+            .unwrap();
+        assert_eq!(unsigned.inputs.len(), 1);
+        assert_eq!(unsigned.inputs[0].decoys.len(), 2);
+        assert!(watch.sign_unsigned(&unsigned.to_bytes()).is_err());
+
+        let signed = cold.sign_unsigned(&unsigned.to_bytes()).unwrap();
+        let tx_hash = watch.submit_signed(&signed).await.unwrap();
+        let submitted = chain.take_submitted();
+        assert_eq!(submitted.len(), 1);
+
+        // Once mined, the watch wallet sees its own spend and the change.
+        chain.push(0, vec![submitted[0].prefix.clone()]);
+        assert_eq!(watch.sync_once().await.unwrap(), 1);
+        assert_eq!(watch.balance().await, 100_000 - 60_000 - MINIMUM_FEE);
+        assert!(!tx_hash.is_empty());
+        assert!(watch.submit_signed(&signed[1..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn subaddress_receives_are_tracked_across_restarts() {
+        use crate::test_daemon::pay_to;
+
+        let (dir, chain, url, service) = served_service().await;
+        let sub = service.create_address().await.unwrap();
+        assert_eq!(service.addresses().await, vec![service.address().await, sub.clone()]);
+        assert_eq!(service.subaddress_index(&sub).await, Some(1));
+
+        let mut keys = service.wallet.lock().unwrap().wallet_keys();
+        let primary_spend = keys.spend_public;
+        keys.spend_public = fuego_crypto::parse_address(&sub).unwrap().0;
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 700, scalar(1))]);
+        keys.spend_public = primary_spend;
+        chain.push(0, vec![pay_to(&keys, 300, scalar(3))]);
+        assert_eq!(service.sync_once().await.unwrap(), 3);
+        assert_eq!(service.balance().await, 1000);
+
+        // The issued count survives a restart, so later receives to the
+        // subaddress are still detected.
+        drop(service);
+        let service = stand_in_service(&url, dir.path());
+        assert_eq!(service.addresses().await.len(), 2);
+        assert_eq!(
+            service.subaddress_balances().await,
+            vec![(service.address().await, 300), (sub, 700)]
+        );
+        let history = service.get_transactions(10).await;
+        assert_eq!(history.iter().find(|h| h.amount == 700).unwrap().subaddress, 1);
+    }
+
+    #[tokio::test]
+    async fn integrated_address_wraps_primary_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = stand_in_service("http://127.0.0.1:1", dir.path());
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+
+        let (address, payment_id) = service.integrated_address(Some(&[0x5a; 32])).await.unwrap();
+        assert_eq!(payment_id, hex::encode([0x5a; 32]));
+        assert_eq!(
+            fuego_crypto::parse_integrated_address(&address),
+            Some((keys.spend_public, keys.view_public, vec![0x5a; 32]))
+        );
+
+        // Without an id a random long one is issued, as the reference
+        // wallet expects.
+        let (address, payment_id) = service.integrated_address(None).await.unwrap();
+        let (_, _, id) = fuego_crypto::parse_integrated_address(&address).unwrap();
+        assert_eq!(hex::encode(id), payment_id);
+        assert_eq!(payment_id.len(), 64);
+        assert!(service.integrated_address(Some(&[1; 16])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn registered_alias_resolves_as_destination() {
+        use crate::test_daemon::pay_to;
+        use fuego_sdk::serialization::parse_extra_alias;
+
+        let (_dir, chain, _url, mut service) = served_service().await;
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 200_000, scalar(1))]);
+        service.sync_once().await.unwrap();
+
+        assert!(service.register_alias("Fuego&me", 10_000).await.is_err());
+        assert!(service.register_alias("fuego", 10_000).await.is_err());
+        let resolver = std::mem::replace(
+            &mut service.aliases,
+            AliasResolver::new("http://127.0.0.1:1"),
+        );
+        assert!(matches!(
+            service.register_alias("fuego&me", 10_000).await,
+            Err(SdkError::Network(_))
+        ));
+        assert!(chain.take_submitted().is_empty());
+        service.aliases = resolver;
+        let tx_hash = service.register_alias("fuego&me", 10_000).await.unwrap();
+        let submitted = chain.take_submitted();
+        assert_eq!(submitted.len(), 1);
+        let blob = fuego_sdk::serialization::serialize_tx(&submitted[0]);
+        assert_eq!(tx_hash, fuego_crypto::cn_fast_hash(&blob));
+        let info = parse_extra_alias(&submitted[0].prefix.extra).unwrap();
+        assert_eq!(info.alias, "fuego&me");
+        assert_eq!(info.address.0, service.address().await);
+        assert_eq!(info.view_key.0, keys.view_public);
+
+        chain.push(0, vec![submitted[0].prefix.clone()]);
+        service.sync_once().await.unwrap();
+        assert_eq!(service.balance().await, 190_000);
+        let own = fuego_sdk::Address(service.address().await);
+        assert_eq!(service.aliases.alias_of(&own).await.unwrap(), Some("fuego&me".into()));
+        assert!(service.register_alias("fuego&me", 10_000).await.is_err());
+
+        service
+            .send_transaction(&[("@fuego&me".into(), 50_000)], MINIMUM_FEE, 2)
+            .await
+            .unwrap();
+        let sent = chain.take_submitted();
+        chain.push(0, vec![sent[0].prefix.clone()]);
+        service.sync_once().await.unwrap();
+        assert_eq!(service.balance().await, 190_000 - MINIMUM_FEE);
+        assert!(service
+            .send_transaction(&[("@nobody00".into(), 1_000)], MINIMUM_FEE, 2)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn falls_over_to_next_daemon_node() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        // Nothing listens on port 1.
+        let dead = "http://127.0.0.1:1".to_string();
+        let service = stand_in_service(&url, dir.path()).with_daemon_nodes(&[dead, url.clone()]);
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 1000, scalar(1))]);
+
+        service.sync_once().await.unwrap();
+        assert_eq!(service.balance().await, 1000);
+        assert_eq!(service.daemon.base_url(), url);
+    }
+
+    #[tokio::test]
+    async fn prefers_daemon_nodes_that_keep_up() {
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let (lagging, current) = (TestChain::default(), TestChain::default());
+        let (lagging_url, current_url) = (lagging.serve().await, current.serve().await);
+        let service = stand_in_service(&current_url, dir.path())
+            .with_daemon_nodes(&[lagging_url, current_url.clone()]);
+        let keys = service.wallet.lock().unwrap().wallet_keys();
+        lagging.push(0, vec![]);
+        current.push(0, vec![]);
+        current.push(0, vec![pay_to(&keys, 1000, scalar(1))]);
+        for _ in 0..8 {
+            current.push(0, vec![]);
+        }
+
+        // Both nodes answer, but the one ten blocks ahead is synced from.
+        service.sync_once().await.unwrap();
+        assert_eq!(service.height().await, 9);
+        assert_eq!(service.balance().await, 1000);
+        assert_eq!(service.daemon.base_url(), current_url);
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_daemon_session() {
+        use crate::replay::{Recorder, ReplayProvider};
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let fixture = dir.path().join("session.json");
+        let recorder = Arc::new(Recorder::create(&fixture).unwrap());
+        let live = stand_in_service(&url, &dir.path().join("live"))
+            .with_daemon(DaemonClient::new(&url).recording(recorder.clone()));
+        let keys = live.wallet.lock().unwrap().wallet_keys();
+        let recipient = WalletService::new([9u8; 32], &url, dir.path().join("to"), true)
+            .unwrap()
+            .address()
+            .await;
+
+        // Equal outputs, so coin selection asks for the same decoys however
+        // it picks.
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 3_000_000, scalar(1)), pay_to(&keys, 3_000_000, scalar(2))]);
+        assert_eq!(live.sync_once().await.unwrap(), 2);
+        live.send_transaction(&[(recipient.clone(), 1_000_000)], MINIMUM_FEE, 2).await.unwrap();
+        live.mint_heat(1_000_000).await.unwrap();
+        assert_eq!(chain.take_submitted().len(), 2);
+        assert!(recorder.exchanges().iter().any(|e| e.method.as_deref() == Some("amm_pool_info")));
+        let unregistered = fuego_sdk::Address(recipient.clone());
+        assert_eq!(live.aliases.alias_of(&unregistered).await.unwrap(), None);
+        assert!(recorder.exchanges().iter().any(|e| e.path == "/get_alias_by_address"));
+
+        // Nothing listens on port 1: every answer comes from the fixture.
+        let replayed = stand_in_service("http://127.0.0.1:1", &dir.path().join("replay"))
+            .with_daemon(DaemonClient::replay(Arc::new(ReplayProvider::load(&fixture).unwrap())));
+        assert_eq!(replayed.sync_once().await.unwrap(), 2);
+        assert_eq!(replayed.balance().await, 6_000_000);
+        replayed.send_transaction(&[(recipient, 1_000_000)], MINIMUM_FEE, 2).await.unwrap();
+        replayed.mint_heat(1_000_000).await.unwrap();
+        assert_eq!(replayed.aliases.alias_of(&unregistered).await.unwrap(), None);
+        assert!(chain.take_submitted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn strict_replay_rejects_unrecorded_calls() {
+        use crate::replay::{Recorder, ReplayProvider};
+        use crate::test_daemon::{pay_to, TestChain};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain = TestChain::default();
+        let url = chain.serve().await;
+        let fixture = dir.path().join("sync.json");
+        let live = stand_in_service(&url, &dir.path().join("live"))
+            .with_daemon(DaemonClient::new(&url).recording(Arc::new(Recorder::create(&fixture).unwrap())));
+        let keys = live.wallet.lock().unwrap().wallet_keys();
+        chain.push(0, vec![]);
+        chain.push(0, vec![pay_to(&keys, 3_000_000, scalar(1))]);
+        live.sync_once().await.unwrap();
+
+        let provider = Arc::new(ReplayProvider::load(&fixture).unwrap().strict());
+        let replayed = stand_in_service("http://127.0.0.1:1", &dir.path().join("replay"))
+            .with_daemon(DaemonClient::replay(provider.clone()));
+        // Same calls in the same order: served, and the fixture is used up.
+        assert_eq!(replayed.sync_once().await.unwrap(), 2);
+        assert_eq!(replayed.balance().await, 3_000_000);
+        assert!(provider.unused().is_empty());
+
+        // A second sync needs a getinfo that was only recorded once.
+        let err = replayed.sync_once().await.unwrap_err();
+        assert!(err.contains("more often than recorded"), "{err}");
+        let err = replayed.mint_heat(1_000_000).await.unwrap_err();
+        assert!(err.contains("unexpected call /json_rpc amm_pool_info"), "{err}");
+    }
 }
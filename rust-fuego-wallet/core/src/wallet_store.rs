@@ -0,0 +1,1140 @@
+//! Keyed sled schema for the wallet's scan state.
+//!
+//! One tree per entity instead of whole-state bincode blobs:
+//!
+//! | tree            | key                          | value                |
+//! |-----------------|------------------------------|----------------------|
+//! | `meta`          | `schema_version` / `height` / `top_hash` / `subaddress_count` | bincode |
+//! | `block_ids`     | height (u64 BE)              | 32-byte block id     |
+//! | `utxos`         | one-time output key          | bincode UtxoEntry    |
+//! | `commitments`   | commitment key               | bincode CommitmentEntry |
+//! | `spent`         | key image                    | empty                |
+//! | `spent_outputs` | key image                    | bincode SpentRecord  |
+//! | `history`       | height (u64 BE) ‖ seq (u32 BE) | bincode HistoryEntry |
+//!
+//! An encrypted store (see [`WalletStore::open`]) keeps a
+//! [`EncryptionHeader`] under `meta`/`encryption`, seals the values of
+//! `utxos`, `commitments`, `spent_outputs`, `history` and the default tree,
+//! and replaces the output-key and key-image keys by blinded ones, which
+//! also gives `spent` a sealed key image as value. `meta` and `block_ids`
+//! only describe the chain and stay readable; history keys keep their
+//! heights so rollbacks can range over them.
+//!
+//! Each scanned block is applied in a single multi-tree transaction, so a
+//! crash never leaves the wallet between blocks, and a reorg rollback only
+//! touches the rows above the fork. Outputs are keyed by their one-time
+//! key rather than their key image, which a view-only wallet cannot compute.
+
+use fuego_sdk::scanner::{
+    BlockScanDelta, CommitmentEntry, HistoryEntry, ScannerStateSnapshot, SpentOutput,
+    SpentRecord, UtxoEntry,
+};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionalTree,
+};
+use fuego_sdk::store::encrypted::{EncryptionHeader, StateCipher};
+use fuego_sdk::store::StateKey;
+use fuego_sdk::vault::KdfParams;
+use sled::Transactional;
+use std::sync::{Arc, RwLock};
+
+/// Current layout. Version 1 was the whole-state blob layout (`utxos`,
+/// `commitments`, `spent`, `history`, `height`, `top_hash` keys in the
+/// default tree), migrated on open.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Deepest reorganization the wallet rolls back. Spend records exist to
+/// restore outputs on a rollback, so older ones are pruned; the `spent`
+/// key images stay.
+pub const MAX_ROLLBACK_DEPTH: u64 = 10_000;
+
+const KEY_SCHEMA_VERSION: &[u8] = b"schema_version";
+const KEY_HEIGHT: &[u8] = b"height";
+const KEY_TOP_HASH: &[u8] = b"top_hash";
+const KEY_SUBADDRESS_COUNT: &[u8] = b"subaddress_count";
+const KEY_ENCRYPTION: &[u8] = b"encryption";
+
+const LEGACY_BLOB_KEYS: [&[u8]; 6] = [
+    b"utxos",
+    b"commitments",
+    b"spent",
+    b"history",
+    b"height",
+    b"top_hash",
+];
+
+/// Encodings of the v1 blobs, from before `UtxoEntry::tx_public_key`
+/// and the `subaddress` / `payment_id` fields, with the one-time secrets
+/// stored next to each output. v1 only detected outputs to the primary
+/// address, so migrated rows get subaddress 0. The secrets are dropped;
+/// the rows cannot re-derive them without a tx public key and get a zero
+/// key, which `WalletStore::rescan_keyless` resolves by rescanning their
+/// blocks.
+mod legacy {
+    use fuego_sdk::scanner::{CommitmentEntry, HistoryDirection, HistoryEntry, UtxoEntry};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct UtxoEntryV1 {
+        amount: u64,
+        output_key: [u8; 32],
+        _secret_key: [u8; 32],
+        key_image: [u8; 32],
+        global_index: u32,
+        tx_hash: [u8; 32],
+        output_position: u32,
+        block_height: u64,
+    }
+
+    impl From<UtxoEntryV1> for UtxoEntry {
+        fn from(u: UtxoEntryV1) -> Self {
+            UtxoEntry {
+                amount: u.amount,
+                output_key: u.output_key,
+                key_image: u.key_image,
+                global_index: u.global_index,
+                tx_hash: u.tx_hash,
+                tx_public_key: [0u8; 32],
+                output_position: u.output_position,
+                block_height: u.block_height,
+                subaddress: 0,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct CommitmentEntryV1 {
+        amount: u64,
+        commit_key: [u8; 32],
+        _key_scalar: [u8; 32],
+        key_image: [u8; 32],
+        global_index: u32,
+        tx_hash: [u8; 32],
+        output_position: u32,
+        term: u32,
+        block_height: u64,
+    }
+
+    impl From<CommitmentEntryV1> for CommitmentEntry {
+        fn from(c: CommitmentEntryV1) -> Self {
+            CommitmentEntry {
+                amount: c.amount,
+                commit_key: c.commit_key,
+                key_image: c.key_image,
+                global_index: c.global_index,
+                tx_hash: c.tx_hash,
+                tx_public_key: [0u8; 32],
+                output_position: c.output_position,
+                term: c.term,
+                block_height: c.block_height,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct HistoryEntryV1 {
+        tx_hash: [u8; 32],
+        block_height: u64,
+        direction: HistoryDirection,
+        amount: u64,
+        fee: u64,
+    }
+
+    impl From<HistoryEntryV1> for HistoryEntry {
+        fn from(h: HistoryEntryV1) -> Self {
+            HistoryEntry {
+                tx_hash: h.tx_hash,
+                block_height: h.block_height,
+                direction: h.direction,
+                amount: h.amount,
+                fee: h.fee,
+                subaddress: 0,
+                payment_id: None,
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WalletStore {
+    db: sled::Db,
+    meta: sled::Tree,
+    block_ids: sled::Tree,
+    utxos: sled::Tree,
+    commitments: sled::Tree,
+    spent: sled::Tree,
+    spent_outputs: sled::Tree,
+    history: sled::Tree,
+    /// Set once the store is encrypted.
+    header: Option<EncryptionHeader>,
+    /// Unlocked key of an encrypted store, shared by every clone.
+    cipher: Arc<RwLock<Option<StateCipher>>>,
+}
+
+/// Row encoding in the store's current state: plain bincode, or sealed
+/// values under blinded keys.
+struct Codec(Option<StateCipher>);
+
+impl Codec {
+    /// Stored key for an output key, key image or default-tree key.
+    fn key(&self, raw: &[u8]) -> Vec<u8> {
+        match &self.0 {
+            Some(cipher) => cipher.blind(raw).to_vec(),
+            None => raw.to_vec(),
+        }
+    }
+
+    fn seal(&self, key: &[u8], bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+        match &self.0 {
+            Some(cipher) => cipher.seal(key, &bytes).map_err(|e| e.to_string()),
+            None => Ok(bytes),
+        }
+    }
+
+    fn open(&self, key: &[u8], bytes: &[u8]) -> Option<Vec<u8>> {
+        match &self.0 {
+            Some(cipher) => cipher.open(key, bytes).ok(),
+            None => Some(bytes.to_vec()),
+        }
+    }
+
+    fn encode<T: serde::Serialize>(&self, key: &[u8], value: &T) -> Result<Vec<u8>, String> {
+        self.seal(key, encode(value)?)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, key: &[u8], bytes: &[u8]) -> Option<T> {
+        decode(&self.open(key, bytes)?)
+    }
+
+    /// `spent` row for `image`: plain stores keep the image as key only.
+    fn spent_row(&self, image: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let key = self.key(image);
+        let value = match self.0 {
+            Some(_) => self.seal(&key, image.to_vec())?,
+            None => Vec::new(),
+        };
+        Ok((key, value))
+    }
+
+    fn spent_image(&self, key: &[u8], value: &[u8]) -> Option<[u8; 32]> {
+        match self.0 {
+            Some(_) => self.open(key, value)?.try_into().ok(),
+            None => key.try_into().ok(),
+        }
+    }
+}
+
+fn history_key(height: u64, seq: u32) -> [u8; 12] {
+    let mut key = [0u8; 12];
+    key[..8].copy_from_slice(&height.to_be_bytes());
+    key[8..].copy_from_slice(&seq.to_be_bytes());
+    key
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    bincode::serialize(value).map_err(|e| format!("encode: {e}"))
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    bincode::deserialize(bytes).ok()
+}
+
+fn txn_error(e: TransactionError<String>) -> String {
+    match e {
+        TransactionError::Abort(e) => e,
+        TransactionError::Storage(e) => format!("sled: {e}"),
+    }
+}
+
+fn abort(e: String) -> ConflictableTransactionError<String> {
+    ConflictableTransactionError::Abort(e)
+}
+
+/// Move a spent output from its live tree to `spent` / `spent_outputs`.
+fn apply_spend(
+    codec: &Codec,
+    utxo_tree: &TransactionalTree,
+    commitment_tree: &TransactionalTree,
+    spent_tree: &TransactionalTree,
+    spent_out_tree: &TransactionalTree,
+    record: &SpentRecord,
+) -> ConflictableTransactionResult<(), String> {
+    let image = record.key_image();
+    match &record.output {
+        SpentOutput::Key(u) => utxo_tree.remove(codec.key(&u.output_key))?,
+        SpentOutput::Commitment(c) => commitment_tree.remove(codec.key(&c.commit_key))?,
+    };
+    let (spent_key, spent_value) = codec.spent_row(&image).map_err(abort)?;
+    spent_tree.insert(spent_key, spent_value)?;
+    let key = codec.key(&image);
+    spent_out_tree.insert(key.as_slice(), codec.encode(&key, record).map_err(abort)?)?;
+    Ok(())
+}
+
+impl WalletStore {
+    /// Open the entity trees, migrating a blob-layout database in place.
+    /// An encrypted store needs `secret`; a plaintext one given a `secret`
+    /// is encrypted under it.
+    pub fn open(db: &sled::Db, secret: Option<&StateKey>) -> Result<Self, String> {
+        Self::open_with_params(db, secret, &KdfParams::default())
+    }
+
+    /// `open` with explicit Argon2id costs for a newly encrypted store.
+    pub fn open_with_params(
+        db: &sled::Db,
+        secret: Option<&StateKey>,
+        params: &KdfParams,
+    ) -> Result<Self, String> {
+        let open = |name: &str| db.open_tree(name).map_err(|e| format!("open {name} tree: {e}"));
+        let mut store = Self {
+            db: db.clone(),
+            meta: open("meta")?,
+            block_ids: open("block_ids")?,
+            utxos: open("utxos")?,
+            commitments: open("commitments")?,
+            spent: open("spent")?,
+            spent_outputs: open("spent_outputs")?,
+            history: open("history")?,
+            header: None,
+            cipher: Arc::new(RwLock::new(None)),
+        };
+
+        if let Some(bytes) = store.meta.get(KEY_ENCRYPTION).map_err(|e| format!("sled: {e}"))? {
+            let header = EncryptionHeader::from_bytes(&bytes).map_err(|e| e.to_string())?;
+            let secret =
+                secret.ok_or("wallet state is encrypted; a passphrase or key is required")?;
+            *store.cipher.write().unwrap() = Some(header.unlock(secret).map_err(|e| e.to_string())?);
+            store.header = Some(header);
+        }
+
+        match store.schema_version() {
+            Some(v) if v == SCHEMA_VERSION => {}
+            Some(v) if v > SCHEMA_VERSION => {
+                return Err(format!(
+                    "wallet state schema v{v} is newer than supported v{SCHEMA_VERSION}"
+                ))
+            }
+            // Stores are encrypted after their blob migration, never before.
+            _ if store.is_encrypted() => {
+                return Err("encrypted wallet state has no schema version".into())
+            }
+            _ => store.migrate_blobs()?,
+        }
+        if let (None, Some(secret)) = (&store.header, secret) {
+            store.encrypt(secret, params)?;
+        }
+        Ok(store)
+    }
+
+    /// Seal every row of a plaintext store under `secret` in one
+    /// transaction, re-keying the output and spend trees by blinded keys.
+    fn encrypt(&mut self, secret: &StateKey, params: &KdfParams) -> Result<(), String> {
+        let (header, cipher) = EncryptionHeader::create(secret, params).map_err(|e| e.to_string())?;
+        let codec = Codec(Some(cipher.clone()));
+        let rows = |tree: &sled::Tree| -> Result<Vec<(sled::IVec, sled::IVec)>, String> {
+            tree.iter().collect::<Result<_, _>>().map_err(|e| format!("sled: {e}"))
+        };
+        let default: &sled::Tree = &self.db;
+        let (blobs, utxos, commitments, spent, spent_outputs, history) = (
+            rows(default)?,
+            rows(&self.utxos)?,
+            rows(&self.commitments)?,
+            rows(&self.spent)?,
+            rows(&self.spent_outputs)?,
+            rows(&self.history)?,
+        );
+        log::info!(
+            "encrypting wallet state ({} outputs, {} history rows)",
+            utxos.len() + commitments.len(),
+            history.len()
+        );
+
+        /// Replace each row by its blinded key and sealed value.
+        fn reseal(
+            codec: &Codec,
+            tree: &TransactionalTree,
+            rows: &[(sled::IVec, sled::IVec)],
+        ) -> ConflictableTransactionResult<(), String> {
+            for (key, value) in rows {
+                tree.remove(key)?;
+                let sealed_key = codec.key(key);
+                tree.insert(sealed_key.as_slice(), codec.seal(&sealed_key, value.to_vec()).map_err(abort)?)?;
+            }
+            Ok(())
+        }
+
+        (
+            default,
+            &self.meta,
+            &self.utxos,
+            &self.commitments,
+            &self.spent,
+            &self.spent_outputs,
+            &self.history,
+        )
+            .transaction(
+                |(default, meta, utxo_tree, commitment_tree, spent_tree, spent_out_tree, history_tree)| {
+                    reseal(&codec, default, &blobs)?;
+                    reseal(&codec, utxo_tree, &utxos)?;
+                    reseal(&codec, commitment_tree, &commitments)?;
+                    reseal(&codec, spent_out_tree, &spent_outputs)?;
+                    for (image, _) in &spent {
+                        let image: [u8; 32] = image
+                            .as_ref()
+                            .try_into()
+                            .map_err(|_| abort("malformed key image in spent tree".into()))?;
+                        spent_tree.remove(&image)?;
+                        let (key, value) = codec.spent_row(&image).map_err(abort)?;
+                        spent_tree.insert(key, value)?;
+                    }
+                    for (key, value) in &history {
+                        history_tree.insert(key, codec.seal(key, value.to_vec()).map_err(abort)?)?;
+                    }
+                    meta.insert(KEY_ENCRYPTION, header.to_bytes())?;
+                    Ok(())
+                },
+            )
+            .map_err(txn_error)?;
+        self.flush();
+        *self.cipher.write().unwrap() = Some(cipher);
+        self.header = Some(header);
+        Ok(())
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.header.is_some()
+    }
+
+    /// Whether the store is encrypted and its key is not in memory.
+    pub fn is_locked(&self) -> bool {
+        self.header.is_some() && self.cipher.read().unwrap().is_none()
+    }
+
+    /// Forget the key of an encrypted store; every read and write of wallet
+    /// rows fails until `unlock`.
+    pub fn lock(&self) {
+        *self.cipher.write().unwrap() = None;
+    }
+
+    pub fn unlock(&self, secret: &StateKey) -> Result<(), String> {
+        let header = self.header.as_ref().ok_or("wallet state is not encrypted")?;
+        let cipher = header.unlock(secret).map_err(|e| e.to_string())?;
+        *self.cipher.write().unwrap() = Some(cipher);
+        Ok(())
+    }
+
+    fn codec(&self) -> Result<Codec, String> {
+        if self.header.is_none() {
+            return Ok(Codec(None));
+        }
+        match &*self.cipher.read().unwrap() {
+            Some(cipher) => Ok(Codec(Some(cipher.clone()))),
+            None => Err("wallet state is locked".into()),
+        }
+    }
+
+    fn schema_version(&self) -> Option<u32> {
+        self.meta
+            .get(KEY_SCHEMA_VERSION)
+            .ok()
+            .flatten()
+            .and_then(|b| decode(&b))
+    }
+
+    /// Move the v1 blobs into the keyed trees in one transaction and drop
+    /// them. A fresh database just gets the version marker.
+    fn migrate_blobs(&self) -> Result<(), String> {
+        fn legacy<T: serde::de::DeserializeOwned>(db: &sled::Db, key: &[u8]) -> Result<Option<T>, String> {
+            match db.get(key).map_err(|e| format!("sled: {e}"))? {
+                Some(bytes) => bincode::deserialize(&bytes).map(Some).map_err(|e| {
+                    format!("legacy {} blob: {e}", String::from_utf8_lossy(key))
+                }),
+                None => Ok(None),
+            }
+        }
+        fn converted<T: serde::de::DeserializeOwned, U: From<T>>(
+            db: &sled::Db,
+            key: &[u8],
+        ) -> Result<Vec<U>, String> {
+            Ok(legacy::<Vec<T>>(db, key)?.unwrap_or_default().into_iter().map(Into::into).collect())
+        }
+        let height: u64 = legacy(&self.db, KEY_HEIGHT)?.unwrap_or(0);
+        let top_hash: Option<[u8; 32]> = legacy(&self.db, KEY_TOP_HASH)?;
+        let utxos: Vec<UtxoEntry> = converted::<legacy::UtxoEntryV1, _>(&self.db, b"utxos")?;
+        let commitments: Vec<CommitmentEntry> =
+            converted::<legacy::CommitmentEntryV1, _>(&self.db, b"commitments")?;
+        let spent: Vec<[u8; 32]> = legacy(&self.db, b"spent")?.unwrap_or_default();
+        let history: Vec<HistoryEntry> = converted::<legacy::HistoryEntryV1, _>(&self.db, b"history")?;
+
+        if !history.is_empty() || !utxos.is_empty() || top_hash.is_some() {
+            log::info!(
+                "migrating wallet state to schema v{SCHEMA_VERSION} ({} outputs, {} history rows)",
+                utxos.len() + commitments.len(),
+                history.len()
+            );
+        }
+
+        let default: &sled::Tree = &self.db;
+        (
+            default,
+            &self.meta,
+            &self.block_ids,
+            &self.utxos,
+            &self.commitments,
+            &self.spent,
+            &self.history,
+        )
+            .transaction(
+                |(default, meta, block_ids, utxo_tree, commitment_tree, spent_tree, history_tree)| {
+                    for u in &utxos {
+                        utxo_tree.insert(&u.output_key, encode(u).map_err(abort)?)?;
+                    }
+                    for c in &commitments {
+                        commitment_tree.insert(&c.commit_key, encode(c).map_err(abort)?)?;
+                    }
+                    for image in &spent {
+                        spent_tree.insert(image, &[])?;
+                    }
+                    let mut seq = 0u32;
+                    let mut last_height = None;
+                    for h in &history {
+                        if last_height != Some(h.block_height) {
+                            last_height = Some(h.block_height);
+                            seq = 0;
+                        }
+                        history_tree.insert(
+                            &history_key(h.block_height, seq),
+                            encode(h).map_err(abort)?,
+                        )?;
+                        seq += 1;
+                    }
+                    meta.insert(KEY_HEIGHT, encode(&height).map_err(abort)?)?;
+                    if let Some(top) = &top_hash {
+                        meta.insert(KEY_TOP_HASH, encode(top).map_err(abort)?)?;
+                        // Blob-era wallets only know their top hash; seed the
+                        // locator with it.
+                        if block_ids.get(height.to_be_bytes())?.is_none() {
+                            block_ids.insert(&height.to_be_bytes(), top)?;
+                        }
+                    }
+                    for key in LEGACY_BLOB_KEYS {
+                        default.remove(key)?;
+                    }
+                    meta.insert(KEY_SCHEMA_VERSION, encode(&SCHEMA_VERSION).map_err(abort)?)?;
+                    Ok(())
+                },
+            )
+            .map_err(txn_error)?;
+        self.flush();
+        self.rescan_keyless()
+    }
+
+    /// Roll back to the oldest block holding an output migrated without
+    /// its tx public key, so the next sync rescans it and records the key
+    /// its secret is re-derived from.
+    fn rescan_keyless(&self) -> Result<(), String> {
+        let state = self.load()?;
+        let spent = state.spent_outputs.iter().map(|r| match &r.output {
+            SpentOutput::Key(u) => (u.tx_public_key, u.block_height),
+            SpentOutput::Commitment(c) => (c.tx_public_key, c.block_height),
+        });
+        let keyless = state
+            .utxos
+            .iter()
+            .map(|u| (u.tx_public_key, u.block_height))
+            .chain(state.commitments.iter().map(|c| (c.tx_public_key, c.block_height)))
+            .chain(spent)
+            .filter(|(r, _)| *r == [0u8; 32])
+            .map(|(_, height)| height)
+            .min();
+        if let Some(height) = keyless {
+            log::warn!("rescanning wallet state from height {height} to recover tx public keys");
+            self.detach(height)?;
+            self.flush();
+        }
+        Ok(())
+    }
+
+    /// Full scan state, for restoring the in-memory scanner on startup.
+    pub fn load(&self) -> Result<ScannerStateSnapshot, String> {
+        fn values<T: serde::de::DeserializeOwned>(codec: &Codec, tree: &sled::Tree) -> Result<Vec<T>, String> {
+            tree.iter()
+                .map(|row| {
+                    let (k, v) = row.map_err(|e| format!("sled: {e}"))?;
+                    codec.decode(&k, &v).ok_or_else(|| format!("undecodable row {}", hex::encode(&k)))
+                })
+                .collect()
+        }
+        let codec = self.codec()?;
+        let spent_images = self
+            .spent
+            .iter()
+            .map(|row| {
+                let (k, v) = row.map_err(|e| format!("sled: {e}"))?;
+                codec.spent_image(&k, &v).ok_or_else(|| "undecodable spent key image".to_string())
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(ScannerStateSnapshot {
+            height: self.height(),
+            utxos: values(&codec, &self.utxos)?,
+            commitments: values(&codec, &self.commitments)?,
+            spent_images,
+            history: values(&codec, &self.history)?,
+            spent_outputs: values(&codec, &self.spent_outputs)?,
+        })
+    }
+
+    pub fn height(&self) -> u64 {
+        self.meta
+            .get(KEY_HEIGHT)
+            .ok()
+            .flatten()
+            .and_then(|b| decode(&b))
+            .unwrap_or(0)
+    }
+
+    pub fn top_hash(&self) -> Option<[u8; 32]> {
+        self.meta
+            .get(KEY_TOP_HASH)
+            .ok()
+            .flatten()
+            .and_then(|b| decode(&b))
+    }
+
+    /// Number of subaddresses issued besides the primary address; the seed
+    /// alone cannot tell how many were handed out.
+    pub fn subaddress_count(&self) -> u32 {
+        self.meta
+            .get(KEY_SUBADDRESS_COUNT)
+            .ok()
+            .flatten()
+            .and_then(|b| decode(&b))
+            .unwrap_or(0)
+    }
+
+    pub fn set_subaddress_count(&self, count: u32) -> Result<(), String> {
+        self.meta
+            .insert(KEY_SUBADDRESS_COUNT, encode(&count)?)
+            .map(|_| ())
+            .map_err(|e| format!("sled: {e}"))
+    }
+
+    pub fn block_id(&self, height: u64) -> Option<[u8; 32]> {
+        self.block_ids
+            .get(height.to_be_bytes())
+            .ok()
+            .flatten()
+            .and_then(|b| <[u8; 32]>::try_from(b.as_ref()).ok())
+    }
+
+    /// Record one scanned block and everything the scanner learned from it.
+    pub fn apply_block(
+        &self,
+        height: u64,
+        block_id: &[u8; 32],
+        delta: &BlockScanDelta,
+    ) -> Result<(), String> {
+        let codec = self.codec()?;
+        (
+            &self.meta,
+            &self.block_ids,
+            &self.utxos,
+            &self.commitments,
+            &self.spent,
+            &self.spent_outputs,
+            &self.history,
+        )
+            .transaction(
+                |(meta, block_ids, utxo_tree, commitment_tree, spent_tree, spent_out_tree, history_tree)| {
+                    for u in &delta.utxos {
+                        let key = codec.key(&u.output_key);
+                        utxo_tree.insert(key.as_slice(), codec.encode(&key, u).map_err(abort)?)?;
+                    }
+                    for c in &delta.commitments {
+                        let key = codec.key(&c.commit_key);
+                        commitment_tree.insert(key.as_slice(), codec.encode(&key, c).map_err(abort)?)?;
+                    }
+                    for r in &delta.spent {
+                        apply_spend(&codec, utxo_tree, commitment_tree, spent_tree, spent_out_tree, r)?;
+                    }
+                    for (seq, h) in delta.history.iter().enumerate() {
+                        let key = history_key(height, seq as u32);
+                        history_tree.insert(&key, codec.encode(&key, h).map_err(abort)?)?;
+                    }
+                    block_ids.insert(&height.to_be_bytes(), block_id)?;
+                    meta.insert(KEY_HEIGHT, encode(&height).map_err(abort)?)?;
+                    meta.insert(KEY_TOP_HASH, encode(block_id).map_err(abort)?)?;
+                    Ok(())
+                },
+            )
+            .map_err(txn_error)
+    }
+
+    /// Remove every row learned from blocks at `height` and above, mirroring
+    /// `Wallet::detach`: outputs spent there are restored, outputs received
+    /// there are dropped.
+    pub fn detach(&self, height: u64) -> Result<(), String> {
+        let codec = self.codec()?;
+        fn rows<T: serde::de::DeserializeOwned>(codec: &Codec, tree: &sled::Tree) -> Vec<(sled::IVec, T)> {
+            tree.iter()
+                .filter_map(|r| r.ok())
+                .filter_map(|(k, v)| codec.decode(&k, &v).map(|t| (k, t)))
+                .collect()
+        }
+        let orphaned_utxos: Vec<(sled::IVec, [u8; 32])> = rows::<UtxoEntry>(&codec, &self.utxos)
+            .into_iter()
+            .filter(|(_, u)| u.block_height >= height)
+            .map(|(k, u)| (k, u.key_image))
+            .collect();
+        let orphaned_commitments: Vec<(sled::IVec, [u8; 32])> =
+            rows::<CommitmentEntry>(&codec, &self.commitments)
+                .into_iter()
+                .filter(|(_, c)| c.block_height >= height)
+                .map(|(k, c)| (k, c.key_image))
+                .collect();
+        let orphaned_spends: Vec<SpentRecord> = rows::<SpentRecord>(&codec, &self.spent_outputs)
+            .into_iter()
+            .map(|(_, r)| r)
+            .filter(|r| r.spent_height >= height)
+            .collect();
+        let from = height.to_be_bytes();
+        let orphaned_history: Vec<sled::IVec> = self
+            .history
+            .range(history_key(height, 0)..)
+            .keys()
+            .filter_map(|k| k.ok())
+            .collect();
+        let orphaned_ids: Vec<sled::IVec> = self
+            .block_ids
+            .range(from..)
+            .keys()
+            .filter_map(|k| k.ok())
+            .collect();
+        let new_top = height.checked_sub(1).and_then(|h| self.block_id(h));
+        let new_height = self.height().min(height.saturating_sub(1));
+
+        (
+            &self.meta,
+            &self.block_ids,
+            &self.utxos,
+            &self.commitments,
+            &self.spent,
+            &self.spent_outputs,
+            &self.history,
+        )
+            .transaction(
+                |(meta, block_ids, utxo_tree, commitment_tree, spent_tree, spent_out_tree, history_tree)| {
+                    for (key, image) in &orphaned_utxos {
+                        utxo_tree.remove(key)?;
+                        spent_tree.remove(codec.key(image))?;
+                    }
+                    for (key, image) in &orphaned_commitments {
+                        commitment_tree.remove(key)?;
+                        spent_tree.remove(codec.key(image))?;
+                    }
+                    for r in &orphaned_spends {
+                        let image = codec.key(&r.key_image());
+                        spent_out_tree.remove(image.as_slice())?;
+                        spent_tree.remove(image)?;
+                        match &r.output {
+                            SpentOutput::Key(u) if u.block_height < height => {
+                                let key = codec.key(&u.output_key);
+                                utxo_tree.insert(key.as_slice(), codec.encode(&key, u).map_err(abort)?)?;
+                            }
+                            SpentOutput::Commitment(c) if c.block_height < height => {
+                                let key = codec.key(&c.commit_key);
+                                commitment_tree.insert(key.as_slice(), codec.encode(&key, c).map_err(abort)?)?;
+                            }
+                            _ => {}
+                        }
+                    }
+                    for key in &orphaned_history {
+                        history_tree.remove(key)?;
+                    }
+                    for key in &orphaned_ids {
+                        block_ids.remove(key)?;
+                    }
+                    meta.insert(KEY_HEIGHT, encode(&new_height).map_err(abort)?)?;
+                    match &new_top {
+                        Some(id) => meta.insert(KEY_TOP_HASH, encode(id).map_err(abort)?)?,
+                        None => meta.remove(KEY_TOP_HASH)?,
+                    };
+                    Ok(())
+                },
+            )
+            .map_err(txn_error)
+    }
+
+    /// Drop spend records of outputs spent below `height`. Returns how many
+    /// were removed.
+    pub fn prune_spent(&self, height: u64) -> Result<usize, String> {
+        let codec = self.codec()?;
+        let mut stale = Vec::new();
+        for row in self.spent_outputs.iter() {
+            let (key, value) = row.map_err(|e| format!("sled: {e}"))?;
+            let record: SpentRecord = codec
+                .decode(&key, &value)
+                .ok_or("undecodable spend record")?;
+            if record.spent_height < height {
+                stale.push(key);
+            }
+        }
+        self.spent_outputs
+            .transaction(|tree| {
+                for key in &stale {
+                    tree.remove(key)?;
+                }
+                Ok(())
+            })
+            .map_err(txn_error)?;
+        Ok(stale.len())
+    }
+
+    /// Overwrite stored outputs changed outside a block scan (key images
+    /// imported into a view-only wallet).
+    pub fn put_utxos(&self, utxos: &[UtxoEntry]) -> Result<(), String> {
+        let codec = self.codec()?;
+        self.utxos
+            .transaction(|tree| {
+                for u in utxos {
+                    let key = codec.key(&u.output_key);
+                    tree.insert(key.as_slice(), codec.encode(&key, u).map_err(abort)?)?;
+                }
+                Ok(())
+            })
+            .map_err(txn_error)
+    }
+
+    /// Record a spend learned outside a block scan.
+    pub fn record_spent(&self, record: &SpentRecord) -> Result<(), String> {
+        let codec = self.codec()?;
+        (&self.utxos, &self.commitments, &self.spent, &self.spent_outputs)
+            .transaction(|(utxo_tree, commitment_tree, spent_tree, spent_out_tree)| {
+                apply_spend(&codec, utxo_tree, commitment_tree, spent_tree, spent_out_tree, record)
+            })
+            .map_err(txn_error)
+    }
+
+    /// Record a key image the daemon reports as spent.
+    pub fn mark_spent(&self, key_image: &[u8; 32]) -> Result<(), String> {
+        let (key, value) = self.codec()?.spent_row(key_image)?;
+        self.spent.insert(key, value).map(|_| ()).map_err(|e| format!("sled: {e}"))
+    }
+
+    /// Value stored under `key` in the default tree (pending sends, sent
+    /// transactions).
+    pub fn get_blob(&self, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let codec = self.codec()?;
+        let key = codec.key(key);
+        match self.db.get(&key).map_err(|e| format!("sled: {e}"))? {
+            Some(bytes) => codec
+                .open(&key, &bytes)
+                .map(Some)
+                .ok_or_else(|| "stored value failed authentication".to_string()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put_blob(&self, key: &[u8], value: &[u8]) -> Result<(), String> {
+        let codec = self.codec()?;
+        let key = codec.key(key);
+        let value = codec.seal(&key, value.to_vec())?;
+        self.db.insert(key, value).map(|_| ()).map_err(|e| format!("sled: {e}"))
+    }
+
+    pub fn flush(&self) {
+        let _ = self.db.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fuego_sdk::scanner::HistoryDirection;
+
+    fn utxo(tag: u8, amount: u64, block_height: u64) -> UtxoEntry {
+        UtxoEntry {
+            amount,
+            output_key: [tag; 32],
+            key_image: [tag; 32],
+            global_index: 0,
+            tx_hash: [tag; 32],
+            tx_public_key: [tag; 32],
+            output_position: 0,
+            block_height,
+            subaddress: 0,
+        }
+    }
+
+    /// `u` in the v1 encoding (secret, no tx_public_key).
+    #[allow(clippy::type_complexity)]
+    fn v1(u: &UtxoEntry) -> (u64, [u8; 32], [u8; 32], [u8; 32], u32, [u8; 32], u32, u64) {
+        (
+            u.amount,
+            u.output_key,
+            [0x5e; 32],
+            u.key_image,
+            u.global_index,
+            u.tx_hash,
+            u.output_position,
+            u.block_height,
+        )
+    }
+
+    fn incoming(tag: u8, amount: u64, block_height: u64) -> HistoryEntry {
+        HistoryEntry {
+            tx_hash: [tag; 32],
+            block_height,
+            direction: HistoryDirection::Incoming,
+            amount,
+            fee: 0,
+            subaddress: 0,
+            payment_id: None,
+        }
+    }
+
+    /// `h` in the v1 encoding (no subaddress or payment id).
+    fn history_v1(h: &HistoryEntry) -> ([u8; 32], u64, HistoryDirection, u64, u64) {
+        (h.tx_hash, h.block_height, h.direction, h.amount, h.fee)
+    }
+
+    #[test]
+    fn migrates_blob_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let utxos = [utxo(1, 1000, 5), utxo(2, 250, 9)];
+        let history = [incoming(1, 1000, 5), incoming(2, 250, 9)];
+        db.insert(b"height", bincode::serialize(&12u64).unwrap()).unwrap();
+        db.insert(b"top_hash", bincode::serialize(&[0xaau8; 32]).unwrap()).unwrap();
+        let blob: Vec<_> = utxos.iter().map(v1).collect();
+        db.insert(b"utxos", bincode::serialize(&blob).unwrap()).unwrap();
+        db.insert(b"spent", bincode::serialize(&vec![[3u8; 32]]).unwrap()).unwrap();
+        let history: Vec<_> = history.iter().map(history_v1).collect();
+        db.insert(b"history", bincode::serialize(&history).unwrap()).unwrap();
+        db.insert(b"pending", b"untouched".to_vec()).unwrap();
+
+        let store = WalletStore::open(&db, None).unwrap();
+        assert_eq!(store.schema_version(), Some(SCHEMA_VERSION));
+        assert!(db.get(b"utxos").unwrap().is_none());
+        assert!(db.get(b"pending").unwrap().is_some());
+        // Blob-era outputs carry no tx public key: everything from the
+        // oldest of them (height 5) is rolled back for a rescan.
+        let state = store.load().unwrap();
+        assert_eq!(state.height, 4);
+        assert!(state.utxos.is_empty());
+        // v1 spent key images carry no height and survive the rollback.
+        assert_eq!(state.spent_images, vec![[3u8; 32]]);
+        assert!(state.history.is_empty());
+        assert_eq!(store.block_id(12), None);
+
+        // Re-opening a migrated store is a no-op.
+        drop(store);
+        let store = WalletStore::open(&db, None).unwrap();
+        assert_eq!(store.load().unwrap().height, 4);
+    }
+
+    #[test]
+    fn refuses_undecodable_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        db.insert(b"utxos", b"garbage".to_vec()).unwrap();
+        assert!(WalletStore::open(&db, None).is_err());
+        // Nothing was migrated or dropped.
+        assert!(db.get(b"utxos").unwrap().is_some());
+    }
+
+    #[test]
+    fn load_refuses_undecodable_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let store = WalletStore::open(&db, None).unwrap();
+        db.open_tree("history").unwrap().insert(history_key(5, 0), b"garbage".to_vec()).unwrap();
+        assert!(store.load().is_err());
+    }
+
+    #[test]
+    fn view_only_outputs_stay_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let store = WalletStore::open(&db, None).unwrap();
+        // View-only outputs share the all-zero key image.
+        let mut a = utxo(2, 10, 6);
+        let mut b = utxo(3, 20, 6);
+        a.key_image = [0; 32];
+        b.key_image = [0; 32];
+        store
+            .apply_block(6, &[6; 32], &BlockScanDelta { utxos: vec![a, b], ..Default::default() })
+            .unwrap();
+        assert_eq!(store.load().unwrap().utxos.len(), 2);
+    }
+
+    #[test]
+    fn encrypts_plaintext_state_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let received = utxo(1, 1000, 3);
+        let spent = utxo(2, 40, 3);
+        {
+            let store = WalletStore::open(&db, None).unwrap();
+            store
+                .apply_block(
+                    3,
+                    &[3; 32],
+                    &BlockScanDelta {
+                        utxos: vec![received.clone(), spent.clone()],
+                        history: vec![incoming(1, 1000, 3)],
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            store
+                .record_spent(&SpentRecord { spent_height: 4, output: SpentOutput::Key(spent) })
+                .unwrap();
+            store.put_blob(b"pending", b"pending sends").unwrap();
+        }
+
+        let passphrase = StateKey::Passphrase(b"state-passphrase".to_vec());
+        let cheap = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let store = WalletStore::open_with_params(&db, Some(&passphrase), &cheap).unwrap();
+        assert!(store.is_encrypted());
+        let state = store.load().unwrap();
+        assert_eq!(state.utxos.len(), 1);
+        assert_eq!(state.utxos[0].amount, 1000);
+        assert_eq!(state.spent_images, vec![[2u8; 32]]);
+        assert_eq!(state.spent_outputs.len(), 1);
+        assert_eq!(state.history.len(), 1);
+        assert_eq!(store.get_blob(b"pending").unwrap(), Some(b"pending sends".to_vec()));
+
+        // Neither output keys nor secrets are on disk in the clear.
+        let utxos = db.open_tree("utxos").unwrap();
+        assert!(utxos.get(received.output_key).unwrap().is_none());
+        assert!(db.get(b"pending").unwrap().is_none());
+
+        // Rolling back works on blinded keys.
+        store.detach(4).unwrap();
+        assert_eq!(store.load().unwrap().utxos.len(), 2);
+        drop(store);
+
+        assert!(WalletStore::open(&db, None).is_err());
+        assert!(WalletStore::open(&db, Some(&StateKey::Passphrase(b"wrong".to_vec()))).is_err());
+        let store = WalletStore::open(&db, Some(&passphrase)).unwrap();
+        assert_eq!(store.load().unwrap().utxos.len(), 2);
+    }
+
+    #[test]
+    fn locked_store_refuses_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let key = StateKey::Key([7u8; 32]);
+        let store = WalletStore::open(&db, Some(&key)).unwrap();
+        let engine = store.clone();
+        let delta = BlockScanDelta { utxos: vec![utxo(1, 1000, 3)], ..Default::default() };
+
+        store.lock();
+        assert!(engine.is_locked());
+        assert!(engine.apply_block(3, &[3; 32], &delta).is_err());
+        assert!(engine.load().is_err());
+        assert!(engine.mark_spent(&[1; 32]).is_err());
+        assert!(store.unlock(&StateKey::Key([8u8; 32])).is_err());
+        assert!(store.is_locked());
+
+        store.unlock(&key).unwrap();
+        engine.apply_block(3, &[3; 32], &delta).unwrap();
+        assert_eq!(store.load().unwrap().utxos.len(), 1);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let meta = db.open_tree("meta").unwrap();
+        meta.insert(KEY_SCHEMA_VERSION, bincode::serialize(&(SCHEMA_VERSION + 1)).unwrap())
+            .unwrap();
+        assert!(WalletStore::open(&db, None).is_err());
+    }
+
+    #[test]
+    fn apply_and_detach_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let store = WalletStore::open(&db, None).unwrap();
+
+        let received = utxo(1, 1000, 3);
+        store
+            .apply_block(
+                3,
+                &[3; 32],
+                &BlockScanDelta {
+                    utxos: vec![received.clone()],
+                    history: vec![incoming(1, 1000, 3)],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        store
+            .apply_block(
+                4,
+                &[4; 32],
+                &BlockScanDelta {
+                    spent: vec![SpentRecord {
+                        spent_height: 4,
+                        output: SpentOutput::Key(received),
+                    }],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let state = store.load().unwrap();
+        assert_eq!(state.height, 4);
+        assert!(state.utxos.is_empty());
+        assert_eq!(state.spent_images, vec![[1u8; 32]]);
+
+        // Orphan block 4: the spend is undone.
+        store.detach(4).unwrap();
+        let state = store.load().unwrap();
+        assert_eq!(state.height, 3);
+        assert_eq!(state.utxos.len(), 1);
+        assert!(state.spent_images.is_empty());
+        assert!(state.spent_outputs.is_empty());
+        assert_eq!(store.top_hash(), Some([3; 32]));
+        assert_eq!(store.block_id(4), None);
+
+        // Orphan block 3: the receive is gone too.
+        store.detach(3).unwrap();
+        let state = store.load().unwrap();
+        assert!(state.utxos.is_empty());
+        assert!(state.history.is_empty());
+        assert_eq!(store.top_hash(), None);
+    }
+
+    #[test]
+    fn prunes_spend_records_below_height() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path().join("wallet_state.sled")).unwrap();
+        let store = WalletStore::open(&db, None).unwrap();
+        let spend = |tag: u8, spent_height: u64| SpentRecord {
+            spent_height,
+            output: SpentOutput::Key(utxo(tag, 100, 1)),
+        };
+        for (height, tag) in [(1u64, 1u8), (2, 2)] {
+            store
+                .apply_block(
+                    height,
+                    &[height as u8; 32],
+                    &BlockScanDelta { utxos: vec![utxo(tag, 100, 1)], ..Default::default() },
+                )
+                .unwrap();
+        }
+        store.record_spent(&spend(1, 5)).unwrap();
+        store.record_spent(&spend(2, 9)).unwrap();
+
+        assert_eq!(store.prune_spent(9).unwrap(), 1);
+        let state = store.load().unwrap();
+        assert_eq!(state.spent_outputs.len(), 1);
+        assert_eq!(state.spent_outputs[0].spent_height, 9);
+        // The pruned output is still known to be spent.
+        assert_eq!(state.spent_images.len(), 2);
+        assert_eq!(store.prune_spent(9).unwrap(), 0);
+    }
+}
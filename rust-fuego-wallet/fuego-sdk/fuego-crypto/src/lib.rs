@@ -17,6 +17,9 @@ pub use ring::{
 pub const ADDRESS_BASE58_PREFIX: u64 = 1753191;
 /// Fuego testnet address prefix (CryptoNoteConfig.h:452).
 pub const TESTNET_ADDRESS_BASE58_PREFIX: u64 = 1075740;
+/// Payment id sizes an integrated address may carry: short (8) or long (32).
+/// The reference wallet only reads long ones.
+pub const PAYMENT_ID_SIZES: [usize; 2] = [8, 32];
 
 // ── CryptoNote block-based Base58 (exact port of Base58.cpp) ───────
 
@@ -125,7 +128,7 @@ fn decode_block(encoded: &str, size: usize) -> Option<Vec<u8>> {
     let mut num: u64 = 0;
     for c in encoded.chars() {
         let digit = ALPHABET.iter().position(|&b| b == c as u8)?;
-        num = num * ALPHABET_SIZE as u64 + digit as u64;
+        num = num.checked_mul(ALPHABET_SIZE as u64)?.checked_add(digit as u64)?;
     }
     let mut block = vec![0u8; size];
     for i in (0..size).rev() {
@@ -231,12 +234,42 @@ pub fn make_address(spend_pub: &[u8; 32], view_pub: &[u8; 32]) -> Address {
 
 /// Address generation with an explicit network prefix.
 pub fn make_address_with_prefix(spend_pub: &[u8; 32], view_pub: &[u8; 32], prefix: u64) -> Address {
+    encode_address(prefix, &[spend_pub, view_pub])
+}
+
+/// Integrated address as the Fuego/Conceal wallet builds it
+/// (`create_integrated`): under the standard address prefix, the payment
+/// id as lowercase hex text followed by the spend/view keys. `None` for a
+/// payment id that isn't 8 or 32 bytes.
+pub fn make_integrated_address_with_prefix(
+    spend_pub: &[u8; 32],
+    view_pub: &[u8; 32],
+    payment_id: &[u8],
+    prefix: u64,
+) -> Option<Address> {
+    if !PAYMENT_ID_SIZES.contains(&payment_id.len()) {
+        return None;
+    }
+    let id_hex = hex::encode(payment_id);
+    Some(encode_address(prefix, &[id_hex.as_bytes(), spend_pub, view_pub]))
+}
+
+pub fn make_integrated_address(
+    spend_pub: &[u8; 32],
+    view_pub: &[u8; 32],
+    payment_id: &[u8],
+) -> Option<Address> {
+    make_integrated_address_with_prefix(spend_pub, view_pub, payment_id, ADDRESS_BASE58_PREFIX)
+}
+
+fn encode_address(prefix: u64, parts: &[&[u8]]) -> Address {
     // Step 1: varint-encode the prefix
     let mut buf = varint_encode(prefix);
-    // Step 2: append spend + view public keys (64 bytes)
-    buf.extend_from_slice(spend_pub);
-    buf.extend_from_slice(view_pub);
-    // Step 3: keccak256 checksum of (prefix || keys)
+    // Step 2: append the keys (and payment id)
+    for part in parts {
+        buf.extend_from_slice(part);
+    }
+    // Step 3: keccak256 checksum of (prefix || body)
     let hash = Keccak256::digest(&buf);
     buf.extend_from_slice(&hash[..ADDR_CHECKSUM_SIZE]);
     // Step 4: CryptoNote block-based base58 encode
@@ -245,13 +278,46 @@ pub fn make_address_with_prefix(spend_pub: &[u8; 32], view_pub: &[u8; 32], prefi
 
 /// Parse a Fuego address (optionally prefixed with "fire" or "TEST") into
 /// (spend pubkey, view pubkey). Validates base58, checksum and prefix tag.
+///
+/// Mainnet addresses encode to a string that itself starts with "fire", so
+/// the address is decoded as given first and only then with a tag stripped.
 pub fn parse_address(address: &str) -> Option<([u8; 32], [u8; 32])> {
-    let stripped = address
-        .strip_prefix("fire")
-        .or_else(|| address.strip_prefix("TEST"))
-        .unwrap_or(address);
-    let decoded = cn_base58_decode(stripped)?;
-    if decoded.len() < 72 {
+    cn_base58_decode(address)
+        .and_then(|decoded| parse_address_bytes(&decoded))
+        .or_else(|| {
+            let stripped = address
+                .strip_prefix("fire")
+                .or_else(|| address.strip_prefix("TEST"))?;
+            parse_address_bytes(&cn_base58_decode(stripped)?)
+        })
+}
+
+fn parse_address_bytes(decoded: &[u8]) -> Option<([u8; 32], [u8; 32])> {
+    let body = address_body(decoded, &[ADDRESS_BASE58_PREFIX, TESTNET_ADDRESS_BASE58_PREFIX])?;
+    if body.len() != 64 {
+        return None;
+    }
+    Some(split_keys(body))
+}
+
+/// Parse an integrated address into (spend pubkey, view pubkey, payment
+/// id). Plain addresses are rejected.
+pub fn parse_integrated_address(address: &str) -> Option<([u8; 32], [u8; 32], Vec<u8>)> {
+    let decoded = cn_base58_decode(address)?;
+    let body = address_body(&decoded, &[ADDRESS_BASE58_PREFIX, TESTNET_ADDRESS_BASE58_PREFIX])?;
+    let id_len = body.len().checked_sub(64)?;
+    if !PAYMENT_ID_SIZES.iter().any(|size| size * 2 == id_len) {
+        return None;
+    }
+    let payment_id = hex::decode(&body[..id_len]).ok()?;
+    let (spend, view) = split_keys(&body[id_len..]);
+    Some((spend, view, payment_id))
+}
+
+/// The bytes after the prefix of a decoded address, if its checksum holds
+/// and its prefix is one of `prefixes`.
+fn address_body<'a>(decoded: &'a [u8], prefixes: &[u64]) -> Option<&'a [u8]> {
+    if decoded.len() < 64 + ADDR_CHECKSUM_SIZE {
         return None;
     }
     let payload = &decoded[..decoded.len() - ADDR_CHECKSUM_SIZE];
@@ -261,18 +327,18 @@ pub fn parse_address(address: &str) -> Option<([u8; 32], [u8; 32])> {
         return None;
     }
     let (prefix, prefix_len) = varint_decode(payload);
-    if prefix != ADDRESS_BASE58_PREFIX && prefix != TESTNET_ADDRESS_BASE58_PREFIX {
-        return None;
-    }
-    let rest = &payload[prefix_len..];
-    if rest.len() != 64 {
+    if !prefixes.contains(&prefix) {
         return None;
     }
+    Some(&payload[prefix_len..])
+}
+
+fn split_keys(keys: &[u8]) -> ([u8; 32], [u8; 32]) {
     let mut spend = [0u8; 32];
     let mut view = [0u8; 32];
-    spend.copy_from_slice(&rest[..32]);
-    view.copy_from_slice(&rest[32..]);
-    Some((spend, view))
+    spend.copy_from_slice(&keys[..32]);
+    view.copy_from_slice(&keys[32..64]);
+    (spend, view)
 }
 
 /// Validate a Fuego address string.
@@ -392,4 +458,27 @@ mod tests {
         let addr = make_address(&spend.public, &view.public);
         assert_eq!(addr.0.len(), 98);
     }
+
+    #[test]
+    fn test_integrated_address_layout() {
+        // 3-byte prefix + 64 hex chars + 64 key bytes + 4 checksum = 135
+        // bytes -> 16 full blocks + 7 bytes: 16 * 11 + 10 = 186 chars,
+        // the length the reference wallet expects.
+        let spend = Keypair::from_secret([0x11; 32]);
+        let view = Keypair::from_secret([0x22; 32]);
+        let payment_id = [0xabu8; 32];
+        let addr = make_integrated_address(&spend.public, &view.public, &payment_id).unwrap();
+        assert_eq!(addr.0.len(), 186);
+
+        let decoded = cn_base58_decode(&addr.0).unwrap();
+        let body = address_body(&decoded, &[ADDRESS_BASE58_PREFIX]).unwrap();
+        assert_eq!(&body[..64], "ab".repeat(32).as_bytes());
+        assert_eq!(&body[64..96], &spend.public);
+        assert_eq!(&body[96..], &view.public);
+        assert_eq!(
+            parse_integrated_address(&addr.0),
+            Some((spend.public, view.public, payment_id.to_vec()))
+        );
+        assert_eq!(parse_address(&addr.0), None);
+    }
 }
@@ -519,6 +519,25 @@ pub fn derive_deposit_secret(derivation: &[u8; 32], output_index: u32) -> [u8; 3
     cn_fast_hash(&pre)
 }
 
+/// Domain byte appended to the derivation when hashing the payment id key
+/// (Monero's ENCRYPTED_PAYMENT_ID_TAIL).
+pub const ENCRYPTED_PAYMENT_ID_TAIL: u8 = 0x8d;
+
+/// crypto.cpp encrypt_payment_id: XOR an 8-byte payment id with
+/// cn_fast_hash(D || 0x8d)[..8]. Sender (r*A) and recipient (a*R) share D,
+/// and XOR is its own inverse, so this also decrypts.
+pub fn encrypt_payment_id(payment_id: &[u8; 8], derivation: &[u8; 32]) -> [u8; 8] {
+    let mut pre = [0u8; 33];
+    pre[..32].copy_from_slice(derivation);
+    pre[32] = ENCRYPTED_PAYMENT_ID_TAIL;
+    let key = cn_fast_hash(&pre);
+    let mut out = *payment_id;
+    for (b, k) in out.iter_mut().zip(key.iter()) {
+        *b ^= k;
+    }
+    out
+}
+
 // ---------------------------------------------------------------- tx proofs
 // Port of crypto.cpp generate_tx_proof / check_tx_proof: a signature proving
 // knowledge of the tx secret key r with R = r*G and D = r*A (raw, no
@@ -776,4 +795,28 @@ mod tests {
         ge_p3_tobytes(&mut pubkey2, &pub_p3_2);
         assert!(!check_signature(&prefix_hash, &pubkey2, &sig));
     }
+
+    #[test]
+    fn payment_id_encryption_round_trip() {
+        let mut rng = rand::thread_rng();
+        let public = |sec: &[u8; 32]| {
+            let mut p3 = GeP3::default();
+            ge_scalarmult_base(&mut p3, sec);
+            let mut out = [0u8; 32];
+            ge_p3_tobytes(&mut out, &p3);
+            out
+        };
+        let view = random_scalar(&mut rng);
+        let tx = random_scalar(&mut rng);
+        let sender = generate_key_derivation(&public(&view), &tx).unwrap();
+        let recipient = generate_key_derivation(&public(&tx), &view).unwrap();
+        assert_eq!(sender, recipient);
+
+        let id = *b"invoice7";
+        let encrypted = encrypt_payment_id(&id, &sender);
+        assert_ne!(encrypted, id);
+        assert_eq!(encrypt_payment_id(&encrypted, &recipient), id);
+        let other = generate_key_derivation(&public(&random_scalar(&mut rng)), &tx).unwrap();
+        assert_ne!(encrypt_payment_id(&encrypted, &other), id);
+    }
 }
@@ -1,10 +1,14 @@
 use fuego_crypto::{Keypair, PublicKey, make_address, generate_key_derivation, derive_public_key, underive_public_key, generate_key_image, cn_base58_encode, cn_fast_hash, generate_signature, check_signature};
 use fuego_vault::Vault;
+use fuego_sdk::events::WalletEvent;
+use fuego_sdk::serialization::parse_prefix;
 use fuego_sdk::types::SwapPair;
+use fuego_sdk::wallet::Wallet;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::ptr;
 use std::slice;
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
 // ── Memory management ──
 
@@ -211,6 +215,89 @@ pub unsafe extern "C" fn fuego_vault_load(path_ptr: *const c_char) -> FuegoBytes
     }
 }
 
+/// Save vault encrypted under a passphrase (Argon2id, v2 vault format).
+///
+/// # Safety
+/// `vault_ptr`/`pass_ptr` must point to `vault_len`/`pass_len` readable bytes
+/// and `path_ptr` to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_vault_save_encrypted(
+    vault_ptr: *const u8,
+    vault_len: usize,
+    path_ptr: *const c_char,
+    pass_ptr: *const u8,
+    pass_len: usize,
+) -> FuegoResult {
+    if vault_ptr.is_null() || path_ptr.is_null() || pass_ptr.is_null() {
+        return FuegoResult { ok: false, error: CString::new("null pointer").unwrap().into_raw() };
+    }
+    let data = slice::from_raw_parts(vault_ptr, vault_len);
+    let path = CStr::from_ptr(path_ptr).to_str().unwrap_or("");
+    let passphrase = slice::from_raw_parts(pass_ptr, pass_len);
+    match bincode::deserialize::<Vault>(data) {
+        Ok(vault) => match vault.save(std::path::PathBuf::from(path), passphrase) {
+            Ok(()) => FuegoResult { ok: true, error: ptr::null_mut() },
+            Err(e) => FuegoResult { ok: false, error: CString::new(e.to_string()).unwrap().into_raw() },
+        },
+        Err(e) => FuegoResult { ok: false, error: CString::new(e.to_string()).unwrap().into_raw() },
+    }
+}
+
+/// Load an encrypted vault (v1 or v2). Returns serialized vault bytes, or an
+/// empty buffer on a wrong passphrase or unreadable file.
+///
+/// # Safety
+/// `pass_ptr` must point to `pass_len` readable bytes and `path_ptr` to a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_vault_load_encrypted(
+    path_ptr: *const c_char,
+    pass_ptr: *const u8,
+    pass_len: usize,
+) -> FuegoBytes {
+    if path_ptr.is_null() || pass_ptr.is_null() {
+        return FuegoBytes { ptr: ptr::null_mut(), len: 0 };
+    }
+    let path = CStr::from_ptr(path_ptr).to_str().unwrap_or("");
+    let passphrase = slice::from_raw_parts(pass_ptr, pass_len);
+    match Vault::load(std::path::PathBuf::from(path), passphrase) {
+        Ok(vault) => {
+            let data = bincode::serialize(&vault).unwrap_or_default();
+            let len = data.len();
+            let mut buf = data.into_boxed_slice();
+            let ptr = buf.as_mut_ptr();
+            std::mem::forget(buf);
+            FuegoBytes { ptr, len }
+        }
+        Err(_) => FuegoBytes { ptr: ptr::null_mut(), len: 0 },
+    }
+}
+
+/// Re-encrypt the vault file at `path` under a new passphrase.
+///
+/// # Safety
+/// `old_ptr`/`new_ptr` must point to `old_len`/`new_len` readable bytes and
+/// `path_ptr` to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_vault_change_passphrase(
+    path_ptr: *const c_char,
+    old_ptr: *const u8,
+    old_len: usize,
+    new_ptr: *const u8,
+    new_len: usize,
+) -> FuegoResult {
+    if path_ptr.is_null() || old_ptr.is_null() || new_ptr.is_null() {
+        return FuegoResult { ok: false, error: CString::new("null pointer").unwrap().into_raw() };
+    }
+    let path = CStr::from_ptr(path_ptr).to_str().unwrap_or("");
+    let old_passphrase = slice::from_raw_parts(old_ptr, old_len);
+    let new_passphrase = slice::from_raw_parts(new_ptr, new_len);
+    match Vault::change_passphrase(std::path::PathBuf::from(path), old_passphrase, new_passphrase) {
+        Ok(()) => FuegoResult { ok: true, error: ptr::null_mut() },
+        Err(e) => FuegoResult { ok: false, error: CString::new(e.to_string()).unwrap().into_raw() },
+    }
+}
+
 // ── Crypto operations ──
 
 /// Generate key derivation. Returns 32-byte derivation as hex string.
@@ -428,6 +515,150 @@ pub unsafe extern "C" fn fuego_base58_encode(
     CString::new(encoded).unwrap().into_raw()
 }
 
+// ── Wallet events ──
+
+/// An open wallet. Create with `fuego_wallet_from_seed` or
+/// `fuego_wallet_view_only`, free with `fuego_wallet_free`.
+pub struct FuegoWallet(Wallet);
+
+/// A subscription to a wallet's events. Free with `fuego_events_free`.
+pub struct FuegoEvents(broadcast::Receiver<WalletEvent>);
+
+/// Open a wallet from a 32-byte seed. Returns null on a null seed.
+///
+/// # Safety
+/// `seed_ptr` must be null or point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_from_seed(seed_ptr: *const u8) -> *mut FuegoWallet {
+    if seed_ptr.is_null() {
+        return ptr::null_mut();
+    }
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(slice::from_raw_parts(seed_ptr, 32));
+    match Wallet::from_seed(seed) {
+        Ok(wallet) => Box::into_raw(Box::new(FuegoWallet(wallet))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Open a watch-only wallet from the 32-byte public spend key and private
+/// view key. Returns null if either is invalid.
+///
+/// # Safety
+/// Both pointers must be null or point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_view_only(
+    spend_public_ptr: *const u8,
+    view_secret_ptr: *const u8,
+) -> *mut FuegoWallet {
+    if spend_public_ptr.is_null() || view_secret_ptr.is_null() {
+        return ptr::null_mut();
+    }
+    let mut spend_public = [0u8; 32];
+    let mut view_secret = [0u8; 32];
+    spend_public.copy_from_slice(slice::from_raw_parts(spend_public_ptr, 32));
+    view_secret.copy_from_slice(slice::from_raw_parts(view_secret_ptr, 32));
+    match Wallet::view_only(spend_public, view_secret) {
+        Ok(wallet) => Box::into_raw(Box::new(FuegoWallet(wallet))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free a wallet. Subscriptions taken from it stay valid but go quiet.
+///
+/// # Safety
+/// `wallet` must be null or a handle from this library not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_free(wallet: *mut FuegoWallet) {
+    if !wallet.is_null() {
+        drop(Box::from_raw(wallet));
+    }
+}
+
+/// Scan a serialized transaction prefix mined at `height`, publishing
+/// any events it causes. Returns 0 on success, -1 on bad input.
+///
+/// # Safety
+/// `wallet` must be a live handle, `tx_hash_ptr` point to 32 bytes and
+/// `prefix_ptr` to `prefix_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_scan_tx_prefix(
+    wallet: *const FuegoWallet,
+    tx_hash_ptr: *const u8,
+    prefix_ptr: *const u8,
+    prefix_len: usize,
+    height: u64,
+) -> c_int {
+    if wallet.is_null() || tx_hash_ptr.is_null() || prefix_ptr.is_null() {
+        return -1;
+    }
+    let mut tx_hash = [0u8; 32];
+    tx_hash.copy_from_slice(slice::from_raw_parts(tx_hash_ptr, 32));
+    let Ok(prefix) = parse_prefix(slice::from_raw_parts(prefix_ptr, prefix_len)) else {
+        return -1;
+    };
+    match (*wallet).0.scan_tx_prefix(&tx_hash, &prefix, height) {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Record that the chain top is now `height`; publishes confirmation,
+/// unlock and deposit maturity events.
+///
+/// # Safety
+/// `wallet` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_set_height(wallet: *const FuegoWallet, height: u64) {
+    if !wallet.is_null() {
+        (*wallet).0.set_height(height);
+    }
+}
+
+/// Subscribe to the wallet's events from now on.
+///
+/// # Safety
+/// `wallet` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_wallet_subscribe(wallet: *const FuegoWallet) -> *mut FuegoEvents {
+    if wallet.is_null() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(FuegoEvents((*wallet).0.subscribe())))
+}
+
+/// Next queued event as JSON, e.g. {"type":"payment_received",
+/// "transactionHash":"hex","amount":n,...}, or null when none is queued.
+/// Never blocks. After falling behind, returns {"type":"lagged","skipped":n}
+/// once; re-read the wallet state then.
+///
+/// # Safety
+/// `events` must be null or a live subscription. Free the returned string
+/// with `fuego_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_events_next(events: *mut FuegoEvents) -> *mut c_char {
+    if events.is_null() {
+        return ptr::null_mut();
+    }
+    let json = match (*events).0.try_recv() {
+        Ok(event) => event.to_json(),
+        Err(TryRecvError::Lagged(skipped)) => serde_json::json!({ "type": "lagged", "skipped": skipped }),
+        Err(TryRecvError::Empty | TryRecvError::Closed) => return ptr::null_mut(),
+    };
+    CString::new(json.to_string()).unwrap().into_raw()
+}
+
+/// Free an event subscription.
+///
+/// # Safety
+/// `events` must be null or a subscription not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn fuego_events_free(events: *mut FuegoEvents) {
+    if !events.is_null() {
+        drop(Box::from_raw(events));
+    }
+}
+
 // ── FFI types ──
 
 /// A byte buffer returned to the caller. Free with `fuego_bytes_free`.
@@ -1,15 +1,33 @@
+use crate::error::{Result, SdkError};
+use crate::http::{HttpConfig, DEST_DAEMON};
 use crate::types::Address;
+use async_trait::async_trait;
 use fuego_crypto::PublicKey;
+use std::sync::Arc;
 
 pub const TX_EXTRA_TAG_ALIAS_INFO: u8 = 0xbf;
 
-#[derive(Debug, Clone)]
+/// Alias length enforced by the daemon.
+pub const ALIAS_LENGTH: usize = 8;
+
+/// Destinations starting with this are aliases to resolve, not addresses.
+pub const ALIAS_PREFIX: char = '@';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AliasInfo {
     pub alias: String,
     pub address: Address,
     pub view_key: PublicKey,
 }
 
+/// The daemon's alias rule: exactly 8 characters of `[a-z0-9&]`.
+pub fn is_valid_alias(alias: &str) -> bool {
+    alias.len() == ALIAS_LENGTH
+        && alias
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'&')
+}
+
 /// Serialize alias info into tx_extra bytes.
 ///
 /// Wire format (matching simplewallet/C++ daemon parser):
@@ -31,3 +49,120 @@ pub fn add_alias_info_to_extra(extra: &mut Vec<u8>, alias_info: &AliasInfo) {
 
     extra.extend_from_slice(&alias_info.view_key.0);
 }
+
+/// How alias lookups reach the daemon: a JSON POST to `path` answered
+/// with a JSON body. Implement it to send lookups through an existing
+/// daemon client instead of a separate HTTP connection.
+#[async_trait]
+pub trait AliasTransport: Send + Sync {
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value>;
+}
+
+/// Plain HTTP to one daemon endpoint.
+struct HttpTransport {
+    endpoint: String,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl AliasTransport for HttpTransport {
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let resp = self
+            .client
+            .post(format!("{}{}", self.endpoint, path))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| SdkError::Network(format!("HTTP request failed: {e}")))?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(SdkError::Network(format!("RPC {path} returned {status}")));
+        }
+        resp.json()
+            .await
+            .map_err(|e| SdkError::Serialization(format!("Failed to decode {path}: {e}")))
+    }
+}
+
+/// Resolves `@alias` destinations through the daemon's `get_alias` /
+/// `get_alias_by_address` endpoints.
+pub struct AliasResolver {
+    transport: Arc<dyn AliasTransport>,
+}
+
+impl AliasResolver {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::with_http(endpoint, &HttpConfig::default()).expect("default HTTP client")
+    }
+
+    /// Alias lookups are daemon traffic and share the daemon's circuit.
+    pub fn with_http(endpoint: impl Into<String>, http: &HttpConfig) -> Result<Self> {
+        Ok(Self::with_transport(Arc::new(HttpTransport {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            client: http.client(DEST_DAEMON)?,
+        })))
+    }
+
+    /// Resolver that sends its lookups through `transport`.
+    pub fn with_transport(transport: Arc<dyn AliasTransport>) -> Self {
+        Self { transport }
+    }
+
+    /// Address registered for `alias` (with or without the leading `@`).
+    pub async fn resolve(&self, alias: &str) -> Result<Address> {
+        let alias = alias.strip_prefix(ALIAS_PREFIX).unwrap_or(alias);
+        if !is_valid_alias(alias) {
+            return Err(SdkError::InvalidAddress(format!("invalid alias: {alias}")));
+        }
+        let resp = self.post("/get_alias", serde_json::json!({ "alias": alias })).await?;
+        let address = resp
+            .get("address")
+            .and_then(|a| a.as_str())
+            .filter(|a| !a.is_empty())
+            .ok_or_else(|| SdkError::NotFound(format!("alias @{alias} is not registered")))?;
+        if fuego_crypto::parse_address(address).is_none() {
+            return Err(SdkError::InvalidAddress(format!(
+                "alias @{alias} resolves to an invalid address"
+            )));
+        }
+        Ok(Address(address.to_string()))
+    }
+
+    /// Alias registered for `address`, if any.
+    pub async fn alias_of(&self, address: &Address) -> Result<Option<String>> {
+        let resp = self
+            .post("/get_alias_by_address", serde_json::json!({ "address": address.0 }))
+            .await?;
+        Ok(resp
+            .get("alias")
+            .and_then(|a| a.as_str())
+            .filter(|a| !a.is_empty())
+            .map(str::to_string))
+    }
+
+    /// A destination as typed by the user: `@alias` is resolved, anything
+    /// else is taken as an address.
+    pub async fn resolve_destination(&self, destination: &str) -> Result<Address> {
+        if destination.starts_with(ALIAS_PREFIX) {
+            self.resolve(destination).await
+        } else {
+            Ok(Address(destination.to_string()))
+        }
+    }
+
+    /// Resolve every destination of a transfer.
+    pub async fn resolve_destinations(
+        &self,
+        destinations: &[(String, u64)],
+    ) -> Result<Vec<(Address, u64)>> {
+        let mut resolved = Vec::with_capacity(destinations.len());
+        for (destination, amount) in destinations {
+            resolved.push((self.resolve_destination(destination).await?, *amount));
+        }
+        Ok(resolved)
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        self.transport.post(path, body).await
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Result;
-use crate::network::RpcProvider;
+use crate::http::HttpConfig;
+use crate::network::{FailoverProvider, P2pConfig, P2pProvider, RpcProvider};
 use crate::node::EmbeddedNode;
 use crate::traits::{NetworkProvider, StorageProvider};
 use crate::types::*;
@@ -13,6 +14,11 @@ pub struct SdkBuilder {
     config: NodeConfig,
     network: Option<Arc<dyn NetworkProvider>>,
     storage: Option<Arc<dyn StorageProvider>>,
+    rpc: Option<(String, u16)>,
+    rpc_nodes: Vec<(String, u16)>,
+    http: HttpConfig,
+    node_quorum: usize,
+    p2p: Option<P2pConfig>,
 }
 
 impl SdkBuilder {
@@ -21,6 +27,11 @@ impl SdkBuilder {
             config: NodeConfig::default(),
             network: None,
             storage: None,
+            rpc: None,
+            rpc_nodes: Vec::new(),
+            http: HttpConfig::default(),
+            node_quorum: 1,
+            p2p: None,
         }
     }
 
@@ -44,13 +55,42 @@ impl SdkBuilder {
         self
     }
 
+    #[deprecated(note = "the node never serves blocks to peers; this has no effect")]
+    #[allow(deprecated)]
     pub fn enable_seeding(mut self, enable: bool) -> Self {
         self.config.enable_seeding = enable;
         self
     }
 
     pub fn rpc(mut self, host: impl Into<String>, port: u16) -> Self {
-        self.network = Some(Arc::new(RpcProvider::new(host, port)));
+        self.rpc = Some((host.into(), port));
+        self
+    }
+
+    /// Proxy, timeouts and TLS settings for the RPC clients.
+    pub fn http(mut self, config: HttpConfig) -> Self {
+        self.http = config;
+        self
+    }
+
+    /// Sync from several daemons through a [`FailoverProvider`]. Ignored if
+    /// a network or a single `rpc` node is set.
+    pub fn rpc_nodes<S: Into<String>>(mut self, nodes: impl IntoIterator<Item = (S, u16)>) -> Self {
+        self.rpc_nodes
+            .extend(nodes.into_iter().map(|(host, port)| (host.into(), port)));
+        self
+    }
+
+    /// Number of `rpc_nodes` that must agree on the chain tip.
+    pub fn node_quorum(mut self, quorum: usize) -> Self {
+        self.node_quorum = quorum;
+        self
+    }
+
+    /// Sync over the P2P network through a [`P2pProvider`], holding at most
+    /// `max_peers` connections. Ignored if a network or RPC nodes are set.
+    pub fn p2p(mut self, config: P2pConfig) -> Self {
+        self.p2p = Some(config);
         self
     }
 
@@ -65,12 +105,23 @@ impl SdkBuilder {
     }
 
     pub fn build(self) -> Result<EmbeddedNode> {
+        let max_peers = self.config.max_peers;
         let mut node = match self.storage {
             Some(storage) => EmbeddedNode::with_storage(self.config, storage)?,
             None => EmbeddedNode::new(self.config)?,
         };
 
-        if let Some(network) = self.network {
+        let network: Option<Arc<dyn NetworkProvider>> = match (self.network, self.rpc) {
+            (Some(network), _) => Some(network),
+            (None, Some((host, port))) => Some(Arc::new(RpcProvider::with_http(host, port, &self.http)?)),
+            (None, None) if !self.rpc_nodes.is_empty() => Some(Arc::new(
+                FailoverProvider::rpc_with_http(self.rpc_nodes, &self.http)?.with_quorum(self.node_quorum),
+            )),
+            (None, None) => self
+                .p2p
+                .map(|config| Arc::new(P2pProvider::new(config.with_max_peers(max_peers))) as _),
+        };
+        if let Some(network) = network {
             node.set_network(network);
         }
 
@@ -90,6 +141,7 @@ impl Default for SdkBuilder {
 
 pub struct WalletBuilder {
     seed: Option<[u8; 32]>,
+    view_only: Option<([u8; 32], [u8; 32])>,
     data_dir: Option<std::path::PathBuf>,
     passphrase: Option<Vec<u8>>,
 }
@@ -98,6 +150,7 @@ impl WalletBuilder {
     pub fn new() -> Self {
         Self {
             seed: None,
+            view_only: None,
             data_dir: None,
             passphrase: None,
         }
@@ -108,6 +161,13 @@ impl WalletBuilder {
         self
     }
 
+    /// Build a watch-only wallet from the public spend key and private view
+    /// key instead of a seed. Nothing is written to `data_dir`.
+    pub fn view_only(mut self, spend_public: [u8; 32], view_secret: [u8; 32]) -> Self {
+        self.view_only = Some((spend_public, view_secret));
+        self
+    }
+
     pub fn data_dir(mut self, path: impl Into<std::path::PathBuf>) -> Self {
         self.data_dir = Some(path.into());
         self
@@ -119,6 +179,15 @@ impl WalletBuilder {
     }
 
     pub fn build(self) -> Result<crate::wallet::Wallet> {
+        if let Some((spend_public, view_secret)) = self.view_only {
+            if self.seed.is_some() {
+                return Err(crate::error::SdkError::Wallet(
+                    "seed and view_only keys are mutually exclusive".into(),
+                ));
+            }
+            return crate::wallet::Wallet::view_only(spend_public, view_secret);
+        }
+
         let vault = match self.seed {
             Some(seed) => crate::vault::WalletVault::from_seed(seed),
             None => crate::vault::WalletVault::generate(),
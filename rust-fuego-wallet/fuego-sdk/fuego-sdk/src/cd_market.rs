@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::error::{Result, SdkError};
+use crate::http::{HttpConfig, DEST_CD_MARKET};
 use crate::types::*;
 
 /// Certificate of Deposit market RPC client.
@@ -11,10 +12,14 @@ pub struct CdMarketClient {
 
 impl CdMarketClient {
     pub fn new(endpoint: impl Into<String>) -> Self {
-        Self {
+        Self::with_http(endpoint, &HttpConfig::default()).expect("default HTTP client")
+    }
+
+    pub fn with_http(endpoint: impl Into<String>, http: &HttpConfig) -> Result<Self> {
+        Ok(Self {
             endpoint: endpoint.into(),
-            client: reqwest::Client::new(),
-        }
+            client: http.client(DEST_CD_MARKET)?,
+        })
     }
 
     // ── CD Listings ───────────────────────────────────────────────────
@@ -1,5 +1,6 @@
 use crate::error::{Result, SdkError};
 use crate::chain::{ChainHeader, MerkleProof, ChainType};
+use crate::http::{HttpConfig, DEST_BTC_RPC};
 use reqwest::Client;
 
 /// JSON-RPC client for Bitcoin-family nodes.
@@ -12,15 +13,22 @@ pub struct BtcRpcClient {
 
 impl BtcRpcClient {
     pub fn new(endpoint: impl Into<String>, user: impl Into<String>, pass: impl Into<String>) -> Self {
-        Self {
+        let http = HttpConfig::default().with_timeout(std::time::Duration::from_secs(30));
+        Self::with_http(endpoint, user, pass, &http).expect("default HTTP client")
+    }
+
+    pub fn with_http(
+        endpoint: impl Into<String>,
+        user: impl Into<String>,
+        pass: impl Into<String>,
+        http: &HttpConfig,
+    ) -> Result<Self> {
+        Ok(Self {
             endpoint: endpoint.into(),
             user: user.into(),
             pass: pass.into(),
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .unwrap_or_else(|_| Client::new()),
-        }
+            client: http.client(DEST_BTC_RPC)?,
+        })
     }
 
     pub async fn get_block_count(&self) -> Result<u64> {
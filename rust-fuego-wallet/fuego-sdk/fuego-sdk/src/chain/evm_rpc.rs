@@ -1,5 +1,6 @@
 use crate::error::{Result, SdkError};
 use crate::chain::evm::{EvmReceipt, EvmBlock, EvmTx};
+use crate::http::{HttpConfig, DEST_EVM_RPC};
 use reqwest::Client;
 
 /// JSON-RPC client for EVM-compatible chains (ETH, ARB, BASE).
@@ -10,13 +11,15 @@ pub struct EvmRpcClient {
 
 impl EvmRpcClient {
     pub fn new(url: impl Into<String>) -> Self {
-        Self {
+        let http = HttpConfig::default().with_timeout(std::time::Duration::from_secs(30));
+        Self::with_http(url, &http).expect("default HTTP client")
+    }
+
+    pub fn with_http(url: impl Into<String>, http: &HttpConfig) -> Result<Self> {
+        Ok(Self {
             url: url.into(),
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .unwrap_or_else(|_| Client::new()),
-        }
+            client: http.client(DEST_EVM_RPC)?,
+        })
     }
 
     pub async fn get_block_number(&self) -> Result<u64> {
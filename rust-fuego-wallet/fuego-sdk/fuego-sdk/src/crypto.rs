@@ -25,6 +25,15 @@ pub fn make_address(spend_pub: &PublicKey, view_pub: &PublicKey) -> Address {
     Address(addr.0)
 }
 
+/// Integrated address: spend/view keys plus an 8- or 32-byte payment id.
+pub fn make_integrated_address(
+    spend_pub: &PublicKey,
+    view_pub: &PublicKey,
+    payment_id: &[u8],
+) -> Option<Address> {
+    fc::make_integrated_address(&spend_pub.0, &view_pub.0, payment_id).map(|a| Address(a.0))
+}
+
 /// Validate address format
 pub fn is_valid_address(address: &str) -> bool {
     fc::is_valid_address(address)
@@ -29,9 +29,15 @@ pub enum SdkError {
     #[error("Transaction error: {0}")]
     Transaction(String),
 
+    #[error("Transaction rejected: {0}")]
+    TxRejected(TxRejection),
+
     #[error("Sync error: {0}")]
     Sync(String),
 
+    #[error("Block {height} rejected: {reason}")]
+    InvalidBlock { height: u64, reason: String },
+
     #[error("Config error: {0}")]
     Config(String),
 
@@ -52,3 +58,60 @@ pub enum SdkError {
 }
 
 pub type Result<T> = std::result::Result<T, SdkError>;
+
+/// Why a daemon refused a broadcast transaction.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TxRejection {
+    #[error("input already spent")]
+    DoubleSpend,
+
+    #[error("fee too low")]
+    FeeTooLow,
+
+    #[error("transaction too big")]
+    TooBig,
+
+    /// Failed verification; the daemon gave no more specific reason.
+    #[error("verification failed{}", .0.as_deref().map(|r| format!(": {r}")).unwrap_or_default())]
+    Invalid(Option<String>),
+
+    /// Accepted into the daemon's pool but not relayed to peers.
+    #[error("not relayed")]
+    NotRelayed,
+
+    #[error("unexpected status {0:?}")]
+    Other(String),
+}
+
+impl TxRejection {
+    /// Classify a `/sendrawtransaction` response; `None` when it was
+    /// accepted. fuegod only reports `"Failed"` or `"Not relayed"`; the
+    /// per-cause flags some daemons add pick the variant when present, and
+    /// any `reason` text is kept as-is rather than guessed at.
+    pub fn from_response(response: &serde_json::Value) -> Option<Self> {
+        let status = response.get("status").and_then(|s| s.as_str()).unwrap_or("");
+        let flag = |name: &str| response.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+        let reason = response
+            .get("reason")
+            .and_then(|r| r.as_str())
+            .filter(|r| !r.is_empty())
+            .or_else(|| status.strip_prefix("Failed:").map(str::trim));
+
+        match status {
+            "OK" | "Success" => return None,
+            "Not relayed" => return Some(TxRejection::NotRelayed),
+            s if s.starts_with("Failed") => {}
+            other => return Some(TxRejection::Other(other.to_string())),
+        }
+
+        Some(if flag("double_spend") {
+            TxRejection::DoubleSpend
+        } else if flag("fee_too_low") {
+            TxRejection::FeeTooLow
+        } else if flag("too_big") {
+            TxRejection::TooBig
+        } else {
+            TxRejection::Invalid(reason.map(str::to_string))
+        })
+    }
+}
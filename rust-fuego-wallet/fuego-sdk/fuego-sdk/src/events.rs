@@ -0,0 +1,140 @@
+//! Typed wallet events. The scanner publishes them as it scans blocks and
+//! as the chain grows; consumers subscribe instead of polling balances.
+
+use crate::serialization::PaymentId;
+use crate::traits::EventListener;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Confirmations after which a received output is spendable
+/// (`CRYPTONOTE_DEFAULT_TX_SPENDABLE_AGE`).
+pub const SPENDABLE_AGE: u64 = 10;
+
+/// Events buffered per subscriber before the slowest one starts lagging.
+const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletEvent {
+    /// A mined transaction paid the wallet.
+    PaymentReceived {
+        tx_hash: [u8; 32],
+        height: u64,
+        amount: u64,
+        subaddress: u32,
+        payment_id: Option<PaymentId>,
+    },
+    /// An incoming transaction's confirmation count changed; reported
+    /// until it reaches `SPENDABLE_AGE`.
+    Confirmations { tx_hash: [u8; 32], confirmations: u64 },
+    /// A mined transaction spent one of the wallet's outputs.
+    OutputSpent { tx_hash: [u8; 32], key_image: [u8; 32], amount: u64, height: u64 },
+    /// An output reached `SPENDABLE_AGE` confirmations.
+    OutputUnlocked { tx_hash: [u8; 32], output_position: u32, amount: u64, height: u64 },
+    /// A send registered with `track_send` was mined.
+    SendConfirmed { tx_hash: [u8; 32], height: u64 },
+    /// A send was rejected, or another transaction spent its inputs.
+    SendFailed { tx_hash: [u8; 32], reason: String },
+    /// A deposit reached the end of its term and can be withdrawn.
+    DepositMatured { tx_hash: [u8; 32], output_position: u32, amount: u64, height: u64 },
+}
+
+impl WalletEvent {
+    /// Event name as used on the wire.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WalletEvent::PaymentReceived { .. } => "payment_received",
+            WalletEvent::Confirmations { .. } => "confirmations",
+            WalletEvent::OutputSpent { .. } => "output_spent",
+            WalletEvent::OutputUnlocked { .. } => "output_unlocked",
+            WalletEvent::SendConfirmed { .. } => "send_confirmed",
+            WalletEvent::SendFailed { .. } => "send_failed",
+            WalletEvent::DepositMatured { .. } => "deposit_matured",
+        }
+    }
+
+    /// Transaction the event is about.
+    pub fn tx_hash(&self) -> [u8; 32] {
+        match self {
+            WalletEvent::PaymentReceived { tx_hash, .. }
+            | WalletEvent::Confirmations { tx_hash, .. }
+            | WalletEvent::OutputSpent { tx_hash, .. }
+            | WalletEvent::OutputUnlocked { tx_hash, .. }
+            | WalletEvent::SendConfirmed { tx_hash, .. }
+            | WalletEvent::SendFailed { tx_hash, .. }
+            | WalletEvent::DepositMatured { tx_hash, .. } => *tx_hash,
+        }
+    }
+
+    /// JSON form for the RPC and FFI: `type` plus the fields, hashes in hex.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = match self {
+            WalletEvent::PaymentReceived { height, amount, subaddress, payment_id, .. } => {
+                serde_json::json!({
+                    "height": height,
+                    "amount": amount,
+                    "subaddress": subaddress,
+                    "paymentId": payment_id.map(|id| id.to_hex()),
+                })
+            }
+            WalletEvent::Confirmations { confirmations, .. } => {
+                serde_json::json!({ "confirmations": confirmations })
+            }
+            WalletEvent::OutputSpent { key_image, amount, height, .. } => serde_json::json!({
+                "keyImage": hex::encode(key_image),
+                "amount": amount,
+                "height": height,
+            }),
+            WalletEvent::OutputUnlocked { output_position, amount, height, .. }
+            | WalletEvent::DepositMatured { output_position, amount, height, .. } => {
+                serde_json::json!({
+                    "outputIndex": output_position,
+                    "amount": amount,
+                    "height": height,
+                })
+            }
+            WalletEvent::SendConfirmed { height, .. } => serde_json::json!({ "height": height }),
+            WalletEvent::SendFailed { reason, .. } => serde_json::json!({ "reason": reason }),
+        };
+        value["type"] = self.kind().into();
+        value["transactionHash"] = hex::encode(self.tx_hash()).into();
+        value
+    }
+}
+
+/// Fan-out of wallet events to broadcast subscribers and registered
+/// [`EventListener`]s. Publishing never blocks; a subscriber that falls
+/// more than `EVENT_CAPACITY` events behind gets `RecvError::Lagged`.
+#[derive(Clone)]
+pub struct WalletEvents {
+    sender: broadcast::Sender<WalletEvent>,
+    listeners: Arc<RwLock<Vec<Arc<dyn EventListener>>>>,
+}
+
+impl WalletEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender, listeners: Arc::new(RwLock::new(Vec::new())) }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WalletEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn add_listener(&self, listener: Arc<dyn EventListener>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    pub fn publish(&self, event: WalletEvent) {
+        for listener in self.listeners.read().unwrap().iter() {
+            listener.on_wallet_event(&event);
+        }
+        // No subscribers is not an error.
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for WalletEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
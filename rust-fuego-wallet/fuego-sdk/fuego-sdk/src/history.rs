@@ -0,0 +1,85 @@
+//! Filtered, paginated views of the wallet's transaction history. The
+//! scanner evaluates a [`HistoryQuery`] over its in-memory rows, which is
+//! what fuego_walletd's `getTransactions` uses; `SqliteStore` turns the
+//! same query into SQL for embedders that keep history in the database.
+
+use crate::scanner::{HistoryDirection, HistoryEntry};
+use crate::serialization::PaymentId;
+use serde::{Deserialize, Serialize};
+
+/// Which history rows to return. Every filter is optional; rows come in
+/// scan order (ascending height) unless `newest_first` is set, and
+/// `offset`/`limit` page through the matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    /// Lowest block height included.
+    pub first_height: u64,
+    /// Number of blocks from `first_height` on; `None` = up to the tip.
+    pub block_count: Option<u64>,
+    pub direction: Option<HistoryDirection>,
+    /// Inclusive amount bounds.
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    pub payment_id: Option<PaymentId>,
+    /// Receiving (incoming) or spending (outgoing) subaddress.
+    pub subaddress: Option<u32>,
+    /// Newest rows first instead of scan order.
+    pub newest_first: bool,
+    /// Matching rows skipped before the page starts.
+    pub offset: usize,
+    /// Page size; `None` = every remaining match.
+    pub limit: Option<usize>,
+}
+
+/// One page of a [`HistoryQuery`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Rows matching the filters, over all pages.
+    pub total: usize,
+}
+
+impl HistoryQuery {
+    /// Rows in blocks `first_height .. first_height + block_count`, the
+    /// range of `getTransactions(firstBlockIndex, blockCount)`.
+    pub fn blocks(first_height: u64, block_count: u64) -> Self {
+        Self { first_height, block_count: Some(block_count), ..Self::default() }
+    }
+
+    /// Exclusive upper height bound, if any.
+    pub fn end_height(&self) -> Option<u64> {
+        self.block_count.map(|count| self.first_height.saturating_add(count))
+    }
+
+    /// Whether `entry` passes every filter (pagination aside).
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        entry.block_height >= self.first_height
+            && self.end_height().is_none_or(|end| entry.block_height < end)
+            && self.direction.is_none_or(|d| entry.direction == d)
+            && self.min_amount.is_none_or(|min| entry.amount >= min)
+            && self.max_amount.is_none_or(|max| entry.amount <= max)
+            && self.payment_id.is_none_or(|id| entry.payment_id == Some(id))
+            && self.subaddress.is_none_or(|s| entry.subaddress == s)
+    }
+
+    /// Filter and page rows already in scan order.
+    pub fn page(&self, entries: &[HistoryEntry]) -> HistoryPage {
+        if self.newest_first {
+            self.page_of(entries.iter().rev())
+        } else {
+            self.page_of(entries.iter())
+        }
+    }
+
+    fn page_of<'a>(&self, entries: impl Iterator<Item = &'a HistoryEntry>) -> HistoryPage {
+        let mut total = 0;
+        let mut page = Vec::new();
+        for entry in entries.filter(|e| self.matches(e)) {
+            if total >= self.offset && self.limit.is_none_or(|limit| page.len() < limit) {
+                page.push(entry.clone());
+            }
+            total += 1;
+        }
+        HistoryPage { entries: page, total }
+    }
+}
@@ -0,0 +1,146 @@
+//! HTTP client settings shared by every outbound client: daemon RPC, the
+//! orderbook and CD market, and the Bitcoin/EVM chain RPCs.
+
+use crate::error::{Result, SdkError};
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Tor's default SOCKS port. `socks5h` resolves hostnames through the
+/// proxy, so lookups don't leak around it.
+pub const TOR_SOCKS_PROXY: &str = "socks5h://127.0.0.1:9050";
+
+/// Destination labels for [`HttpConfig::client`]. With isolation on, each
+/// label gets its own Tor circuit.
+pub const DEST_DAEMON: &str = "daemon";
+pub const DEST_ORDERBOOK: &str = "orderbook";
+pub const DEST_CD_MARKET: &str = "cd-market";
+pub const DEST_BTC_RPC: &str = "btc-rpc";
+pub const DEST_EVM_RPC: &str = "evm-rpc";
+
+/// Hosts that never go through the proxy: a Tor exit can't reach them.
+const LOOPBACK_HOSTS: &str = "localhost,127.0.0.1,::1";
+
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+    /// `socks5h://`, `socks5://`, `http://` or `https://` proxy for all
+    /// non-loopback traffic.
+    pub proxy: Option<String>,
+    /// Give each destination its own SOCKS credentials. Tor isolates
+    /// streams by credentials (IsolateSOCKSAuth), so destinations never
+    /// share a circuit.
+    pub isolate_destinations: bool,
+    /// Whole-request timeout.
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Extra trusted root certificates, PEM encoded, for nodes behind a
+    /// private CA.
+    pub ca_certificates: Vec<Vec<u8>>,
+    /// Refuse plain-HTTP URLs.
+    pub https_only: bool,
+    /// Sent as HTTP basic auth with every request.
+    pub basic_auth: Option<(String, String)>,
+}
+
+impl HttpConfig {
+    /// Route through a local Tor daemon, one circuit per destination.
+    pub fn tor() -> Self {
+        Self {
+            proxy: Some(TOR_SOCKS_PROXY.to_string()),
+            isolate_destinations: true,
+            timeout: Some(Duration::from_secs(120)),
+            connect_timeout: Some(Duration::from_secs(60)),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn isolate_destinations(mut self, isolate: bool) -> Self {
+        self.isolate_destinations = isolate;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_ca_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_certificates.push(pem.into());
+        self
+    }
+
+    pub fn https_only(mut self, https_only: bool) -> Self {
+        self.https_only = https_only;
+        self
+    }
+
+    pub fn with_basic_auth(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.basic_auth = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Scheme for clients that build URLs from a host and port.
+    pub fn scheme(&self) -> &'static str {
+        if self.https_only {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    /// Client for traffic to `destination` (one of the `DEST_*` labels or
+    /// any other name).
+    pub fn client(&self, destination: &str) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().https_only(self.https_only);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        for pem in &self.ca_certificates {
+            let cert = reqwest::Certificate::from_pem(pem)
+                .map_err(|e| SdkError::Config(format!("CA certificate: {e}")))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some((user, password)) = &self.basic_auth {
+            let token = base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+            let mut value = HeaderValue::from_str(&format!("Basic {token}"))
+                .map_err(|e| SdkError::Config(format!("basic auth: {e}")))?;
+            value.set_sensitive(true);
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value);
+            builder = builder.default_headers(headers);
+        }
+        if let Some(proxy) = &self.proxy {
+            let mut proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| SdkError::Config(format!("proxy: {e}")))?
+                .no_proxy(reqwest::NoProxy::from_string(LOOPBACK_HOSTS));
+            if self.isolate_destinations {
+                proxy = proxy.basic_auth(destination, isolation_token());
+            }
+            builder = builder.proxy(proxy);
+        }
+        builder
+            .build()
+            .map_err(|e| SdkError::Config(format!("HTTP client: {e}")))
+    }
+}
+
+/// Per-process SOCKS password, so two wallets on one Tor daemon don't
+/// share circuits either.
+fn isolation_token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| hex::encode(rand::random::<[u8; 8]>()))
+}
@@ -34,14 +34,20 @@
 pub mod builder;
 pub mod crypto;
 pub mod error;
+pub mod events;
+pub mod history;
+pub mod http;
 pub mod network;
 pub mod node;
 pub mod scanner;
 pub mod serialization;
 pub mod store;
+pub mod sync_service;
 pub mod traits;
 pub mod transaction_builder;
+pub mod unsigned_tx;
 pub mod types;
+pub mod validation;
 pub mod vault;
 pub mod wallet;
 pub mod alias;
@@ -50,14 +56,18 @@ pub mod cd_market;
 pub mod chain;
 
 pub use builder::{SdkBuilder, WalletBuilder};
-pub use error::{Result, SdkError};
-pub use network::{NullNetwork, RpcProvider};
+pub use error::{Result, SdkError, TxRejection};
+pub use events::{WalletEvent, WalletEvents};
+pub use history::{HistoryPage, HistoryQuery};
+pub use http::HttpConfig;
+pub use network::{FailoverProvider, NullNetwork, P2pConfig, P2pProvider, RpcProvider};
 pub use node::EmbeddedNode;
 pub use scanner::UtxoScanner;
 pub use store::MemoryStore;
+pub use sync_service::{SyncHandle, SyncRound, SyncService, SyncTarget};
 pub use traits::*;
 pub use types::*;
-pub use vault::WalletVault;
+pub use vault::{KdfParams, WalletVault};
 pub use wallet::Wallet;
 pub use orderbook::OrderbookClient;
 pub use cd_market::CdMarketClient;
@@ -0,0 +1,370 @@
+use crate::error::{Result, SdkError};
+use crate::http::HttpConfig;
+use crate::network::RpcProvider;
+use crate::traits::{NetworkProvider, PeerInfo};
+use crate::transaction_builder::BuiltTransaction;
+use crate::types::*;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Consecutive failures after which a node is only tried once every
+/// healthier node has failed too.
+const MAX_CONSECUTIVE_ERRORS: u32 = 3;
+
+/// Blocks a node may trail the best known height before it is ranked
+/// behind the nodes that keep up.
+pub const DEFAULT_MAX_LAG: u64 = 5;
+
+/// Per-node health, as tracked by [`FailoverProvider`].
+#[derive(Debug, Clone, Default)]
+pub struct NodeStats {
+    /// Smoothed response time of successful calls.
+    pub latency: Option<Duration>,
+    pub consecutive_errors: u32,
+    pub total_errors: u64,
+    /// Height the node last reported.
+    pub height: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+struct Node<B> {
+    backend: B,
+    stats: Mutex<NodeStats>,
+}
+
+/// `NetworkProvider` over several backends. Calls go to the healthiest node
+/// first (fewest recent errors, not lagging, lowest latency) and fall over
+/// to the next one on network failures. Lookups by height skip nodes known
+/// not to have reached it. With a quorum above one, the tip, headers,
+/// blocks and transactions are only trusted once that many nodes that keep
+/// up agree on them.
+///
+/// Ranking works for any backend `B`: a client with its own transport
+/// keeps one handle per node and routes calls through
+/// [`FailoverProvider::first_ok`].
+pub struct FailoverProvider<B = Arc<dyn NetworkProvider>> {
+    nodes: Vec<Node<B>>,
+    quorum: usize,
+    max_lag: u64,
+}
+
+impl FailoverProvider {
+    /// Failover over one [`RpcProvider`] per `(host, port)`.
+    pub fn rpc<S: Into<String>>(nodes: impl IntoIterator<Item = (S, u16)>) -> Self {
+        Self::rpc_with_http(nodes, &HttpConfig::default()).expect("default HTTP client")
+    }
+
+    pub fn rpc_with_http<S: Into<String>>(
+        nodes: impl IntoIterator<Item = (S, u16)>,
+        http: &HttpConfig,
+    ) -> Result<Self> {
+        let backends = nodes
+            .into_iter()
+            .map(|(host, port)| {
+                RpcProvider::with_http(host, port, http).map(|p| Arc::new(p) as Arc<dyn NetworkProvider>)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self::new(backends))
+    }
+
+    /// Nodes that may hold block `height`: those not known to be below it.
+    /// Every node when none qualifies, so the error comes from a node.
+    fn holding(&self, height: u64) -> Vec<usize> {
+        let stats = self.node_stats();
+        let order = self.ranked();
+        let holding: Vec<usize> =
+            order.iter().copied().filter(|&i| stats[i].height.is_none_or(|h| h > height)).collect();
+        if holding.is_empty() {
+            order
+        } else {
+            holding
+        }
+    }
+
+    /// Nodes within `max_lag` of the best known height, healthiest first.
+    fn current(&self) -> Vec<usize> {
+        let stats = self.node_stats();
+        let best = stats.iter().filter_map(|s| s.height).max().unwrap_or(0);
+        self.ranked()
+            .into_iter()
+            .filter(|&i| stats[i].height.is_none_or(|h| h + self.max_lag >= best))
+            .collect()
+    }
+
+    /// First answer at least `quorum` of `nodes` agree on, compared by
+    /// `key`. Network failures count as no vote.
+    async fn agreed<T, K, F, Fut>(&self, nodes: &[usize], call: F, key: impl Fn(&T) -> K) -> Result<T>
+    where
+        T: Send + 'static,
+        K: PartialEq,
+        F: Fn(Arc<dyn NetworkProvider>) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let mut last_error = SdkError::Network("no nodes configured".into());
+        let mut answers = Vec::new();
+        for (_, result) in self.ask_all(nodes, call).await {
+            match result {
+                Ok(answer) => answers.push(answer),
+                Err(e) if is_node_failure(&e) => last_error = e,
+                Err(e) => return Err(e),
+            }
+        }
+        let keys: Vec<K> = answers.iter().map(&key).collect();
+        let agreed = (0..answers.len()).find(|&i| keys.iter().filter(|k| **k == keys[i]).count() >= self.quorum);
+        match agreed {
+            Some(i) => Ok(answers.swap_remove(i)),
+            None if answers.is_empty() => Err(last_error),
+            None => Err(SdkError::Network(format!("no {} nodes agree on the answer", self.quorum))),
+        }
+    }
+}
+
+impl<B: Clone + Send + Sync + 'static> FailoverProvider<B> {
+    pub fn new(backends: Vec<B>) -> Self {
+        Self {
+            nodes: backends
+                .into_iter()
+                .map(|backend| Node { backend, stats: Mutex::new(NodeStats::default()) })
+                .collect(),
+            quorum: 1,
+            max_lag: DEFAULT_MAX_LAG,
+        }
+    }
+
+    /// Require `quorum` nodes to agree on the tip hash before trusting it.
+    pub fn with_quorum(mut self, quorum: usize) -> Self {
+        self.quorum = quorum.max(1);
+        self
+    }
+
+    pub fn with_max_lag(mut self, blocks: u64) -> Self {
+        self.max_lag = blocks;
+        self
+    }
+
+    /// Health of every node, in the order they were given.
+    pub fn node_stats(&self) -> Vec<NodeStats> {
+        self.nodes.iter().map(|n| n.stats.lock().unwrap().clone()).collect()
+    }
+
+    /// The node calls currently go to first.
+    pub fn preferred(&self) -> Option<&B> {
+        self.ranked().first().map(|&i| &self.nodes[i].backend)
+    }
+
+    /// Node indices, healthiest first.
+    fn ranked(&self) -> Vec<usize> {
+        let stats = self.node_stats();
+        let best = stats.iter().filter_map(|s| s.height).max().unwrap_or(0);
+        let mut order: Vec<usize> = (0..self.nodes.len()).collect();
+        order.sort_by_key(|&i| {
+            let s = &stats[i];
+            let failing = s.consecutive_errors >= MAX_CONSECUTIVE_ERRORS;
+            let lagging = s.height.is_some_and(|h| h + self.max_lag < best);
+            (failing, lagging, s.consecutive_errors, s.latency.unwrap_or(Duration::MAX))
+        });
+        order
+    }
+
+    fn record<T>(&self, index: usize, elapsed: Duration, result: &Result<T>) {
+        let mut stats = self.nodes[index].stats.lock().unwrap();
+        match result {
+            Err(e) if is_node_failure(e) => {
+                stats.consecutive_errors += 1;
+                stats.total_errors += 1;
+                stats.last_error = Some(e.to_string());
+            }
+            _ => {
+                stats.consecutive_errors = 0;
+                stats.latency = Some(match stats.latency {
+                    Some(avg) => (avg * 3 + elapsed) / 4,
+                    None => elapsed,
+                });
+            }
+        }
+    }
+
+    /// Run `call` against each node in rank order until one answers with
+    /// something other than a network failure.
+    pub async fn first_ok<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn(B) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.first_ok_of(&self.ranked(), call).await
+    }
+
+    /// `first_ok` over `nodes` only, in the given order.
+    async fn first_ok_of<T, F, Fut>(&self, nodes: &[usize], call: F) -> Result<T>
+    where
+        F: Fn(B) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = SdkError::Network("no nodes configured".into());
+        for &index in nodes {
+            let started = Instant::now();
+            let result = call(self.nodes[index].backend.clone()).await;
+            self.record(index, started.elapsed(), &result);
+            match result {
+                Err(e) if is_node_failure(&e) => last_error = e,
+                other => return other,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Ask every node at once for an answer carrying its height, such as
+    /// its tip or node info, and record those heights so ranking, lookups
+    /// by height and the lag checks know which nodes keep up. Results come
+    /// back sorted by node index.
+    pub async fn poll_heights<T, F, Fut>(&self, call: F, height: impl Fn(&T) -> u64) -> Vec<(usize, Result<T>)>
+    where
+        T: Send + 'static,
+        F: Fn(B) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let all: Vec<usize> = (0..self.nodes.len()).collect();
+        let results = self.ask_all(&all, call).await;
+        for (index, result) in &results {
+            if let Ok(answer) = result {
+                self.nodes[*index].stats.lock().unwrap().height = Some(height(answer));
+            }
+        }
+        results
+    }
+
+    /// Ask the given nodes concurrently; results come back sorted by node
+    /// index.
+    async fn ask_all<T, F, Fut>(&self, nodes: &[usize], call: F) -> Vec<(usize, Result<T>)>
+    where
+        T: Send + 'static,
+        F: Fn(B) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let mut tasks = tokio::task::JoinSet::new();
+        for &index in nodes {
+            let fut = call(self.nodes[index].backend.clone());
+            tasks.spawn(async move {
+                let started = Instant::now();
+                let result = fut.await;
+                (index, started.elapsed(), result)
+            });
+        }
+        let mut results = Vec::with_capacity(nodes.len());
+        while let Some(joined) = tasks.join_next().await {
+            // A panicking backend just drops out of the answer set.
+            let Ok((index, elapsed, result)) = joined else {
+                continue;
+            };
+            self.record(index, elapsed, &result);
+            results.push((index, result));
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results
+    }
+}
+
+impl FailoverProvider {
+    /// Highest height whose top block at least `quorum` nodes agree on.
+    async fn agreed_height(&self, heights: &[(usize, u64)]) -> Result<u64> {
+        let mut candidates: Vec<u64> = heights.iter().map(|&(_, h)| h).filter(|&h| h > 0).collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        candidates.dedup();
+
+        for height in candidates {
+            let voters: Vec<usize> =
+                heights.iter().filter(|&&(_, h)| h >= height).map(|&(i, _)| i).collect();
+            if voters.len() < self.quorum {
+                continue;
+            }
+            let tops = self
+                .ask_all(&voters, |node| async move { node.get_header(height - 1).await })
+                .await;
+            let hashes: Vec<[u8; 32]> =
+                tops.into_iter().filter_map(|(_, r)| r.ok().map(|h| h.hash)).collect();
+            if hashes.iter().any(|hash| hashes.iter().filter(|h| *h == hash).count() >= self.quorum) {
+                return Ok(height);
+            }
+        }
+        Err(SdkError::Network(format!(
+            "no {} nodes agree on a chain tip",
+            self.quorum
+        )))
+    }
+}
+
+/// Errors that say something about the node rather than the request.
+fn is_node_failure(error: &SdkError) -> bool {
+    matches!(
+        error,
+        SdkError::Network(_) | SdkError::Io(_) | SdkError::Json(_) | SdkError::Serialization(_)
+    )
+}
+
+#[async_trait]
+impl NetworkProvider for FailoverProvider {
+    async fn get_height(&self) -> Result<u64> {
+        let mut heights = Vec::new();
+        let mut last_error = SdkError::Network("no nodes configured".into());
+        for (index, result) in self.poll_heights(|node| async move { node.get_height().await }, |h| *h).await {
+            match result {
+                Ok(height) => heights.push((index, height)),
+                Err(e) => last_error = e,
+            }
+        }
+        if heights.is_empty() {
+            return Err(last_error);
+        }
+        if self.quorum <= 1 {
+            return Ok(heights.iter().map(|&(_, h)| h).max().unwrap_or(0));
+        }
+        self.agreed_height(&heights).await
+    }
+
+    async fn get_header(&self, height: u64) -> Result<BlockHeader> {
+        let nodes = self.holding(height);
+        if self.quorum <= 1 {
+            return self.first_ok_of(&nodes, |node| async move { node.get_header(height).await }).await;
+        }
+        self.agreed(&nodes, |node| async move { node.get_header(height).await }, |h| h.hash)
+            .await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<Block> {
+        let nodes = self.holding(height);
+        if self.quorum <= 1 {
+            return self.first_ok_of(&nodes, |node| async move { node.get_block(height).await }).await;
+        }
+        self.agreed(&nodes, |node| async move { node.get_block(height).await }, |b| b.header.hash)
+            .await
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<[u8; 32]> {
+        self.first_ok(|node| async move { node.send_transaction(tx).await }).await
+    }
+
+    async fn broadcast(&self, tx: &BuiltTransaction) -> Result<[u8; 32]> {
+        self.first_ok(|node| async move { node.broadcast(tx).await }).await
+    }
+
+    async fn get_peers(&self) -> Result<Vec<PeerInfo>> {
+        self.first_ok(|node| async move { node.get_peers().await }).await
+    }
+
+    async fn get_transaction(&self, hash: &[u8; 32]) -> Result<Option<Transaction>> {
+        // A lagging node may not have seen the transaction yet.
+        let nodes = self.current();
+        if self.quorum <= 1 {
+            return self.first_ok_of(&nodes, |node| async move { node.get_transaction(hash).await }).await;
+        }
+        let hash = *hash;
+        self.agreed(
+            &nodes,
+            |node| async move { node.get_transaction(&hash).await },
+            |tx| tx.as_ref().map(|tx| (tx.hash, tx.blob.clone())),
+        )
+        .await
+    }
+}
@@ -0,0 +1,534 @@
+//! Levin framing and the CryptoNote P2P messages [`super::P2pProvider`]
+//! speaks. Message bodies are epee KV documents, read and written with the
+//! codec in [`crate::serialization`]. Public so stand-in peers (tests,
+//! tooling) can talk the same protocol.
+
+use crate::error::{Result, SdkError};
+use crate::serialization::{parse_kv_document, write_kv_document, KvValue};
+use std::collections::BTreeMap;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const LEVIN_SIGNATURE: u64 = 0x0101_0101_0101_2101;
+pub const LEVIN_PROTOCOL_VERSION: u32 = 1;
+pub const LEVIN_HEADER_LEN: usize = 33;
+/// LEVIN_DEFAULT_MAX_PACKET_SIZE: larger bodies are a protocol violation.
+pub const LEVIN_MAX_PACKET_SIZE: u64 = 100_000_000;
+
+pub const LEVIN_PACKET_REQUEST: u32 = 1;
+pub const LEVIN_PACKET_RESPONSE: u32 = 2;
+
+pub const COMMAND_HANDSHAKE: u32 = 1001;
+pub const COMMAND_TIMED_SYNC: u32 = 1002;
+pub const COMMAND_PING: u32 = 1003;
+pub const NOTIFY_NEW_BLOCK: u32 = 2001;
+pub const NOTIFY_NEW_TRANSACTIONS: u32 = 2002;
+pub const NOTIFY_REQUEST_GET_OBJECTS: u32 = 2003;
+pub const NOTIFY_RESPONSE_GET_OBJECTS: u32 = 2004;
+pub const NOTIFY_REQUEST_CHAIN: u32 = 2006;
+pub const NOTIFY_RESPONSE_CHAIN_ENTRY: u32 = 2007;
+
+/// One Levin packet. Invokes (`expect_response`) are answered with a
+/// response packet carrying the same command; notifications are not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevinMessage {
+    pub command: u32,
+    pub expect_response: bool,
+    pub is_response: bool,
+    pub return_code: i32,
+    pub body: Vec<u8>,
+}
+
+impl LevinMessage {
+    pub fn invoke(command: u32, body: Vec<u8>) -> Self {
+        Self { command, expect_response: true, is_response: false, return_code: 0, body }
+    }
+
+    pub fn notify(command: u32, body: Vec<u8>) -> Self {
+        Self { command, expect_response: false, is_response: false, return_code: 0, body }
+    }
+
+    pub fn response(command: u32, body: Vec<u8>) -> Self {
+        Self { command, expect_response: false, is_response: true, return_code: 1, body }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(LEVIN_HEADER_LEN + self.body.len());
+        out.extend_from_slice(&LEVIN_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&(self.body.len() as u64).to_le_bytes());
+        out.push(self.expect_response as u8);
+        out.extend_from_slice(&self.command.to_le_bytes());
+        out.extend_from_slice(&self.return_code.to_le_bytes());
+        let flags = if self.is_response { LEVIN_PACKET_RESPONSE } else { LEVIN_PACKET_REQUEST };
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&LEVIN_PROTOCOL_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &LevinMessage) -> Result<()> {
+    writer.write_all(&message.encode()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<LevinMessage> {
+    let mut header = [0u8; LEVIN_HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    let signature = u64::from_le_bytes(header[0..8].try_into().unwrap());
+    if signature != LEVIN_SIGNATURE {
+        return Err(SdkError::Network(format!("levin: bad signature {signature:#x}")));
+    }
+    let body_len = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    if body_len > LEVIN_MAX_PACKET_SIZE {
+        return Err(SdkError::Network(format!("levin: {body_len} byte packet exceeds limit")));
+    }
+    let mut body = vec![0u8; body_len as usize];
+    reader.read_exact(&mut body).await?;
+    Ok(LevinMessage {
+        expect_response: header[16] != 0,
+        command: u32_at(17),
+        return_code: u32_at(21) as i32,
+        is_response: u32_at(25) & LEVIN_PACKET_RESPONSE != 0,
+        body,
+    })
+}
+
+// ---------------------------------------------------------------- payloads
+
+type KvObject = BTreeMap<String, KvValue>;
+
+fn decode_document(body: &[u8], what: &str) -> Result<KvObject> {
+    parse_kv_document(body).map_err(|e| SdkError::Serialization(format!("{what}: {}", e.0)))
+}
+
+fn field<'a>(object: &'a KvObject, name: &str, what: &str) -> Result<&'a KvValue> {
+    object
+        .get(name)
+        .ok_or_else(|| SdkError::Serialization(format!("{what}: missing {name}")))
+}
+
+fn field_u64(object: &KvObject, name: &str, what: &str) -> Result<u64> {
+    field(object, name, what)?
+        .as_u64()
+        .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} is not an integer")))
+}
+
+fn field_bytes<'a>(object: &'a KvObject, name: &str, what: &str) -> Result<&'a [u8]> {
+    field(object, name, what)?
+        .as_bytes()
+        .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} is not a string")))
+}
+
+fn field_object<'a>(object: &'a KvObject, name: &str, what: &str) -> Result<&'a KvObject> {
+    field(object, name, what)?
+        .as_object()
+        .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} is not a section")))
+}
+
+/// Absent arrays are empty: epee leaves out empty containers.
+fn field_array<'a>(object: &'a KvObject, name: &str, what: &str) -> Result<&'a [KvValue]> {
+    match object.get(name) {
+        None => Ok(&[]),
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} is not an array"))),
+    }
+}
+
+fn field_blobs(object: &KvObject, name: &str, what: &str) -> Result<Vec<Vec<u8>>> {
+    field_array(object, name, what)?
+        .iter()
+        .map(|item| {
+            item.as_bytes()
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} holds a non-string")))
+        })
+        .collect()
+}
+
+/// Hash lists travel as one string of concatenated 32-byte ids.
+fn field_hashes(object: &KvObject, name: &str, what: &str) -> Result<Vec<[u8; 32]>> {
+    let Some(value) = object.get(name) else {
+        return Ok(Vec::new());
+    };
+    let bytes = value
+        .as_bytes()
+        .ok_or_else(|| SdkError::Serialization(format!("{what}: {name} is not a string")))?;
+    if bytes.len() % 32 != 0 {
+        return Err(SdkError::Serialization(format!("{what}: {name} is not a list of hashes")));
+    }
+    Ok(bytes.chunks_exact(32).map(|c| c.try_into().unwrap()).collect())
+}
+
+fn hashes_value(hashes: &[[u8; 32]]) -> KvValue {
+    KvValue::Bytes(hashes.concat())
+}
+
+fn blobs_value(blobs: &[Vec<u8>]) -> KvValue {
+    KvValue::Array(blobs.iter().cloned().map(KvValue::Bytes).collect())
+}
+
+fn object(entries: impl IntoIterator<Item = (&'static str, KvValue)>) -> KvObject {
+    entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+}
+
+/// `basic_node_data`: who the sender is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicNodeData {
+    pub network_id: [u8; 16],
+    pub version: u8,
+    pub local_time: u64,
+    /// Inbound P2P port; 0 tells the peer not to connect back.
+    pub my_port: u32,
+    pub peer_id: u64,
+}
+
+impl BasicNodeData {
+    fn to_kv(&self) -> KvValue {
+        KvValue::Object(object([
+            ("network_id", KvValue::Bytes(self.network_id.to_vec())),
+            ("version", KvValue::Unsigned(self.version as u64)),
+            ("local_time", KvValue::Unsigned(self.local_time)),
+            ("my_port", KvValue::Unsigned(self.my_port as u64)),
+            ("peer_id", KvValue::Unsigned(self.peer_id)),
+        ]))
+    }
+
+    fn from_kv(object: &KvObject) -> Result<Self> {
+        const WHAT: &str = "node_data";
+        let network_id = field_bytes(object, "network_id", WHAT)?
+            .try_into()
+            .map_err(|_| SdkError::Serialization("node_data: network_id is not 16 bytes".into()))?;
+        Ok(Self {
+            network_id,
+            version: field_u64(object, "version", WHAT)? as u8,
+            local_time: field_u64(object, "local_time", WHAT).unwrap_or(0),
+            my_port: field_u64(object, "my_port", WHAT).unwrap_or(0) as u32,
+            peer_id: field_u64(object, "peer_id", WHAT)?,
+        })
+    }
+}
+
+/// `CORE_SYNC_DATA`: the sender's chain tip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreSyncData {
+    pub current_height: u64,
+    pub top_id: [u8; 32],
+}
+
+impl CoreSyncData {
+    fn to_kv(self) -> KvValue {
+        KvValue::Object(object([
+            ("current_height", KvValue::Unsigned(self.current_height)),
+            ("top_id", KvValue::Bytes(self.top_id.to_vec())),
+        ]))
+    }
+
+    fn from_kv(object: &KvObject) -> Result<Self> {
+        const WHAT: &str = "payload_data";
+        let top_id = field_bytes(object, "top_id", WHAT)?
+            .try_into()
+            .map_err(|_| SdkError::Serialization("payload_data: top_id is not a hash".into()))?;
+        Ok(Self { current_height: field_u64(object, "current_height", WHAT)?, top_id })
+    }
+}
+
+/// Packed `peerlist_entry`: ip u32, port u32, id u64, last_seen u64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerlistEntry {
+    /// IPv4 address in network byte order, as the daemon stores it.
+    pub ip: u32,
+    pub port: u32,
+    pub id: u64,
+    pub last_seen: u64,
+}
+
+const PEERLIST_ENTRY_LEN: usize = 24;
+
+impl PeerlistEntry {
+    pub fn address(&self) -> String {
+        format!("{}:{}", std::net::Ipv4Addr::from(self.ip.to_le_bytes()), self.port)
+    }
+
+    fn encode_list(entries: &[Self]) -> KvValue {
+        let mut out = Vec::with_capacity(entries.len() * PEERLIST_ENTRY_LEN);
+        for entry in entries {
+            out.extend_from_slice(&entry.ip.to_le_bytes());
+            out.extend_from_slice(&entry.port.to_le_bytes());
+            out.extend_from_slice(&entry.id.to_le_bytes());
+            out.extend_from_slice(&entry.last_seen.to_le_bytes());
+        }
+        KvValue::Bytes(out)
+    }
+
+    fn decode_list(object: &KvObject, what: &str) -> Result<Vec<Self>> {
+        let Some(value) = object.get("local_peerlist") else {
+            return Ok(Vec::new());
+        };
+        let bytes = value
+            .as_bytes()
+            .filter(|b| b.len() % PEERLIST_ENTRY_LEN == 0)
+            .ok_or_else(|| SdkError::Serialization(format!("{what}: malformed local_peerlist")))?;
+        Ok(bytes
+            .chunks_exact(PEERLIST_ENTRY_LEN)
+            .map(|c| Self {
+                ip: u32::from_le_bytes(c[0..4].try_into().unwrap()),
+                port: u32::from_le_bytes(c[4..8].try_into().unwrap()),
+                id: u64::from_le_bytes(c[8..16].try_into().unwrap()),
+                last_seen: u64::from_le_bytes(c[16..24].try_into().unwrap()),
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeRequest {
+    pub node_data: BasicNodeData,
+    pub payload_data: CoreSyncData,
+}
+
+impl HandshakeRequest {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([
+            ("node_data", self.node_data.to_kv()),
+            ("payload_data", self.payload_data.to_kv()),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "handshake request";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self {
+            node_data: BasicNodeData::from_kv(field_object(&root, "node_data", WHAT)?)?,
+            payload_data: CoreSyncData::from_kv(field_object(&root, "payload_data", WHAT)?)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeResponse {
+    pub node_data: BasicNodeData,
+    pub payload_data: CoreSyncData,
+    pub local_peerlist: Vec<PeerlistEntry>,
+}
+
+impl HandshakeResponse {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([
+            ("node_data", self.node_data.to_kv()),
+            ("payload_data", self.payload_data.to_kv()),
+            ("local_peerlist", PeerlistEntry::encode_list(&self.local_peerlist)),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "handshake response";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self {
+            node_data: BasicNodeData::from_kv(field_object(&root, "node_data", WHAT)?)?,
+            payload_data: CoreSyncData::from_kv(field_object(&root, "payload_data", WHAT)?)?,
+            local_peerlist: PeerlistEntry::decode_list(&root, WHAT)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedSyncRequest {
+    pub payload_data: CoreSyncData,
+}
+
+impl TimedSyncRequest {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([("payload_data", self.payload_data.to_kv())]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "timed sync request";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self { payload_data: CoreSyncData::from_kv(field_object(&root, "payload_data", WHAT)?)? })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedSyncResponse {
+    pub local_time: u64,
+    pub payload_data: CoreSyncData,
+    pub local_peerlist: Vec<PeerlistEntry>,
+}
+
+impl TimedSyncResponse {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([
+            ("local_time", KvValue::Unsigned(self.local_time)),
+            ("payload_data", self.payload_data.to_kv()),
+            ("local_peerlist", PeerlistEntry::encode_list(&self.local_peerlist)),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "timed sync response";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self {
+            local_time: field_u64(&root, "local_time", WHAT).unwrap_or(0),
+            payload_data: CoreSyncData::from_kv(field_object(&root, "payload_data", WHAT)?)?,
+            local_peerlist: PeerlistEntry::decode_list(&root, WHAT)?,
+        })
+    }
+}
+
+/// Body of a PING response.
+pub fn ping_response(peer_id: u64) -> Vec<u8> {
+    write_kv_document(&object([
+        ("status", KvValue::Bytes(b"OK".to_vec())),
+        ("peer_id", KvValue::Unsigned(peer_id)),
+    ]))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotifyNewTransactions {
+    pub txs: Vec<Vec<u8>>,
+}
+
+impl NotifyNewTransactions {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([("txs", blobs_value(&self.txs))]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "new transactions";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self { txs: field_blobs(&root, "txs", WHAT)? })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestGetObjects {
+    pub txs: Vec<[u8; 32]>,
+    pub blocks: Vec<[u8; 32]>,
+}
+
+impl RequestGetObjects {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([
+            ("txs", hashes_value(&self.txs)),
+            ("blocks", hashes_value(&self.blocks)),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "get objects request";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self {
+            txs: field_hashes(&root, "txs", WHAT)?,
+            blocks: field_hashes(&root, "blocks", WHAT)?,
+        })
+    }
+}
+
+/// A block blob with the blobs of its non-miner transactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockCompleteEntry {
+    pub block: Vec<u8>,
+    pub txs: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseGetObjects {
+    pub txs: Vec<Vec<u8>>,
+    pub blocks: Vec<BlockCompleteEntry>,
+    pub missed_ids: Vec<[u8; 32]>,
+    pub current_blockchain_height: u64,
+}
+
+impl ResponseGetObjects {
+    pub fn encode(&self) -> Vec<u8> {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|entry| {
+                KvValue::Object(object([
+                    ("block", KvValue::Bytes(entry.block.clone())),
+                    ("txs", blobs_value(&entry.txs)),
+                ]))
+            })
+            .collect();
+        write_kv_document(&object([
+            ("txs", blobs_value(&self.txs)),
+            ("blocks", KvValue::Array(blocks)),
+            ("missed_ids", hashes_value(&self.missed_ids)),
+            ("current_blockchain_height", KvValue::Unsigned(self.current_blockchain_height)),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "get objects response";
+        let root = decode_document(body, WHAT)?;
+        let blocks = field_array(&root, "blocks", WHAT)?
+            .iter()
+            .map(|entry| {
+                let entry = entry
+                    .as_object()
+                    .ok_or_else(|| SdkError::Serialization(format!("{WHAT}: block entry is not a section")))?;
+                Ok(BlockCompleteEntry {
+                    block: field_bytes(entry, "block", WHAT)?.to_vec(),
+                    txs: field_blobs(entry, "txs", WHAT)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            txs: field_blobs(&root, "txs", WHAT)?,
+            blocks,
+            missed_ids: field_hashes(&root, "missed_ids", WHAT)?,
+            current_blockchain_height: field_u64(&root, "current_blockchain_height", WHAT)?,
+        })
+    }
+}
+
+/// Sparse chain locator, newest id first and ending with genesis.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestChain {
+    pub block_ids: Vec<[u8; 32]>,
+}
+
+impl RequestChain {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([("block_ids", hashes_value(&self.block_ids))]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "chain request";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self { block_ids: field_hashes(&root, "block_ids", WHAT)? })
+    }
+}
+
+/// Block ids from `start_height` (the newest locator entry the peer
+/// shares) onward.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseChainEntry {
+    pub start_height: u64,
+    pub total_height: u64,
+    pub block_ids: Vec<[u8; 32]>,
+}
+
+impl ResponseChainEntry {
+    pub fn encode(&self) -> Vec<u8> {
+        write_kv_document(&object([
+            ("start_height", KvValue::Unsigned(self.start_height)),
+            ("total_height", KvValue::Unsigned(self.total_height)),
+            ("m_block_ids", hashes_value(&self.block_ids)),
+        ]))
+    }
+
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        const WHAT: &str = "chain entry";
+        let root = decode_document(body, WHAT)?;
+        Ok(Self {
+            start_height: field_u64(&root, "start_height", WHAT)?,
+            total_height: field_u64(&root, "total_height", WHAT)?,
+            block_ids: field_hashes(&root, "m_block_ids", WHAT)?,
+        })
+    }
+}
@@ -1,5 +1,77 @@
+use crate::error::{Result, SdkError};
+use crate::serialization::{self, OutputTarget};
+use crate::types::{Transaction, TxInput, TxOutput};
+
+pub mod failover;
+pub mod levin;
 pub mod null;
+pub mod p2p;
 pub mod rpc;
 
+pub use failover::{FailoverProvider, NodeStats};
 pub use null::NullNetwork;
+pub use p2p::{P2pConfig, P2pProvider};
 pub use rpc::RpcProvider;
+
+/// Map a wire transaction onto [`Transaction`]. CryptoNote inputs name
+/// no outpoint: a spending input reports its key image as
+/// `prev_tx_hash` and its ring size as `prev_output_index`; a miner
+/// input reports a zero hash and the block index.
+pub(crate) fn decode_transaction(hash: [u8; 32], blob: &[u8]) -> Result<Transaction> {
+    let mut pos = 0;
+    let prefix = serialization::parse_prefix_at(blob, &mut pos)
+        .map_err(|e| SdkError::Serialization(format!("transaction {}: {}", hex::encode(hash), e.0)))?;
+
+    let mut signatures = &blob[pos..];
+    let inputs = prefix
+        .inputs
+        .iter()
+        .map(|input| {
+            let sig_len = (input.ring_size() * 64).min(signatures.len());
+            let (signature, rest) = signatures.split_at(sig_len);
+            signatures = rest;
+            let (prev_tx_hash, prev_output_index) = match input {
+                serialization::TxInput::Base(b) => ([0u8; 32], b.block_index),
+                _ => (
+                    input.key_image().copied().unwrap_or_default(),
+                    input.ring_size() as u32,
+                ),
+            };
+            TxInput {
+                prev_tx_hash,
+                prev_output_index,
+                signature: signature.to_vec(),
+            }
+        })
+        .collect();
+
+    let outputs = prefix
+        .outputs
+        .iter()
+        .map(|output| TxOutput {
+            amount: output.amount,
+            pubkey: match &output.target {
+                OutputTarget::Key(key) => *key,
+                OutputTarget::Commitment(c) => c.commit_key,
+            },
+        })
+        .collect();
+
+    let is_miner_tx = matches!(prefix.inputs.first(), Some(serialization::TxInput::Base(_)));
+    let fee = if is_miner_tx {
+        0
+    } else {
+        let spent: u64 = prefix.inputs.iter().map(|i| i.amount()).sum();
+        let paid: u64 = prefix.outputs.iter().map(|o| o.amount).sum();
+        spent.saturating_sub(paid)
+    };
+
+    Ok(Transaction {
+        hash,
+        inputs,
+        outputs,
+        extra: prefix.extra,
+        fee,
+        blob: blob.to_vec(),
+    })
+}
@@ -1,5 +1,6 @@
 use crate::error::{Result, SdkError};
 use crate::traits::{NetworkProvider, PeerInfo};
+use crate::transaction_builder::BuiltTransaction;
 use crate::types::*;
 use async_trait::async_trait;
 
@@ -23,6 +24,10 @@ impl NetworkProvider for NullNetwork {
         Err(SdkError::Network("No network configured".into()))
     }
 
+    async fn broadcast(&self, _tx: &BuiltTransaction) -> Result<[u8; 32]> {
+        Err(SdkError::Network("No network configured".into()))
+    }
+
     async fn get_peers(&self) -> Result<Vec<PeerInfo>> {
         Ok(Vec::new())
     }
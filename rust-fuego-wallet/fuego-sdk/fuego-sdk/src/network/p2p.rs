@@ -0,0 +1,513 @@
+use crate::error::{Result, SdkError};
+use crate::network::levin::*;
+use crate::serialization;
+use crate::traits::{NetworkProvider, PeerInfo};
+use crate::transaction_builder::BuiltTransaction;
+use crate::types::*;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// Default Fuego P2P port.
+pub const DEFAULT_P2P_PORT: u16 = 10808;
+
+/// Peer-to-peer protocol version sent in the handshake.
+pub const P2P_VERSION: u8 = 1;
+
+/// Locator entries listed one by one before the step starts doubling.
+const LOCATOR_DENSE_ENTRIES: usize = 10;
+
+/// Public seed peers as `host:port`, from the hosts the reference wallet
+/// lists. No testnet or stagenet seeds are published.
+const MAINNET_SEEDS: &[&str] = &[
+    "207.244.247.64:10808",
+    "node1.usexfg.org:10808",
+    "node2.usexfg.org:10808",
+    "fuego.seednode1.com:10808",
+    "fuego.seednode2.com:10808",
+    "fuego.communitynode.net:10808",
+];
+const TESTNET_SEEDS: &[&str] = &[];
+const STAGENET_SEEDS: &[&str] = &[];
+
+/// Built-in seed peers of `network`.
+pub fn seed_nodes(network: NetworkType) -> &'static [&'static str] {
+    match network {
+        NetworkType::Mainnet => MAINNET_SEEDS,
+        NetworkType::Testnet => TESTNET_SEEDS,
+        NetworkType::Stagenet => STAGENET_SEEDS,
+    }
+}
+
+/// How to reach and recognise the Fuego P2P network.
+#[derive(Debug, Clone)]
+pub struct P2pConfig {
+    /// `P2P_NETWORK_ID`; peers on another network drop the handshake.
+    pub network_id: [u8; 16],
+    /// Id of block 0, the root of every chain locator.
+    pub genesis_hash: [u8; 32],
+    /// `host:port` addresses to bootstrap from.
+    pub seeds: Vec<String>,
+    /// Most peers held open at once (`NodeConfig::max_peers`).
+    pub max_peers: usize,
+    /// Deadline for connecting and for each reply.
+    pub timeout: Duration,
+}
+
+impl P2pConfig {
+    pub fn new(network_id: [u8; 16], genesis_hash: [u8; 32]) -> Self {
+        Self {
+            network_id,
+            genesis_hash,
+            seeds: Vec::new(),
+            max_peers: NodeConfig::default().max_peers,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// [`P2pConfig::new`] seeded with the built-in peers of `network`.
+    pub fn for_network(network: NetworkType, network_id: [u8; 16], genesis_hash: [u8; 32]) -> Self {
+        Self::new(network_id, genesis_hash).with_seeds(seed_nodes(network).iter().copied())
+    }
+
+    pub fn with_seeds<S: Into<String>>(mut self, seeds: impl IntoIterator<Item = S>) -> Self {
+        self.seeds.extend(seeds.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// One handshaken peer connection.
+struct Connection {
+    stream: TcpStream,
+    our_id: u64,
+    timeout: Duration,
+}
+
+impl Connection {
+    async fn open(address: &str, config: &P2pConfig, our_id: u64) -> Result<(Self, HandshakeResponse)> {
+        let stream = tokio::time::timeout(config.timeout, TcpStream::connect(address))
+            .await
+            .map_err(|_| SdkError::Network(format!("{address}: connect timed out")))??;
+        let mut conn = Self { stream, our_id, timeout: config.timeout };
+
+        let request = HandshakeRequest {
+            node_data: BasicNodeData {
+                network_id: config.network_id,
+                version: P2P_VERSION,
+                local_time: chrono::Utc::now().timestamp().max(0) as u64,
+                my_port: 0,
+                peer_id: our_id,
+            },
+            payload_data: CoreSyncData { current_height: 1, top_id: config.genesis_hash },
+        };
+        let body = conn.invoke(COMMAND_HANDSHAKE, request.encode()).await?;
+        let response = HandshakeResponse::decode(&body)?;
+        if response.node_data.network_id != config.network_id {
+            return Err(SdkError::Network(format!("{address}: peer is on another network")));
+        }
+        Ok((conn, response))
+    }
+
+    async fn send(&mut self, message: LevinMessage) -> Result<()> {
+        let timeout = self.timeout;
+        tokio::time::timeout(timeout, write_message(&mut self.stream, &message))
+            .await
+            .map_err(|_| SdkError::Network("peer write timed out".into()))?
+    }
+
+    /// Wait for the response to an invoke or for a notification, answering
+    /// the peer's own keep-alive invokes and skipping unrelated traffic.
+    async fn wait_for(&mut self, command: u32, response: bool) -> Result<Vec<u8>> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            let message = tokio::time::timeout_at(deadline, read_message(&mut self.stream))
+                .await
+                .map_err(|_| SdkError::Network(format!("peer did not answer command {command}")))??;
+            if message.command == command && message.is_response == response {
+                if message.return_code < 0 {
+                    return Err(SdkError::Network(format!(
+                        "peer failed command {command} with code {}",
+                        message.return_code
+                    )));
+                }
+                return Ok(message.body);
+            }
+            if message.expect_response {
+                self.answer(&message).await?;
+            }
+        }
+    }
+
+    async fn answer(&mut self, request: &LevinMessage) -> Result<()> {
+        let body = match request.command {
+            COMMAND_PING => ping_response(self.our_id),
+            // We hold no chain of our own to advertise.
+            COMMAND_TIMED_SYNC => TimedSyncResponse {
+                local_time: chrono::Utc::now().timestamp().max(0) as u64,
+                payload_data: CoreSyncData { current_height: 0, top_id: [0u8; 32] },
+                local_peerlist: Vec::new(),
+            }
+            .encode(),
+            _ => Vec::new(),
+        };
+        self.send(LevinMessage::response(request.command, body)).await
+    }
+
+    async fn invoke(&mut self, command: u32, body: Vec<u8>) -> Result<Vec<u8>> {
+        self.send(LevinMessage::invoke(command, body)).await?;
+        self.wait_for(command, true).await
+    }
+
+    async fn timed_sync(&mut self, genesis: [u8; 32]) -> Result<TimedSyncResponse> {
+        let request = TimedSyncRequest {
+            payload_data: CoreSyncData { current_height: 1, top_id: genesis },
+        };
+        TimedSyncResponse::decode(&self.invoke(COMMAND_TIMED_SYNC, request.encode()).await?)
+    }
+
+    async fn request_chain(&mut self, locator: Vec<[u8; 32]>) -> Result<ResponseChainEntry> {
+        let request = RequestChain { block_ids: locator };
+        self.send(LevinMessage::notify(NOTIFY_REQUEST_CHAIN, request.encode())).await?;
+        ResponseChainEntry::decode(&self.wait_for(NOTIFY_RESPONSE_CHAIN_ENTRY, false).await?)
+    }
+
+    async fn get_objects(&mut self, request: RequestGetObjects) -> Result<ResponseGetObjects> {
+        self.send(LevinMessage::notify(NOTIFY_REQUEST_GET_OBJECTS, request.encode())).await?;
+        ResponseGetObjects::decode(&self.wait_for(NOTIFY_RESPONSE_GET_OBJECTS, false).await?)
+    }
+
+    async fn relay(&mut self, txs: Vec<Vec<u8>>) -> Result<()> {
+        let notify = NotifyNewTransactions { txs };
+        self.send(LevinMessage::notify(NOTIFY_NEW_TRANSACTIONS, notify.encode())).await
+    }
+}
+
+struct Peer {
+    address: String,
+    id: u64,
+    height: Mutex<u64>,
+    conn: tokio::sync::Mutex<Connection>,
+}
+
+type PeerCall<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Addresses known to the provider and which of them were tried.
+#[derive(Default)]
+struct Candidates {
+    known: Vec<String>,
+    tried: HashSet<String>,
+}
+
+/// `NetworkProvider` speaking the CryptoNote Levin protocol to daemons
+/// directly, so syncing does not hinge on one RPC server. Holds up to
+/// `max_peers` connections, learns further peers from handshakes, and
+/// follows the chain through block-id requests; blocks are then fetched
+/// by id and checked to link up.
+pub struct P2pProvider {
+    config: P2pConfig,
+    peer_id: u64,
+    peers: tokio::sync::Mutex<Vec<Arc<Peer>>>,
+    candidates: Mutex<Candidates>,
+    /// Main-chain block ids by height; index 0 is genesis.
+    chain: Mutex<Vec<[u8; 32]>>,
+}
+
+impl P2pProvider {
+    pub fn new(config: P2pConfig) -> Self {
+        let candidates = Candidates { known: config.seeds.clone(), tried: HashSet::new() };
+        Self {
+            chain: Mutex::new(vec![config.genesis_hash]),
+            peer_id: rand::random(),
+            peers: tokio::sync::Mutex::new(Vec::new()),
+            candidates: Mutex::new(candidates),
+            config,
+        }
+    }
+
+    /// Number of open peer connections.
+    pub async fn peer_count(&self) -> usize {
+        self.peers.lock().await.len()
+    }
+
+    /// Connect to untried candidates until `max_peers` are open. Returns
+    /// the open peers, or an error if none could be reached.
+    async fn connected_peers(&self) -> Result<Vec<Arc<Peer>>> {
+        let mut peers = self.peers.lock().await;
+        let mut last_error = None;
+        while peers.len() < self.config.max_peers {
+            let next = {
+                let mut candidates = self.candidates.lock().unwrap();
+                let Candidates { known, tried } = &mut *candidates;
+                let next = known.iter().find(|a| !tried.contains(*a)).cloned();
+                if let Some(address) = &next {
+                    tried.insert(address.clone());
+                }
+                next
+            };
+            let Some(address) = next else { break };
+            match Connection::open(&address, &self.config, self.peer_id).await {
+                Ok((conn, handshake)) => {
+                    self.learn_peers(&handshake.local_peerlist);
+                    peers.push(Arc::new(Peer {
+                        address,
+                        id: handshake.node_data.peer_id,
+                        height: Mutex::new(handshake.payload_data.current_height),
+                        conn: tokio::sync::Mutex::new(conn),
+                    }));
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if peers.is_empty() {
+            // Give every address another chance on the next call.
+            self.candidates.lock().unwrap().tried.clear();
+            return Err(last_error.unwrap_or_else(|| SdkError::Network("no peers to connect to".into())));
+        }
+        Ok(peers.clone())
+    }
+
+    fn learn_peers(&self, entries: &[PeerlistEntry]) {
+        let mut candidates = self.candidates.lock().unwrap();
+        for entry in entries {
+            let address = entry.address();
+            if !candidates.known.contains(&address) {
+                candidates.known.push(address);
+            }
+        }
+    }
+
+    async fn drop_peer(&self, peer: &Arc<Peer>) {
+        self.peers.lock().await.retain(|p| !Arc::ptr_eq(p, peer));
+    }
+
+    /// Run `call` on each peer in turn until one answers; peers that fail
+    /// at the network level are disconnected.
+    async fn on_any_peer<T>(&self, call: impl Fn(&mut Connection) -> PeerCall<'_, T>) -> Result<T> {
+        let mut last_error = SdkError::Network("no peers to connect to".into());
+        for peer in self.connected_peers().await? {
+            let result = call(&mut *peer.conn.lock().await).await;
+            match result {
+                Err(e @ (SdkError::Network(_) | SdkError::Io(_) | SdkError::Serialization(_))) => {
+                    self.drop_peer(&peer).await;
+                    last_error = e;
+                }
+                other => return other,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Sparse locator over the known chain: the newest ids one by one,
+    /// then at doubling distances, always ending with genesis.
+    fn locator(chain: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        let mut ids = Vec::new();
+        let mut height = chain.len() - 1;
+        let mut step = 1;
+        while height > 0 {
+            ids.push(chain[height]);
+            if ids.len() >= LOCATOR_DENSE_ENTRIES {
+                step *= 2;
+            }
+            height = height.saturating_sub(step);
+        }
+        ids.push(chain[0]);
+        ids
+    }
+
+    /// Id of the main-chain block at `height`, extending the id cache from
+    /// peers when it does not reach that far yet.
+    async fn block_id(&self, height: u64) -> Result<[u8; 32]> {
+        loop {
+            let (locator, known) = {
+                let chain = self.chain.lock().unwrap();
+                if let Some(id) = chain.get(height as usize) {
+                    return Ok(*id);
+                }
+                (Self::locator(&chain), chain.len())
+            };
+            let entry = self
+                .on_any_peer(|conn| Box::pin(conn.request_chain(locator.clone())))
+                .await?;
+
+            let mut chain = self.chain.lock().unwrap();
+            let start = entry.start_height as usize;
+            if entry.block_ids.first().is_none_or(|id| chain.get(start) != Some(id)) {
+                return Err(SdkError::Network(format!(
+                    "peer chain entry does not attach at height {start}"
+                )));
+            }
+            // A differing suffix is a reorg: the peer's ids replace ours.
+            chain.truncate(start);
+            chain.extend_from_slice(&entry.block_ids);
+            if chain.len() <= known && chain.len() <= height as usize {
+                return Err(SdkError::NotFound(format!("block {height}")));
+            }
+        }
+    }
+
+    /// Block at `height` with every transaction, miner transaction first.
+    async fn fetch_block(&self, height: u64) -> Result<Block> {
+        let id = self.block_id(height).await?;
+        let request = RequestGetObjects { txs: Vec::new(), blocks: vec![id] };
+        let response = self
+            .on_any_peer(|conn| Box::pin(conn.get_objects(request.clone())))
+            .await?;
+        let entry = response
+            .blocks
+            .into_iter()
+            .next()
+            .ok_or_else(|| SdkError::NotFound(format!("block {}", hex::encode(id))))?;
+
+        let block = serialization::parse_block(&entry.block)
+            .map_err(|e| SdkError::Serialization(format!("block {height}: {}", e.0)))?;
+        if serialization::block_id(&block) != id {
+            return Err(SdkError::Network(format!(
+                "block {height}: peer sent a block that does not hash to {}",
+                hex::encode(id)
+            )));
+        }
+        let prev_hash = if height == 0 { [0u8; 32] } else { self.block_id(height - 1).await? };
+        if height > 0 && block.prev_id != prev_hash {
+            return Err(SdkError::Network(format!("block {height} does not link to its parent")));
+        }
+
+        let miner_blob = serialization::serialize_tx(&block.miner_tx);
+        let mut transactions = vec![super::decode_transaction(
+            fuego_crypto::cn_fast_hash(&miner_blob),
+            &miner_blob,
+        )?];
+        let mut blobs: Vec<([u8; 32], &Vec<u8>)> =
+            entry.txs.iter().map(|blob| (fuego_crypto::cn_fast_hash(blob), blob)).collect();
+        for hash in &block.tx_hashes {
+            let index = blobs.iter().position(|(h, _)| h == hash).ok_or_else(|| {
+                SdkError::Network(format!("block {height}: peer left out transaction {}", hex::encode(hash)))
+            })?;
+            let (hash, blob) = blobs.swap_remove(index);
+            transactions.push(super::decode_transaction(hash, blob)?);
+        }
+
+        Ok(Block {
+            header: BlockHeader {
+                height,
+                hash: id,
+                prev_hash,
+                timestamp: block.timestamp,
+                tx_count: transactions.len() as u32,
+            },
+            transactions,
+        })
+    }
+}
+
+#[async_trait]
+impl NetworkProvider for P2pProvider {
+    /// Highest height any connected peer reports.
+    async fn get_height(&self) -> Result<u64> {
+        let peers = self.connected_peers().await?;
+        let genesis = self.config.genesis_hash;
+        let mut tasks = tokio::task::JoinSet::new();
+        for peer in peers {
+            tasks.spawn(async move {
+                let result = peer.conn.lock().await.timed_sync(genesis).await;
+                (peer, result)
+            });
+        }
+
+        let mut best = None;
+        let mut last_error = SdkError::Network("no peers answered".into());
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((peer, result)) = joined else { continue };
+            match result {
+                Ok(sync) => {
+                    self.learn_peers(&sync.local_peerlist);
+                    let height = sync.payload_data.current_height;
+                    *peer.height.lock().unwrap() = height;
+                    best = best.max(Some(height));
+                }
+                Err(e) => {
+                    self.drop_peer(&peer).await;
+                    last_error = e;
+                }
+            }
+        }
+        best.ok_or(last_error)
+    }
+
+    /// Needs the block itself: P2P has no header-only request.
+    async fn get_header(&self, height: u64) -> Result<BlockHeader> {
+        Ok(self.fetch_block(height).await?.header)
+    }
+
+    async fn get_block(&self, height: u64) -> Result<Block> {
+        self.fetch_block(height).await
+    }
+
+    async fn send_transaction(&self, _tx: &Transaction) -> Result<[u8; 32]> {
+        Err(SdkError::Transaction(
+            "relaying needs the wire encoding; use broadcast with a BuiltTransaction".into(),
+        ))
+    }
+
+    /// Relay to every connected peer. Peers don't acknowledge relays, so
+    /// success means at least one peer took the bytes.
+    async fn broadcast(&self, tx: &BuiltTransaction) -> Result<[u8; 32]> {
+        let mut relayed = false;
+        let mut last_error = SdkError::Network("no peers to relay to".into());
+        for peer in self.connected_peers().await? {
+            match peer.conn.lock().await.relay(vec![tx.serialized.clone()]).await {
+                Ok(()) => relayed = true,
+                Err(e) => {
+                    self.drop_peer(&peer).await;
+                    last_error = e;
+                }
+            }
+        }
+        if relayed {
+            Ok(tx.tx_hash)
+        } else {
+            Err(last_error)
+        }
+    }
+
+    async fn get_peers(&self) -> Result<Vec<PeerInfo>> {
+        let peers = self.connected_peers().await?;
+        Ok(peers
+            .iter()
+            .map(|peer| PeerInfo {
+                id: format!("{:016x}", peer.id),
+                address: peer.address.clone(),
+                version: P2P_VERSION as u32,
+                height: *peer.height.lock().unwrap(),
+                last_seen: 0,
+            })
+            .collect())
+    }
+
+    /// Peers only serve transactions from their pool or main chain.
+    async fn get_transaction(&self, hash: &[u8; 32]) -> Result<Option<Transaction>> {
+        let request = RequestGetObjects { txs: vec![*hash], blocks: Vec::new() };
+        let response = self
+            .on_any_peer(|conn| Box::pin(conn.get_objects(request.clone())))
+            .await?;
+        response
+            .txs
+            .iter()
+            .find(|blob| fuego_crypto::cn_fast_hash(blob) == *hash)
+            .map(|blob| super::decode_transaction(*hash, blob))
+            .transpose()
+    }
+}
@@ -1,29 +1,37 @@
-use crate::error::{Result, SdkError};
+use crate::error::{Result, SdkError, TxRejection};
+use crate::http::{HttpConfig, DEST_DAEMON};
 use crate::traits::{NetworkProvider, PeerInfo};
+use crate::transaction_builder::BuiltTransaction;
 use crate::types::*;
 use async_trait::async_trait;
 
 pub struct RpcProvider {
     host: String,
     port: u16,
+    scheme: &'static str,
     client: reqwest::Client,
 }
 
 impl RpcProvider {
     pub fn new(host: impl Into<String>, port: u16) -> Self {
-        Self {
+        Self::with_http(host, port, &HttpConfig::default()).expect("default HTTP client")
+    }
+
+    pub fn with_http(host: impl Into<String>, port: u16, http: &HttpConfig) -> Result<Self> {
+        Ok(Self {
             host: host.into(),
             port,
-            client: reqwest::Client::new(),
-        }
+            scheme: http.scheme(),
+            client: http.client(DEST_DAEMON)?,
+        })
     }
 
     fn json_rpc_url(&self) -> String {
-        format!("http://{}:{}/json_rpc", self.host, self.port)
+        format!("{}://{}:{}/json_rpc", self.scheme, self.host, self.port)
     }
 
     fn rest_url(&self, path: &str) -> String {
-        format!("http://{}:{}/{}", self.host, self.port, path)
+        format!("{}://{}:{}/{}", self.scheme, self.host, self.port, path)
     }
 
     async fn call(
@@ -62,6 +70,92 @@ impl RpcProvider {
             .unwrap_or(serde_json::Value::Null))
     }
 
+    /// POST a JSON body to one of the daemon's plain HTTP endpoints.
+    async fn rest(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let resp = self
+            .client
+            .post(self.rest_url(path))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| SdkError::Network(format!("HTTP: {e}")))?;
+
+        resp.json()
+            .await
+            .map_err(|e| SdkError::Network(format!("JSON parse: {e}")))
+    }
+
+    async fn send_raw(&self, blob: &[u8], hash: [u8; 32]) -> Result<[u8; 32]> {
+        let body = serde_json::json!({ "tx_as_hex": hex::encode(blob) });
+        let json = self.rest("sendrawtransaction", body).await?;
+        match TxRejection::from_response(&json) {
+            None => Ok(hash),
+            Some(rejection) => Err(SdkError::TxRejected(rejection)),
+        }
+    }
+
+    /// Hashes of every transaction in the block, miner transaction first.
+    async fn block_tx_hashes(&self, block_hash: &[u8; 32]) -> Result<Vec<[u8; 32]>> {
+        let result = self
+            .call("f_block_json", serde_json::json!({ "hash": hex::encode(block_hash) }))
+            .await?;
+        let txs = result
+            .get("block")
+            .and_then(|b| b.get("transactions"))
+            .and_then(|t| t.as_array())
+            .ok_or_else(|| SdkError::Network("missing block transactions".into()))?;
+        txs.iter()
+            .map(|tx| {
+                tx.get("hash")
+                    .and_then(|h| h.as_str())
+                    .and_then(Self::decode_hash)
+                    .ok_or_else(|| SdkError::Network("invalid transaction hash".into()))
+            })
+            .collect()
+    }
+
+    /// Fetch transactions by hash via `/gettransactions`. Hashes the daemon
+    /// does not know are left out of the result.
+    async fn fetch_transactions(&self, hashes: &[[u8; 32]]) -> Result<Vec<Transaction>> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let requested: Vec<String> = hashes.iter().map(hex::encode).collect();
+        let json = self
+            .rest("gettransactions", serde_json::json!({ "txs_hashes": requested }))
+            .await?;
+
+        let status = json.get("status").and_then(|v| v.as_str()).unwrap_or("");
+        if status != "OK" {
+            return Err(SdkError::Network(format!("gettransactions: {status}")));
+        }
+
+        json.get("txs_as_hex")
+            .and_then(|v| v.as_array())
+            .map(|blobs| blobs.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|blob| {
+                let blob = blob
+                    .as_str()
+                    .and_then(|b| hex::decode(b).ok())
+                    .ok_or_else(|| SdkError::Network("invalid transaction hex".into()))?;
+                let hash = fuego_crypto::cn_fast_hash(&blob);
+                if !hashes.contains(&hash) {
+                    return Err(SdkError::Network(format!(
+                        "gettransactions returned unrequested transaction {}",
+                        hex::encode(hash)
+                    )));
+                }
+                super::decode_transaction(hash, &blob)
+            })
+            .collect()
+    }
+
+    fn decode_hash(hex_str: &str) -> Option<[u8; 32]> {
+        hex::decode(hex_str).ok()?.try_into().ok()
+    }
+
     fn parse_hash(hex_str: &str) -> [u8; 32] {
         let bytes = hex::decode(hex_str).unwrap_or_else(|_| vec![0u8; 32]);
         let mut hash = [0u8; 32];
@@ -82,12 +176,8 @@ impl NetworkProvider for RpcProvider {
         Ok(count)
     }
 
+    /// Header only, without fetching the block's transactions.
     async fn get_header(&self, height: u64) -> Result<BlockHeader> {
-        let block = self.get_block(height).await?;
-        Ok(block.header)
-    }
-
-    async fn get_block(&self, height: u64) -> Result<Block> {
         let params = serde_json::json!({ "height": height });
         let result = self.call("getblockheaderbyheight", params).await?;
 
@@ -102,48 +192,53 @@ impl NetworkProvider for RpcProvider {
         let prev_hash = Self::parse_hash(prev_hash_str);
 
         let timestamp = header.get("timestamp").and_then(|v| v.as_u64()).unwrap_or(0);
+        let tx_count = header.get("num_txes").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
 
-        Ok(Block {
-            header: BlockHeader {
-                height,
-                hash,
-                prev_hash,
-                timestamp,
-                tx_count: 0,
-            },
-            transactions: Vec::new(),
+        Ok(BlockHeader {
+            height,
+            hash,
+            prev_hash,
+            timestamp,
+            tx_count,
         })
     }
 
-    async fn send_transaction(&self, tx: &Transaction) -> Result<[u8; 32]> {
-        let tx_hex = hex::encode(bincode::serialize(tx).unwrap_or_default());
-        let body = serde_json::json!({ "tx_as_hex": tx_hex });
-
-        let resp = self
-            .client
-            .post(self.rest_url("sendrawtransaction"))
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| SdkError::Network(format!("HTTP: {e}")))?;
-
-        let json: serde_json::Value = resp
-            .json()
-            .await
-            .map_err(|e| SdkError::Network(format!("JSON parse: {e}")))?;
-
-        let status = json
-            .get("status")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+    async fn get_block(&self, height: u64) -> Result<Block> {
+        let mut header = self.get_header(height).await?;
 
-        if status != "OK" && status != "Success" {
+        let tx_hashes = self.block_tx_hashes(&header.hash).await?;
+        let transactions = self.fetch_transactions(&tx_hashes).await?;
+        if transactions.len() != tx_hashes.len() {
             return Err(SdkError::Network(format!(
-                "sendrawtransaction: {status}"
+                "block {height}: daemon returned {} of {} transactions",
+                transactions.len(),
+                tx_hashes.len()
             )));
         }
+        // gettransactions does not promise to keep the request order.
+        let transactions = tx_hashes
+            .iter()
+            .filter_map(|h| transactions.iter().find(|tx| tx.hash == *h).cloned())
+            .collect::<Vec<_>>();
+
+        header.tx_count = transactions.len() as u32;
+        Ok(Block { header, transactions })
+    }
+
+    /// Sends [`Transaction::blob`]; a transaction without its wire
+    /// encoding cannot be re-encoded, since [`Transaction`] drops the ring
+    /// offsets and input amounts.
+    async fn send_transaction(&self, tx: &Transaction) -> Result<[u8; 32]> {
+        if tx.blob.is_empty() {
+            return Err(SdkError::Transaction(
+                "sendrawtransaction needs the wire encoding; use broadcast with a BuiltTransaction".into(),
+            ));
+        }
+        self.send_raw(&tx.blob, tx.hash).await
+    }
 
-        Ok(tx.hash)
+    async fn broadcast(&self, tx: &BuiltTransaction) -> Result<[u8; 32]> {
+        self.send_raw(&tx.serialized, tx.tx_hash).await
     }
 
     async fn get_peers(&self) -> Result<Vec<PeerInfo>> {
@@ -179,7 +274,7 @@ impl NetworkProvider for RpcProvider {
         Ok(connections)
     }
 
-    async fn get_transaction(&self, _hash: &[u8; 32]) -> Result<Option<Transaction>> {
-        Ok(None)
+    async fn get_transaction(&self, hash: &[u8; 32]) -> Result<Option<Transaction>> {
+        Ok(self.fetch_transactions(&[*hash]).await?.pop())
     }
 }
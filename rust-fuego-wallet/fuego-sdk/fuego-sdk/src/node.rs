@@ -1,26 +1,48 @@
 use crate::error::Result;
 use crate::network::NullNetwork;
 use crate::store::MemoryStore;
+use crate::sync_service::{SyncRound, SyncService, SyncTarget};
 use crate::traits::{BlockObserver, EventListener, NetworkProvider, StorageProvider};
 use crate::types::*;
-use std::sync::{Arc, RwLock};
+use crate::validation::{HeaderValidator, TIMESTAMP_CHECK_WINDOW};
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::broadcast;
 
+/// Blocks fetched per background sync round, so a stop request is
+/// honored within one batch.
+const SYNC_ROUND_BLOCKS: u64 = 100;
+
+/// Block requests kept in flight while syncing. Blocks are still
+/// validated and applied strictly in height order.
+const DOWNLOAD_PARALLELISM: usize = 8;
+
+/// Deepest reorg the node follows. A provider that disagrees with every
+/// stored block this far back is treated as serving another chain.
+const MAX_REORG_DEPTH: u64 = 10_000;
+
 pub struct EmbeddedNode {
-    _config: NodeConfig,
+    config: NodeConfig,
     network: Arc<dyn NetworkProvider>,
     storage: Arc<dyn StorageProvider>,
     observers: Vec<Arc<dyn BlockObserver>>,
     listeners: Vec<Arc<dyn EventListener>>,
     state: Arc<RwLock<NodeState>>,
+    validator: Mutex<HeaderValidator>,
     event_tx: broadcast::Sender<NodeEvent>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct NodeState {
     pub height: u64,
+    /// Last block height the network reported, as of the latest sync.
+    pub target_height: u64,
     pub is_syncing: bool,
     pub peer_count: usize,
+    /// Unix time the node last reached its sync target.
+    pub last_sync_time: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -28,8 +50,12 @@ pub enum NodeEvent {
     SyncStarted { target: u64 },
     SyncProgress { current: u64, target: u64 },
     SyncComplete,
+    /// A block passed validation and was stored.
     BlockReceived(BlockHeader),
+    /// Hash of a transaction in a stored block, after its `BlockReceived`.
     TransactionReceived([u8; 32]),
+    /// Blocks above `fork_height` were orphaned and detached.
+    Reorg { fork_height: u64, detached: u64 },
     Error(String),
 }
 
@@ -42,7 +68,8 @@ impl EmbeddedNode {
     pub fn with_storage(config: NodeConfig, storage: Arc<dyn StorageProvider>) -> Result<Self> {
         let (event_tx, _) = broadcast::channel(100);
         Ok(Self {
-            _config: config,
+            validator: Mutex::new(HeaderValidator::new(config.network)),
+            config,
             network: Arc::new(NullNetwork),
             storage,
             observers: Vec::new(),
@@ -52,6 +79,10 @@ impl EmbeddedNode {
         })
     }
 
+    pub fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
     pub fn set_network(&mut self, network: Arc<dyn NetworkProvider>) {
         self.network = network;
     }
@@ -64,6 +95,20 @@ impl EmbeddedNode {
         self.listeners.push(listener);
     }
 
+    /// Require the block at `height` to have `hash`, on top of the
+    /// network's hard-coded checkpoints.
+    pub fn add_checkpoint(&mut self, height: u64, hash: [u8; 32]) {
+        self.validator.get_mut().unwrap().add_checkpoint(height, hash);
+    }
+
+    /// `add_checkpoint` for each entry, e.g. from `parse_checkpoints`.
+    pub fn add_checkpoints(&mut self, checkpoints: impl IntoIterator<Item = (u64, [u8; 32])>) {
+        let validator = self.validator.get_mut().unwrap();
+        for (height, hash) in checkpoints {
+            validator.add_checkpoint(height, hash);
+        }
+    }
+
     pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
         self.event_tx.subscribe()
     }
@@ -76,9 +121,9 @@ impl EmbeddedNode {
         let state = self.state.read().unwrap();
         SyncStatus {
             current_height: state.height,
-            target_height: 0,
+            target_height: state.target_height,
             is_syncing: state.is_syncing,
-            last_sync_time: None,
+            last_sync_time: state.last_sync_time,
         }
     }
 
@@ -92,17 +137,24 @@ impl EmbeddedNode {
             None => self.network.get_height().await?,
         };
 
-        let start = self.height();
+        let mut start = self.height();
         // Block heights are 0-indexed: a chain of `target` blocks has valid
         // heights 0..=target-1. Clamp the sync window to the last existing
         // block so we never ask the daemon for one past the tip (which the
         // daemon rejects with "Too big height").
         let last_existing = target.saturating_sub(1);
+        self.state.write().unwrap().target_height = last_existing;
+        if let Some(fork) = self.find_fork(start.min(last_existing)).await? {
+            self.detach_above(fork)?;
+            start = fork;
+        }
         if start >= last_existing {
+            self.state.write().unwrap().last_sync_time = Some(unix_now());
             return Ok(0);
         }
 
-        let end = std::cmp::min(start + max_blocks, last_existing);
+        let end = std::cmp::min(start.saturating_add(max_blocks), last_existing);
+        self.load_validator_window(start)?;
 
         {
             let mut state = self.state.write().unwrap();
@@ -114,9 +166,12 @@ impl EmbeddedNode {
         }
         let _ = self.event_tx.send(NodeEvent::SyncStarted { target: end });
 
+        let mut blocks = stream::iter(start + 1..=end)
+            .map(|height| self.network.get_block(height))
+            .buffered(DOWNLOAD_PARALLELISM);
         let mut current = start;
-        while current < end {
-            match self.sync_block(current + 1).await {
+        while let Some(fetched) = blocks.next().await {
+            match fetched.and_then(|block| self.apply_block(current + 1, block)) {
                 Ok(block) => {
                     current = block.header.height;
 
@@ -129,6 +184,17 @@ impl EmbeddedNode {
                         state.height = current;
                     }
 
+                    for listener in &self.listeners {
+                        listener.on_block(&block.header);
+                        for tx in &block.transactions {
+                            listener.on_transaction(tx);
+                        }
+                    }
+                    let _ = self.event_tx.send(NodeEvent::BlockReceived(block.header.clone()));
+                    for tx in &block.transactions {
+                        let _ = self.event_tx.send(NodeEvent::TransactionReceived(tx.hash));
+                    }
+
                     for listener in &self.listeners {
                         listener.on_sync_progress(current, end);
                     }
@@ -141,6 +207,9 @@ impl EmbeddedNode {
                         let mut state = self.state.write().unwrap();
                         state.is_syncing = false;
                     }
+                    for listener in &self.listeners {
+                        listener.on_error(&e);
+                    }
                     let _ = self.event_tx.send(NodeEvent::Error(e.to_string()));
                     return Err(e);
                 }
@@ -151,6 +220,9 @@ impl EmbeddedNode {
             let mut state = self.state.write().unwrap();
             state.height = current;
             state.is_syncing = false;
+            if current >= last_existing {
+                state.last_sync_time = Some(unix_now());
+            }
         }
 
         self.storage.save_height(current)?;
@@ -168,12 +240,85 @@ impl EmbeddedNode {
         Ok(())
     }
 
-    async fn sync_block(&self, height: u64) -> Result<Block> {
-        let block = self.network.get_block(height).await?;
+    /// Keep syncing in the background every `sync_interval_secs`. The
+    /// returned service stops when dropped.
+    pub fn start_sync(self: &Arc<Self>) -> SyncService {
+        let interval = Duration::from_secs(self.config.sync_interval_secs.max(1));
+        SyncService::start(self.clone(), interval)
+    }
+
+    /// Validate a downloaded block as the one at `height` and store it.
+    fn apply_block(&self, height: u64, block: Block) -> Result<Block> {
+        self.validator.lock().unwrap().validate(&block.header, height, unix_now())?;
         self.storage.save_block(&block)?;
+        self.validator.lock().unwrap().accept(block.header.clone());
         Ok(block)
     }
 
+    /// Compare the stored block at `tip` with the network's and, if they
+    /// differ, walk back to the last block both still agree on. `None`
+    /// when nothing needs detaching, or when no common block is found
+    /// above the last checkpoint within `MAX_REORG_DEPTH`; the next block
+    /// is then rejected by validation as usual.
+    async fn find_fork(&self, tip: u64) -> Result<Option<u64>> {
+        let floor = {
+            let validator = self.validator.lock().unwrap();
+            validator.last_checkpoint(tip).unwrap_or(0).max(tip.saturating_sub(MAX_REORG_DEPTH))
+        };
+        let mut height = tip;
+        loop {
+            let Some(stored) = self.storage.get_block(height)? else {
+                return Ok(None);
+            };
+            if self.network.get_header(height).await?.hash == stored.header.hash {
+                return Ok((height != tip).then_some(height));
+            }
+            if height == floor {
+                return Ok(None);
+            }
+            height -= 1;
+        }
+    }
+
+    /// Drop the stored blocks above `fork` and the validator window that
+    /// ends in them.
+    fn detach_above(&self, fork: u64) -> Result<()> {
+        let tip = self.height();
+        self.storage.save_height(fork)?;
+        for height in (fork + 1..=tip).rev() {
+            self.storage.delete_block(height)?;
+        }
+        self.validator.lock().unwrap().reset();
+        self.state.write().unwrap().height = fork;
+
+        for observer in &self.observers {
+            observer.on_detach(fork)?;
+        }
+        let _ = self.event_tx.send(NodeEvent::Reorg { fork_height: fork, detached: tip - fork });
+        Ok(())
+    }
+
+    /// Rebuild the validator's window from stored blocks when it doesn't
+    /// end at `tip`. Without a stored tip the next block is only checked
+    /// against checkpoints and the clock.
+    fn load_validator_window(&self, tip: u64) -> Result<()> {
+        let mut validator = self.validator.lock().unwrap();
+        if validator.tip().map(|h| h.height) == Some(tip) {
+            return Ok(());
+        }
+        validator.reset();
+        let first = tip.saturating_sub(TIMESTAMP_CHECK_WINDOW as u64 - 1);
+        for height in first..=tip {
+            if let Some(block) = self.storage.get_block(height)? {
+                validator.accept(block.header);
+            }
+        }
+        if validator.tip().map(|h| h.height) != Some(tip) {
+            validator.reset();
+        }
+        Ok(())
+    }
+
     pub fn get_block(&self, height: u64) -> Result<Option<Block>> {
         self.storage.get_block(height)
     }
@@ -186,3 +331,21 @@ impl EmbeddedNode {
         &self.network
     }
 }
+
+#[async_trait]
+impl SyncTarget for EmbeddedNode {
+    async fn sync_round(&self) -> Result<SyncRound> {
+        let before = self.height();
+        self.sync_batch(None, SYNC_ROUND_BLOCKS).await?;
+        let state = self.state.read().unwrap();
+        Ok(SyncRound {
+            synced: state.height.saturating_sub(before),
+            height: state.height,
+            target: state.target_height,
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::error::{Result, SdkError};
+use crate::http::{HttpConfig, DEST_ORDERBOOK};
 use crate::types::*;
 
 /// Orderbook RPC client wrapping fuegod daemon endpoints.
@@ -10,10 +11,14 @@ pub struct OrderbookClient {
 
 impl OrderbookClient {
     pub fn new(endpoint: impl Into<String>) -> Self {
-        Self {
+        Self::with_http(endpoint, &HttpConfig::default()).expect("default HTTP client")
+    }
+
+    pub fn with_http(endpoint: impl Into<String>, http: &HttpConfig) -> Result<Self> {
+        Ok(Self {
             endpoint: endpoint.into(),
-            client: reqwest::Client::new(),
-        }
+            client: http.client(DEST_ORDERBOOK)?,
+        })
     }
 
     // ── Swap Offers ───────────────────────────────────────────────────
@@ -1,59 +1,57 @@
 use crate::error::{Result, SdkError};
+use crate::events::{WalletEvent, WalletEvents, SPENDABLE_AGE};
+use crate::history::{HistoryPage, HistoryQuery};
 use crate::serialization::{
-    parse_extra_pubkey, CommitmentSpendInput, OutputTarget, TransactionPrefix, TxInput, HEAT_TERM,
+    parse_extra_payment_id, parse_extra_pubkey, CommitmentSpendInput, OutputTarget, PaymentId,
+    TransactionPrefix, HEAT_TERM,
 };
 use crate::transaction_builder::{
     build_transaction as build_signed_transaction, compute_change, select_inputs,
-    BuildDestination, BuiltTransaction, DecoyEntry, SpendableOutput, DEFAULT_DUST_THRESHOLD,
+    BuildDestination, BuiltTransaction, CommitmentDeposit, DecoyEntry, SpendableOutput,
+    DEFAULT_DUST_THRESHOLD,
 };
 use crate::types::{Address, Balance};
+use crate::unsigned_tx::{SignedTransaction, UnsignedTransaction};
 use crate::vault::WalletVault;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use zeroize::{Zeroize, Zeroizing};
 
-/// A spendable key output owned by this wallet, with everything needed to
-/// build and sign a transaction.
+/// A key output owned by this wallet. Only public data is kept: the
+/// one-time secret is re-derived from the wallet keys when signing
+/// (`UtxoScanner::spendable_output`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UtxoEntry {
     pub amount: u64,
     /// One-time output key P.
     pub output_key: [u8; 32],
-    /// One-time secret key x (x*G == P).
-    pub secret_key: [u8; 32],
     /// Key image I = x * H_p(P).
     pub key_image: [u8; 32],
     /// Global output index (from /get_o_indexes.bin, attached after scan).
     pub global_index: u32,
     pub tx_hash: [u8; 32],
+    /// Tx public key R of the funding transaction; with `output_position`
+    /// it re-derives the output secret x = b + Hs(a·R || i).
+    pub tx_public_key: [u8; 32],
     /// Position of this output within its funding transaction.
     pub output_position: u32,
     pub block_height: u64,
+    /// Receiving subaddress (0 = primary address).
+    pub subaddress: u32,
 }
 
-impl From<&UtxoEntry> for SpendableOutput {
-    fn from(u: &UtxoEntry) -> Self {
-        SpendableOutput {
-            amount: u.amount,
-            output_key: u.output_key,
-            secret_key: u.secret_key,
-            key_image: u.key_image,
-            global_index: u.global_index,
-            tx_hash: u.tx_hash,
-            output_position: u.output_position,
-        }
-    }
-}
-
-/// A commitment output owned by this wallet (HEAT, HEAT CDs, XFG CDs).
+/// A commitment output owned by this wallet (HEAT, HEAT CDs, XFG CDs). Its
+/// key scalar is re-derived when spending (`UtxoScanner::commitment_deposit`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitmentEntry {
     pub amount: u64,
     pub commit_key: [u8; 32],
-    pub key_scalar: [u8; 32],
     pub key_image: [u8; 32],
     pub global_index: u32,
     pub tx_hash: [u8; 32],
+    /// Tx public key R of the funding transaction.
+    pub tx_public_key: [u8; 32],
     pub output_position: u32,
     /// Lock term in blocks. HEAT_TERM = HEAT (spendable); finite term = CD.
     pub term: u32,
@@ -66,13 +64,97 @@ pub enum HistoryDirection {
     Outgoing,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HistoryEntry {
     pub tx_hash: [u8; 32],
     pub block_height: u64,
     pub direction: HistoryDirection,
     pub amount: u64,
     pub fee: u64,
+    /// Subaddress that received (incoming) or whose output was spent
+    /// (outgoing); 0 = primary address.
+    pub subaddress: u32,
+    /// Payment id from the tx extra nonce of an incoming transfer,
+    /// decrypted, so a deposit to an integrated address can be matched to
+    /// its invoice.
+    pub payment_id: Option<PaymentId>,
+}
+
+/// An owned output consumed by a scanned transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpentOutput {
+    Key(UtxoEntry),
+    Commitment(CommitmentEntry),
+}
+
+/// A scanned spend of one of our outputs. Retained so a chain
+/// reorganization that orphans the spending block can restore the output
+/// (TransfersContainer::detach in the C++ wallet).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpentRecord {
+    pub spent_height: u64,
+    pub output: SpentOutput,
+}
+
+impl SpentRecord {
+    pub fn key_image(&self) -> [u8; 32] {
+        match &self.output {
+            SpentOutput::Key(u) => u.key_image,
+            SpentOutput::Commitment(c) => c.key_image,
+        }
+    }
+}
+
+/// A key image exported from the full wallet for a view-only one, with a
+/// one-member ring signature over the key image made with the output's
+/// secret — the proof that `key_image` really belongs to `output_key`
+/// (CryptoNote cold-wallet key image export).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedKeyImage {
+    pub output_key: [u8; 32],
+    pub key_image: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+impl SignedKeyImage {
+    pub fn sign(
+        output_key: &[u8; 32],
+        secret_key: &[u8; 32],
+        rng: &mut impl rand::RngCore,
+    ) -> Option<Self> {
+        let key_image = fuego_crypto::ring::generate_key_image(output_key, secret_key);
+        let signature = fuego_crypto::ring::generate_ring_signature(
+            &key_image,
+            &key_image,
+            &[*output_key],
+            secret_key,
+            0,
+            rng,
+        )?;
+        Some(Self {
+            output_key: *output_key,
+            key_image,
+            signature: signature[0],
+        })
+    }
+
+    pub fn verify(&self) -> bool {
+        fuego_crypto::ring::check_ring_signature(
+            &self.key_image,
+            &self.key_image,
+            &[self.output_key],
+            &[self.signature],
+        )
+    }
+}
+
+/// Outcome of `UtxoScanner::import_key_images`.
+#[derive(Debug, Clone, Default)]
+pub struct KeyImageImport {
+    /// Outputs that got their key image and are not known to be spent.
+    pub attached: Vec<UtxoEntry>,
+    /// Outputs whose imported image had already been seen spent.
+    pub spent: Vec<SpentRecord>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,11 +164,64 @@ pub struct ScannerStateSnapshot {
     pub commitments: Vec<CommitmentEntry>,
     pub spent_images: Vec<[u8; 32]>,
     pub history: Vec<HistoryEntry>,
+    #[serde(default)]
+    pub spent_outputs: Vec<SpentRecord>,
+}
+
+/// Everything the scanner learned from one block: outputs received there
+/// (still unspent), spends recorded there, and its history rows in scan
+/// order. Lets callers persist scan state incrementally per block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockScanDelta {
+    pub utxos: Vec<UtxoEntry>,
+    pub commitments: Vec<CommitmentEntry>,
+    pub spent: Vec<SpentRecord>,
+    pub history: Vec<HistoryEntry>,
+}
+
+/// Effect of an unconfirmed pool transaction on the wallet, from
+/// [`UtxoScanner::preview_tx_prefix`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolTxEffect {
+    pub tx_hash: [u8; 32],
+    /// Amount paid to this wallet.
+    pub received: u64,
+    /// Amount of this wallet's outputs the transaction spends.
+    pub spent: u64,
+    /// Key images of those outputs.
+    pub spent_key_images: Vec<[u8; 32]>,
+    /// Amount paid to each receiving subaddress (0 = primary address), in
+    /// subaddress order.
+    pub received_by: Vec<(u32, u64)>,
+    /// Decrypted payment id of an incoming transfer.
+    pub payment_id: Option<PaymentId>,
+}
+
+/// An output of a scanned transaction that belongs to the wallet.
+enum OwnedOutput {
+    Key { position: usize, output_key: [u8; 32], subaddress: u32 },
+    Commitment { position: usize, commit_key: [u8; 32], key_image: [u8; 32], term: u32 },
 }
 
 pub struct UtxoScanner {
-    vault: WalletVault,
+    keys: ScanKeys,
+    /// Spend public key → subaddress index, consulted during output
+    /// detection (WalletGreen's spend key map: all subaddresses share the
+    /// primary view key).
+    subaddresses: HashMap<[u8; 32], u32>,
     state: Arc<RwLock<ScannerState>>,
+    events: WalletEvents,
+}
+
+/// Where the scanner's key material comes from: a full vault, or only the
+/// public spend key and the private view key of a watch-only wallet.
+enum ScanKeys {
+    Vault(WalletVault),
+    ViewOnly {
+        spend_public: [u8; 32],
+        view_secret: [u8; 32],
+        view_public: [u8; 32],
+    },
 }
 
 struct ScannerState {
@@ -95,11 +230,17 @@ struct ScannerState {
     commitments: Vec<CommitmentEntry>,
     spent_images: HashSet<[u8; 32]>,
     history: Vec<HistoryEntry>,
+    spent_outputs: Vec<SpentRecord>,
+    /// Key image → hash of the broadcast send spending it, for the send
+    /// confirmed/failed events. Not persisted: callers re-track pending
+    /// sends after a restore.
+    sends: HashMap<[u8; 32], [u8; 32]>,
     balance: Balance,
 }
 
 /// The wallet's core key material: index 0 = spend, index 1 = view
-/// (matches WalletVault::get_address layout).
+/// (matches WalletVault::get_address layout). `spend_secret` is all zeros
+/// for a view-only wallet.
 pub struct WalletKeys {
     pub spend_secret: [u8; 32],
     pub spend_public: [u8; 32],
@@ -109,36 +250,186 @@ pub struct WalletKeys {
 
 impl UtxoScanner {
     pub fn new(vault: WalletVault) -> Self {
-        Self {
-            vault,
+        Self::with_keys(ScanKeys::Vault(vault))
+    }
+
+    /// A watch-only scanner: detects incoming outputs and reports balance
+    /// and history, but cannot compute key images or sign. Outputs are
+    /// marked spent once their key images are imported from the full wallet.
+    pub fn view_only(spend_public: [u8; 32], view_secret: [u8; 32]) -> Result<Self> {
+        if !fuego_crypto::ring::point_is_valid(&spend_public) {
+            return Err(SdkError::Crypto("invalid spend public key".into()));
+        }
+        if !fuego_crypto::ref10::sc_check(&view_secret) {
+            return Err(SdkError::Crypto("view secret is not a canonical scalar".into()));
+        }
+        Ok(Self::with_keys(ScanKeys::ViewOnly {
+            spend_public,
+            view_secret,
+            view_public: fuego_crypto::ring::secret_key_to_public_key(&view_secret),
+        }))
+    }
+
+    fn with_keys(keys: ScanKeys) -> Self {
+        let mut scanner = Self {
+            keys,
+            subaddresses: HashMap::new(),
             state: Arc::new(RwLock::new(ScannerState {
                 height: 0,
                 utxos: Vec::new(),
                 commitments: Vec::new(),
                 spent_images: HashSet::new(),
                 history: Vec::new(),
+                spent_outputs: Vec::new(),
+                sends: HashMap::new(),
                 balance: Balance::default(),
             })),
+            events: WalletEvents::new(),
+        };
+        scanner.register_subaddresses();
+        scanner
+    }
+
+    /// Rebuild the subaddress lookup table: the primary spend key, plus
+    /// every subaddress the vault has issued. A view-only scanner knows
+    /// only its primary spend key.
+    fn register_subaddresses(&mut self) {
+        let count = self.subaddress_count();
+        self.subaddresses = (0..=count)
+            .filter_map(|index| Some((self.subaddress_spend_public(index)?, index)))
+            .collect();
+    }
+
+    /// Number of subaddresses issued besides the primary address.
+    pub fn subaddress_count(&self) -> u32 {
+        self.vault().map_or(0, |vault| vault.subaddress_count())
+    }
+
+    /// Restore the number of issued subaddresses (e.g. after reopening a
+    /// wallet from its seed) so outputs to all of them are detected.
+    pub fn set_subaddress_count(&mut self, count: u32) {
+        if let Some(vault) = self.vault_mut() {
+            vault.set_subaddress_count(count);
+        }
+        self.register_subaddresses();
+    }
+
+    /// Issue the next subaddress; returns its index.
+    pub fn new_subaddress(&mut self) -> Result<u32> {
+        let vault = self.vault_mut().ok_or_else(|| {
+            SdkError::Wallet("view-only wallet cannot create subaddresses".into())
+        })?;
+        vault.new_subaddress();
+        let index = vault.subaddress_count();
+        self.register_subaddresses();
+        Ok(index)
+    }
+
+    /// Spend keypair of a subaddress: keypair(0) for the primary address,
+    /// the vault's subaddress spend index otherwise. The secret is reduced
+    /// mod l, matching the public key the vault derives from it.
+    fn subaddress_spend_keypair(&self, index: u32) -> Option<fuego_crypto::Keypair> {
+        let vault = self.vault()?;
+        if index > vault.subaddress_count() {
+            return None;
+        }
+        let mut keypair = if index == 0 {
+            vault.derive_keypair(0)
+        } else {
+            vault.derive_keypair(vault.get_subaddress_spend_index(index))
+        };
+        fuego_crypto::ref10::sc_reduce32(&mut keypair.secret);
+        Some(keypair)
+    }
+
+    /// Spend public key of subaddress `index` (0 = primary), if issued.
+    pub fn subaddress_spend_public(&self, index: u32) -> Option<[u8; 32]> {
+        match &self.keys {
+            ScanKeys::Vault(_) => self.subaddress_spend_keypair(index).map(|kp| kp.public),
+            ScanKeys::ViewOnly { spend_public, .. } => (index == 0).then_some(*spend_public),
+        }
+    }
+
+    /// Address of subaddress `index` (0 = primary): its own spend key with
+    /// the primary view key. `None` if it has not been issued.
+    pub fn subaddress(&self, index: u32) -> Option<Address> {
+        let spend_public = self.subaddress_spend_public(index)?;
+        let view_public = self.wallet_keys().view_public;
+        Some(Address(
+            fuego_crypto::make_address(&spend_public, &view_public).0,
+        ))
+    }
+
+    /// Confirmed balance of each subaddress, indexed by subaddress.
+    pub fn subaddress_balances(&self) -> Vec<u64> {
+        let mut balances = vec![0u64; self.subaddress_count() as usize + 1];
+        let state = self.state.read().unwrap();
+        for utxo in &state.utxos {
+            if let Some(balance) = balances.get_mut(utxo.subaddress as usize) {
+                *balance += utxo.amount;
+            }
         }
+        balances
     }
 
-    pub fn vault(&self) -> &WalletVault {
-        &self.vault
+    /// The subaddress owning key output `i` of a transaction, found by
+    /// recovering the spend key P - Hs(D || i)·G and looking it up.
+    fn output_subaddress(
+        &self,
+        derivation: &fuego_crypto::KeyDerivation,
+        i: usize,
+        output_key: &[u8; 32],
+    ) -> Option<u32> {
+        let spend_public = fuego_crypto::underive_public_key(
+            derivation,
+            i as u64,
+            &fuego_crypto::PublicKey(*output_key),
+        )?;
+        self.subaddresses.get(&spend_public.0).copied()
+    }
+
+    /// The backing vault; `None` for a view-only scanner.
+    pub fn vault(&self) -> Option<&WalletVault> {
+        match &self.keys {
+            ScanKeys::Vault(vault) => Some(vault),
+            ScanKeys::ViewOnly { .. } => None,
+        }
     }
 
-    pub fn vault_mut(&mut self) -> &mut WalletVault {
-        &mut self.vault
+    pub fn vault_mut(&mut self) -> Option<&mut WalletVault> {
+        match &mut self.keys {
+            ScanKeys::Vault(vault) => Some(vault),
+            ScanKeys::ViewOnly { .. } => None,
+        }
+    }
+
+    pub fn is_view_only(&self) -> bool {
+        matches!(self.keys, ScanKeys::ViewOnly { .. })
     }
 
     /// Primary wallet keys: keypair(0) = spend, keypair(1) = view.
     pub fn wallet_keys(&self) -> WalletKeys {
-        let spend = self.vault.derive_keypair(0);
-        let view = self.vault.derive_keypair(1);
-        WalletKeys {
-            spend_secret: spend.secret,
-            spend_public: spend.public,
-            view_secret: view.secret,
-            view_public: view.public,
+        match &self.keys {
+            ScanKeys::Vault(vault) => {
+                let spend = vault.derive_keypair(0);
+                let view = vault.derive_keypair(1);
+                WalletKeys {
+                    spend_secret: spend.secret,
+                    spend_public: spend.public,
+                    view_secret: view.secret,
+                    view_public: view.public,
+                }
+            }
+            ScanKeys::ViewOnly {
+                spend_public,
+                view_secret,
+                view_public,
+            } => WalletKeys {
+                spend_secret: [0u8; 32],
+                spend_public: *spend_public,
+                view_secret: *view_secret,
+                view_public: *view_public,
+            },
         }
     }
 
@@ -146,8 +437,26 @@ impl UtxoScanner {
         self.state.read().unwrap().height
     }
 
+    /// Advance (or roll back) the scanned height. Moving forward publishes
+    /// confirmation, unlock and deposit maturity events.
     pub fn set_height(&self, height: u64) {
-        self.state.write().unwrap().height = height;
+        let events = {
+            let mut state = self.state.write().unwrap();
+            let previous = std::mem::replace(&mut state.height, height);
+            if height > previous {
+                height_events(&state, previous, height)
+            } else {
+                Vec::new()
+            }
+        };
+        for event in events {
+            self.events.publish(event);
+        }
+    }
+
+    /// The scanner's event bus.
+    pub fn events(&self) -> &WalletEvents {
+        &self.events
     }
 
     pub fn balance(&self) -> Balance {
@@ -190,13 +499,18 @@ impl UtxoScanner {
         state.history.iter().rev().take(limit).cloned().collect()
     }
 
+    pub fn query_history(&self, query: &HistoryQuery) -> HistoryPage {
+        query.page(&self.state.read().unwrap().history)
+    }
+
     pub fn is_spent(&self, key_image: &[u8; 32]) -> bool {
         self.state.read().unwrap().spent_images.contains(key_image)
     }
 
     /// Scan one transaction prefix for outputs we own and inputs spending
     /// our outputs, using the standard CryptoNote discovery rules:
-    /// key outputs: P == Hs(a·R || i) · G + B;
+    /// key outputs: P - Hs(a·R || i) · G is the spend key B of the primary
+    /// address or of a subaddress;
     /// commitment outputs: commitKey == deriveCommitmentKeys(Hs(D || i)).commitKey
     /// with D = a·R.
     pub fn scan_tx_prefix(
@@ -206,17 +520,22 @@ impl UtxoScanner {
         block_height: u64,
     ) -> Result<(u64, u64)> {
         let keys = self.wallet_keys();
+        let view_only = self.is_view_only();
         let mut state = self.state.write().unwrap();
 
         let mut received = 0u64;
         let mut spent = 0u64;
+        // Amount received per subaddress; commitment outputs pay the primary.
+        let mut received_by = BTreeMap::new();
+        let mut events = Vec::new();
+        // Tracked sends whose inputs this transaction spends.
+        let mut sends = HashSet::new();
 
         // Spend detection first: any input key image matching our unspent
         // outputs removes it.
         for input in &prefix.inputs {
-            let image = match input {
-                TxInput::Key(k) => &k.key_image,
-                TxInput::CommitmentSpend(c) => &c.key_image,
+            let Some(image) = input.key_image() else {
+                continue;
             };
             if let Some(idx) = state
                 .utxos
@@ -225,13 +544,28 @@ impl UtxoScanner {
             {
                 let entry = state.utxos.remove(idx);
                 state.spent_images.insert(entry.key_image);
+                if let Some(send) = state.sends.remove(&entry.key_image) {
+                    sends.insert(send);
+                }
                 spent += entry.amount;
+                events.push(WalletEvent::OutputSpent {
+                    tx_hash: *tx_hash,
+                    key_image: entry.key_image,
+                    amount: entry.amount,
+                    height: block_height,
+                });
                 state.history.push(HistoryEntry {
                     tx_hash: *tx_hash,
                     block_height,
                     direction: HistoryDirection::Outgoing,
                     amount: entry.amount,
                     fee: prefix_inputs_amount_delta(prefix),
+                    subaddress: entry.subaddress,
+                    payment_id: None,
+                });
+                state.spent_outputs.push(SpentRecord {
+                    spent_height: block_height,
+                    output: SpentOutput::Key(entry),
                 });
                 continue;
             }
@@ -242,109 +576,230 @@ impl UtxoScanner {
             {
                 let entry = state.commitments.remove(idx);
                 state.spent_images.insert(entry.key_image);
+                if let Some(send) = state.sends.remove(&entry.key_image) {
+                    sends.insert(send);
+                }
                 spent += entry.amount;
+                events.push(WalletEvent::OutputSpent {
+                    tx_hash: *tx_hash,
+                    key_image: entry.key_image,
+                    amount: entry.amount,
+                    height: block_height,
+                });
                 state.history.push(HistoryEntry {
                     tx_hash: *tx_hash,
                     block_height,
                     direction: HistoryDirection::Outgoing,
                     amount: entry.amount,
                     fee: prefix_inputs_amount_delta(prefix),
+                    subaddress: 0,
+                    payment_id: None,
+                });
+                state.spent_outputs.push(SpentRecord {
+                    spent_height: block_height,
+                    output: SpentOutput::Commitment(entry),
                 });
             }
         }
 
         // Output detection.
-        let r = match parse_extra_pubkey(&prefix.extra) {
-            Some(k) => k,
-            None => return Ok((received, spent)),
-        };
-        let derivation = match fuego_crypto::generate_key_derivation(
-            &fuego_crypto::PublicKey(r),
-            &keys.view_secret,
-        ) {
-            Some(d) => d,
-            None => return Ok((received, spent)),
-        };
-
-        for (i, output) in prefix.outputs.iter().enumerate() {
-            match &output.target {
-                OutputTarget::Key(output_key) => {
-                    let expected = match fuego_crypto::derive_public_key(
-                        &derivation,
-                        i as u64,
-                        &keys.spend_public,
-                    ) {
-                        Some(p) => p,
-                        None => continue,
-                    };
-                    if expected.0 != *output_key {
-                        continue;
+        let mut payment_id = None;
+        if let Some((r, derivation, owned)) = self.owned_outputs(&keys, prefix) {
+            for owned_output in owned {
+                match owned_output {
+                    OwnedOutput::Key { position: i, output_key, subaddress } => {
+                        // View-only: the output is ours but its key image stays
+                        // unknown until it is imported. The secret is needed
+                        // only for the key image and is not kept.
+                        let key_image = if view_only {
+                            [0u8; 32]
+                        } else {
+                            let Some(spend) = self.subaddress_spend_keypair(subaddress) else {
+                                continue;
+                            };
+                            let Some(secret) = fuego_crypto::derive_secret_key(
+                                &derivation,
+                                i as u64,
+                                &spend.secret,
+                            )
+                            .map(Zeroizing::new) else {
+                                continue;
+                            };
+                            fuego_crypto::generate_key_image(
+                                &fuego_crypto::PublicKey(output_key),
+                                &secret,
+                            )
+                            .0
+                        };
+                        let amount = prefix.outputs[i].amount;
+                        state.utxos.push(UtxoEntry {
+                            amount,
+                            output_key,
+                            key_image,
+                            global_index: 0,
+                            tx_hash: *tx_hash,
+                            tx_public_key: r,
+                            output_position: i as u32,
+                            block_height,
+                            subaddress,
+                        });
+                        *received_by.entry(subaddress).or_insert(0) += amount;
+                        received += amount;
                     }
-                    let secret = match fuego_crypto::derive_secret_key(
-                        &derivation,
-                        i as u64,
-                        &keys.spend_secret,
-                    ) {
-                        Some(s) => s,
-                        None => continue,
-                    };
-                    let key_image = fuego_crypto::generate_key_image(
-                        &fuego_crypto::PublicKey(*output_key),
-                        &secret,
-                    );
-                    state.utxos.push(UtxoEntry {
-                        amount: output.amount,
-                        output_key: *output_key,
-                        secret_key: secret,
-                        key_image: key_image.0,
-                        global_index: 0,
-                        tx_hash: *tx_hash,
-                        output_position: i as u32,
-                        block_height,
-                    });
-                    received += output.amount;
-                }
-                OutputTarget::Commitment(commit) => {
-                    let deposit_secret =
-                        fuego_crypto::ring::derive_deposit_secret(&derivation, i as u32);
-                    let ck = fuego_crypto::ring::derive_commitment_keys(&deposit_secret);
-                    if ck.commit_key != commit.commit_key {
-                        continue;
+                    OwnedOutput::Commitment { position: i, commit_key, key_image, term } => {
+                        let amount = prefix.outputs[i].amount;
+                        state.commitments.push(CommitmentEntry {
+                            amount,
+                            commit_key,
+                            key_image,
+                            global_index: 0,
+                            tx_hash: *tx_hash,
+                            tx_public_key: r,
+                            output_position: i as u32,
+                            term,
+                            block_height,
+                        });
+                        *received_by.entry(0).or_insert(0) += amount;
+                        received += amount;
                     }
-                    state.commitments.push(CommitmentEntry {
-                        amount: output.amount,
-                        commit_key: commit.commit_key,
-                        key_scalar: ck.key_scalar,
-                        key_image: ck.key_image,
-                        global_index: 0,
-                        tx_hash: *tx_hash,
-                        output_position: i as u32,
-                        term: commit.term,
-                        block_height,
-                    });
-                    received += output.amount;
                 }
             }
+            if received > 0 {
+                payment_id = parse_extra_payment_id(&prefix.extra).map(|id| id.encrypt(&derivation));
+            }
         }
 
-        if received > 0 || spent > 0 {
+        for (&subaddress, &amount) in &received_by {
             state.history.push(HistoryEntry {
                 tx_hash: *tx_hash,
                 block_height,
-                direction: if received > 0 {
-                    HistoryDirection::Incoming
-                } else {
-                    HistoryDirection::Outgoing
-                },
-                amount: received.max(spent),
+                direction: HistoryDirection::Incoming,
+                amount,
                 fee: 0,
+                subaddress,
+                payment_id,
+            });
+        }
+        if received == 0 && spent > 0 {
+            state.history.push(HistoryEntry {
+                tx_hash: *tx_hash,
+                block_height,
+                direction: HistoryDirection::Outgoing,
+                amount: spent,
+                fee: 0,
+                subaddress: 0,
+                payment_id,
             });
         }
 
         state.balance.confirmed = state.utxos.iter().map(|u| u.amount).sum();
+        drop(state);
+
+        for send in sends {
+            events.push(if send == *tx_hash {
+                WalletEvent::SendConfirmed { tx_hash: send, height: block_height }
+            } else {
+                WalletEvent::SendFailed {
+                    tx_hash: send,
+                    reason: format!("inputs spent by {}", hex::encode(tx_hash)),
+                }
+            });
+        }
+        for (subaddress, amount) in received_by {
+            events.push(WalletEvent::PaymentReceived {
+                tx_hash: *tx_hash,
+                height: block_height,
+                amount,
+                subaddress,
+                payment_id,
+            });
+        }
+        for event in events {
+            self.events.publish(event);
+        }
         Ok((received, spent))
     }
 
+    /// Outputs of `prefix` addressed to this wallet, with the transaction
+    /// public key R and the derivation a·R. `None` when the extra carries
+    /// no usable R.
+    fn owned_outputs(
+        &self,
+        keys: &WalletKeys,
+        prefix: &TransactionPrefix,
+    ) -> Option<([u8; 32], fuego_crypto::KeyDerivation, Vec<OwnedOutput>)> {
+        let r = parse_extra_pubkey(&prefix.extra)?;
+        let derivation =
+            fuego_crypto::generate_key_derivation(&fuego_crypto::PublicKey(r), &keys.view_secret)?;
+        let mut owned = Vec::new();
+        for (i, output) in prefix.outputs.iter().enumerate() {
+            match &output.target {
+                OutputTarget::Key(output_key) => {
+                    if let Some(subaddress) = self.output_subaddress(&derivation, i, output_key) {
+                        owned.push(OwnedOutput::Key { position: i, output_key: *output_key, subaddress });
+                    }
+                }
+                OutputTarget::Commitment(commit) => {
+                    let mut deposit_secret =
+                        fuego_crypto::ring::derive_deposit_secret(&derivation, i as u32);
+                    let mut ck = fuego_crypto::ring::derive_commitment_keys(&deposit_secret);
+                    deposit_secret.zeroize();
+                    ck.key_scalar.zeroize();
+                    if ck.commit_key == commit.commit_key {
+                        owned.push(OwnedOutput::Commitment {
+                            position: i,
+                            commit_key: ck.commit_key,
+                            key_image: ck.key_image,
+                            term: commit.term,
+                        });
+                    }
+                }
+            }
+        }
+        Some((r, derivation, owned))
+    }
+
+    /// What `prefix` would do to this wallet if it were mined, without
+    /// recording anything: for transactions still in the pool.
+    pub fn preview_tx_prefix(&self, tx_hash: &[u8; 32], prefix: &TransactionPrefix) -> PoolTxEffect {
+        let mut effect = PoolTxEffect { tx_hash: *tx_hash, ..Default::default() };
+        {
+            let state = self.state.read().unwrap();
+            for image in prefix.inputs.iter().filter_map(|input| input.key_image()) {
+                let amount = state
+                    .utxos
+                    .iter()
+                    .find(|u| u.key_image == *image)
+                    .map(|u| u.amount)
+                    .or_else(|| state.commitments.iter().find(|c| c.key_image == *image).map(|c| c.amount));
+                if let Some(amount) = amount {
+                    effect.spent += amount;
+                    effect.spent_key_images.push(*image);
+                }
+            }
+        }
+
+        let keys = self.wallet_keys();
+        if let Some((_, derivation, owned)) = self.owned_outputs(&keys, prefix) {
+            let mut received_by = BTreeMap::new();
+            for owned_output in owned {
+                let (position, subaddress) = match owned_output {
+                    OwnedOutput::Key { position, subaddress, .. } => (position, subaddress),
+                    OwnedOutput::Commitment { position, .. } => (position, 0),
+                };
+                let amount = prefix.outputs[position].amount;
+                effect.received += amount;
+                *received_by.entry(subaddress).or_insert(0) += amount;
+            }
+            effect.received_by = received_by.into_iter().collect();
+            if effect.received > 0 {
+                effect.payment_id =
+                    parse_extra_payment_id(&prefix.extra).map(|id| id.encrypt(&derivation));
+            }
+        }
+        effect
+    }
+
     /// Attach global output indices (from /get_o_indexes.bin, aligned with
     /// the transaction's outputs) to outputs of the given tx.
     pub fn attach_global_indices(&self, tx_hash: &[u8; 32], indices: &[u64]) {
@@ -378,6 +833,201 @@ impl UtxoScanner {
         }
     }
 
+    /// One-time secret x = b + Hs(a·R || i) of key output `position` of
+    /// the transaction with public key R, and the subaddress it pays.
+    /// Fails unless x·G is `output_key`.
+    fn output_secret(
+        &self,
+        tx_public_key: &[u8; 32],
+        position: u32,
+        output_key: &[u8; 32],
+    ) -> Result<(Zeroizing<[u8; 32]>, u32)> {
+        let not_ours = || {
+            SdkError::Crypto(format!(
+                "output {} does not belong to this wallet",
+                hex::encode(output_key)
+            ))
+        };
+        let view_secret = Zeroizing::new(self.wallet_keys().view_secret);
+        let derivation = fuego_crypto::generate_key_derivation(
+            &fuego_crypto::PublicKey(*tx_public_key),
+            &view_secret,
+        )
+        .ok_or_else(not_ours)?;
+        let subaddress = self
+            .output_subaddress(&derivation, position as usize, output_key)
+            .ok_or_else(not_ours)?;
+        let spend = self.subaddress_spend_keypair(subaddress).ok_or_else(not_ours)?;
+        let secret = fuego_crypto::derive_secret_key(&derivation, position as u64, &spend.secret)
+            .map(Zeroizing::new)
+            .ok_or_else(not_ours)?;
+        if fuego_crypto::ring::secret_key_to_public_key(&secret) != *output_key {
+            return Err(not_ours());
+        }
+        Ok((secret, subaddress))
+    }
+
+    /// `utxo` as a transaction input, with its one-time secret re-derived.
+    /// The secret is wiped when the returned value is dropped.
+    pub fn spendable_output(&self, utxo: &UtxoEntry) -> Result<SpendableOutput> {
+        if self.is_view_only() {
+            return Err(SdkError::Wallet(
+                "view-only wallet cannot sign transactions".into(),
+            ));
+        }
+        let (secret, _) =
+            self.output_secret(&utxo.tx_public_key, utxo.output_position, &utxo.output_key)?;
+        Ok(SpendableOutput {
+            amount: utxo.amount,
+            output_key: utxo.output_key,
+            secret_key: *secret,
+            key_image: utxo.key_image,
+            global_index: utxo.global_index,
+            tx_hash: utxo.tx_hash,
+            output_position: utxo.output_position,
+        })
+    }
+
+    pub fn spendable_outputs(&self, utxos: &[UtxoEntry]) -> Result<Vec<SpendableOutput>> {
+        utxos.iter().map(|u| self.spendable_output(u)).collect()
+    }
+
+    /// `entry` as a commitment spend, with its key scalar re-derived from
+    /// Hs(a·R || i). Wiped when the returned value is dropped.
+    pub fn commitment_deposit(
+        &self,
+        entry: &CommitmentEntry,
+        claimed_interest: u64,
+    ) -> Result<CommitmentDeposit> {
+        let not_ours = || {
+            SdkError::Crypto(format!(
+                "commitment {} does not belong to this wallet",
+                hex::encode(entry.commit_key)
+            ))
+        };
+        let view_secret = Zeroizing::new(self.wallet_keys().view_secret);
+        let derivation = fuego_crypto::generate_key_derivation(
+            &fuego_crypto::PublicKey(entry.tx_public_key),
+            &view_secret,
+        )
+        .ok_or_else(not_ours)?;
+        let mut deposit_secret =
+            fuego_crypto::ring::derive_deposit_secret(&derivation, entry.output_position);
+        let mut ck = fuego_crypto::ring::derive_commitment_keys(&deposit_secret);
+        deposit_secret.zeroize();
+        let deposit = (ck.commit_key == entry.commit_key).then(|| CommitmentDeposit {
+            amount: entry.amount,
+            commit_key: entry.commit_key,
+            key_scalar: ck.key_scalar,
+            key_image: entry.key_image,
+            global_index: entry.global_index,
+            claimed_interest,
+        });
+        ck.key_scalar.zeroize();
+        deposit.ok_or_else(not_ours)
+    }
+
+    /// Signed key images of every key output this wallet holds or still
+    /// keeps a spend record for (see `prune_spent`), for import into a
+    /// view-only wallet.
+    pub fn export_key_images(&self, rng: &mut impl rand::RngCore) -> Result<Vec<SignedKeyImage>> {
+        if self.is_view_only() {
+            return Err(SdkError::Wallet(
+                "view-only wallet cannot export key images".into(),
+            ));
+        }
+        let state = self.state.read().unwrap();
+        let spent = state.spent_outputs.iter().filter_map(|r| match &r.output {
+            SpentOutput::Key(u) => Some(u),
+            SpentOutput::Commitment(_) => None,
+        });
+        state
+            .utxos
+            .iter()
+            .chain(spent)
+            .map(|u| {
+                let (secret, _) =
+                    self.output_secret(&u.tx_public_key, u.output_position, &u.output_key)?;
+                SignedKeyImage::sign(&u.output_key, &secret, rng).ok_or_else(|| {
+                    SdkError::Crypto(format!(
+                        "cannot sign key image for output {}",
+                        hex::encode(u.output_key)
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Attach key images exported by the full wallet to the outputs a
+    /// view-only scan found without them. Every signature is checked before
+    /// anything is applied. Outputs whose image was already seen spent are
+    /// marked spent; later spends are detected by scanning. Spends that
+    /// predate the import are the caller's to check against the daemon and
+    /// `mark_spent`.
+    pub fn import_key_images(&self, images: &[SignedKeyImage]) -> Result<KeyImageImport> {
+        if let Some(bad) = images.iter().find(|i| !i.verify()) {
+            return Err(SdkError::Crypto(format!(
+                "invalid key image signature for output {}",
+                hex::encode(bad.output_key)
+            )));
+        }
+        let mut updated = Vec::new();
+        {
+            let mut state = self.state.write().unwrap();
+            for image in images {
+                if let Some(utxo) = state
+                    .utxos
+                    .iter_mut()
+                    .find(|u| u.output_key == image.output_key && u.key_image != image.key_image)
+                {
+                    utxo.key_image = image.key_image;
+                    updated.push(utxo.clone());
+                }
+            }
+        }
+        let known_spent: Vec<[u8; 32]> = {
+            let state = self.state.read().unwrap();
+            updated
+                .iter()
+                .map(|u| u.key_image)
+                .filter(|image| state.spent_images.contains(image))
+                .collect()
+        };
+        updated.retain(|u| !known_spent.contains(&u.key_image));
+        Ok(KeyImageImport {
+            attached: updated,
+            spent: known_spent.iter().filter_map(|i| self.mark_spent(i)).collect(),
+        })
+    }
+
+    /// Mark an owned output spent by a transaction that was not scanned
+    /// (e.g. it predates a view-only wallet's key image import). Records the
+    /// spend at the current height and returns it, or `None` if no unspent
+    /// output carries `key_image`.
+    pub fn mark_spent(&self, key_image: &[u8; 32]) -> Option<SpentRecord> {
+        let mut state = self.state.write().unwrap();
+        let spent_height = state.height;
+        let output = if let Some(idx) = state.utxos.iter().position(|u| &u.key_image == key_image) {
+            SpentOutput::Key(state.utxos.remove(idx))
+        } else if let Some(idx) = state
+            .commitments
+            .iter()
+            .position(|c| &c.key_image == key_image)
+        {
+            SpentOutput::Commitment(state.commitments.remove(idx))
+        } else {
+            return None;
+        };
+        let record = SpentRecord {
+            spent_height,
+            output,
+        };
+        state.spent_images.insert(*key_image);
+        state.spent_outputs.push(record.clone());
+        state.balance.confirmed = state.utxos.iter().map(|u| u.amount).sum();
+        Some(record)
+    }
+
     pub fn snapshot(&self) -> ScannerStateSnapshot {
         let state = self.state.read().unwrap();
         ScannerStateSnapshot {
@@ -386,6 +1036,7 @@ impl UtxoScanner {
             commitments: state.commitments.clone(),
             spent_images: state.spent_images.iter().copied().collect(),
             history: state.history.clone(),
+            spent_outputs: state.spent_outputs.clone(),
         }
     }
 
@@ -396,6 +1047,87 @@ impl UtxoScanner {
         state.commitments = snapshot.commitments.clone();
         state.spent_images = snapshot.spent_images.iter().copied().collect();
         state.history = snapshot.history.clone();
+        state.spent_outputs = snapshot.spent_outputs.clone();
+        state.balance.confirmed = state.utxos.iter().map(|u| u.amount).sum();
+    }
+
+    pub fn block_delta(&self, height: u64) -> BlockScanDelta {
+        let state = self.state.read().unwrap();
+        BlockScanDelta {
+            utxos: state
+                .utxos
+                .iter()
+                .filter(|u| u.block_height == height)
+                .cloned()
+                .collect(),
+            commitments: state
+                .commitments
+                .iter()
+                .filter(|c| c.block_height == height)
+                .cloned()
+                .collect(),
+            spent: state
+                .spent_outputs
+                .iter()
+                .filter(|r| r.spent_height == height)
+                .cloned()
+                .collect(),
+            history: state
+                .history
+                .iter()
+                .filter(|h| h.block_height == height)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Drop records of outputs spent below `height`, which no rollback will
+    /// restore any more. Their key images stay marked as spent.
+    pub fn prune_spent(&self, height: u64) {
+        self.state
+            .write()
+            .unwrap()
+            .spent_outputs
+            .retain(|r| r.spent_height >= height);
+    }
+
+    /// Forget everything learned from blocks at `height` and above, after a
+    /// chain reorganization orphaned them: outputs received there are
+    /// dropped, outputs spent there become unspent again, and history rows
+    /// are removed. The scan height becomes `height - 1` so the caller can
+    /// rescan the new branch. Mempool reservations of restored outputs are
+    /// released; callers re-reserve images of sends that are still pending.
+    pub fn detach(&self, height: u64) {
+        let mut state = self.state.write().unwrap();
+        let state = &mut *state;
+
+        for utxo in state.utxos.iter().filter(|u| u.block_height >= height) {
+            state.spent_images.remove(&utxo.key_image);
+        }
+        for commitment in state.commitments.iter().filter(|c| c.block_height >= height) {
+            state.spent_images.remove(&commitment.key_image);
+        }
+        state.utxos.retain(|u| u.block_height < height);
+        state.commitments.retain(|c| c.block_height < height);
+
+        let (orphaned, kept): (Vec<SpentRecord>, Vec<SpentRecord>) = state
+            .spent_outputs
+            .drain(..)
+            .partition(|r| r.spent_height >= height);
+        state.spent_outputs = kept;
+        for record in orphaned {
+            state.spent_images.remove(&record.key_image());
+            match record.output {
+                SpentOutput::Key(u) if u.block_height < height => state.utxos.push(u),
+                SpentOutput::Commitment(c) if c.block_height < height => {
+                    state.commitments.push(c)
+                }
+                _ => {}
+            }
+        }
+
+        state.history.retain(|h| h.block_height < height);
+        state.height = state.height.min(height.saturating_sub(1));
         state.balance.confirmed = state.utxos.iter().map(|u| u.amount).sum();
     }
 
@@ -415,14 +1147,10 @@ impl UtxoScanner {
         }
         // Only outputs with a confirmed global index are spendable (index 0
         // belongs to the genesis miner transaction and can never be ours).
-        let spendable: Vec<SpendableOutput> = state
-            .utxos
-            .iter()
-            .filter(|u| u.global_index != 0)
-            .map(|u| u.into())
-            .collect();
+        let spendable: Vec<&UtxoEntry> =
+            state.utxos.iter().filter(|u| u.global_index != 0).collect();
         let (selected, found) =
-            select_inputs(&spendable, total_needed, DEFAULT_DUST_THRESHOLD, rng);
+            select_inputs(&spendable, |u| u.amount, total_needed, DEFAULT_DUST_THRESHOLD, rng);
         if found < total_needed {
             return Err(SdkError::InsufficientFunds {
                 need: total_needed,
@@ -432,7 +1160,7 @@ impl UtxoScanner {
         let selected: Vec<UtxoEntry> = state
             .utxos
             .iter()
-            .filter(|u| selected.iter().any(|s| s.key_image == u.key_image))
+            .filter(|u| selected.iter().any(|s| s.output_key == u.output_key))
             .cloned()
             .collect();
         Ok(selected)
@@ -467,6 +1195,11 @@ impl UtxoScanner {
         extra_extra: &[u8],
         rng: &mut impl rand::RngCore,
     ) -> Result<BuiltTransaction> {
+        if self.is_view_only() {
+            return Err(SdkError::Wallet(
+                "view-only wallet cannot build transactions".into(),
+            ));
+        }
         let keys = self.wallet_keys();
 
         let dests_amount: u64 = destinations.iter().map(|(_, a)| *a).sum();
@@ -482,8 +1215,8 @@ impl UtxoScanner {
         let mut dests: Vec<BuildDestination> =
             Vec::with_capacity(destinations.len() + change_chunks.len() + 1);
         for (addr, amount) in destinations {
-            let (spend_pub, view_pub) = match fuego_crypto::parse_address(&addr.0) {
-                Some(k) => k,
+            let (spend_pub, view_pub, payment_id) = match resolve_destination(&addr.0) {
+                Some(d) => d,
                 None => {
                     return Err(SdkError::Crypto(format!(
                         "invalid destination address: {}",
@@ -495,6 +1228,7 @@ impl UtxoScanner {
                 amount: *amount,
                 spend_pub,
                 view_pub,
+                payment_id,
             });
         }
         for chunk in change_chunks {
@@ -502,6 +1236,7 @@ impl UtxoScanner {
                 amount: chunk,
                 spend_pub: change_spend,
                 view_pub: change_view,
+                payment_id: None,
             });
         }
         if dust > 0 {
@@ -509,11 +1244,11 @@ impl UtxoScanner {
                 amount: dust,
                 spend_pub: change_spend,
                 view_pub: change_view,
+                payment_id: None,
             });
         }
 
-        let inputs: Vec<SpendableOutput> = selected.iter().map(|u| u.into()).collect();
-        let inputs = crate::transaction_builder::prepare_inputs(inputs);
+        let inputs = crate::transaction_builder::prepare_inputs(self.spendable_outputs(selected)?);
         // Rebuild the input order matching `inputs` for decoy alignment:
         // decoys are indexed by position in `selected`; prepare_inputs only
         // sorts (stable by amount), so reorder decoys the same way.
@@ -537,11 +1272,85 @@ impl UtxoScanner {
         )
     }
 
+    /// Sign a watch wallet's unsigned transaction: re-derive each input's
+    /// one-time secret from its tx public key, check it opens the output
+    /// key, then build with the carried decoys. Key image proofs for the
+    /// spent outputs ride along so the watch wallet can track the spend.
+    pub fn sign_unsigned(
+        &self,
+        unsigned: &UnsignedTransaction,
+        rng: &mut impl rand::RngCore,
+    ) -> Result<SignedTransaction> {
+        if self.is_view_only() {
+            return Err(SdkError::Wallet(
+                "view-only wallet cannot build transactions".into(),
+            ));
+        }
+
+        let mut selected = Vec::with_capacity(unsigned.inputs.len());
+        let mut decoys = Vec::with_capacity(unsigned.inputs.len());
+        let mut key_images = Vec::with_capacity(unsigned.inputs.len());
+        for input in &unsigned.inputs {
+            if input.decoys.len() != unsigned.mixin {
+                return Err(SdkError::Serialization(format!(
+                    "input {} has {} decoys, expected {}",
+                    hex::encode(input.output_key),
+                    input.decoys.len(),
+                    unsigned.mixin
+                )));
+            }
+            let (secret, subaddress) =
+                self.output_secret(&input.tx_public_key, input.output_position, &input.output_key)?;
+            let image = SignedKeyImage::sign(&input.output_key, &secret, rng).ok_or_else(|| {
+                SdkError::Crypto(format!(
+                    "cannot sign key image for output {}",
+                    hex::encode(input.output_key)
+                ))
+            })?;
+            selected.push(UtxoEntry {
+                amount: input.amount,
+                output_key: input.output_key,
+                key_image: image.key_image,
+                global_index: input.global_index,
+                tx_hash: input.tx_hash,
+                tx_public_key: input.tx_public_key,
+                output_position: input.output_position,
+                block_height: 0,
+                subaddress,
+            });
+            decoys.push(input.decoy_entries());
+            key_images.push(image);
+        }
+
+        let built = self.build_with_selection_ext(
+            &selected,
+            &unsigned.destinations,
+            unsigned.fee,
+            unsigned.mixin,
+            &decoys,
+            unsigned.unlock_time,
+            &unsigned.extra,
+            rng,
+        )?;
+        Ok(SignedTransaction { built, key_images })
+    }
+
     /// Mark a pending (broadcast) transaction's inputs as reserved so they
     /// are not double-selected before confirmation.
     pub fn reserve_pending(&self, key_images: &[[u8; 32]]) {
         self.reserve_key_images(key_images);
     }
+
+    /// Reserve the inputs of send `tx_hash` and remember it, so the block
+    /// spending them publishes `SendConfirmed`, or `SendFailed` when a
+    /// different transaction spent them.
+    pub fn track_send(&self, tx_hash: &[u8; 32], key_images: &[[u8; 32]]) {
+        self.reserve_key_images(key_images);
+        let mut state = self.state.write().unwrap();
+        for image in key_images {
+            state.sends.insert(*image, *tx_hash);
+        }
+    }
 }
 
 impl Default for UtxoScanner {
@@ -550,6 +1359,66 @@ impl Default for UtxoScanner {
     }
 }
 
+/// Confirmations of a block mined at `mined` once the chain top is `top`.
+fn confirmations(mined: u64, top: u64) -> u64 {
+    (top + 1).saturating_sub(mined)
+}
+
+/// Confirmation, unlock and maturity events for the top moving from
+/// `previous` to `height`.
+fn height_events(state: &ScannerState, previous: u64, height: u64) -> Vec<WalletEvent> {
+    let crossed = |at: u64| previous < at && at <= height;
+    let mut events = Vec::new();
+
+    // History is in scan order: stop at the first entry already past the
+    // window.
+    let mut reported = HashSet::new();
+    for entry in state.history.iter().rev() {
+        let before = confirmations(entry.block_height, previous);
+        if before >= SPENDABLE_AGE {
+            break;
+        }
+        let after = confirmations(entry.block_height, height);
+        if entry.direction == HistoryDirection::Incoming
+            && after > before
+            && reported.insert(entry.tx_hash)
+        {
+            events.push(WalletEvent::Confirmations { tx_hash: entry.tx_hash, confirmations: after });
+        }
+    }
+
+    let unlocked = state
+        .utxos
+        .iter()
+        .map(|u| (u.tx_hash, u.output_position, u.amount, u.block_height))
+        .chain(
+            state
+                .commitments
+                .iter()
+                .filter(|c| c.term == HEAT_TERM)
+                .map(|c| (c.tx_hash, c.output_position, c.amount, c.block_height)),
+        );
+    for (tx_hash, output_position, amount, mined) in unlocked {
+        let at = mined + SPENDABLE_AGE - 1;
+        if crossed(at) {
+            events.push(WalletEvent::OutputUnlocked { tx_hash, output_position, amount, height: at });
+        }
+    }
+
+    for deposit in state.commitments.iter().filter(|c| c.term != HEAT_TERM) {
+        let at = deposit.block_height + deposit.term as u64;
+        if crossed(at) && !state.spent_images.contains(&deposit.key_image) {
+            events.push(WalletEvent::DepositMatured {
+                tx_hash: deposit.tx_hash,
+                output_position: deposit.output_position,
+                amount: deposit.amount,
+                height: at,
+            });
+        }
+    }
+    events
+}
+
 /// Approximate the fee of a spend (inputs sum - outputs sum) for history
 /// display purposes.
 fn prefix_inputs_amount_delta(prefix: &TransactionPrefix) -> u64 {
@@ -558,6 +1427,16 @@ fn prefix_inputs_amount_delta(prefix: &TransactionPrefix) -> u64 {
     in_amount.saturating_sub(out_amount)
 }
 
+/// Destination keys for a standard or integrated address, plus the
+/// integrated address's payment id.
+fn resolve_destination(address: &str) -> Option<([u8; 32], [u8; 32], Option<PaymentId>)> {
+    if let Some((spend, view)) = fuego_crypto::parse_address(address) {
+        return Some((spend, view, None));
+    }
+    let (spend, view, id) = fuego_crypto::parse_integrated_address(address)?;
+    Some((spend, view, Some(PaymentId::from_bytes(&id)?)))
+}
+
 // Keep the import used for clarity in the builder call sites.
 #[allow(unused)]
 fn _commitment_spend_type_ref(_c: &CommitmentSpendInput) {}
@@ -8,6 +8,9 @@ use std::fmt;
 
 use serde_json::Value;
 
+use crate::alias::{AliasInfo, TX_EXTRA_TAG_ALIAS_INFO};
+use crate::types::Address;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SerializationError(pub String);
 
@@ -63,6 +66,8 @@ pub fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, SerializationErr
 
 // ---------------------------------------------------------------- tx types
 
+/// Miner (coinbase) input: the first input of every block's base transaction.
+pub const TX_INPUT_TAG_BASE: u8 = 0xff;
 pub const TX_INPUT_TAG_KEY: u8 = 0x02;
 pub const TX_INPUT_TAG_COMMITMENT_SPEND: u8 = 0x04;
 pub const TX_OUTPUT_TAG_KEY: u8 = 0x02;
@@ -70,6 +75,10 @@ pub const TX_OUTPUT_TAG_COMMITMENT: u8 = 0x04;
 pub const TX_EXTRA_TAG_PADDING: u8 = 0x00;
 pub const TX_EXTRA_TAG_PUBKEY: u8 = 0x01;
 pub const TX_EXTRA_TAG_NONCE: u8 = 0x02;
+/// Nonce sub-tags (TransactionExtra.h): plain long payment id 0x00 || 32
+/// bytes, encrypted short payment id 0x01 || 8 bytes.
+pub const TX_EXTRA_NONCE_PAYMENT_ID: u8 = 0x00;
+pub const TX_EXTRA_NONCE_ENCRYPTED_PAYMENT_ID: u8 = 0x01;
 pub const TX_EXTRA_HEAT_MINT_AUTH: u8 = 0xF5;
 pub const TX_EXTRA_AMM_SWAP_AUTH: u8 = 0xF6;
 pub const TX_EXTRA_AMM_LP_ADD_AUTH: u8 = 0xF7;
@@ -97,6 +106,11 @@ pub const DEPOSIT_TERM_POOL_HEAT: u32 = 0x504F_4C48;
 /// MembershipProof size: FUEGO_MEMBERSHIP_N(4) * 2 scalars * 32 bytes.
 pub const AMOUNT_PROOF_LEN: usize = 256;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseInput {
+    pub block_index: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyInput {
     pub amount: u64,
@@ -118,6 +132,7 @@ pub struct CommitmentSpendInput {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TxInput {
+    Base(BaseInput),
     Key(KeyInput),
     CommitmentSpend(CommitmentSpendInput),
 }
@@ -125,10 +140,29 @@ pub enum TxInput {
 impl TxInput {
     pub fn amount(&self) -> u64 {
         match self {
+            TxInput::Base(_) => 0,
             TxInput::Key(k) => k.amount,
             TxInput::CommitmentSpend(c) => c.amount,
         }
     }
+
+    /// Key image of a spending input; `None` for a miner input.
+    pub fn key_image(&self) -> Option<&[u8; 32]> {
+        match self {
+            TxInput::Base(_) => None,
+            TxInput::Key(k) => Some(&k.key_image),
+            TxInput::CommitmentSpend(c) => Some(&c.key_image),
+        }
+    }
+
+    /// Ring size, i.e. the number of signatures the input carries.
+    pub fn ring_size(&self) -> usize {
+        match self {
+            TxInput::Base(_) => 0,
+            TxInput::Key(k) => k.offsets.len(),
+            TxInput::CommitmentSpend(c) => c.offsets.len(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -238,6 +272,10 @@ fn serialize_inputs_into(inputs: &[TxInput], out: &mut Vec<u8>) {
     write_varint(inputs.len() as u64, out);
     for input in inputs {
         match input {
+            TxInput::Base(b) => {
+                out.push(TX_INPUT_TAG_BASE);
+                write_u32_varint(b.block_index, out);
+            }
             TxInput::Key(k) => {
                 out.push(TX_INPUT_TAG_KEY);
                 write_varint(k.amount, out);
@@ -319,6 +357,9 @@ fn read_key(data: &[u8], pos: &mut usize) -> Result<[u8; 32], SerializationError
 fn read_input_at(data: &[u8], pos: &mut usize) -> Result<TxInput, SerializationError> {
     let tag = read_bytes(data, pos, 1)?[0];
     match tag {
+        TX_INPUT_TAG_BASE => Ok(TxInput::Base(BaseInput {
+            block_index: read_u32_varint(data, pos)?,
+        })),
         TX_INPUT_TAG_KEY => {
             let amount = read_varint(data, pos)?;
             let offs_count = read_varint(data, pos)?;
@@ -388,7 +429,7 @@ fn read_output_at(data: &[u8], pos: &mut usize) -> Result<TxOutput, Serializatio
     }
 }
 
-/// Parse a transaction prefix from its serialized form. Only Key /
+/// Parse a transaction prefix from its serialized form. Only Base / Key /
 /// CommitmentSpend inputs and Key / Commitment outputs are accepted (the
 /// chain carries no other types today); an unknown tag is an error.
 pub fn parse_prefix(data: &[u8]) -> Result<TransactionPrefix, SerializationError> {
@@ -437,8 +478,192 @@ pub fn parse_prefix_at(
     })
 }
 
+/// Parse a full transaction: the prefix followed by one 64-byte signature
+/// per ring member of each input (the inverse of [`serialize_tx`]).
+pub fn parse_tx(data: &[u8]) -> Result<Transaction, SerializationError> {
+    let mut pos = 0usize;
+    let tx = parse_tx_at(data, &mut pos)?;
+    if pos != data.len() {
+        return Err(SerializationError(format!(
+            "tx parse: {} trailing bytes",
+            data.len() - pos
+        )));
+    }
+    Ok(tx)
+}
+
+fn parse_tx_at(data: &[u8], pos: &mut usize) -> Result<Transaction, SerializationError> {
+    let prefix = parse_prefix_at(data, pos)?;
+    let mut signatures = Vec::with_capacity(prefix.inputs.len());
+    for input in &prefix.inputs {
+        let ring_size = input.ring_size();
+        let mut sigs = Vec::with_capacity(ring_size);
+        for _ in 0..ring_size {
+            let mut sig = [0u8; 64];
+            sig.copy_from_slice(read_bytes(data, pos, 64)?);
+            sigs.push(sig);
+        }
+        signatures.push(sigs);
+    }
+    Ok(Transaction { prefix, signatures })
+}
+
+// ---------------------------------------------------------------- blocks
+
+/// A block as relayed between peers: header, miner transaction, and the
+/// hashes of the other transactions (which travel as separate blobs).
+/// Fuego blocks carry no merge-mining parent block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedBlock {
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub timestamp: u64,
+    pub prev_id: [u8; 32],
+    pub nonce: u32,
+    pub miner_tx: Transaction,
+    pub tx_hashes: Vec<[u8; 32]>,
+}
+
+pub fn serialize_block(block: &ParsedBlock) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varint(block.major_version as u64, &mut out);
+    write_varint(block.minor_version as u64, &mut out);
+    write_varint(block.timestamp, &mut out);
+    out.extend_from_slice(&block.prev_id);
+    out.extend_from_slice(&block.nonce.to_le_bytes());
+    out.extend_from_slice(&serialize_tx(&block.miner_tx));
+    write_varint(block.tx_hashes.len() as u64, &mut out);
+    for hash in &block.tx_hashes {
+        out.extend_from_slice(hash);
+    }
+    out
+}
+
+pub fn parse_block(data: &[u8]) -> Result<ParsedBlock, SerializationError> {
+    let mut pos = 0usize;
+    let major_version = read_varint(data, &mut pos)? as u8;
+    let minor_version = read_varint(data, &mut pos)? as u8;
+    let timestamp = read_varint(data, &mut pos)?;
+    let prev_id = read_key(data, &mut pos)?;
+    let nonce = u32::from_le_bytes(read_bytes(data, &mut pos, 4)?.try_into().unwrap());
+    let miner_tx = parse_tx_at(data, &mut pos)?;
+    let count = read_varint(data, &mut pos)?;
+    // Each hash takes 32 bytes; don't trust the count for the allocation.
+    let mut tx_hashes = Vec::with_capacity((count as usize).min(data.len() / 32));
+    for _ in 0..count {
+        tx_hashes.push(read_key(data, &mut pos)?);
+    }
+    if pos != data.len() {
+        return Err(SerializationError(format!(
+            "block parse: {} trailing bytes",
+            data.len() - pos
+        )));
+    }
+    Ok(ParsedBlock {
+        major_version,
+        minor_version,
+        timestamp,
+        prev_id,
+        nonce,
+        miner_tx,
+        tx_hashes,
+    })
+}
+
+/// CryptoNote Merkle root (`tree_hash`): pairs are hashed level by level,
+/// with the leaves that do not fit a power of two promoted unhashed.
+/// `hashes` must not be empty.
+pub fn tree_hash(hashes: &[[u8; 32]]) -> [u8; 32] {
+    let pair = |a: &[u8; 32], b: &[u8; 32]| {
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(a);
+        buf[32..].copy_from_slice(b);
+        fuego_crypto::cn_fast_hash(&buf)
+    };
+    match hashes.len() {
+        0 => panic!("tree_hash of no hashes"),
+        1 => hashes[0],
+        2 => pair(&hashes[0], &hashes[1]),
+        count => {
+            // Largest power of two below `count`.
+            let mut cnt = 1usize << (usize::BITS - 1 - (count - 1).leading_zeros());
+            let promoted = 2 * cnt - count;
+            let mut level: Vec<[u8; 32]> = hashes[..promoted].to_vec();
+            level.extend(hashes[promoted..].chunks(2).map(|c| pair(&c[0], &c[1])));
+            while cnt > 2 {
+                cnt /= 2;
+                level = level.chunks(2).map(|c| pair(&c[0], &c[1])).collect();
+            }
+            pair(&level[0], &level[1])
+        }
+    }
+}
+
+/// Block id: the hash of the length-prefixed hashing blob, i.e. the
+/// header, the Merkle root of the miner and listed transactions, and
+/// their count.
+pub fn block_id(block: &ParsedBlock) -> [u8; 32] {
+    let mut blob = Vec::new();
+    write_varint(block.major_version as u64, &mut blob);
+    write_varint(block.minor_version as u64, &mut blob);
+    write_varint(block.timestamp, &mut blob);
+    blob.extend_from_slice(&block.prev_id);
+    blob.extend_from_slice(&block.nonce.to_le_bytes());
+    let mut hashes = vec![fuego_crypto::cn_fast_hash(&serialize_tx(&block.miner_tx))];
+    hashes.extend_from_slice(&block.tx_hashes);
+    blob.extend_from_slice(&tree_hash(&hashes));
+    write_varint(hashes.len() as u64, &mut blob);
+
+    let mut prefixed = Vec::with_capacity(blob.len() + 10);
+    write_varint(blob.len() as u64, &mut prefixed);
+    prefixed.extend_from_slice(&blob);
+    fuego_crypto::cn_fast_hash(&prefixed)
+}
+
 // ---------------------------------------------------------------- tx extra
 
+/// Payment id carried in a tx extra nonce, as embedded in an integrated
+/// address. Short ids travel encrypted with the sender-recipient
+/// derivation (see [`PaymentId::encrypt`]); long ids travel in the clear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PaymentId {
+    Short([u8; 8]),
+    Long([u8; 32]),
+}
+
+impl PaymentId {
+    /// 8 bytes → short, 32 bytes → long; anything else is not a payment id.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes.len() {
+            8 => Some(PaymentId::Short(bytes.try_into().ok()?)),
+            32 => Some(PaymentId::Long(bytes.try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            PaymentId::Short(id) => id,
+            PaymentId::Long(id) => id,
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.as_bytes())
+    }
+
+    /// Encrypt (or, applied again, decrypt) a short id with the key
+    /// derivation both sides share; long ids are returned unchanged.
+    pub fn encrypt(self, derivation: &[u8; 32]) -> Self {
+        match self {
+            PaymentId::Short(id) => {
+                PaymentId::Short(fuego_crypto::ring::encrypt_payment_id(&id, derivation))
+            }
+            long => long,
+        }
+    }
+}
+
 /// Parse the tx public key (tag 0x01) out of the extra blob.
 pub fn parse_extra_pubkey(extra: &[u8]) -> Option<[u8; 32]> {
     let mut pos = 0usize;
@@ -477,6 +702,94 @@ pub fn parse_extra_pubkey(extra: &[u8]) -> Option<[u8; 32]> {
     None
 }
 
+/// Split the extra into (tag, field) pairs, stopping at the first tag whose
+/// length is unknown. A nonce field excludes its length byte; an alias
+/// field starts at its alias length byte.
+fn extra_fields(extra: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut fields = Vec::new();
+    let mut pos = 0usize;
+    while let Some(&tag) = extra.get(pos) {
+        pos += 1;
+        let (skip, len) = match tag {
+            TX_EXTRA_TAG_PADDING => (0, 0),
+            TX_EXTRA_TAG_PUBKEY => (0, 32),
+            TX_EXTRA_TAG_NONCE => match extra.get(pos) {
+                Some(&n) => (1, n as usize),
+                None => break,
+            },
+            TX_EXTRA_TAG_ALIAS_INFO => match alias_field_len(&extra[pos..]) {
+                Some(n) => (0, n),
+                None => break,
+            },
+            _ => break,
+        };
+        pos += skip;
+        match extra.get(pos..pos + len) {
+            Some(field) => fields.push((tag, field)),
+            None => break,
+        }
+        pos += len;
+    }
+    fields
+}
+
+/// Length of an alias field: alias_len || alias || addr_len || addr ||
+/// view_key.
+fn alias_field_len(field: &[u8]) -> Option<usize> {
+    let alias_len = *field.first()? as usize;
+    let addr_len = *field.get(1 + alias_len)? as usize;
+    Some(1 + alias_len + 1 + addr_len + 32)
+}
+
+/// Parse the payment id out of the first extra nonce that carries one. A
+/// short id is returned as it appears on chain, still encrypted.
+pub fn parse_extra_payment_id(extra: &[u8]) -> Option<PaymentId> {
+    extra_fields(extra)
+        .into_iter()
+        .filter(|(tag, _)| *tag == TX_EXTRA_TAG_NONCE)
+        .find_map(|(_, nonce)| match nonce.split_first() {
+            Some((&TX_EXTRA_NONCE_PAYMENT_ID, id)) if id.len() == 32 => PaymentId::from_bytes(id),
+            Some((&TX_EXTRA_NONCE_ENCRYPTED_PAYMENT_ID, id)) if id.len() == 8 => {
+                PaymentId::from_bytes(id)
+            }
+            _ => None,
+        })
+}
+
+/// Parse the alias registration (tag 0xbf) out of the extra blob; the
+/// inverse of [`crate::alias::add_alias_info_to_extra`].
+pub fn parse_extra_alias(extra: &[u8]) -> Option<AliasInfo> {
+    let (_, field) = extra_fields(extra)
+        .into_iter()
+        .find(|(tag, _)| *tag == TX_EXTRA_TAG_ALIAS_INFO)?;
+    let alias_len = field[0] as usize;
+    let alias = std::str::from_utf8(&field[1..1 + alias_len]).ok()?;
+    let addr_len = field[1 + alias_len] as usize;
+    let addr_start = 2 + alias_len;
+    let address = std::str::from_utf8(&field[addr_start..addr_start + addr_len]).ok()?;
+    let view_key: [u8; 32] = field[addr_start + addr_len..].try_into().ok()?;
+    Some(AliasInfo {
+        alias: alias.to_string(),
+        address: Address(address.to_string()),
+        view_key: fuego_crypto::PublicKey(view_key),
+    })
+}
+
+/// TransactionExtra.cpp setPaymentIdToTransactionExtraNonce +
+/// addExtraNonceToTransactionExtra: 0x02 || len || sub-tag || id. A short
+/// id must already be encrypted.
+pub fn add_payment_id_extra(extra: &mut Vec<u8>, payment_id: &PaymentId) {
+    let sub_tag = match payment_id {
+        PaymentId::Short(_) => TX_EXTRA_NONCE_ENCRYPTED_PAYMENT_ID,
+        PaymentId::Long(_) => TX_EXTRA_NONCE_PAYMENT_ID,
+    };
+    let id = payment_id.as_bytes();
+    extra.push(TX_EXTRA_TAG_NONCE);
+    extra.push((id.len() + 1) as u8);
+    extra.push(sub_tag);
+    extra.extend_from_slice(id);
+}
+
 /// Build a standard extra: 0x01 || R (32 bytes). Matches
 /// addTransactionPublicKeyToExtra.
 pub fn build_extra_with_pubkey(r: &[u8; 32]) -> Vec<u8> {
@@ -759,8 +1072,57 @@ pub fn parse_query_blocks_lite_response(
     parse_kv_query_blocks_lite_response(data)
 }
 
-#[derive(Debug)]
-enum KvValue {
+/// Request body of /get_pool_changes_lite.bin: the wallet's tail block id
+/// and the pool transactions it already knows, so only changes come back.
+pub fn pool_changes_lite_request(tail_block_id: &[u8; 32], known_tx_ids: &[[u8; 32]]) -> Vec<u8> {
+    let mut root = BTreeMap::new();
+    root.insert("tailBlockId".to_string(), KvValue::Bytes(tail_block_id.to_vec()));
+    root.insert("knownTxsIds".to_string(), KvValue::Bytes(known_tx_ids.concat()));
+    write_kv_document(&root)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolChangesLiteResponse {
+    /// False when the daemon's chain moved past `tailBlockId`; the wallet
+    /// should sync blocks before trusting the pool view.
+    pub is_tail_block_actual: bool,
+    pub added_txs: Vec<TxPrefixInfo>,
+    pub deleted_tx_ids: Vec<[u8; 32]>,
+}
+
+/// Parse the binary response of /get_pool_changes_lite.bin.
+pub fn parse_pool_changes_lite_response(
+    data: &[u8],
+) -> Result<PoolChangesLiteResponse, SerializationError> {
+    let root = parse_kv_document(data)?;
+    let status = String::from_utf8_lossy(&kv_bytes(&root, &["status"])?).to_string();
+    if status != "OK" {
+        return Err(SerializationError(format!("pool changes status: {status}")));
+    }
+    let is_tail_block_actual = match kv_get(&root, &["isTailBlockActual"]) {
+        Some(KvValue::Bool(value)) => *value,
+        _ => return Err(SerializationError("missing isTailBlockActual".into())),
+    };
+    let added_txs = match kv_get(&root, &["addedTxs"]) {
+        Some(KvValue::Array(values)) => parse_kv_tx_prefix_infos(values)?,
+        None => Vec::new(),
+        _ => return Err(SerializationError("addedTxs is not an array".into())),
+    };
+    let deleted = kv_bytes(&root, &["deletedTxsIds"]).unwrap_or_default();
+    if !deleted.len().is_multiple_of(32) {
+        return Err(SerializationError("deletedTxsIds is not a list of hashes".into()));
+    }
+    let deleted_tx_ids = deleted
+        .chunks_exact(32)
+        .map(|id| id.try_into().expect("32-byte chunk"))
+        .collect();
+    Ok(PoolChangesLiteResponse { is_tail_block_actual, added_txs, deleted_tx_ids })
+}
+
+/// A value of an epee portable-storage ("KV binary") document, the body
+/// format of the daemon's .bin RPCs and of Levin P2P messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvValue {
     Signed(i64),
     Unsigned(u64),
     Float(f64),
@@ -893,7 +1255,9 @@ fn parse_kv_array(
     item_type: u8,
 ) -> Result<KvValue, SerializationError> {
     let count = read_kv_portable_size(data, pos)?;
-    let mut values = Vec::with_capacity(count);
+    // Every item takes at least one byte; don't let a peer-supplied count
+    // size the allocation.
+    let mut values = Vec::with_capacity(count.min(data.len().saturating_sub(*pos)));
     for _ in 0..count {
         values.push(parse_kv_value(data, pos, item_type)?);
     }
@@ -922,7 +1286,8 @@ fn parse_kv_section(
     Ok(object)
 }
 
-fn parse_kv_document(data: &[u8]) -> Result<BTreeMap<String, KvValue>, SerializationError> {
+/// Parse a KV binary document into its root section.
+pub fn parse_kv_document(data: &[u8]) -> Result<BTreeMap<String, KvValue>, SerializationError> {
     if data.len() < 9 {
         return Err(SerializationError("kv document: truncated header".into()));
     }
@@ -1029,6 +1394,9 @@ fn parse_kv_input(object: &BTreeMap<String, KvValue>) -> Result<TxInput, Seriali
         .try_into()
         .map_err(|_| SerializationError("invalid key image".into()))?;
     match tag {
+        TX_INPUT_TAG_BASE => Ok(TxInput::Base(BaseInput {
+            block_index: kv_u64(value, &["height", "blockIndex"]).unwrap_or(0) as u32,
+        })),
         0x02 => Ok(TxInput::Key(KeyInput {
             amount,
             offsets,
@@ -1124,12 +1492,93 @@ fn parse_kv_transaction_prefix(
 }
 
 impl KvValue {
-    fn as_object(&self) -> Option<&BTreeMap<String, KvValue>> {
+    pub fn as_object(&self) -> Option<&BTreeMap<String, KvValue>> {
         match self {
             KvValue::Object(value) => Some(value),
             _ => None,
         }
     }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            KvValue::Unsigned(value) => Some(*value),
+            KvValue::Signed(value) => u64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            KvValue::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[KvValue]> {
+        match self {
+            KvValue::Array(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// BIN_KV_SERIALIZE_TYPE_* tag. Integers are written at full width;
+    /// the daemon widens every integer type on load anyway.
+    fn kv_type(&self) -> u8 {
+        match self {
+            KvValue::Signed(_) => 1,
+            KvValue::Unsigned(_) => 5,
+            KvValue::Float(_) => 9,
+            KvValue::Bytes(_) => 10,
+            KvValue::Bool(_) => 11,
+            KvValue::Object(_) => 12,
+            // Arrays are typed by their items; an empty one by convention
+            // as a string array.
+            KvValue::Array(items) => 0x80 | items.first().map_or(10, KvValue::kv_type),
+        }
+    }
+}
+
+/// Serialize a KV binary document (the inverse of [`parse_kv_document`]).
+/// Nested arrays are not supported by the format and are not written.
+pub fn write_kv_document(root: &BTreeMap<String, KvValue>) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&KV_SIGNATURE_A.to_le_bytes());
+    out.extend_from_slice(&KV_SIGNATURE_B.to_le_bytes());
+    out.push(1);
+    write_kv_section(root, &mut out);
+    out
+}
+
+fn write_kv_section(object: &BTreeMap<String, KvValue>, out: &mut Vec<u8>) {
+    write_portable_size(object.len(), out);
+    for (name, value) in object {
+        write_kv_name(name.as_bytes(), out);
+        out.push(value.kv_type());
+        match value {
+            KvValue::Array(items) => {
+                write_portable_size(items.len(), out);
+                for item in items {
+                    write_kv_value(item, out);
+                }
+            }
+            _ => write_kv_value(value, out),
+        }
+    }
+}
+
+fn write_kv_value(value: &KvValue, out: &mut Vec<u8>) {
+    match value {
+        KvValue::Signed(v) => out.extend_from_slice(&v.to_le_bytes()),
+        KvValue::Unsigned(v) => out.extend_from_slice(&v.to_le_bytes()),
+        KvValue::Float(v) => out.extend_from_slice(&v.to_le_bytes()),
+        KvValue::Bytes(v) => {
+            write_portable_size(v.len(), out);
+            out.extend_from_slice(v);
+        }
+        KvValue::Bool(v) => out.push(*v as u8),
+        KvValue::Object(v) => write_kv_section(v, out),
+        KvValue::Array(_) => {}
+    }
 }
 
 fn parse_kv_query_blocks_lite_response(
@@ -1147,26 +1596,11 @@ fn parse_kv_query_blocks_lite_response(
         let item = item
             .as_object()
             .ok_or_else(|| SerializationError("query item is not an object".into()))?;
-        let empty_tx_values = Vec::new();
-        let tx_values = match kv_get(item, &["txPrefixes"]) {
-            Some(KvValue::Array(values)) => values,
-            None => &empty_tx_values,
+        let tx_prefixes = match kv_get(item, &["txPrefixes"]) {
+            Some(KvValue::Array(values)) => parse_kv_tx_prefix_infos(values)?,
+            None => Vec::new(),
             _ => return Err(SerializationError("txPrefixes is not an array".into())),
         };
-        let mut tx_prefixes = Vec::with_capacity(tx_values.len());
-        for tx_value in tx_values {
-            let tx_value = tx_value
-                .as_object()
-                .ok_or_else(|| SerializationError("tx prefix info is not an object".into()))?;
-            let parsed = parse_kv_transaction_prefix(kv_object(tx_value, &["txPrefix"])? )?;
-            tx_prefixes.push(TxPrefixInfo {
-                tx_hash: kv_fixed_bytes(tx_value, &["txHash"], 32)?
-                    .try_into()
-                    .map_err(|_| SerializationError("invalid transaction hash".into()))?,
-                tx_prefix: serialize_prefix(&parsed),
-                parsed,
-            });
-        }
         parsed_items.push(BlockShortInfo {
             block_id: kv_fixed_bytes(item, &["blockId"], 32)?
                 .try_into()
@@ -1184,6 +1618,25 @@ fn parse_kv_query_blocks_lite_response(
     })
 }
 
+/// TransactionPrefixInfo entries, shared by the block and pool queries.
+fn parse_kv_tx_prefix_infos(values: &[KvValue]) -> Result<Vec<TxPrefixInfo>, SerializationError> {
+    let mut tx_prefixes = Vec::with_capacity(values.len());
+    for tx_value in values {
+        let tx_value = tx_value
+            .as_object()
+            .ok_or_else(|| SerializationError("tx prefix info is not an object".into()))?;
+        let parsed = parse_kv_transaction_prefix(kv_object(tx_value, &["txPrefix"])? )?;
+        tx_prefixes.push(TxPrefixInfo {
+            tx_hash: kv_fixed_bytes(tx_value, &["txHash"], 32)?
+                .try_into()
+                .map_err(|_| SerializationError("invalid transaction hash".into()))?,
+            tx_prefix: serialize_prefix(&parsed),
+            parsed,
+        });
+    }
+    Ok(tx_prefixes)
+}
+
 // Kept for callers that need to inspect a KV response during diagnostics.
 #[allow(dead_code)]
 fn kv_value_to_json(value: &KvValue) -> Value {
@@ -0,0 +1,399 @@
+//! Encryption at rest for wallet state. Values are sealed with
+//! ChaCha20-Poly1305 under a key derived from the wallet passphrase
+//! (Argon2id) or from a key held elsewhere, such as the keystore's keyring
+//! key. Each value is bound to the storage key it sits under, so rows can't
+//! be swapped on disk. An [`EncryptionHeader`] kept next to the data
+//! records how the key was derived and tells a wrong passphrase apart from
+//! corrupted rows.
+
+use crate::error::{Result, SdkError};
+use crate::traits::{BatchOp, KvPairs, StorageProvider, WriteBatch};
+use crate::types::{Block, Utxo};
+use crate::vault::KdfParams;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit};
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::RwLock;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+const HEADER_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Sealed into the header; opening it proves the key is right.
+const CHECK_PLAINTEXT: &[u8] = b"fuego-wallet-state";
+
+/// Key of the [`EncryptionHeader`] inside an [`EncryptedStore`]'s inner
+/// store.
+pub const HEADER_KEY: &[u8] = b"\0encryption";
+
+/// Secret the state key is derived from.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub enum StateKey {
+    /// Wallet passphrase, stretched with Argon2id.
+    Passphrase(Vec<u8>),
+    /// A random 32-byte key kept outside the database.
+    Key([u8; 32]),
+}
+
+fn locked() -> SdkError {
+    SdkError::Storage("wallet state is locked".into())
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> Zeroizing<[u8; 32]> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+/// Unlocked keys: one seals values, the other blinds lookup keys that
+/// would reveal wallet data (output keys, key images).
+#[derive(Clone)]
+pub struct StateCipher {
+    seal_key: Zeroizing<[u8; 32]>,
+    blind_key: Zeroizing<[u8; 32]>,
+}
+
+impl StateCipher {
+    fn from_master(master: &[u8; 32]) -> Self {
+        Self {
+            seal_key: hmac(master, &[b"fuego-state-seal"]),
+            blind_key: hmac(master, &[b"fuego-state-blind"]),
+        }
+    }
+
+    /// Encrypt `value` stored under `key`, as nonce ‖ ciphertext.
+    pub fn seal(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+        let cipher = ChaCha20Poly1305::new((&*self.seal_key).into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: value, aad: key })
+            .map_err(|e| SdkError::Storage(format!("encrypt: {e}")))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value sealed under `key`.
+    pub fn open(&self, key: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(SdkError::Storage("sealed value too short".into()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new((&*self.seal_key).into());
+        cipher
+            .decrypt(nonce.into(), Payload { msg: ciphertext, aad: key })
+            .map_err(|_| SdkError::Storage("stored value failed authentication".into()))
+    }
+
+    /// Stand-in for a lookup key: stable under one wallet key, meaningless
+    /// without it.
+    pub fn blind(&self, key: &[u8]) -> [u8; 32] {
+        *hmac(&*self.blind_key, &[key])
+    }
+}
+
+/// How an encrypted store's key was derived, plus a check value. Stored in
+/// the clear next to the data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionHeader {
+    version: u8,
+    /// Argon2id (memory KiB, iterations, lanes); `None` for a raw key.
+    kdf: Option<(u32, u32, u32)>,
+    salt: [u8; SALT_LEN],
+    check: Vec<u8>,
+}
+
+impl EncryptionHeader {
+    /// A fresh header for `secret`, with the cipher it unlocks.
+    /// `params` only applies to a passphrase.
+    pub fn create(secret: &StateKey, params: &KdfParams) -> Result<(Self, StateCipher)> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let kdf = match secret {
+            StateKey::Passphrase(_) => {
+                Some((params.memory_kib, params.iterations, params.parallelism))
+            }
+            StateKey::Key(_) => None,
+        };
+        let mut header = Self { version: HEADER_VERSION, kdf, salt, check: Vec::new() };
+        let cipher = header.derive(secret)?;
+        header.check = cipher.seal(&salt, CHECK_PLAINTEXT)?;
+        Ok((header, cipher))
+    }
+
+    /// The cipher for `secret`, if it is the one this header was made with.
+    pub fn unlock(&self, secret: &StateKey) -> Result<StateCipher> {
+        let cipher = self.derive(secret)?;
+        match cipher.open(&self.salt, &self.check) {
+            Ok(check) if check == CHECK_PLAINTEXT => Ok(cipher),
+            _ => Err(SdkError::Storage("wrong wallet state passphrase or key".into())),
+        }
+    }
+
+    fn derive(&self, secret: &StateKey) -> Result<StateCipher> {
+        let master = match (secret, self.kdf) {
+            (StateKey::Passphrase(passphrase), Some((memory_kib, iterations, parallelism))) => {
+                let params = KdfParams { memory_kib, iterations, parallelism };
+                Zeroizing::new(
+                    params
+                        .derive_key(passphrase, &self.salt)
+                        .map_err(|e| SdkError::Storage(e.to_string()))?,
+                )
+            }
+            (StateKey::Key(key), None) => hmac(key, &[b"fuego-state-key", &self.salt]),
+            (StateKey::Passphrase(_), None) => {
+                return Err(SdkError::Storage("wallet state is encrypted with a key, not a passphrase".into()))
+            }
+            (StateKey::Key(_), Some(_)) => {
+                return Err(SdkError::Storage("wallet state is encrypted with a passphrase, not a key".into()))
+            }
+        };
+        Ok(StateCipher::from_master(&master))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("header serializes")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header: Self = bincode::deserialize(bytes)
+            .map_err(|e| SdkError::Serialization(format!("encryption header: {e}")))?;
+        if header.version != HEADER_VERSION {
+            return Err(SdkError::Storage(format!(
+                "unsupported encryption header v{}",
+                header.version
+            )));
+        }
+        Ok(header)
+    }
+}
+
+/// [`StorageProvider`] that seals every value before it reaches `S`. UTXO
+/// keys, which name the wallet's transactions, are blinded; block heights
+/// and caller keys pass through so ranges over them keep working. While
+/// locked, every read, write, delete and lookup fails.
+pub struct EncryptedStore<S> {
+    inner: S,
+    header: EncryptionHeader,
+    cipher: RwLock<Option<StateCipher>>,
+}
+
+impl<S: StorageProvider> EncryptedStore<S> {
+    /// Open `inner` under `secret`, starting encryption if it has no header
+    /// yet. Rows already in a store without a header are sealed in place,
+    /// in one batch.
+    pub fn open(inner: S, secret: &StateKey) -> Result<Self> {
+        Self::open_with_params(inner, secret, &KdfParams::default())
+    }
+
+    /// `open` with explicit Argon2id costs for a new header.
+    pub fn open_with_params(inner: S, secret: &StateKey, params: &KdfParams) -> Result<Self> {
+        let (header, cipher) = match inner.get(HEADER_KEY)? {
+            Some(bytes) => {
+                let header = EncryptionHeader::from_bytes(&bytes)?;
+                let cipher = header.unlock(secret)?;
+                (header, cipher)
+            }
+            None => {
+                let (header, cipher) = EncryptionHeader::create(secret, params)?;
+                let mut batch = reseal(&inner, &cipher)?;
+                batch.put(HEADER_KEY, &header.to_bytes());
+                inner.apply_batch(batch)?;
+                (header, cipher)
+            }
+        };
+        Ok(Self { inner, header, cipher: RwLock::new(Some(cipher)) })
+    }
+
+    /// Open an encrypted store without its key; it stays locked until
+    /// `unlock`.
+    pub fn open_locked(inner: S) -> Result<Self> {
+        let bytes = inner
+            .get(HEADER_KEY)?
+            .ok_or_else(|| SdkError::Storage("store is not encrypted".into()))?;
+        let header = EncryptionHeader::from_bytes(&bytes)?;
+        Ok(Self { inner, header, cipher: RwLock::new(None) })
+    }
+
+    /// Drop the key from memory.
+    pub fn lock(&self) {
+        *self.cipher.write().unwrap() = None;
+    }
+
+    pub fn unlock(&self, secret: &StateKey) -> Result<()> {
+        let cipher = self.header.unlock(secret)?;
+        *self.cipher.write().unwrap() = Some(cipher);
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.cipher.read().unwrap().is_none()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn cipher(&self) -> Result<StateCipher> {
+        self.cipher.read().unwrap().clone().ok_or_else(locked)
+    }
+
+    /// Stored key of the UTXO `index` of `tx_hash`.
+    fn utxo_key(&self, tx_hash: &[u8; 32], index: u32) -> Result<Vec<u8>> {
+        Ok(self.cipher()?.blind(&utxo_key(tx_hash, index)).to_vec())
+    }
+
+    fn get_sealed(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cipher = self.cipher()?;
+        match self.inner.get(key)? {
+            Some(sealed) => cipher.open(key, &sealed).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn put_sealed(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let cipher = self.cipher()?;
+        self.inner.put(key, &cipher.seal(key, value)?)
+    }
+
+    fn get_decoded<T: serde::de::DeserializeOwned>(&self, key: &[u8]) -> Result<Option<T>> {
+        self.get_sealed(key)?
+            .map(|bytes| {
+                bincode::deserialize(&bytes).map_err(|e| SdkError::Serialization(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn put_encoded<T: Serialize>(&self, key: &[u8], value: &T) -> Result<()> {
+        let data = bincode::serialize(value).map_err(|e| SdkError::Serialization(e.to_string()))?;
+        self.put_sealed(key, &data)
+    }
+}
+
+fn utxo_key(tx_hash: &[u8; 32], index: u32) -> Vec<u8> {
+    let mut key = tx_hash.to_vec();
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+/// Batch sealing every plaintext row of `inner` under `cipher`. A row is
+/// taken for a UTXO when it decodes as one stored under its own key, and
+/// moves to the blinded key.
+fn reseal<S: StorageProvider>(inner: &S, cipher: &StateCipher) -> Result<WriteBatch> {
+    let mut batch = WriteBatch::new();
+    for (key, value) in inner.range(&[], None)? {
+        let utxo = bincode::deserialize::<Utxo>(&value)
+            .ok()
+            .filter(|u| utxo_key(&u.tx_hash, u.output_index) == key);
+        match utxo {
+            Some(_) => {
+                let blinded = cipher.blind(&key);
+                batch.delete(&key).put(&blinded, &cipher.seal(&blinded, &value)?);
+            }
+            None => {
+                batch.put(&key, &cipher.seal(&key, &value)?);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+impl<S: StorageProvider> StorageProvider for EncryptedStore<S> {
+    fn get_block(&self, height: u64) -> Result<Option<Block>> {
+        self.get_decoded(&height.to_be_bytes())
+    }
+
+    fn save_block(&self, block: &Block) -> Result<()> {
+        self.put_encoded(&block.header.height.to_be_bytes(), block)
+    }
+
+    fn delete_block(&self, height: u64) -> Result<()> {
+        self.delete(&height.to_be_bytes())
+    }
+
+    fn get_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<Option<Utxo>> {
+        self.get_decoded(&self.utxo_key(tx_hash, index)?)
+    }
+
+    fn save_utxo(&self, utxo: &Utxo) -> Result<()> {
+        self.put_encoded(&self.utxo_key(&utxo.tx_hash, utxo.output_index)?, utxo)
+    }
+
+    fn delete_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<()> {
+        self.delete(&self.utxo_key(tx_hash, index)?)
+    }
+
+    fn get_height(&self) -> Result<u64> {
+        match self.get_sealed(b"height")? {
+            Some(data) => {
+                let bytes: [u8; 8] = data
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| SdkError::Storage("Invalid height".into()))?;
+                Ok(u64::from_be_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn save_height(&self, height: u64) -> Result<()> {
+        self.put_sealed(b"height", &height.to_be_bytes())
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_sealed(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.put_sealed(key, value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        if self.is_locked() {
+            return Err(locked());
+        }
+        self.inner.delete(key)
+    }
+
+    fn exists(&self, key: &[u8]) -> Result<bool> {
+        if self.is_locked() {
+            return Err(locked());
+        }
+        self.inner.exists(key)
+    }
+
+    fn range(&self, start: &[u8], end: Option<&[u8]>) -> Result<KvPairs> {
+        let cipher = self.cipher()?;
+        self.inner
+            .range(start, end)?
+            .into_iter()
+            .filter(|(key, _)| key != HEADER_KEY)
+            .map(|(key, sealed)| {
+                let value = cipher.open(&key, &sealed)?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    fn apply_batch(&self, batch: WriteBatch) -> Result<()> {
+        let cipher = self.cipher()?;
+        let mut sealed = WriteBatch::new();
+        for op in batch {
+            match op {
+                BatchOp::Put(key, value) => sealed.put(&key, &cipher.seal(&key, &value)?),
+                BatchOp::Delete(key) => sealed.delete(&key),
+            };
+        }
+        self.inner.apply_batch(sealed)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.inner.flush()
+    }
+}
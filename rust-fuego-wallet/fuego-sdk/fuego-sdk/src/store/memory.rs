@@ -1,13 +1,14 @@
 use crate::error::Result;
-use crate::traits::StorageProvider;
+use crate::traits::{BatchOp, KvPairs, StorageProvider, WriteBatch};
 use crate::types::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
 use std::sync::RwLock;
 
 pub struct MemoryStore {
     blocks: RwLock<HashMap<u64, Block>>,
     utxos: RwLock<HashMap<(Vec<u8>, u32), Utxo>>,
-    kv: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    kv: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
     height: RwLock<u64>,
 }
 
@@ -22,7 +23,7 @@ impl MemoryStore {
         Self {
             blocks: RwLock::new(HashMap::new()),
             utxos: RwLock::new(HashMap::new()),
-            kv: RwLock::new(HashMap::new()),
+            kv: RwLock::new(BTreeMap::new()),
             height: RwLock::new(0),
         }
     }
@@ -41,6 +42,11 @@ impl StorageProvider for MemoryStore {
         Ok(())
     }
 
+    fn delete_block(&self, height: u64) -> Result<()> {
+        self.blocks.write().unwrap().remove(&height);
+        Ok(())
+    }
+
     fn get_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<Option<Utxo>> {
         Ok(self
             .utxos
@@ -95,4 +101,37 @@ impl StorageProvider for MemoryStore {
     fn exists(&self, key: &[u8]) -> Result<bool> {
         Ok(self.kv.read().unwrap().contains_key(key))
     }
+
+    fn range(&self, start: &[u8], end: Option<&[u8]>) -> Result<KvPairs> {
+        if end.is_some_and(|end| end <= start) {
+            return Ok(Vec::new());
+        }
+        let end = end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec()));
+        Ok(self
+            .kv
+            .read()
+            .unwrap()
+            .range((Bound::Included(start.to_vec()), end))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn apply_batch(&self, batch: WriteBatch) -> Result<()> {
+        let mut kv = self.kv.write().unwrap();
+        for op in batch {
+            match op {
+                BatchOp::Put(key, value) => {
+                    kv.insert(key, value);
+                }
+                BatchOp::Delete(key) => {
+                    kv.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 }
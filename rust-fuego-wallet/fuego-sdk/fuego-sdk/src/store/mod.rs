@@ -1,6 +1,11 @@
+pub mod encrypted;
 pub mod memory;
 
 #[cfg(feature = "storage")]
 pub mod sled;
 
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use encrypted::{EncryptedStore, StateKey};
 pub use memory::MemoryStore;
@@ -1,6 +1,8 @@
+use super::encrypted::{EncryptedStore, StateKey};
 use crate::error::{Result, SdkError};
-use crate::traits::StorageProvider;
+use crate::traits::{BatchOp, KvPairs, StorageProvider, WriteBatch};
 use crate::types::*;
+use crate::vault::KdfParams;
 
 pub struct SledStore {
     db: sled::Db,
@@ -12,6 +14,26 @@ impl SledStore {
             .map_err(|e| SdkError::Storage(format!("Failed to open sled: {}", e)))?;
         Ok(Self { db })
     }
+
+    /// Open the store at `path` encrypted under `secret`. A store written
+    /// without encryption is sealed in place first, in one batch.
+    pub fn open_encrypted(
+        path: impl AsRef<std::path::Path>,
+        secret: &StateKey,
+    ) -> Result<EncryptedStore<Self>> {
+        Self::open_encrypted_with_params(path, secret, &KdfParams::default())
+    }
+
+    /// `open_encrypted` with explicit Argon2id costs for a new header.
+    pub fn open_encrypted_with_params(
+        path: impl AsRef<std::path::Path>,
+        secret: &StateKey,
+        params: &KdfParams,
+    ) -> Result<EncryptedStore<Self>> {
+        let store = EncryptedStore::open_with_params(Self::open(path)?, secret, params)?;
+        store.flush()?;
+        Ok(store)
+    }
 }
 
 impl StorageProvider for SledStore {
@@ -40,6 +62,13 @@ impl StorageProvider for SledStore {
         Ok(())
     }
 
+    fn delete_block(&self, height: u64) -> Result<()> {
+        self.db
+            .remove(height.to_be_bytes())
+            .map_err(|e| SdkError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
     fn get_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<Option<Utxo>> {
         let mut key = tx_hash.to_vec();
         key.extend_from_slice(&index.to_be_bytes());
@@ -95,7 +124,7 @@ impl StorageProvider for SledStore {
 
     fn save_height(&self, height: u64) -> Result<()> {
         self.db
-            .insert(b"height", height.to_be_bytes())
+            .insert(b"height", &height.to_be_bytes())
             .map_err(|e| SdkError::Storage(e.to_string()))?;
         Ok(())
     }
@@ -126,4 +155,47 @@ impl StorageProvider for SledStore {
             .contains_key(key)
             .map_err(|e| SdkError::Storage(e.to_string()))
     }
+
+    fn range(&self, start: &[u8], end: Option<&[u8]>) -> Result<KvPairs> {
+        let rows = match end {
+            Some(end) if end <= start => return Ok(Vec::new()),
+            Some(end) => self.db.range(start..end),
+            None => self.db.range(start..),
+        };
+        rows.map(|row| {
+            row.map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .map_err(|e| SdkError::Storage(e.to_string()))
+        })
+        .collect()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvPairs> {
+        self.db
+            .scan_prefix(prefix)
+            .map(|row| {
+                row.map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(|e| SdkError::Storage(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn apply_batch(&self, batch: WriteBatch) -> Result<()> {
+        let mut sled_batch = sled::Batch::default();
+        for op in batch {
+            match op {
+                BatchOp::Put(key, value) => sled_batch.insert(key, value),
+                BatchOp::Delete(key) => sled_batch.remove(key),
+            }
+        }
+        self.db
+            .apply_batch(sled_batch)
+            .map_err(|e| SdkError::Storage(e.to_string()))
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.db
+            .flush()
+            .map(|_| ())
+            .map_err(|e| SdkError::Storage(e.to_string()))
+    }
 }
@@ -0,0 +1,535 @@
+//! SQLite-backed store. Besides the [`StorageProvider`] key/value surface
+//! it keeps the wallet's records in real tables, so history can be
+//! filtered and paged by the database instead of scanned in memory:
+//!
+//! | table          | rows                                            |
+//! |----------------|-------------------------------------------------|
+//! | `blocks`       | block header columns + bincode `Block`          |
+//! | `outputs`      | `Utxo`, keyed by (tx hash, output index)        |
+//! | `key_images`   | spent key images with the height they were seen |
+//! | `history`      | `HistoryEntry` columns, in scan order           |
+//! | `notes`        | free-text note per transaction                  |
+//! | `address_book` | saved payees                                    |
+//! | `meta` / `kv`  | height; raw key/value pairs                     |
+
+use crate::error::{Result, SdkError};
+use crate::history::{HistoryPage, HistoryQuery};
+use crate::scanner::{HistoryDirection, HistoryEntry};
+use crate::serialization::PaymentId;
+use crate::traits::{BatchOp, KvPairs, StorageProvider, WriteBatch};
+use crate::types::*;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS kv (key BLOB PRIMARY KEY, value BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS blocks (
+        height INTEGER PRIMARY KEY,
+        hash BLOB NOT NULL,
+        prev_hash BLOB NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS outputs (
+        tx_hash BLOB NOT NULL,
+        output_index INTEGER NOT NULL,
+        amount INTEGER NOT NULL,
+        pubkey BLOB NOT NULL,
+        height INTEGER NOT NULL,
+        PRIMARY KEY (tx_hash, output_index)
+    );
+    CREATE INDEX IF NOT EXISTS outputs_height ON outputs (height);
+    CREATE TABLE IF NOT EXISTS key_images (
+        key_image BLOB PRIMARY KEY,
+        height INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        tx_hash BLOB NOT NULL,
+        height INTEGER NOT NULL,
+        direction INTEGER NOT NULL,
+        amount INTEGER NOT NULL,
+        fee INTEGER NOT NULL,
+        subaddress INTEGER NOT NULL,
+        payment_id BLOB
+    );
+    CREATE INDEX IF NOT EXISTS history_height ON history (height);
+    CREATE INDEX IF NOT EXISTS history_payment_id ON history (payment_id);
+    CREATE TABLE IF NOT EXISTS notes (tx_hash BLOB PRIMARY KEY, note TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS address_book (
+        address TEXT PRIMARY KEY,
+        label TEXT NOT NULL,
+        payment_id BLOB
+    );
+";
+
+/// A saved payee. `payment_id` is sent along when paying a plain address
+/// that expects one (exchanges).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressBookEntry {
+    pub address: String,
+    pub label: String,
+    pub payment_id: Option<PaymentId>,
+}
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+fn db_err(e: rusqlite::Error) -> SdkError {
+    SdkError::Storage(e.to_string())
+}
+
+/// SQLite integers are signed; amounts and heights never get near 2^63.
+fn int(value: u64) -> Result<i64> {
+    i64::try_from(value).map_err(|_| SdkError::Storage(format!("{value} does not fit in SQLite")))
+}
+
+fn hash(bytes: Vec<u8>) -> rusqlite::Result<[u8; 32]> {
+    bytes.try_into().map_err(|b: Vec<u8>| {
+        rusqlite::Error::FromSqlConversionFailure(
+            b.len(),
+            rusqlite::types::Type::Blob,
+            "expected 32 bytes".into(),
+        )
+    })
+}
+
+fn direction_code(direction: HistoryDirection) -> i64 {
+    match direction {
+        HistoryDirection::Incoming => 0,
+        HistoryDirection::Outgoing => 1,
+    }
+}
+
+fn history_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        tx_hash: hash(row.get(0)?)?,
+        block_height: row.get::<_, i64>(1)? as u64,
+        direction: match row.get::<_, i64>(2)? {
+            0 => HistoryDirection::Incoming,
+            _ => HistoryDirection::Outgoing,
+        },
+        amount: row.get::<_, i64>(3)? as u64,
+        fee: row.get::<_, i64>(4)? as u64,
+        subaddress: row.get::<_, i64>(5)? as u32,
+        payment_id: row
+            .get::<_, Option<Vec<u8>>>(6)?
+            .and_then(|id| PaymentId::from_bytes(&id)),
+    })
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| SdkError::Storage(format!("Failed to open sqlite: {}", e)))?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(db_err)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA).map_err(db_err)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Append history rows after the existing ones, in one transaction.
+    pub fn append_history(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        {
+            let mut insert = tx
+                .prepare_cached(
+                    "INSERT INTO history (tx_hash, height, direction, amount, fee, subaddress, payment_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(db_err)?;
+            for entry in entries {
+                insert
+                    .execute(params![
+                        &entry.tx_hash[..],
+                        int(entry.block_height)?,
+                        direction_code(entry.direction),
+                        int(entry.amount)?,
+                        int(entry.fee)?,
+                        entry.subaddress,
+                        entry.payment_id.as_ref().map(|id| id.as_bytes()),
+                    ])
+                    .map_err(db_err)?;
+            }
+        }
+        tx.commit().map_err(db_err)
+    }
+
+    /// One page of the history rows matching `query`, in scan order.
+    pub fn query_history(&self, query: &HistoryQuery) -> Result<HistoryPage> {
+        // Bounds beyond the integer range match nothing or everything.
+        let clamp = |v: u64| v.min(i64::MAX as u64) as i64;
+        let mut clauses = vec!["height >= ?".to_string()];
+        let mut args = vec![Value::Integer(clamp(query.first_height))];
+        if let Some(end) = query.end_height() {
+            clauses.push("height < ?".into());
+            args.push(Value::Integer(clamp(end)));
+        }
+        if let Some(direction) = query.direction {
+            clauses.push("direction = ?".into());
+            args.push(Value::Integer(direction_code(direction)));
+        }
+        if let Some(min) = query.min_amount {
+            clauses.push("amount >= ?".into());
+            args.push(Value::Integer(clamp(min)));
+        }
+        if let Some(max) = query.max_amount {
+            clauses.push("amount <= ?".into());
+            args.push(Value::Integer(clamp(max)));
+        }
+        if let Some(id) = &query.payment_id {
+            clauses.push("payment_id = ?".into());
+            args.push(Value::Blob(id.as_bytes().to_vec()));
+        }
+        if let Some(subaddress) = query.subaddress {
+            clauses.push("subaddress = ?".into());
+            args.push(Value::Integer(subaddress.into()));
+        }
+        let filter = clauses.join(" AND ");
+
+        let conn = self.conn.lock().unwrap();
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM history WHERE {filter}"),
+                params_from_iter(args.iter()),
+                |row| row.get(0),
+            )
+            .map_err(db_err)?;
+        // LIMIT -1 is SQLite's "no limit".
+        args.push(Value::Integer(query.limit.map_or(-1, |l| clamp(l as u64))));
+        args.push(Value::Integer(clamp(query.offset as u64)));
+        let order = if query.newest_first { "height DESC, id DESC" } else { "height, id" };
+        let mut select = conn
+            .prepare(&format!(
+                "SELECT tx_hash, height, direction, amount, fee, subaddress, payment_id
+                 FROM history WHERE {filter} ORDER BY {order} LIMIT ? OFFSET ?"
+            ))
+            .map_err(db_err)?;
+        let entries = select
+            .query_map(params_from_iter(args.iter()), history_row)
+            .map_err(db_err)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(db_err)?;
+        Ok(HistoryPage { entries, total: total as usize })
+    }
+
+    /// Record a spent key image first seen at `height`.
+    pub fn add_key_image(&self, key_image: &[u8; 32], height: u64) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO key_images (key_image, height) VALUES (?1, ?2)",
+                params![&key_image[..], int(height)?],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    pub fn is_key_image_spent(&self, key_image: &[u8; 32]) -> Result<bool> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM key_images WHERE key_image = ?1",
+                params![&key_image[..]],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(db_err)
+    }
+
+    /// Drop everything learned from blocks at `height` and above (a reorg).
+    /// Notes and the address book are the user's and stay.
+    pub fn detach(&self, height: u64) -> Result<()> {
+        let height = int(height)?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        for table in ["blocks", "outputs", "key_images", "history"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE height >= ?1"), params![height])
+                .map_err(db_err)?;
+        }
+        tx.execute(
+            "UPDATE meta SET value = ?1 WHERE key = 'height' AND value > ?1",
+            params![(height - 1).max(0)],
+        )
+        .map_err(db_err)?;
+        tx.commit().map_err(db_err)
+    }
+
+    /// Attach a note to a transaction; an empty note removes it.
+    pub fn set_note(&self, tx_hash: &[u8; 32], note: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        if note.is_empty() {
+            conn.execute("DELETE FROM notes WHERE tx_hash = ?1", params![&tx_hash[..]])
+        } else {
+            conn.execute(
+                "INSERT OR REPLACE INTO notes (tx_hash, note) VALUES (?1, ?2)",
+                params![&tx_hash[..], note],
+            )
+        }
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    pub fn note(&self, tx_hash: &[u8; 32]) -> Result<Option<String>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT note FROM notes WHERE tx_hash = ?1",
+                params![&tx_hash[..]],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_err)
+    }
+
+    /// Add a payee, or replace the entry with the same address.
+    pub fn save_contact(&self, entry: &AddressBookEntry) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO address_book (address, label, payment_id) VALUES (?1, ?2, ?3)",
+                params![
+                    entry.address,
+                    entry.label,
+                    entry.payment_id.as_ref().map(|id| id.as_bytes()),
+                ],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    pub fn remove_contact(&self, address: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM address_book WHERE address = ?1", params![address])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    /// The address book, sorted by label.
+    pub fn contacts(&self) -> Result<Vec<AddressBookEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn
+            .prepare("SELECT address, label, payment_id FROM address_book ORDER BY label, address")
+            .map_err(db_err)?;
+        let rows = select
+            .query_map([], |row| {
+                Ok(AddressBookEntry {
+                    address: row.get(0)?,
+                    label: row.get(1)?,
+                    payment_id: row
+                        .get::<_, Option<Vec<u8>>>(2)?
+                        .and_then(|id| PaymentId::from_bytes(&id)),
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_err)
+    }
+}
+
+impl StorageProvider for SqliteStore {
+    fn get_block(&self, height: u64) -> Result<Option<Block>> {
+        let data: Option<Vec<u8>> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM blocks WHERE height = ?1",
+                params![int(height)?],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_err)?;
+        data.map(|d| bincode::deserialize(&d).map_err(|e| SdkError::Serialization(e.to_string())))
+            .transpose()
+    }
+
+    fn save_block(&self, block: &Block) -> Result<()> {
+        let data = bincode::serialize(block).map_err(|e| SdkError::Serialization(e.to_string()))?;
+        let header = &block.header;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO blocks (height, hash, prev_hash, timestamp, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    int(header.height)?,
+                    &header.hash[..],
+                    &header.prev_hash[..],
+                    int(header.timestamp)?,
+                    data,
+                ],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn delete_block(&self, height: u64) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM blocks WHERE height = ?1", params![int(height)?])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn get_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<Option<Utxo>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT amount, pubkey, height FROM outputs WHERE tx_hash = ?1 AND output_index = ?2",
+                params![&tx_hash[..], index],
+                |row| {
+                    Ok(Utxo {
+                        tx_hash: *tx_hash,
+                        output_index: index,
+                        amount: row.get::<_, i64>(0)? as u64,
+                        pubkey: hash(row.get(1)?)?,
+                        height: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_err)
+    }
+
+    fn save_utxo(&self, utxo: &Utxo) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO outputs (tx_hash, output_index, amount, pubkey, height)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    &utxo.tx_hash[..],
+                    utxo.output_index,
+                    int(utxo.amount)?,
+                    &utxo.pubkey[..],
+                    int(utxo.height)?,
+                ],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn delete_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM outputs WHERE tx_hash = ?1 AND output_index = ?2",
+                params![&tx_hash[..], index],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn get_height(&self) -> Result<u64> {
+        let height: Option<i64> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT value FROM meta WHERE key = 'height'", [], |row| row.get(0))
+            .optional()
+            .map_err(db_err)?;
+        Ok(height.unwrap_or(0) as u64)
+    }
+
+    fn save_height(&self, height: u64) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('height', ?1)",
+                params![int(height)?],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT value FROM kv WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(db_err)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO kv (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM kv WHERE key = ?1", params![key])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn exists(&self, key: &[u8]) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    fn range(&self, start: &[u8], end: Option<&[u8]>) -> Result<KvPairs> {
+        let conn = self.conn.lock().unwrap();
+        // BLOBs compare bytewise, like the other backends' keys.
+        let mut stmt = conn
+            .prepare("SELECT key, value FROM kv WHERE key >= ?1 AND (?2 IS NULL OR key < ?2) ORDER BY key")
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![start, end], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(db_err)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(db_err)
+    }
+
+    fn apply_batch(&self, batch: WriteBatch) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        for op in batch {
+            match op {
+                BatchOp::Put(key, value) => tx.execute(
+                    "INSERT OR REPLACE INTO kv (key, value) VALUES (?1, ?2)",
+                    params![key, value],
+                ),
+                BatchOp::Delete(key) => tx.execute("DELETE FROM kv WHERE key = ?1", params![key]),
+            }
+            .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)
+    }
+
+    /// Every statement commits durably; there is nothing buffered.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
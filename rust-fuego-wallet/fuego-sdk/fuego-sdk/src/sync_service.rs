@@ -0,0 +1,188 @@
+//! Managed background sync: a task that polls a [`SyncTarget`] on an
+//! interval, with pause/resume, sync-now and cancellation handles, and a
+//! [`SyncStatus`] kept up to date for status endpoints.
+
+use crate::error::Result;
+use crate::types::SyncStatus;
+use async_trait::async_trait;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Outcome of one sync round.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncRound {
+    /// Blocks processed in this round.
+    pub synced: u64,
+    /// Local height after the round.
+    pub height: u64,
+    /// Network height the round synced towards.
+    pub target: u64,
+}
+
+/// Anything that syncs in bounded rounds: the embedded node, a wallet
+/// scanner.
+#[async_trait]
+pub trait SyncTarget: Send + Sync {
+    async fn sync_round(&self) -> Result<SyncRound>;
+}
+
+struct Shared {
+    status: RwLock<SyncStatus>,
+    last_error: RwLock<Option<String>>,
+    wake: Notify,
+    paused: watch::Sender<bool>,
+    cancel: CancellationToken,
+}
+
+/// Cloneable control handle of a running [`SyncService`].
+#[derive(Clone)]
+pub struct SyncHandle {
+    shared: Arc<Shared>,
+}
+
+impl SyncHandle {
+    /// Finish the current round, then wait until `resume`.
+    pub fn pause(&self) {
+        self.shared.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.shared.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.shared.paused.borrow()
+    }
+
+    /// Skip the rest of the current wait and sync now.
+    pub fn sync_now(&self) {
+        self.shared.wake.notify_one();
+    }
+
+    /// Stop after the current round. The task can't be restarted.
+    pub fn stop(&self) {
+        self.shared.cancel.cancel();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.shared.cancel.is_cancelled()
+    }
+
+    /// Token cancelled when the service stops; child tasks can select on it.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shared.cancel.clone()
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.shared.status.read().unwrap().clone()
+    }
+
+    /// Error of the last round, cleared by the next successful one.
+    pub fn last_error(&self) -> Option<String> {
+        self.shared.last_error.read().unwrap().clone()
+    }
+}
+
+/// Background task driving a [`SyncTarget`]. Rounds that make progress
+/// short of the target run back to back; otherwise the task waits
+/// `interval` (or for `sync_now`) before polling again. Dropping the
+/// service stops it.
+pub struct SyncService {
+    handle: SyncHandle,
+    task: Option<JoinHandle<()>>,
+}
+
+impl SyncService {
+    /// Spawn the task on the current tokio runtime.
+    pub fn start(target: Arc<dyn SyncTarget>, interval: Duration) -> Self {
+        let (paused, _) = watch::channel(false);
+        let shared = Arc::new(Shared {
+            status: RwLock::new(SyncStatus {
+                current_height: 0,
+                target_height: 0,
+                is_syncing: false,
+                last_sync_time: None,
+            }),
+            last_error: RwLock::new(None),
+            wake: Notify::new(),
+            paused,
+            cancel: CancellationToken::new(),
+        });
+        let task = tokio::spawn(run(target, interval, shared.clone()));
+        Self { handle: SyncHandle { shared }, task: Some(task) }
+    }
+
+    pub fn handle(&self) -> SyncHandle {
+        self.handle.clone()
+    }
+
+    /// Stop and wait for the current round to finish.
+    pub async fn shutdown(mut self) {
+        self.handle.stop();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for SyncService {
+    fn drop(&mut self) {
+        self.handle.stop();
+    }
+}
+
+async fn run(target: Arc<dyn SyncTarget>, interval: Duration, shared: Arc<Shared>) {
+    let mut paused = shared.paused.subscribe();
+    loop {
+        if *paused.borrow_and_update() {
+            // Wake-ups while paused are dropped, not saved for later.
+            tokio::select! {
+                _ = shared.cancel.cancelled() => return,
+                _ = paused.changed() => continue,
+                _ = shared.wake.notified() => continue,
+            }
+        }
+        if shared.cancel.is_cancelled() {
+            return;
+        }
+
+        shared.status.write().unwrap().is_syncing = true;
+        let result = target.sync_round().await;
+        let caught_up = {
+            let mut status = shared.status.write().unwrap();
+            status.is_syncing = false;
+            match result {
+                Ok(round) => {
+                    status.current_height = round.height;
+                    status.target_height = round.target;
+                    *shared.last_error.write().unwrap() = None;
+                    if round.height >= round.target {
+                        status.last_sync_time = Some(
+                            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+                        );
+                    }
+                    // A round that made no progress waits out the interval
+                    // even when still behind.
+                    round.synced == 0 || round.height >= round.target
+                }
+                Err(e) => {
+                    *shared.last_error.write().unwrap() = Some(e.to_string());
+                    true
+                }
+            }
+        };
+        if !caught_up {
+            continue;
+        }
+
+        tokio::select! {
+            _ = shared.cancel.cancelled() => return,
+            _ = tokio::time::sleep(interval) => {}
+            _ = shared.wake.notified() => {}
+            _ = paused.changed() => {}
+        }
+    }
+}
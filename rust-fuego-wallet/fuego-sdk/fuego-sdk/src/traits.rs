@@ -1,4 +1,5 @@
 use crate::error::Result;
+use crate::transaction_builder::BuiltTransaction;
 use crate::types::*;
 use async_trait::async_trait;
 
@@ -17,6 +18,14 @@ pub trait NetworkProvider: Send + Sync {
     /// Broadcast transaction
     async fn send_transaction(&self, tx: &Transaction) -> Result<[u8; 32]>;
 
+    /// Broadcast the wire bytes produced by `transaction_builder`; returns
+    /// the transaction hash. Defaults to decoding them and calling
+    /// `send_transaction`.
+    async fn broadcast(&self, tx: &BuiltTransaction) -> Result<[u8; 32]> {
+        let decoded = crate::network::decode_transaction(tx.tx_hash, &tx.serialized)?;
+        self.send_transaction(&decoded).await
+    }
+
     /// Get peers list
     async fn get_peers(&self) -> Result<Vec<PeerInfo>>;
 
@@ -34,10 +43,78 @@ pub struct PeerInfo {
     pub last_seen: u64,
 }
 
+/// Key/value pairs in ascending key order.
+pub type KvPairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// One write in a [`WriteBatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// Key/value writes that `StorageProvider::apply_batch` applies in order,
+/// all or none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> &mut Self {
+        self.ops.push(BatchOp::Put(key.to_vec(), value.to_vec()));
+        self
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> &mut Self {
+        self.ops.push(BatchOp::Delete(key.to_vec()));
+        self
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl IntoIterator for WriteBatch {
+    type Item = BatchOp;
+    type IntoIter = std::vec::IntoIter<BatchOp>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ops.into_iter()
+    }
+}
+
+/// Smallest key above every key starting with `prefix`; `None` when the
+/// prefix is empty or all `0xff`.
+pub fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xff)?;
+    let mut end = prefix[..=last].to_vec();
+    end[last] += 1;
+    Some(end)
+}
+
 /// Storage provider trait — implemented by Memory, Sled, RocksDB, etc.
+///
+/// `scan_prefix`, `range` and `apply_batch` work on the key/value space of
+/// `get`/`put`. Backends that keep blocks, UTXOs and the height in that
+/// same space (sled) return those records from scans too.
 pub trait StorageProvider: Send + Sync {
     fn get_block(&self, height: u64) -> Result<Option<Block>>;
     fn save_block(&self, block: &Block) -> Result<()>;
+    fn delete_block(&self, height: u64) -> Result<()>;
     fn get_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<Option<Utxo>>;
     fn save_utxo(&self, utxo: &Utxo) -> Result<()>;
     fn delete_utxo(&self, tx_hash: &[u8; 32], index: u32) -> Result<()>;
@@ -47,11 +124,33 @@ pub trait StorageProvider: Send + Sync {
     fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
     fn delete(&self, key: &[u8]) -> Result<()>;
     fn exists(&self, key: &[u8]) -> Result<bool>;
+
+    /// Entries with `start <= key < end`; `end = None` runs to the last
+    /// key. Empty when `end <= start`.
+    fn range(&self, start: &[u8], end: Option<&[u8]>) -> Result<KvPairs>;
+
+    /// Entries whose key starts with `prefix`.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvPairs> {
+        self.range(prefix, prefix_end(prefix).as_deref())
+    }
+
+    /// Apply every write in `batch` atomically: readers and a crash see
+    /// either none of it or all of it.
+    fn apply_batch(&self, batch: WriteBatch) -> Result<()>;
+
+    /// Return once every completed write is durable.
+    fn flush(&self) -> Result<()>;
 }
 
 /// Block observer trait — for scanning blocks
 pub trait BlockObserver: Send + Sync {
     fn on_block(&self, block: &Block) -> Result<()>;
+
+    /// Blocks above `height` were orphaned by a reorg; forget what was
+    /// learned from them.
+    fn on_detach(&self, _height: u64) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Event listener for SDK events
@@ -62,4 +161,5 @@ pub trait EventListener: Send + Sync {
     fn on_transaction(&self, _tx: &Transaction) {}
     fn on_block(&self, _block: &BlockHeader) {}
     fn on_error(&self, _error: &crate::error::SdkError) {}
+    fn on_wallet_event(&self, _event: &crate::events::WalletEvent) {}
 }
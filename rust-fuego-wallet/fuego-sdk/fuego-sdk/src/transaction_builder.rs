@@ -12,9 +12,10 @@
 use crate::error::{Result, SdkError};
 use crate::serialization::{
     add_amm_swap_auth_extra, add_heat_mint_auth_extra, add_limit_deposit_extra,
-    add_lp_add_auth_extra, add_lp_remove_auth_extra, build_extra_with_pubkey, serialize_inputs,
-    serialize_tx, tx_prefix_hash, CommitmentOutputTarget, CommitmentSpendInput, KeyInput,
-    OutputTarget, Transaction, TransactionPrefix, TxInput, TxOutput, HEAT_TERM, AMOUNT_PROOF_LEN,
+    add_lp_add_auth_extra, add_lp_remove_auth_extra, add_payment_id_extra,
+    build_extra_with_pubkey, serialize_inputs, serialize_tx, tx_prefix_hash,
+    CommitmentOutputTarget, CommitmentSpendInput, KeyInput, OutputTarget, PaymentId,
+    Transaction, TransactionPrefix, TxInput, TxOutput, HEAT_TERM, AMOUNT_PROOF_LEN,
 };
 use fuego_crypto::ring::{
     check_ring_signature, derive_commitment_keys, derive_deposit_secret, derive_public_key,
@@ -24,6 +25,7 @@ use fuego_crypto::ring::{
 use fuego_crypto::ref10::{ge_p3_tobytes, ge_scalarmult_base, GeP3};
 use rand::RngCore;
 use std::collections::BTreeMap;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// Flat fee for block major version >= 10 (CryptoNoteConfig.h MINIMUM_FEE_8KH).
 pub const MINIMUM_FEE: u64 = 8000;
@@ -32,8 +34,13 @@ pub const DEFAULT_DUST_THRESHOLD: u64 = 1000;
 /// CryptoNoteConfig.h MAX_TX_MIXIN_SIZE.
 pub const MAX_MIXIN: usize = 32;
 
-/// A spendable output owned by this wallet.
-#[derive(Debug, Clone)]
+/// One input's ring signer: ring public keys, real index, key image and
+/// the secret scalar.
+type Signer = (Vec<[u8; 32]>, usize, [u8; 32], Zeroizing<[u8; 32]>);
+
+/// A spendable output owned by this wallet, with its re-derived one-time
+/// secret. Built just before signing and wiped when dropped.
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SpendableOutput {
     pub amount: u64,
     /// The one-time output key P.
@@ -58,12 +65,41 @@ pub struct DecoyEntry {
     pub out_key: [u8; 32],
 }
 
-/// One destination: an address and amount.
+/// One destination: an address and amount. `payment_id` is set when the
+/// address was an integrated address; it is emitted as an extra nonce.
 #[derive(Debug, Clone)]
 pub struct BuildDestination {
     pub amount: u64,
     pub spend_pub: [u8; 32],
     pub view_pub: [u8; 32],
+    pub payment_id: Option<PaymentId>,
+}
+
+/// Append the destinations' payment id as an extra nonce, a short id
+/// encrypted to its recipient's view key. A transaction carries at most
+/// one, so distinct ids (or one id for distinct recipients) are an error.
+fn add_destination_payment_id(
+    extra: &mut Vec<u8>,
+    dests: &[BuildDestination],
+    tx_secret: &[u8; 32],
+) -> Result<()> {
+    let mut payment_id: Option<(PaymentId, [u8; 32])> = None;
+    for (id, view_pub) in dests.iter().filter_map(|d| Some((d.payment_id?, d.view_pub))) {
+        match payment_id {
+            Some(existing) if existing != (id, view_pub) => {
+                return Err(SdkError::Serialization(
+                    "destinations carry different payment ids".into(),
+                ))
+            }
+            _ => payment_id = Some((id, view_pub)),
+        }
+    }
+    if let Some((id, view_pub)) = payment_id {
+        let derivation = generate_key_derivation(&view_pub, tx_secret)
+            .ok_or_else(|| SdkError::Crypto("key derivation failed".into()))?;
+        add_payment_id_extra(extra, &id.encrypt(&derivation));
+    }
+    Ok(())
 }
 
 /// A commitment destination: minted HEAT or a term-locked CD. The commit
@@ -132,29 +168,31 @@ pub struct BuiltTransaction {
 /// Shuffles the available outputs, buckets them by decimal digit count
 /// (base10 buckets), and takes one from each bucket per round until the
 /// needed amount is reached. Outputs at or below `dust` are never selected.
-pub fn select_inputs(
-    available: &[SpendableOutput],
+/// Only amounts are looked at, so `available` can be anything `amount_of`
+/// reads one from, e.g. stored outputs before any secret is derived.
+pub fn select_inputs<T: Clone>(
+    available: &[T],
+    amount_of: impl Fn(&T) -> u64,
     needed_money: u64,
     dust: u64,
     rng: &mut impl RngCore,
-) -> (Vec<SpendableOutput>, u64) {
+) -> (Vec<T>, u64) {
     use rand::seq::SliceRandom;
 
-    let mut outputs: Vec<&SpendableOutput> =
-        available.iter().filter(|o| o.amount > dust).collect();
+    let mut outputs: Vec<&T> = available.iter().filter(|o| amount_of(o) > dust).collect();
     outputs.shuffle(rng);
 
     // BTreeMap instead of the C++ unordered_map: the bucket iteration order
     // is otherwise randomized per process (HashMap RandomState), which would
     // make coin selection nondeterministic. Privacy comes from the shuffle
     // above; bucket order itself does not need to be secret.
-    let mut buckets: BTreeMap<usize, Vec<&SpendableOutput>> = BTreeMap::new();
+    let mut buckets: BTreeMap<usize, Vec<&T>> = BTreeMap::new();
     for output in outputs {
-        let digits = digits_of(output.amount);
+        let digits = digits_of(amount_of(output));
         buckets.entry(digits).or_default().push(output);
     }
 
-    let mut selected: Vec<SpendableOutput> = Vec::new();
+    let mut selected: Vec<T> = Vec::new();
     let mut found_money = 0u64;
 
     while found_money < needed_money && !buckets.is_empty() {
@@ -167,7 +205,7 @@ pub fn select_inputs(
             }
             if found_money < needed_money {
                 let out = bucket.pop().unwrap();
-                found_money += out.amount;
+                found_money += amount_of(out);
                 selected.push(out.clone());
             }
         }
@@ -337,6 +375,7 @@ pub fn build_transaction(
     }
 
     let mut extra = build_extra_with_pubkey(&txkey_pub);
+    add_destination_payment_id(&mut extra, &dests, &txkey)?;
     extra.extend_from_slice(extra_extra);
 
     let prefix = TransactionPrefix {
@@ -442,8 +481,9 @@ pub fn change_output_secret(
         .ok_or_else(|| SdkError::Crypto("change secret derivation failed".into()))
 }
 
-/// A commitment output being spent (HEAT or CD).
-#[derive(Debug, Clone)]
+/// A commitment output being spent (HEAT or CD). Like [`SpendableOutput`]
+/// it carries a re-derived secret and is wiped when dropped.
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct CommitmentDeposit {
     pub amount: u64,
     pub commit_key: [u8; 32],
@@ -560,6 +600,7 @@ pub fn build_mixed_output_transaction(
     }
 
     let mut extra = build_extra_with_pubkey(&txkey_pub);
+    add_destination_payment_id(&mut extra, &key_dests, &txkey)?;
     extra.extend_from_slice(extra_extra);
 
     let has_commitment_outputs = !commitment_destinations.is_empty();
@@ -646,6 +687,7 @@ pub fn build_mint_transaction(
             amount: chunk,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -653,6 +695,7 @@ pub fn build_mint_transaction(
             amount: dust,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
 
@@ -762,6 +805,7 @@ pub fn build_commitment_spend_transaction(
     }
 
     let mut extra = build_extra_with_pubkey(&txkey_pub);
+    add_destination_payment_id(&mut extra, &key_dests, &txkey)?;
     extra.extend_from_slice(extra_extra);
 
     let has_commitment_outputs = !commitment_destinations.is_empty();
@@ -828,7 +872,7 @@ pub struct BuildPoolCommitmentDestination {
 #[allow(clippy::too_many_arguments)]
 fn assemble_outputs_and_sign(
     wire_inputs: &[TxInput],
-    signers: &[(Vec<[u8; 32]>, usize, [u8; 32], [u8; 32])],
+    signers: &[Signer],
     commitment_destinations: &[BuildCommitmentDestination],
     key_destinations: &[BuildDestination],
     pool_destinations: &[BuildPoolCommitmentDestination],
@@ -894,6 +938,7 @@ fn assemble_outputs_and_sign(
     }
 
     let mut extra = build_extra_with_pubkey(&txkey_pub);
+    add_destination_payment_id(&mut extra, &key_dests, &txkey)?;
     extra.extend_from_slice(extra_extra);
 
     let has_commitment_outputs = !commitment_destinations.is_empty() || !pool_destinations.is_empty();
@@ -976,6 +1021,7 @@ pub fn build_swap_xfg_to_heat_transaction(
             amount: chunk,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -983,6 +1029,7 @@ pub fn build_swap_xfg_to_heat_transaction(
             amount: dust,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
 
@@ -1026,6 +1073,7 @@ pub fn build_swap_heat_to_xfg_transaction(
             amount: chunk,
             spend_pub: *spend_pub,
             view_pub: *view_pub_dest,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -1033,6 +1081,7 @@ pub fn build_swap_heat_to_xfg_transaction(
             amount: dust,
             spend_pub: *spend_pub,
             view_pub: *view_pub_dest,
+            payment_id: None,
         });
     }
 
@@ -1145,7 +1194,7 @@ pub fn build_lp_add_transaction(
     }
 
     // Signers: key inputs then commitment spends.
-    let mut signers: Vec<(Vec<[u8; 32]>, usize, [u8; 32], [u8; 32])> =
+    let mut signers: Vec<Signer> =
         Vec::with_capacity(xfg_inputs.len() + heat_deposits.len());
     for (i, input) in xfg_inputs.iter().enumerate() {
         let pubs: Vec<[u8; 32]> = rings[i].iter().map(|(_, k)| *k).collect();
@@ -1153,7 +1202,7 @@ pub fn build_lp_add_transaction(
             .iter()
             .position(|(idx, _)| *idx == input.global_index)
             .ok_or_else(|| SdkError::Crypto("real output index not found in ring".into()))?;
-        signers.push((pubs, sec_index, input.key_image, input.secret_key));
+        signers.push((pubs, sec_index, input.key_image, Zeroizing::new(input.secret_key)));
     }
     for (i, deposit) in heat_deposits.iter().enumerate() {
         let pubs: Vec<[u8; 32]> = c_rings[i].iter().map(|(_, k)| *k).collect();
@@ -1161,7 +1210,7 @@ pub fn build_lp_add_transaction(
             .iter()
             .position(|(idx, _)| *idx == deposit.global_index)
             .ok_or_else(|| SdkError::Crypto("real commitment index not found in ring".into()))?;
-        signers.push((pubs, sec_index, deposit.key_image, deposit.key_scalar));
+        signers.push((pubs, sec_index, deposit.key_image, Zeroizing::new(deposit.key_scalar)));
     }
 
     // Outputs: LP commitment first, then HEAT change commitment, then key change.
@@ -1189,6 +1238,7 @@ pub fn build_lp_add_transaction(
             amount: chunk,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -1196,6 +1246,7 @@ pub fn build_lp_add_transaction(
             amount: dust,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
 
@@ -1239,6 +1290,7 @@ pub fn build_lp_remove_transaction(
             amount: chunk,
             spend_pub: *spend_pub,
             view_pub: *view_pub_dest,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -1246,6 +1298,7 @@ pub fn build_lp_remove_transaction(
             amount: dust,
             spend_pub: *spend_pub,
             view_pub: *view_pub_dest,
+            payment_id: None,
         });
     }
 
@@ -1305,6 +1358,7 @@ pub fn build_place_order_transaction(
             amount: chunk,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -1312,6 +1366,7 @@ pub fn build_place_order_transaction(
             amount: dust,
             spend_pub: *change_spend,
             view_pub: *change_view,
+            payment_id: None,
         });
     }
 
@@ -1365,7 +1420,7 @@ pub fn build_place_order_transaction(
         })
         .collect();
 
-    let mut signers: Vec<(Vec<[u8; 32]>, usize, [u8; 32], [u8; 32])> =
+    let mut signers: Vec<Signer> =
         Vec::with_capacity(inputs.len());
     for (i, input) in inputs.iter().enumerate() {
         let pubs: Vec<[u8; 32]> = rings[i].iter().map(|(_, k)| *k).collect();
@@ -1373,7 +1428,7 @@ pub fn build_place_order_transaction(
             .iter()
             .position(|(idx, _)| *idx == input.global_index)
             .ok_or_else(|| SdkError::Crypto("real output index not found in ring".into()))?;
-        signers.push((pubs, sec_index, input.key_image, input.secret_key));
+        signers.push((pubs, sec_index, input.key_image, Zeroizing::new(input.secret_key)));
     }
 
     assemble_outputs_and_sign(
@@ -353,6 +353,10 @@ pub struct Transaction {
     pub outputs: Vec<TxOutput>,
     pub extra: Vec<u8>,
     pub fee: u64,
+    /// Wire encoding, when the transaction came from the daemon or the
+    /// builder; empty for one assembled with [`TxBuilder`].
+    #[serde(default)]
+    pub blob: Vec<u8>,
 }
 
 /// Transaction input
@@ -396,10 +400,12 @@ pub struct NodeConfig {
     pub network: NetworkType,
     pub max_peers: usize,
     pub sync_interval_secs: u64,
+    #[deprecated(note = "the node never serves blocks to peers; this has no effect")]
     pub enable_seeding: bool,
 }
 
 impl Default for NodeConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             data_dir: "./fuego-data".to_string(),
@@ -477,6 +483,7 @@ impl TxBuilder {
             outputs: self.outputs,
             extra: self.extra,
             fee: 0,
+            blob: Vec::new(),
         }
     }
 }
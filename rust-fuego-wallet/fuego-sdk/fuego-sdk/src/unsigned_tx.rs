@@ -0,0 +1,327 @@
+//! Offline (cold) signing files.
+//!
+//! An online watch wallet selects outputs and fetches their decoys into an
+//! [`UnsignedTransaction`]; an air-gapped full wallet re-derives the output
+//! secrets from its keys and signs it (`Wallet::sign_unsigned`), producing a
+//! [`SignedTransaction`] that the online wallet broadcasts. The signed file
+//! also carries signed key images for the spent outputs, so a watch wallet
+//! recognizes its own spend when it is mined.
+//!
+//! Both files are `magic ‖ version ‖ body ‖ checksum`, where the checksum is
+//! the first 4 bytes of cn_fast_hash over everything before it (as in
+//! CryptoNote addresses) and the body uses CryptoNote varints.
+
+use crate::error::{Result, SdkError};
+use crate::scanner::{SignedKeyImage, UtxoEntry};
+use crate::serialization::{parse_tx, read_varint, tx_prefix_hash, write_varint, TxInput};
+use crate::transaction_builder::{BuiltTransaction, DecoyEntry};
+use crate::types::Address;
+
+pub const UNSIGNED_TX_MAGIC: [u8; 8] = *b"FUEGOUTX";
+pub const SIGNED_TX_MAGIC: [u8; 8] = *b"FUEGOSTX";
+pub const COLD_FILE_VERSION: u8 = 1;
+const CHECKSUM_SIZE: usize = 4;
+
+/// One selected output with its decoy ring members. Carries no secrets:
+/// the signer derives the one-time secret from `tx_public_key` and
+/// `output_position`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsignedInput {
+    pub amount: u64,
+    pub output_key: [u8; 32],
+    pub tx_public_key: [u8; 32],
+    pub output_position: u32,
+    pub global_index: u32,
+    pub tx_hash: [u8; 32],
+    /// Exactly `mixin` decoys, as returned by /getrandom_outs.bin.
+    pub decoys: Vec<(u32, [u8; 32])>,
+}
+
+impl UnsignedInput {
+    pub fn new(utxo: &UtxoEntry, decoys: &[DecoyEntry]) -> Self {
+        Self {
+            amount: utxo.amount,
+            output_key: utxo.output_key,
+            tx_public_key: utxo.tx_public_key,
+            output_position: utxo.output_position,
+            global_index: utxo.global_index,
+            tx_hash: utxo.tx_hash,
+            decoys: decoys.iter().map(|d| (d.global_index, d.out_key)).collect(),
+        }
+    }
+
+    pub fn decoy_entries(&self) -> Vec<DecoyEntry> {
+        self.decoys
+            .iter()
+            .map(|(global_index, out_key)| DecoyEntry {
+                global_index: *global_index,
+                out_key: *out_key,
+            })
+            .collect()
+    }
+}
+
+/// Everything `build_transaction` needs except the spend key. Change is
+/// returned to the signing wallet's primary address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsignedTransaction {
+    pub inputs: Vec<UnsignedInput>,
+    pub destinations: Vec<(Address, u64)>,
+    pub fee: u64,
+    pub mixin: usize,
+    pub unlock_time: u64,
+    /// Bytes appended to the extra after the tx pubkey tag.
+    pub extra: Vec<u8>,
+}
+
+/// A signed transaction ready for broadcast.
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    pub built: BuiltTransaction,
+    /// Key images of the spent outputs, provable against their output keys.
+    pub key_images: Vec<SignedKeyImage>,
+}
+
+fn file_error(msg: impl Into<String>) -> SdkError {
+    SdkError::Serialization(msg.into())
+}
+
+fn seal(mut body: Vec<u8>) -> Vec<u8> {
+    let hash = fuego_crypto::cn_fast_hash(&body);
+    body.extend_from_slice(&hash[..CHECKSUM_SIZE]);
+    body
+}
+
+/// Check magic, version and checksum; returns the body after the header.
+fn open<'a>(data: &'a [u8], magic: &[u8; 8], what: &str) -> Result<&'a [u8]> {
+    let header = magic.len() + 1;
+    if data.len() < header + CHECKSUM_SIZE {
+        return Err(file_error(format!("{what} file too short")));
+    }
+    if &data[..magic.len()] != magic {
+        return Err(file_error(format!("not a {what} file")));
+    }
+    let version = data[magic.len()];
+    if version != COLD_FILE_VERSION {
+        return Err(file_error(format!("unsupported {what} file version {version}")));
+    }
+    let (sealed, checksum) = data.split_at(data.len() - CHECKSUM_SIZE);
+    if fuego_crypto::cn_fast_hash(sealed)[..CHECKSUM_SIZE] != *checksum {
+        return Err(file_error(format!("{what} file checksum mismatch")));
+    }
+    Ok(&sealed[header..])
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn varint(&mut self) -> Result<u64> {
+        read_varint(self.data, &mut self.pos).map_err(|e| file_error(e.0))
+    }
+
+    /// An output position or global index, which the chain keeps as `u32`.
+    fn index(&mut self) -> Result<u32> {
+        let value = self.varint()?;
+        u32::try_from(value).map_err(|_| file_error(format!("index {value} out of range")))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let value = self.varint()?;
+        usize::try_from(value).map_err(|_| file_error(format!("length {value} out of range")))
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return Err(file_error("unexpected end of data"));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn blob(&mut self) -> Result<&'a [u8]> {
+        let len = self.len()?;
+        self.bytes(len)
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.pos != self.data.len() {
+            return Err(file_error(format!(
+                "{} trailing bytes",
+                self.data.len() - self.pos
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn write_blob(bytes: &[u8], out: &mut Vec<u8>) {
+    write_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+impl UnsignedTransaction {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = UNSIGNED_TX_MAGIC.to_vec();
+        out.push(COLD_FILE_VERSION);
+        write_varint(self.fee, &mut out);
+        write_varint(self.unlock_time, &mut out);
+        write_varint(self.mixin as u64, &mut out);
+        write_varint(self.inputs.len() as u64, &mut out);
+        for input in &self.inputs {
+            debug_assert_eq!(input.decoys.len(), self.mixin);
+            write_varint(input.amount, &mut out);
+            out.extend_from_slice(&input.output_key);
+            out.extend_from_slice(&input.tx_public_key);
+            write_varint(input.output_position as u64, &mut out);
+            write_varint(input.global_index as u64, &mut out);
+            out.extend_from_slice(&input.tx_hash);
+            for (global_index, out_key) in &input.decoys {
+                write_varint(*global_index as u64, &mut out);
+                out.extend_from_slice(out_key);
+            }
+        }
+        write_varint(self.destinations.len() as u64, &mut out);
+        for (address, amount) in &self.destinations {
+            write_blob(address.0.as_bytes(), &mut out);
+            write_varint(*amount, &mut out);
+        }
+        write_blob(&self.extra, &mut out);
+        seal(out)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut r = Reader {
+            data: open(data, &UNSIGNED_TX_MAGIC, "unsigned transaction")?,
+            pos: 0,
+        };
+        let fee = r.varint()?;
+        let unlock_time = r.varint()?;
+        let mixin = r.len()?;
+        if mixin > crate::transaction_builder::MAX_MIXIN {
+            return Err(file_error(format!("mixin {mixin} too large")));
+        }
+        let input_count = r.varint()?;
+        let mut inputs = Vec::new();
+        for _ in 0..input_count {
+            let amount = r.varint()?;
+            let output_key = r.array()?;
+            let tx_public_key = r.array()?;
+            let output_position = r.index()?;
+            let global_index = r.index()?;
+            let tx_hash = r.array()?;
+            let mut decoys = Vec::with_capacity(mixin);
+            for _ in 0..mixin {
+                decoys.push((r.index()?, r.array()?));
+            }
+            inputs.push(UnsignedInput {
+                amount,
+                output_key,
+                tx_public_key,
+                output_position,
+                global_index,
+                tx_hash,
+                decoys,
+            });
+        }
+        let destination_count = r.varint()?;
+        let mut destinations = Vec::new();
+        for _ in 0..destination_count {
+            let address = std::str::from_utf8(r.blob()?)
+                .map_err(|_| file_error("destination address is not UTF-8"))?
+                .to_string();
+            destinations.push((Address(address), r.varint()?));
+        }
+        let extra = r.blob()?.to_vec();
+        r.finish()?;
+        Ok(Self {
+            inputs,
+            destinations,
+            fee,
+            mixin,
+            unlock_time,
+            extra,
+        })
+    }
+
+    /// Sum of the selected outputs.
+    pub fn input_amount(&self) -> u64 {
+        self.inputs.iter().map(|i| i.amount).sum()
+    }
+}
+
+impl SignedTransaction {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = SIGNED_TX_MAGIC.to_vec();
+        out.push(COLD_FILE_VERSION);
+        write_blob(&self.built.serialized, &mut out);
+        write_varint(self.key_images.len() as u64, &mut out);
+        for image in &self.key_images {
+            out.extend_from_slice(&image.output_key);
+            out.extend_from_slice(&image.key_image);
+            out.extend_from_slice(&image.signature);
+        }
+        seal(out)
+    }
+
+    /// Decode and check a signed file: the transaction must parse, and every
+    /// key image must carry a valid proof and be spent by one of its inputs.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut r = Reader {
+            data: open(data, &SIGNED_TX_MAGIC, "signed transaction")?,
+            pos: 0,
+        };
+        let serialized = r.blob()?.to_vec();
+        let image_count = r.varint()?;
+        let mut key_images = Vec::new();
+        for _ in 0..image_count {
+            key_images.push(SignedKeyImage {
+                output_key: r.array()?,
+                key_image: r.array()?,
+                signature: r.array()?,
+            });
+        }
+        r.finish()?;
+
+        let tx = parse_tx(&serialized).map_err(|e| file_error(e.0))?;
+        for image in &key_images {
+            let spent_here = tx.prefix.inputs.iter().any(|input| match input {
+                TxInput::Key(k) => k.key_image == image.key_image,
+                TxInput::Base(_) | TxInput::CommitmentSpend(_) => false,
+            });
+            if !spent_here || !image.verify() {
+                return Err(SdkError::Crypto(format!(
+                    "key image {} does not match the transaction",
+                    hex::encode(image.key_image)
+                )));
+            }
+        }
+        Ok(Self {
+            built: BuiltTransaction {
+                tx_hash: fuego_crypto::cn_fast_hash(&serialized),
+                prefix_hash: tx_prefix_hash(&tx.prefix),
+                tx,
+                serialized,
+            },
+            key_images,
+        })
+    }
+
+    /// Key images spent by the transaction's inputs.
+    pub fn input_key_images(&self) -> Vec<[u8; 32]> {
+        self.built
+            .tx
+            .prefix
+            .inputs
+            .iter()
+            .filter_map(|input| input.key_image().copied())
+            .collect()
+    }
+}
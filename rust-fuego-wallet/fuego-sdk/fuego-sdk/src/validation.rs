@@ -0,0 +1,165 @@
+//! Header checks applied to every block before the node stores it, so a
+//! faulty or malicious network provider can't feed it an inconsistent
+//! chain. Block hashes are taken from the provider; checkpoints are what
+//! pin them to the real chain.
+
+use crate::error::{Result, SdkError};
+use crate::types::{BlockHeader, NetworkType};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Blocks whose timestamps form the median a new block must not fall
+/// below (`BLOCKCHAIN_TIMESTAMP_CHECK_WINDOW` in CryptoNote).
+pub const TIMESTAMP_CHECK_WINDOW: usize = 60;
+
+/// How far ahead of the local clock a block timestamp may be, in seconds
+/// (`CRYPTONOTE_BLOCK_FUTURE_TIME_LIMIT`).
+pub const BLOCK_FUTURE_TIME_LIMIT: u64 = 2 * 60 * 60;
+
+/// Main-chain block hashes as (height, hex hash), from fuegod's checkpoint
+/// list. Checkpoints loaded with `parse_checkpoints` are added on top.
+const MAINNET_CHECKPOINTS: &[(u64, &str)] = &[];
+const TESTNET_CHECKPOINTS: &[(u64, &str)] = &[];
+const STAGENET_CHECKPOINTS: &[(u64, &str)] = &[];
+
+/// Hard-coded checkpoints of `network`.
+pub fn checkpoints(network: NetworkType) -> BTreeMap<u64, [u8; 32]> {
+    let table = match network {
+        NetworkType::Mainnet => MAINNET_CHECKPOINTS,
+        NetworkType::Testnet => TESTNET_CHECKPOINTS,
+        NetworkType::Stagenet => STAGENET_CHECKPOINTS,
+    };
+    table
+        .iter()
+        .map(|(height, hash)| {
+            let mut bytes = [0u8; 32];
+            hex::decode_to_slice(hash, &mut bytes).expect("checkpoint hash is 32 bytes of hex");
+            (*height, bytes)
+        })
+        .collect()
+}
+
+/// Parse a checkpoint file: one `height,hash` pair per line, the hash in
+/// hex. Blank lines and lines starting with `#` are skipped.
+pub fn parse_checkpoints(text: &str) -> Result<BTreeMap<u64, [u8; 32]>> {
+    let mut checkpoints = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || SdkError::Config(format!("checkpoint line {}: expected height,hash", number + 1));
+        let (height, hash) = line.split_once(',').ok_or_else(invalid)?;
+        let height = height.trim().parse().map_err(|_| invalid())?;
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hash.trim(), &mut bytes).map_err(|_| invalid())?;
+        checkpoints.insert(height, bytes);
+    }
+    Ok(checkpoints)
+}
+
+/// Tracks the validated tip and the recent timestamps it needs to judge
+/// the next block.
+pub struct HeaderValidator {
+    checkpoints: BTreeMap<u64, [u8; 32]>,
+    recent: VecDeque<BlockHeader>,
+}
+
+impl HeaderValidator {
+    pub fn new(network: NetworkType) -> Self {
+        Self { checkpoints: checkpoints(network), recent: VecDeque::new() }
+    }
+
+    pub fn with_checkpoint(mut self, height: u64, hash: [u8; 32]) -> Self {
+        self.add_checkpoint(height, hash);
+        self
+    }
+
+    pub fn add_checkpoint(&mut self, height: u64, hash: [u8; 32]) {
+        self.checkpoints.insert(height, hash);
+    }
+
+    /// Highest checkpointed height at or below `height`.
+    pub fn last_checkpoint(&self, height: u64) -> Option<u64> {
+        self.checkpoints.range(..=height).next_back().map(|(h, _)| *h)
+    }
+
+    /// Last accepted header.
+    pub fn tip(&self) -> Option<&BlockHeader> {
+        self.recent.back()
+    }
+
+    /// Forget the window, e.g. when the node starts from a different height.
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+
+    /// Check `header`, expected at `height`, against the tip, checkpoints
+    /// and timestamp rules. `now` is the local time in unix seconds.
+    pub fn validate(&self, header: &BlockHeader, height: u64, now: u64) -> Result<()> {
+        let reject = |reason: String| Err(SdkError::InvalidBlock { height, reason });
+
+        if header.height != height {
+            return reject(format!("provider returned height {}", header.height));
+        }
+        if let Some(tip) = self.tip() {
+            if tip.height + 1 != height {
+                return reject(format!("not contiguous with tip {}", tip.height));
+            }
+            if header.prev_hash != tip.hash {
+                return reject(format!(
+                    "prev_hash {} does not link to tip {}",
+                    hex::encode(header.prev_hash),
+                    hex::encode(tip.hash)
+                ));
+            }
+        }
+        if let Some(expected) = self.checkpoints.get(&height) {
+            if &header.hash != expected {
+                return reject(format!(
+                    "hash {} does not match checkpoint {}",
+                    hex::encode(header.hash),
+                    hex::encode(expected)
+                ));
+            }
+        }
+        if header.timestamp > now + BLOCK_FUTURE_TIME_LIMIT {
+            return reject(format!("timestamp {} is too far in the future", header.timestamp));
+        }
+        if let Some(median) = self.median_timestamp() {
+            if header.timestamp < median {
+                return reject(format!(
+                    "timestamp {} is below the median {} of the last {} blocks",
+                    header.timestamp, median, TIMESTAMP_CHECK_WINDOW
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make `header` the new tip. Call only after `validate` passed, or to
+    /// seed the window from blocks already stored.
+    pub fn accept(&mut self, header: BlockHeader) {
+        if self.tip().is_some_and(|tip| tip.height + 1 != header.height) {
+            self.recent.clear();
+        }
+        self.recent.push_back(header);
+        if self.recent.len() > TIMESTAMP_CHECK_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Median of the window, once it is full.
+    fn median_timestamp(&self) -> Option<u64> {
+        if self.recent.len() < TIMESTAMP_CHECK_WINDOW {
+            return None;
+        }
+        let mut timestamps: Vec<u64> = self.recent.iter().map(|h| h.timestamp).collect();
+        timestamps.sort_unstable();
+        let mid = timestamps.len() / 2;
+        Some(if timestamps.len().is_multiple_of(2) {
+            (timestamps[mid - 1] + timestamps[mid]) / 2
+        } else {
+            timestamps[mid]
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::{Result, SdkError};
 use fuego_crypto as crypto;
+pub use fuego_vault::KdfParams;
 use fuego_vault::Vault;
 use std::path::PathBuf;
 
@@ -33,6 +34,19 @@ impl WalletVault {
             .map_err(|e| SdkError::Vault(format!("Failed to save vault: {e}")))
     }
 
+    /// Save with explicit Argon2id cost parameters instead of the defaults.
+    pub fn save_with_params(&self, path: PathBuf, passphrase: &[u8], params: &KdfParams) -> Result<()> {
+        self.inner
+            .save_with_params(path, passphrase, params)
+            .map_err(|e| SdkError::Vault(format!("Failed to save vault: {e}")))
+    }
+
+    /// Re-encrypt the vault file at `path` under a new passphrase.
+    pub fn change_passphrase(path: PathBuf, old_passphrase: &[u8], new_passphrase: &[u8]) -> Result<()> {
+        Vault::change_passphrase(path, old_passphrase, new_passphrase)
+            .map_err(|e| SdkError::Vault(format!("Failed to change vault passphrase: {e}")))
+    }
+
     pub fn master_seed(&self) -> [u8; 32] {
         self.inner.master_seed
     }
@@ -53,6 +67,10 @@ impl WalletVault {
         self.inner.subaddress_count
     }
 
+    pub fn set_subaddress_count(&mut self, count: u32) {
+        self.inner.subaddress_count = count;
+    }
+
     pub fn get_subaddress_spend_index(&self, subaddress_number: u32) -> u32 {
         self.inner.get_subaddress_spend_index(subaddress_number)
     }
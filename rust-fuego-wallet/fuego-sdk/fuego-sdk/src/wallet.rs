@@ -1,14 +1,19 @@
 use crate::error::{Result, SdkError};
+use crate::events::{WalletEvent, WalletEvents};
+use crate::history::{HistoryPage, HistoryQuery};
 use crate::scanner::{
-    CommitmentEntry, HistoryEntry, ScannerStateSnapshot, UtxoEntry, UtxoScanner, WalletKeys,
+    BlockScanDelta, CommitmentEntry, HistoryEntry, KeyImageImport, PoolTxEffect, ScannerStateSnapshot,
+    SignedKeyImage, SpentRecord, UtxoEntry, UtxoScanner, WalletKeys,
 };
 use crate::serialization::TransactionPrefix;
-use crate::transaction_builder::{BuiltTransaction, DecoyEntry};
+use crate::transaction_builder::{BuiltTransaction, CommitmentDeposit, DecoyEntry, SpendableOutput};
 use crate::types::*;
+use crate::unsigned_tx::{SignedTransaction, UnsignedTransaction};
 use crate::vault::WalletVault;
 use crate::chain::{ChainSpv, PaymentProof};
 use sha2::{Sha256, Digest};
 use std::path::PathBuf;
+use tokio::sync::broadcast;
 
 pub struct Wallet {
     pub(crate) scanner: UtxoScanner,
@@ -33,6 +38,14 @@ impl Wallet {
         }
     }
 
+    /// A watch-only wallet from the public spend key and private view key:
+    /// it tracks incoming funds but cannot sign or compute key images.
+    pub fn view_only(spend_public: [u8; 32], view_secret: [u8; 32]) -> Result<Self> {
+        Ok(Self {
+            scanner: UtxoScanner::view_only(spend_public, view_secret)?,
+        })
+    }
+
     pub fn load(path: PathBuf, passphrase: &[u8]) -> Result<Self> {
         let vault = WalletVault::load(path, passphrase)?;
         Ok(Self {
@@ -41,24 +54,86 @@ impl Wallet {
     }
 
     pub fn save(&self, path: PathBuf, passphrase: &[u8]) -> Result<()> {
-        self.scanner.vault().save(path, passphrase)
+        self.spending_vault()?.save(path, passphrase)
+    }
+
+    pub fn is_view_only(&self) -> bool {
+        self.scanner.is_view_only()
     }
 
     pub fn primary_address(&self) -> Address {
         self.get_address(0)
     }
 
+    /// A view-only wallet only knows its primary address, which is
+    /// returned for every index.
     pub fn get_address(&self, index: u32) -> Address {
-        let addr = self.scanner.vault().get_address(index);
-        Address(addr.0)
+        match self.scanner.vault() {
+            Some(vault) => Address(vault.get_address(index).0),
+            None => {
+                let keys = self.scanner.wallet_keys();
+                Address(fuego_crypto::make_address(&keys.spend_public, &keys.view_public).0)
+            }
+        }
+    }
+
+    /// Issue the next subaddress; returns its index. Subaddresses share the
+    /// primary view key, so one scan covers all of them.
+    pub fn new_subaddress(&mut self) -> Result<u32> {
+        self.scanner.new_subaddress()
+    }
+
+    /// Address of subaddress `index` (0 = primary), if issued.
+    pub fn subaddress(&self, index: u32) -> Option<Address> {
+        self.scanner.subaddress(index)
+    }
+
+    /// The primary address followed by every issued subaddress.
+    pub fn subaddresses(&self) -> Vec<Address> {
+        (0..=self.scanner.subaddress_count())
+            .filter_map(|index| self.scanner.subaddress(index))
+            .collect()
+    }
+
+    /// Spend public key of subaddress `index`; its view key is the
+    /// primary view key.
+    pub fn subaddress_spend_public(&self, index: u32) -> Option<[u8; 32]> {
+        self.scanner.subaddress_spend_public(index)
+    }
+
+    pub fn subaddress_count(&self) -> u32 {
+        self.scanner.subaddress_count()
+    }
+
+    pub fn set_subaddress_count(&mut self, count: u32) {
+        self.scanner.set_subaddress_count(count);
+    }
+
+    /// Confirmed balance of each subaddress, indexed by subaddress.
+    pub fn subaddress_balances(&self) -> Vec<u64> {
+        self.scanner.subaddress_balances()
     }
 
+    /// # Panics
+    /// On a view-only wallet; see `try_get_keypair`.
     pub fn get_keypair(&self, index: u32) -> Keypair {
-        let kp = self.scanner.vault().derive_keypair(index);
-        Keypair {
+        self.try_get_keypair(index)
+            .expect("view-only wallet has no spend key")
+    }
+
+    /// `get_keypair` that fails instead of panicking on a view-only wallet.
+    pub fn try_get_keypair(&self, index: u32) -> Result<Keypair> {
+        let kp = self.spending_vault()?.derive_keypair(index);
+        Ok(Keypair {
             secret: SecretKey(kp.secret),
             public: PublicKey(kp.public),
-        }
+        })
+    }
+
+    fn spending_vault(&self) -> Result<&WalletVault> {
+        self.scanner
+            .vault()
+            .ok_or_else(|| SdkError::Wallet("view-only wallet has no spend key".into()))
     }
 
     pub fn balance(&self) -> Balance {
@@ -82,6 +157,11 @@ impl Wallet {
         self.scanner.scan_tx_prefix(tx_hash, prefix, block_height)
     }
 
+    /// Scan a pool transaction without recording it.
+    pub fn preview_tx_prefix(&self, tx_hash: &[u8; 32], prefix: &TransactionPrefix) -> PoolTxEffect {
+        self.scanner.preview_tx_prefix(tx_hash, prefix)
+    }
+
     pub fn attach_global_indices(&self, tx_hash: &[u8; 32], indices: &[u64]) {
         self.scanner.attach_global_indices(tx_hash, indices);
     }
@@ -131,6 +211,10 @@ impl Wallet {
         )
     }
 
+    pub fn sign_unsigned(&self, unsigned: &UnsignedTransaction) -> Result<SignedTransaction> {
+        self.scanner.sign_unsigned(unsigned, &mut rand::thread_rng())
+    }
+
     pub fn deposits(&self) -> Vec<CommitmentEntry> {
         self.scanner.deposits()
     }
@@ -139,10 +223,29 @@ impl Wallet {
         self.scanner.heat_outputs()
     }
 
+    /// Inputs for the transaction builders, secrets re-derived; drop them
+    /// as soon as the transaction is signed.
+    pub fn spendable_outputs(&self, utxos: &[UtxoEntry]) -> Result<Vec<SpendableOutput>> {
+        self.scanner.spendable_outputs(utxos)
+    }
+
+    pub fn commitment_deposit(
+        &self,
+        entry: &CommitmentEntry,
+        claimed_interest: u64,
+    ) -> Result<CommitmentDeposit> {
+        self.scanner.commitment_deposit(entry, claimed_interest)
+    }
+
     pub fn reserve_pending(&self, key_images: &[[u8; 32]]) {
         self.scanner.reserve_pending(key_images);
     }
 
+    /// Reserve a broadcast send's inputs and report its outcome as events.
+    pub fn track_send(&self, tx_hash: &[u8; 32], key_images: &[[u8; 32]]) {
+        self.scanner.track_send(tx_hash, key_images);
+    }
+
     pub fn snapshot_state(&self) -> ScannerStateSnapshot {
         self.scanner.snapshot()
     }
@@ -151,10 +254,39 @@ impl Wallet {
         self.scanner.restore(snapshot);
     }
 
+    pub fn export_key_images(&self) -> Result<Vec<SignedKeyImage>> {
+        self.scanner.export_key_images(&mut rand::thread_rng())
+    }
+
+    pub fn import_key_images(&self, images: &[SignedKeyImage]) -> Result<KeyImageImport> {
+        self.scanner.import_key_images(images)
+    }
+
+    pub fn mark_spent(&self, key_image: &[u8; 32]) -> Option<SpentRecord> {
+        self.scanner.mark_spent(key_image)
+    }
+
+    pub fn block_delta(&self, height: u64) -> BlockScanDelta {
+        self.scanner.block_delta(height)
+    }
+
+    pub fn detach(&self, height: u64) {
+        self.scanner.detach(height)
+    }
+
+    pub fn prune_spent(&self, height: u64) {
+        self.scanner.prune_spent(height)
+    }
+
     pub fn get_transactions(&self, limit: usize) -> Vec<HistoryEntry> {
         self.scanner.history(limit)
     }
 
+    /// History rows matching `query`, one page at a time.
+    pub fn query_transactions(&self, query: &HistoryQuery) -> HistoryPage {
+        self.scanner.query_history(query)
+    }
+
     pub fn utxos(&self) -> Vec<UtxoEntry> {
         self.scanner.utxos()
     }
@@ -163,17 +295,39 @@ impl Wallet {
         self.scanner.set_height(height);
     }
 
+    /// Events published as blocks are scanned and the height advances.
+    pub fn events(&self) -> &WalletEvents {
+        self.scanner.events()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WalletEvent> {
+        self.scanner.events().subscribe()
+    }
+
     pub fn add_guardian(&self, _address: Address) -> Result<()> {
         Err(SdkError::Vault(
             "Use vault_mut().add_guardian() for guardian management".into(),
         ))
     }
 
+    /// # Panics
+    /// On a view-only wallet, which has no vault; see `try_vault`.
     pub fn vault(&self) -> &WalletVault {
-        self.scanner.vault()
+        self.try_vault().expect("view-only wallet has no vault")
     }
 
+    /// # Panics
+    /// On a view-only wallet; see `try_vault_mut`.
     pub fn vault_mut(&mut self) -> &mut WalletVault {
+        self.try_vault_mut().expect("view-only wallet has no vault")
+    }
+
+    /// The backing vault; `None` for a view-only wallet.
+    pub fn try_vault(&self) -> Option<&WalletVault> {
+        self.scanner.vault()
+    }
+
+    pub fn try_vault_mut(&mut self) -> Option<&mut WalletVault> {
         self.scanner.vault_mut()
     }
 
@@ -0,0 +1,374 @@
+//! Levin P2P provider against stand-in peers: local TCP listeners that
+//! speak just enough of the daemon's protocol to serve a short chain.
+
+use fuego_sdk::network::levin::*;
+use fuego_sdk::serialization::{
+    block_id, parse_block, serialize_block, serialize_tx, tree_hash, BaseInput, KeyInput, OutputTarget,
+    ParsedBlock, Transaction, TransactionPrefix, TxInput, TxOutput,
+};
+use fuego_sdk::transaction_builder::BuiltTransaction;
+use fuego_sdk::{NetworkProvider, P2pConfig, P2pProvider, SdkError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+
+const NETWORK_ID: [u8; 16] = [0x46; 16];
+
+fn miner_tx(height: u32) -> Transaction {
+    Transaction {
+        prefix: TransactionPrefix {
+            version: 1,
+            unlock_time: height as u64 + 10,
+            inputs: vec![TxInput::Base(BaseInput { block_index: height })],
+            outputs: vec![TxOutput { amount: 8_000_000, target: OutputTarget::Key([height as u8; 32]) }],
+            extra: vec![],
+        },
+        signatures: vec![vec![]],
+    }
+}
+
+fn spend_tx(seed: u8) -> Vec<u8> {
+    serialize_tx(&Transaction {
+        prefix: TransactionPrefix {
+            version: 1,
+            unlock_time: 0,
+            inputs: vec![TxInput::Key(KeyInput { amount: 5_000, offsets: vec![3], key_image: [seed; 32] })],
+            outputs: vec![TxOutput { amount: 4_000, target: OutputTarget::Key([seed ^ 0xff; 32]) }],
+            extra: vec![],
+        },
+        signatures: vec![vec![[seed; 64]]],
+    })
+}
+
+fn hash(data: &[u8]) -> [u8; 32] {
+    fuego_crypto::cn_fast_hash(data)
+}
+
+/// Chain served by a stand-in peer, with real block ids.
+struct Chain {
+    ids: Vec<[u8; 32]>,
+    blocks: Vec<Vec<u8>>,
+    txs: Vec<Vec<Vec<u8>>>,
+}
+
+impl Chain {
+    /// `len` blocks; block 2 carries one spend besides its miner tx.
+    fn new(len: u32) -> Self {
+        let mut chain = Chain { ids: vec![], blocks: vec![], txs: vec![] };
+        for height in 0..len {
+            let txs = if height == 2 { vec![spend_tx(7)] } else { vec![] };
+            let block = ParsedBlock {
+                major_version: 1,
+                minor_version: 0,
+                timestamp: 1_700_000_000 + height as u64 * 480,
+                prev_id: chain.ids.last().copied().unwrap_or_default(),
+                nonce: height,
+                miner_tx: miner_tx(height),
+                tx_hashes: txs.iter().map(|tx| hash(tx)).collect(),
+            };
+            chain.ids.push(block_id(&block));
+            chain.blocks.push(serialize_block(&block));
+            chain.txs.push(txs);
+        }
+        chain
+    }
+}
+
+#[derive(Default)]
+struct PeerLog {
+    relayed: Vec<Vec<u8>>,
+    pings_answered: usize,
+}
+
+struct StandInPeer {
+    address: String,
+    log: Arc<Mutex<PeerLog>>,
+}
+
+async fn spawn_peer(chain: Arc<Chain>, network_id: [u8; 16], peerlist: Vec<PeerlistEntry>) -> StandInPeer {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let log = Arc::new(Mutex::new(PeerLog::default()));
+    let peer_log = log.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, chain.clone(), network_id, peerlist.clone(), peer_log.clone()));
+        }
+    });
+    StandInPeer { address, log }
+}
+
+fn peer_entry(address: &str) -> PeerlistEntry {
+    let addr: std::net::SocketAddrV4 = address.parse().unwrap();
+    PeerlistEntry {
+        ip: u32::from_le_bytes(addr.ip().octets()),
+        port: addr.port() as u32,
+        id: 99,
+        last_seen: 0,
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    chain: Arc<Chain>,
+    network_id: [u8; 16],
+    peerlist: Vec<PeerlistEntry>,
+    log: Arc<Mutex<PeerLog>>,
+) {
+    let sync_data = CoreSyncData { current_height: chain.ids.len() as u64, top_id: *chain.ids.last().unwrap() };
+    while let Ok(message) = read_message(&mut stream).await {
+        let reply = match message.command {
+            COMMAND_HANDSHAKE => {
+                HandshakeRequest::decode(&message.body).unwrap();
+                LevinMessage::response(
+                    COMMAND_HANDSHAKE,
+                    HandshakeResponse {
+                        node_data: BasicNodeData {
+                            network_id,
+                            version: 1,
+                            local_time: 0,
+                            my_port: 0,
+                            peer_id: 42,
+                        },
+                        payload_data: sync_data,
+                        local_peerlist: peerlist.clone(),
+                    }
+                    .encode(),
+                )
+            }
+            COMMAND_TIMED_SYNC => LevinMessage::response(
+                COMMAND_TIMED_SYNC,
+                TimedSyncResponse { local_time: 0, payload_data: sync_data, local_peerlist: vec![] }.encode(),
+            ),
+            COMMAND_PING if message.is_response => {
+                log.lock().unwrap().pings_answered += 1;
+                continue;
+            }
+            NOTIFY_REQUEST_CHAIN => {
+                let request = RequestChain::decode(&message.body).unwrap();
+                let start = request
+                    .block_ids
+                    .iter()
+                    .find_map(|id| chain.ids.iter().position(|known| known == id))
+                    .unwrap();
+                LevinMessage::notify(
+                    NOTIFY_RESPONSE_CHAIN_ENTRY,
+                    ResponseChainEntry {
+                        start_height: start as u64,
+                        total_height: chain.ids.len() as u64,
+                        block_ids: chain.ids[start..].to_vec(),
+                    }
+                    .encode(),
+                )
+            }
+            NOTIFY_REQUEST_GET_OBJECTS => {
+                // Interleave a keep-alive the client has to answer first.
+                let ping = LevinMessage::invoke(COMMAND_PING, Vec::new());
+                write_message(&mut stream, &ping).await.unwrap();
+
+                let request = RequestGetObjects::decode(&message.body).unwrap();
+                let mut response = ResponseGetObjects {
+                    current_blockchain_height: chain.ids.len() as u64,
+                    ..Default::default()
+                };
+                for id in request.blocks {
+                    match chain.ids.iter().position(|known| *known == id) {
+                        Some(h) => response.blocks.push(BlockCompleteEntry {
+                            block: chain.blocks[h].clone(),
+                            txs: chain.txs[h].clone(),
+                        }),
+                        None => response.missed_ids.push(id),
+                    }
+                }
+                for id in request.txs {
+                    match chain.txs.iter().flatten().find(|tx| hash(tx) == id) {
+                        Some(tx) => response.txs.push(tx.clone()),
+                        None => response.missed_ids.push(id),
+                    }
+                }
+                LevinMessage::notify(NOTIFY_RESPONSE_GET_OBJECTS, response.encode())
+            }
+            NOTIFY_NEW_TRANSACTIONS => {
+                let notify = NotifyNewTransactions::decode(&message.body).unwrap();
+                log.lock().unwrap().relayed.extend(notify.txs);
+                continue;
+            }
+            _ => continue,
+        };
+        if write_message(&mut stream, &reply).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn config(chain: &Chain, seeds: &[&str]) -> P2pConfig {
+    P2pConfig::new(NETWORK_ID, chain.ids[0])
+        .with_seeds(seeds.iter().copied())
+        .with_timeout(Duration::from_secs(5))
+}
+
+#[test]
+fn test_levin_header_roundtrip() {
+    let message = LevinMessage::invoke(COMMAND_TIMED_SYNC, vec![1, 2, 3]);
+    let encoded = message.encode();
+    assert_eq!(encoded.len(), LEVIN_HEADER_LEN + 3);
+    assert_eq!(&encoded[..8], &LEVIN_SIGNATURE.to_le_bytes());
+
+    let decoded = tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(async { read_message(&mut encoded.as_slice()).await })
+        .unwrap();
+    assert_eq!(decoded, message);
+}
+
+#[test]
+fn test_levin_payload_roundtrip() {
+    let response = ResponseGetObjects {
+        txs: vec![vec![1, 2], vec![3]],
+        blocks: vec![BlockCompleteEntry { block: vec![9; 40], txs: vec![vec![4; 5]] }],
+        missed_ids: vec![[7u8; 32], [8u8; 32]],
+        current_blockchain_height: 1234,
+    };
+    assert_eq!(ResponseGetObjects::decode(&response.encode()).unwrap(), response);
+
+    let handshake = HandshakeResponse {
+        node_data: BasicNodeData { network_id: NETWORK_ID, version: 1, local_time: 5, my_port: 10808, peer_id: 77 },
+        payload_data: CoreSyncData { current_height: 10, top_id: [3u8; 32] },
+        local_peerlist: vec![peer_entry("10.0.0.1:10808")],
+    };
+    let decoded = HandshakeResponse::decode(&handshake.encode()).unwrap();
+    assert_eq!(decoded, handshake);
+    assert_eq!(decoded.local_peerlist[0].address(), "10.0.0.1:10808");
+}
+
+#[test]
+fn test_block_blob_roundtrip() {
+    let chain = Chain::new(3);
+    let block = parse_block(&chain.blocks[2]).unwrap();
+    assert_eq!(block.prev_id, chain.ids[1]);
+    assert_eq!(block.tx_hashes, vec![hash(&chain.txs[2][0])]);
+    assert_eq!(serialize_block(&block), chain.blocks[2]);
+    assert!(parse_block(&chain.blocks[2][..chain.blocks[2].len() - 1]).is_err());
+}
+
+#[test]
+fn test_tree_hash_shape() {
+    let h: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+    let pair = |a: &[u8; 32], b: &[u8; 32]| hash(&[a.as_slice(), b.as_slice()].concat());
+
+    assert_eq!(tree_hash(&h[..1]), h[0]);
+    assert_eq!(tree_hash(&h[..2]), pair(&h[0], &h[1]));
+    assert_eq!(tree_hash(&h[..3]), pair(&h[0], &pair(&h[1], &h[2])));
+    assert_eq!(tree_hash(&h[..4]), pair(&pair(&h[0], &h[1]), &pair(&h[2], &h[3])));
+    assert_eq!(tree_hash(&h), pair(&pair(&h[0], &h[1]), &pair(&h[2], &pair(&h[3], &h[4]))));
+}
+
+#[tokio::test]
+async fn test_p2p_syncs_blocks_from_peer() {
+    let chain = Arc::new(Chain::new(4));
+    let peer = spawn_peer(chain.clone(), NETWORK_ID, vec![]).await;
+    let provider = P2pProvider::new(config(&chain, &[&peer.address]));
+
+    assert_eq!(provider.get_height().await.unwrap(), 4);
+
+    let block = provider.get_block(2).await.unwrap();
+    assert_eq!(block.header.hash, chain.ids[2]);
+    assert_eq!(block.header.prev_hash, chain.ids[1]);
+    assert_eq!(block.header.tx_count, 2);
+    assert_eq!(block.transactions[0].hash, hash(&serialize_tx(&miner_tx(2))));
+    assert_eq!(block.transactions[0].inputs[0].prev_output_index, 2);
+    assert_eq!(block.transactions[1].hash, hash(&chain.txs[2][0]));
+    assert_eq!(block.transactions[1].fee, 1_000);
+
+    let header = provider.get_header(3).await.unwrap();
+    assert_eq!(header.timestamp, 1_700_000_000 + 3 * 480);
+    assert!(matches!(provider.get_block(9).await, Err(SdkError::NotFound(_))));
+
+    // The client answered the peer's pings while waiting for objects.
+    assert!(peer.log.lock().unwrap().pings_answered >= 2);
+}
+
+#[tokio::test]
+async fn test_p2p_rejects_block_not_matching_its_id() {
+    let mut chain = Chain::new(4);
+    let mut forged = parse_block(&chain.blocks[2]).unwrap();
+    forged.nonce += 1;
+    chain.blocks[2] = serialize_block(&forged);
+    let chain = Arc::new(chain);
+    let peer = spawn_peer(chain.clone(), NETWORK_ID, vec![]).await;
+    let provider = P2pProvider::new(config(&chain, &[&peer.address]));
+
+    assert!(provider.get_block(1).await.is_ok());
+    match provider.get_block(2).await {
+        Err(SdkError::Network(e)) => assert!(e.contains("does not hash to"), "{e}"),
+        other => panic!("expected an id mismatch, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_p2p_config_for_network() {
+    use fuego_sdk::types::NetworkType;
+
+    let mainnet = P2pConfig::for_network(NetworkType::Mainnet, NETWORK_ID, [0u8; 32]);
+    assert!(!mainnet.seeds.is_empty());
+    assert!(mainnet.seeds.iter().all(|s| s.ends_with(":10808")));
+    assert!(P2pConfig::for_network(NetworkType::Testnet, NETWORK_ID, [0u8; 32]).seeds.is_empty());
+}
+
+#[tokio::test]
+async fn test_p2p_fetches_and_relays_transactions() {
+    let chain = Arc::new(Chain::new(3));
+    let peer = spawn_peer(chain.clone(), NETWORK_ID, vec![]).await;
+    let provider = P2pProvider::new(config(&chain, &[&peer.address]));
+
+    let known = hash(&chain.txs[2][0]);
+    let tx = provider.get_transaction(&known).await.unwrap().unwrap();
+    assert_eq!(tx.inputs[0].prev_tx_hash, [7u8; 32]);
+    assert!(provider.get_transaction(&[1u8; 32]).await.unwrap().is_none());
+
+    let blob = spend_tx(9);
+    let parsed = fuego_sdk::serialization::parse_tx(&blob).unwrap();
+    let built = BuiltTransaction {
+        tx_hash: hash(&blob),
+        prefix_hash: fuego_sdk::serialization::tx_prefix_hash(&parsed.prefix),
+        tx: parsed,
+        serialized: blob.clone(),
+    };
+    assert_eq!(provider.broadcast(&built).await.unwrap(), hash(&blob));
+
+    // Relays are fire-and-forget; give the peer a moment to read it.
+    for _ in 0..50 {
+        if !peer.log.lock().unwrap().relayed.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(peer.log.lock().unwrap().relayed, vec![blob]);
+}
+
+#[tokio::test]
+async fn test_p2p_honors_max_peers() {
+    let chain = Arc::new(Chain::new(2));
+    let second = spawn_peer(chain.clone(), NETWORK_ID, vec![]).await;
+    // The seed advertises the second peer in its handshake peer list.
+    let seed = spawn_peer(chain.clone(), NETWORK_ID, vec![peer_entry(&second.address)]).await;
+
+    let one = P2pProvider::new(config(&chain, &[&seed.address]).with_max_peers(1));
+    assert_eq!(one.get_peers().await.unwrap().len(), 1);
+
+    let two = P2pProvider::new(config(&chain, &[&seed.address]).with_max_peers(2));
+    let mut addresses: Vec<String> = two.get_peers().await.unwrap().into_iter().map(|p| p.address).collect();
+    addresses.sort();
+    let mut expected = vec![seed.address.clone(), second.address.clone()];
+    expected.sort();
+    assert_eq!(addresses, expected);
+}
+
+#[tokio::test]
+async fn test_p2p_rejects_other_network() {
+    let chain = Arc::new(Chain::new(2));
+    let peer = spawn_peer(chain.clone(), [0x11; 16], vec![]).await;
+    let provider = P2pProvider::new(config(&chain, &[&peer.address]));
+    assert!(matches!(provider.get_height().await, Err(SdkError::Network(_))));
+    assert_eq!(provider.peer_count().await, 0);
+}
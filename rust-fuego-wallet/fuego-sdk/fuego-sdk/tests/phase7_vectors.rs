@@ -301,6 +301,7 @@ fn commitment_spend_transaction() {
         amount: payout,
         spend_pub,
         view_pub,
+        payment_id: None,
     }];
 
     let built = build_commitment_spend_transaction(
@@ -473,6 +474,7 @@ fn print_cross_language_artifacts() {
             amount: total - MINIMUM_FEE,
             spend_pub,
             view_pub,
+            payment_id: None,
         }],
         &[],
         &view_pub,
@@ -1,11 +1,13 @@
 use fuego_sdk::builder::{SdkBuilder, WalletBuilder};
 use fuego_sdk::crypto;
 use fuego_sdk::error::SdkError;
-use fuego_sdk::node::EmbeddedNode;
-use fuego_sdk::store::MemoryStore;
+use fuego_sdk::events::WalletEvent;
+use fuego_sdk::node::{EmbeddedNode, NodeEvent};
+use fuego_sdk::store::encrypted::HEADER_KEY;
+use fuego_sdk::store::{EncryptedStore, MemoryStore, StateKey};
 use fuego_sdk::traits::*;
 use fuego_sdk::types::*;
-use fuego_sdk::vault::WalletVault;
+use fuego_sdk::vault::{KdfParams, WalletVault};
 use fuego_sdk::wallet::Wallet;
 use std::sync::Arc;
 use tempfile::tempdir;
@@ -36,6 +38,37 @@ fn test_make_address() {
     assert!(crypto::is_valid_address(&addr.0));
 }
 
+#[test]
+fn test_parse_address_round_trip() {
+    let kp = crypto::generate_keypair();
+    let view_kp = crypto::generate_keypair();
+    let addr = crypto::make_address(&kp.public, &view_kp.public);
+    assert!(addr.0.starts_with("fire"));
+    assert_eq!(fuego_crypto::parse_address(&addr.0), Some((kp.public.0, view_kp.public.0)));
+    assert_eq!(fuego_crypto::parse_address("fire1guardian"), None);
+}
+
+#[test]
+fn test_integrated_address_round_trip() {
+    let kp = crypto::generate_keypair();
+    let view_kp = crypto::generate_keypair();
+    for payment_id in [vec![7u8; 8], vec![9u8; 32]] {
+        let addr =
+            fuego_crypto::make_integrated_address(&kp.public.0, &view_kp.public.0, &payment_id)
+                .unwrap();
+        assert_eq!(
+            fuego_crypto::parse_integrated_address(&addr.0),
+            Some((kp.public.0, view_kp.public.0, payment_id))
+        );
+        // Not a standard address, and vice versa.
+        assert_eq!(fuego_crypto::parse_address(&addr.0), None);
+    }
+    let plain = crypto::make_address(&kp.public, &view_kp.public);
+    assert_eq!(fuego_crypto::parse_integrated_address(&plain.0), None);
+    assert!(fuego_crypto::make_integrated_address(&kp.public.0, &view_kp.public.0, &[1; 16])
+        .is_none());
+}
+
 #[test]
 fn test_sign_and_verify() {
     let kp = crypto::generate_keypair();
@@ -152,6 +185,261 @@ fn test_memory_store_kv() {
     assert!(!store.exists(b"key").unwrap());
 }
 
+/// Argon2id costs low enough for tests.
+const TEST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+/// Range, prefix and batch behaviour every `StorageProvider` must share.
+/// Keys stay under `t/` so backends keeping typed records in the same key
+/// space don't interfere.
+fn check_storage_conformance(store: &dyn StorageProvider) {
+    let keys = |rows: KvPairs| rows.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    for key in [&b"t/3"[..], b"t/1", b"t/2", b"t/\xff", b"t/\xff\xff", b"t0", b"u"] {
+        store.put(key, &[key.len() as u8]).unwrap();
+    }
+
+    let rows = store.scan_prefix(b"t/").unwrap();
+    assert_eq!(rows[0], (b"t/1".to_vec(), vec![3]));
+    assert_eq!(
+        keys(rows),
+        vec![b"t/1".to_vec(), b"t/2".to_vec(), b"t/3".to_vec(), b"t/\xff".to_vec(), b"t/\xff\xff".to_vec()]
+    );
+    assert_eq!(
+        keys(store.scan_prefix(b"t/\xff").unwrap()),
+        vec![b"t/\xff".to_vec(), b"t/\xff\xff".to_vec()]
+    );
+    assert!(store.scan_prefix(b"v").unwrap().is_empty());
+
+    assert_eq!(keys(store.range(b"t/2", Some(b"t/3")).unwrap()), vec![b"t/2".to_vec()]);
+    assert_eq!(keys(store.range(b"t0", None).unwrap()), vec![b"t0".to_vec(), b"u".to_vec()]);
+    assert!(store.range(b"t/3", Some(b"t/3")).unwrap().is_empty());
+    assert!(store.range(b"t/3", Some(b"t/1")).unwrap().is_empty());
+
+    let mut batch = WriteBatch::new();
+    batch.put(b"t/4", b"four").delete(b"t/1").put(b"t/2", b"two").put(b"t/5", b"x").delete(b"t/5");
+    assert_eq!(batch.len(), 5);
+    store.apply_batch(batch).unwrap();
+    assert_eq!(
+        store.range(b"t/1", Some(b"t/5")).unwrap(),
+        vec![
+            (b"t/2".to_vec(), b"two".to_vec()),
+            (b"t/3".to_vec(), vec![3]),
+            (b"t/4".to_vec(), b"four".to_vec()),
+        ]
+    );
+    assert!(!store.exists(b"t/5").unwrap());
+    store.apply_batch(WriteBatch::new()).unwrap();
+    store.flush().unwrap();
+}
+
+#[test]
+fn test_memory_store_conformance() {
+    check_storage_conformance(&MemoryStore::new());
+}
+
+#[test]
+fn test_encrypted_store_conformance() {
+    let secret = StateKey::Key([7u8; 32]);
+    let store = EncryptedStore::open(MemoryStore::new(), &secret).unwrap();
+    check_storage_conformance(&store);
+    assert_ne!(store.inner().get(b"t/2").unwrap(), Some(b"two".to_vec()));
+
+    store.lock();
+    assert!(store.scan_prefix(b"t/").is_err());
+    assert!(store.apply_batch(WriteBatch::new()).is_err());
+}
+
+#[cfg(feature = "storage")]
+#[test]
+fn test_sled_store_conformance() {
+    use fuego_sdk::store::sled::SledStore;
+
+    let dir = tempdir().unwrap();
+    check_storage_conformance(&SledStore::open(dir.path().join("store.sled")).unwrap());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store_conformance() {
+    use fuego_sdk::store::sqlite::SqliteStore;
+
+    check_storage_conformance(&SqliteStore::open_in_memory().unwrap());
+}
+
+#[test]
+fn test_encrypted_store_seals_values() {
+    let passphrase = StateKey::Passphrase(b"state-passphrase".to_vec());
+    let store = EncryptedStore::open_with_params(MemoryStore::new(), &passphrase, &TEST_KDF).unwrap();
+    store.put(b"key", b"secret value").unwrap();
+    store.save_height(42).unwrap();
+    assert_eq!(store.get(b"key").unwrap(), Some(b"secret value".to_vec()));
+    assert_eq!(store.get_height().unwrap(), 42);
+
+    let raw = store.inner().get(b"key").unwrap().unwrap();
+    assert!(!raw.windows(6).any(|w| w == b"secret"));
+
+    // Locked, nothing is readable or writable until the right key returns.
+    store.lock();
+    assert!(store.is_locked());
+    assert!(store.get(b"key").is_err());
+    assert!(store.put(b"other", b"value").is_err());
+    assert!(store.exists(b"key").is_err());
+    assert!(store.unlock(&StateKey::Passphrase(b"wrong".to_vec())).is_err());
+    assert!(store.unlock(&StateKey::Key([7u8; 32])).is_err());
+    assert!(store.is_locked());
+    store.unlock(&passphrase).unwrap();
+    assert_eq!(store.get(b"key").unwrap(), Some(b"secret value".to_vec()));
+}
+
+#[test]
+fn test_encrypted_store_binds_values_to_keys() {
+    let store = EncryptedStore::open(MemoryStore::new(), &StateKey::Key([7u8; 32])).unwrap();
+    store.put(b"a", b"first").unwrap();
+    store.put(b"b", b"second").unwrap();
+    let a = store.inner().get(b"a").unwrap().unwrap();
+    store.inner().put(b"b", &a).unwrap();
+    assert!(store.get(b"b").is_err());
+    assert!(store.inner().exists(HEADER_KEY).unwrap());
+}
+
+#[test]
+fn test_encrypted_store_seals_existing_rows_and_blinds_utxo_keys() {
+    let utxo = Utxo { tx_hash: [1u8; 32], output_index: 3, amount: 1000, pubkey: [2u8; 32], height: 10 };
+    let mut utxo_key = utxo.tx_hash.to_vec();
+    utxo_key.extend_from_slice(&3u32.to_be_bytes());
+    // A UTXO row as SledStore writes it.
+    let plain = MemoryStore::new();
+    plain.put(&utxo_key, &bincode::serialize(&utxo).unwrap()).unwrap();
+    plain.put(b"note", b"plaintext note").unwrap();
+
+    let store = EncryptedStore::open(plain, &StateKey::Key([7u8; 32])).unwrap();
+    assert_eq!(store.get(b"note").unwrap(), Some(b"plaintext note".to_vec()));
+    assert_ne!(store.inner().get(b"note").unwrap(), Some(b"plaintext note".to_vec()));
+    assert_eq!(store.get_utxo(&[1u8; 32], 3).unwrap().unwrap().amount, 1000);
+    assert!(!store.inner().exists(&utxo_key).unwrap());
+
+    store.save_utxo(&Utxo { output_index: 4, ..utxo.clone() }).unwrap();
+    utxo_key[35] = 4;
+    assert!(!store.inner().exists(&utxo_key).unwrap());
+    store.delete_utxo(&[1u8; 32], 4).unwrap();
+    assert!(store.get_utxo(&[1u8; 32], 4).unwrap().is_none());
+}
+
+#[cfg(feature = "storage")]
+#[test]
+fn test_sled_store_encrypts_in_place() {
+    use fuego_sdk::store::sled::SledStore;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store.sled");
+    let utxo = Utxo { tx_hash: [1u8; 32], output_index: 3, amount: 1000, pubkey: [2u8; 32], height: 10 };
+    {
+        let plain = SledStore::open(&path).unwrap();
+        plain.save_utxo(&utxo).unwrap();
+        plain.save_height(12).unwrap();
+        plain.put(b"note", b"plaintext note").unwrap();
+    }
+
+    let passphrase = StateKey::Passphrase(b"state-passphrase".to_vec());
+    let store = SledStore::open_encrypted_with_params(&path, &passphrase, &TEST_KDF).unwrap();
+    assert_eq!(store.get_utxo(&[1u8; 32], 3).unwrap().unwrap().amount, 1000);
+    assert_eq!(store.get_height().unwrap(), 12);
+    assert_eq!(store.get(b"note").unwrap(), Some(b"plaintext note".to_vec()));
+    assert_ne!(store.inner().get(b"note").unwrap(), Some(b"plaintext note".to_vec()));
+    let mut utxo_key = utxo.tx_hash.to_vec();
+    utxo_key.extend_from_slice(&utxo.output_index.to_be_bytes());
+    assert!(!store.inner().exists(&utxo_key).unwrap());
+    drop(store);
+
+    // Re-opening does not seal twice; a wrong passphrase is refused.
+    assert!(SledStore::open_encrypted(&path, &StateKey::Passphrase(b"wrong".to_vec())).is_err());
+    let store = SledStore::open_encrypted(&path, &passphrase).unwrap();
+    assert_eq!(store.get_height().unwrap(), 12);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store_provider() {
+    use fuego_sdk::store::sqlite::SqliteStore;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("wallet.sqlite");
+    let block = Block {
+        header: BlockHeader { height: 7, hash: [7u8; 32], prev_hash: [6u8; 32], timestamp: 1000, tx_count: 0 },
+        transactions: Vec::new(),
+    };
+    let utxo = Utxo { tx_hash: [1u8; 32], output_index: 3, amount: 1000, pubkey: [2u8; 32], height: 7 };
+    {
+        let store = SqliteStore::open(&path).unwrap();
+        store.save_block(&block).unwrap();
+        store.save_utxo(&utxo).unwrap();
+        store.save_height(7).unwrap();
+        store.put(b"key", b"value").unwrap();
+    }
+
+    let store = SqliteStore::open(&path).unwrap();
+    assert_eq!(store.get_block(7).unwrap().unwrap().header.prev_hash, [6u8; 32]);
+    assert!(store.get_block(8).unwrap().is_none());
+    assert_eq!(store.get_utxo(&[1u8; 32], 3).unwrap().unwrap().pubkey, [2u8; 32]);
+    assert_eq!(store.get_height().unwrap(), 7);
+    assert_eq!(store.get(b"key").unwrap(), Some(b"value".to_vec()));
+    store.delete(b"key").unwrap();
+    assert!(!store.exists(b"key").unwrap());
+    store.delete_utxo(&[1u8; 32], 3).unwrap();
+    assert!(store.get_utxo(&[1u8; 32], 3).unwrap().is_none());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store_wallet_tables() {
+    use fuego_sdk::history::HistoryQuery;
+    use fuego_sdk::scanner::HistoryDirection;
+    use fuego_sdk::serialization::PaymentId;
+    use fuego_sdk::store::sqlite::{AddressBookEntry, SqliteStore};
+
+    let store = SqliteStore::open_in_memory().unwrap();
+    let rows = history_rows();
+    store.append_history(&rows).unwrap();
+
+    // The database answers exactly like the in-memory evaluation.
+    let queries = [
+        HistoryQuery::default(),
+        HistoryQuery::blocks(20, 20),
+        HistoryQuery { direction: Some(HistoryDirection::Outgoing), ..Default::default() },
+        HistoryQuery { min_amount: Some(200), max_amount: Some(400), ..Default::default() },
+        HistoryQuery { payment_id: Some(PaymentId::Short([9u8; 8])), ..Default::default() },
+        HistoryQuery { subaddress: Some(1), offset: 1, limit: Some(1), ..Default::default() },
+        HistoryQuery { offset: 2, limit: Some(2), ..Default::default() },
+        HistoryQuery { newest_first: true, offset: 1, limit: Some(3), ..Default::default() },
+    ];
+    for query in &queries {
+        assert_eq!(store.query_history(query).unwrap(), query.page(&rows), "{query:?}");
+    }
+
+    store.add_key_image(&[4u8; 32], 30).unwrap();
+    store.save_height(40).unwrap();
+    assert!(store.is_key_image_spent(&[4u8; 32]).unwrap());
+    store.set_note(&rows[0].tx_hash, "rent").unwrap();
+    store.detach(30).unwrap();
+    assert!(!store.is_key_image_spent(&[4u8; 32]).unwrap());
+    assert_eq!(store.get_height().unwrap(), 29);
+    assert_eq!(store.query_history(&HistoryQuery::default()).unwrap().total, 3);
+    assert_eq!(store.note(&rows[0].tx_hash).unwrap().as_deref(), Some("rent"));
+    store.set_note(&rows[0].tx_hash, "").unwrap();
+    assert_eq!(store.note(&rows[0].tx_hash).unwrap(), None);
+
+    let exchange = AddressBookEntry {
+        address: "fire_exchange".into(),
+        label: "exchange".into(),
+        payment_id: Some(PaymentId::Long([3u8; 32])),
+    };
+    let friend = AddressBookEntry { address: "fire_friend".into(), label: "alice".into(), payment_id: None };
+    store.save_contact(&exchange).unwrap();
+    store.save_contact(&friend).unwrap();
+    assert_eq!(store.contacts().unwrap(), vec![friend.clone(), exchange]);
+    store.remove_contact("fire_exchange").unwrap();
+    assert_eq!(store.contacts().unwrap(), vec![friend]);
+}
+
 // ============================================================
 // Node builder tests
 // ============================================================
@@ -180,7 +468,6 @@ fn test_sdk_builder_custom_config() {
         .data_dir("/tmp/fuego-test")
         .max_peers(100)
         .sync_interval(60)
-        .enable_seeding(true)
         .build()
         .unwrap();
     assert_eq!(node.height(), 0);
@@ -192,6 +479,445 @@ fn test_sdk_builder_rpc() {
     assert_eq!(node.height(), 0);
 }
 
+#[test]
+fn test_sdk_builder_rpc_nodes() {
+    let node = SdkBuilder::new()
+        .rpc_nodes([("127.0.0.1", 18180), ("127.0.0.2", 18180)])
+        .node_quorum(2)
+        .build()
+        .unwrap();
+    assert_eq!(node.height(), 0);
+}
+
+// ============================================================
+// Failover tests
+// ============================================================
+
+/// In-memory daemon serving a linked chain: `fork` salts block hashes so
+/// nodes on different chains disagree.
+struct MockNode {
+    height: u64,
+    fork: u8,
+    /// Blocks below this height hash as on chain 1, so `fork` only salts
+    /// the branch from here on.
+    shared: u64,
+    failing: std::sync::atomic::AtomicBool,
+    calls: std::sync::atomic::AtomicUsize,
+    /// Latency of `get_block`, in milliseconds.
+    block_delay: std::sync::atomic::AtomicU64,
+    in_flight: std::sync::atomic::AtomicUsize,
+    max_in_flight: std::sync::atomic::AtomicUsize,
+}
+
+impl MockNode {
+    fn new(height: u64, fork: u8) -> Arc<Self> {
+        Self::reorged(height, fork, 0)
+    }
+
+    fn reorged(height: u64, fork: u8, shared: u64) -> Arc<Self> {
+        Arc::new(Self {
+            height,
+            fork,
+            shared,
+            failing: Default::default(),
+            calls: Default::default(),
+            block_delay: Default::default(),
+            in_flight: Default::default(),
+            max_in_flight: Default::default(),
+        })
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn answer<T>(&self, value: T) -> fuego_sdk::Result<T> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(SdkError::Network("connection refused".into()));
+        }
+        Ok(value)
+    }
+
+    fn hash(&self, height: u64) -> [u8; 32] {
+        let mut hash = [if height < self.shared { 1 } else { self.fork }; 32];
+        hash[..8].copy_from_slice(&height.to_le_bytes());
+        hash
+    }
+
+    fn header(&self, height: u64) -> BlockHeader {
+        BlockHeader {
+            height,
+            hash: self.hash(height),
+            prev_hash: self.hash(height.wrapping_sub(1)),
+            timestamp: 1_500_000_000 + height * 480,
+            tx_count: 0,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl NetworkProvider for MockNode {
+    async fn get_height(&self) -> fuego_sdk::Result<u64> {
+        self.answer(self.height)
+    }
+
+    async fn get_header(&self, height: u64) -> fuego_sdk::Result<BlockHeader> {
+        if height >= self.height {
+            return self.answer(()).and(Err(SdkError::Network("height out of range".into())));
+        }
+        self.answer(self.header(height))
+    }
+
+    async fn get_block(&self, height: u64) -> fuego_sdk::Result<Block> {
+        use std::sync::atomic::Ordering;
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        let delay = self.block_delay.load(Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let header = self.get_header(height).await?;
+        Ok(Block { header, transactions: Vec::new() })
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> fuego_sdk::Result<[u8; 32]> {
+        self.answer(tx.hash)
+    }
+
+    async fn get_peers(&self) -> fuego_sdk::Result<Vec<PeerInfo>> {
+        self.answer(Vec::new())
+    }
+
+    async fn get_transaction(&self, _hash: &[u8; 32]) -> fuego_sdk::Result<Option<Transaction>> {
+        self.answer(None)
+    }
+}
+
+fn failover(nodes: &[Arc<MockNode>]) -> fuego_sdk::FailoverProvider {
+    fuego_sdk::FailoverProvider::new(
+        nodes.iter().map(|n| n.clone() as Arc<dyn NetworkProvider>).collect(),
+    )
+}
+
+#[tokio::test]
+async fn test_failover_skips_failing_node() {
+    let (a, b) = (MockNode::new(100, 1), MockNode::new(100, 1));
+    a.failing.store(true, std::sync::atomic::Ordering::SeqCst);
+    let provider = failover(&[a.clone(), b.clone()]);
+
+    assert_eq!(provider.get_block(10).await.unwrap().header.hash, b.header(10).hash);
+    let stats = provider.node_stats();
+    assert_eq!(stats[0].consecutive_errors, 1);
+    assert!(stats[0].last_error.as_deref().unwrap().contains("connection refused"));
+    assert!(stats[1].latency.is_some());
+
+    // The failing node is now ranked last and not retried while b answers.
+    let before = a.calls();
+    provider.get_header(11).await.unwrap();
+    assert_eq!(a.calls(), before);
+
+    b.failing.store(true, std::sync::atomic::Ordering::SeqCst);
+    assert!(matches!(provider.get_header(12).await, Err(SdkError::Network(_))));
+}
+
+#[tokio::test]
+async fn test_failover_prefers_nodes_that_keep_up() {
+    let (lagging, current) = (MockNode::new(10, 1), MockNode::new(100, 1));
+    let provider = failover(&[lagging.clone(), current.clone()]);
+
+    assert_eq!(provider.get_height().await.unwrap(), 100);
+    let before = lagging.calls();
+    provider.get_block(5).await.unwrap();
+    assert_eq!(lagging.calls(), before);
+    assert_eq!(provider.node_stats()[0].height, Some(10));
+}
+
+#[tokio::test]
+async fn test_failover_quorum_on_tip() {
+    let honest = [MockNode::new(50, 1), MockNode::new(51, 1)];
+    let liar = MockNode::new(60, 2);
+    let nodes = [honest[0].clone(), honest[1].clone(), liar];
+
+    assert_eq!(failover(&nodes).get_height().await.unwrap(), 60);
+    assert_eq!(failover(&nodes).with_quorum(2).get_height().await.unwrap(), 50);
+    assert!(failover(&nodes).with_quorum(3).get_height().await.is_err());
+}
+
+#[tokio::test]
+async fn test_failover_quorum_on_headers_and_blocks() {
+    let nodes = [MockNode::new(50, 2), MockNode::new(50, 1), MockNode::new(50, 1)];
+    let provider = failover(&nodes).with_quorum(2);
+    assert_eq!(provider.get_header(7).await.unwrap().hash, nodes[1].header(7).hash);
+    assert_eq!(provider.get_block(7).await.unwrap().header.hash, nodes[1].header(7).hash);
+
+    let split = [MockNode::new(50, 1), MockNode::new(50, 2)];
+    assert!(failover(&split).with_quorum(2).get_header(7).await.is_err());
+    assert!(failover(&split).with_quorum(2).get_block(7).await.is_err());
+}
+
+#[tokio::test]
+async fn test_failover_skips_nodes_below_requested_height() {
+    let (short, long) = (MockNode::new(10, 1), MockNode::new(100, 1));
+    let provider = failover(&[short.clone(), long.clone()]).with_max_lag(1000);
+    provider.get_height().await.unwrap();
+
+    let before = short.calls();
+    assert_eq!(provider.get_header(50).await.unwrap().hash, long.header(50).hash);
+    assert_eq!(short.calls(), before);
+    assert_eq!(provider.node_stats()[0].consecutive_errors, 0);
+}
+
+// ============================================================
+// RPC provider tests
+// ============================================================
+
+/// fuegod's HTTP interface as far as `RpcProvider` uses it:
+/// getblockheaderbyheight and f_block_json over JSON-RPC, /gettransactions
+/// and /sendrawtransaction, over a fixed chain.
+#[derive(Default)]
+struct RpcStub {
+    /// Block id and transaction blobs per height.
+    blocks: Vec<([u8; 32], Vec<Vec<u8>>)>,
+    submitted: std::sync::Mutex<Vec<Vec<u8>>>,
+    /// /sendrawtransaction answer; `None` accepts.
+    send_reply: std::sync::Mutex<Option<serde_json::Value>>,
+}
+
+impl RpcStub {
+    fn with_blocks(blocks: Vec<Vec<fuego_sdk::serialization::TransactionPrefix>>) -> Self {
+        let blocks = blocks
+            .iter()
+            .enumerate()
+            .map(|(height, txs)| ([height as u8 + 1; 32], txs.iter().map(tx_blob).collect()))
+            .collect();
+        Self { blocks, ..Default::default() }
+    }
+
+    fn reply(&self, path: &str, request: &serde_json::Value) -> serde_json::Value {
+        match path {
+            "/json_rpc" => {
+                let params = &request["params"];
+                let result = match request["method"].as_str().unwrap_or_default() {
+                    "getblockheaderbyheight" => {
+                        let height = params["height"].as_u64().unwrap_or(u64::MAX) as usize;
+                        self.blocks.get(height).map(|(id, txs)| {
+                            let prev = height.checked_sub(1).map(|h| self.blocks[h].0).unwrap_or_default();
+                            serde_json::json!({ "block_header": {
+                                "height": height,
+                                "hash": hex::encode(id),
+                                "prev_hash": hex::encode(prev),
+                                "timestamp": 1_700_000_000 + height as u64,
+                                "num_txes": txs.len(),
+                            }})
+                        })
+                    }
+                    "f_block_json" => {
+                        let hash = params["hash"].as_str().unwrap_or_default();
+                        self.blocks.iter().find(|(id, _)| hex::encode(id) == hash).map(|(_, txs)| {
+                            let txs: Vec<_> = txs
+                                .iter()
+                                .map(|blob| serde_json::json!({ "hash": hex::encode(fuego_crypto::cn_fast_hash(blob)) }))
+                                .collect();
+                            serde_json::json!({ "block": { "hash": hash, "transactions": txs } })
+                        })
+                    }
+                    _ => None,
+                };
+                match result {
+                    Some(result) => serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                    None => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -1, "message": "not found" },
+                    }),
+                }
+            }
+            "/gettransactions" => {
+                let wanted: Vec<&str> =
+                    request["txs_hashes"].as_array().into_iter().flatten().filter_map(|h| h.as_str()).collect();
+                let found: Vec<String> = self
+                    .blocks
+                    .iter()
+                    .flat_map(|(_, txs)| txs)
+                    .filter(|blob| wanted.contains(&hex::encode(fuego_crypto::cn_fast_hash(blob)).as_str()))
+                    .map(hex::encode)
+                    .collect();
+                serde_json::json!({ "txs_as_hex": found, "status": "OK" })
+            }
+            "/sendrawtransaction" => {
+                if let Some(reply) = self.send_reply.lock().unwrap().clone() {
+                    return reply;
+                }
+                let blob = hex::decode(request["tx_as_hex"].as_str().unwrap_or_default()).unwrap();
+                self.submitted.lock().unwrap().push(blob);
+                serde_json::json!({ "status": "OK" })
+            }
+            _ => serde_json::json!({ "status": "unknown endpoint" }),
+        }
+    }
+
+    /// Serve on a loopback port; returns the port.
+    async fn serve(self: Arc<Self>) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let stub = self.clone();
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut byte = [0u8; 1];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        if stream.read_exact(&mut byte).await.is_err() {
+                            return;
+                        }
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8_lossy(&head).to_lowercase();
+                    let path = head.split_whitespace().nth(1).unwrap_or_default().to_string();
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|n| n.trim().parse().ok())
+                        .unwrap_or(0);
+                    let mut body = vec![0u8; length];
+                    if stream.read_exact(&mut body).await.is_err() {
+                        return;
+                    }
+                    let request = serde_json::from_slice(&body).unwrap_or_default();
+                    let reply = stub.reply(&path, &request).to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        reply.len(),
+                        reply
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+}
+
+/// Full transaction blob with zero-filled signatures.
+fn tx_blob(prefix: &fuego_sdk::serialization::TransactionPrefix) -> Vec<u8> {
+    fuego_sdk::serialization::serialize_tx(&fuego_sdk::serialization::Transaction {
+        prefix: prefix.clone(),
+        signatures: prefix.inputs.iter().map(|i| vec![[0u8; 64]; i.ring_size()]).collect(),
+    })
+}
+
+#[tokio::test]
+async fn test_rpc_provider_returns_block_transactions() {
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let payment = pay_to_wallet(&wallet, 1000);
+    let spending = spend_image([9u8; 32], 400);
+    let stub = Arc::new(RpcStub::with_blocks(vec![vec![], vec![payment.clone(), spending]]));
+    let provider = fuego_sdk::RpcProvider::new("127.0.0.1", stub.serve().await);
+
+    let genesis = provider.get_block(0).await.unwrap();
+    assert!(genesis.transactions.is_empty());
+    let block = provider.get_block(1).await.unwrap();
+    assert_eq!(block.header.prev_hash, genesis.header.hash);
+    assert_eq!(block.header.tx_count, 2);
+
+    let received = &block.transactions[0];
+    assert_eq!(received.hash, fuego_crypto::cn_fast_hash(&tx_blob(&payment)));
+    assert_eq!(received.outputs[0].amount, 1000);
+    assert_eq!(received.extra, payment.extra);
+    let spent = &block.transactions[1];
+    assert_eq!(spent.inputs[0].prev_tx_hash, [9u8; 32]);
+    assert_eq!(spent.inputs[0].signature.len(), 64);
+    assert_eq!(spent.fee, 400);
+
+    let by_hash = provider.get_transaction(&spent.hash).await.unwrap().unwrap();
+    assert_eq!(by_hash.inputs[0].prev_tx_hash, [9u8; 32]);
+    assert_eq!(fuego_crypto::cn_fast_hash(&by_hash.blob), spent.hash);
+    assert!(provider.get_transaction(&[1u8; 32]).await.unwrap().is_none());
+    assert!(provider.get_block(2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_node_reports_stored_blocks_and_transactions() {
+    #[derive(Default)]
+    struct Collect(std::sync::Mutex<Vec<(u64, Option<[u8; 32]>)>>);
+    impl EventListener for Collect {
+        fn on_block(&self, block: &BlockHeader) {
+            self.0.lock().unwrap().push((block.height, None));
+        }
+        fn on_transaction(&self, tx: &Transaction) {
+            self.0.lock().unwrap().push((u64::MAX, Some(tx.hash)));
+        }
+    }
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let txs = vec![pay_to_wallet(&wallet, 1000), spend_image([9u8; 32], 400)];
+    let hashes: Vec<[u8; 32]> = txs.iter().map(|tx| fuego_crypto::cn_fast_hash(&tx_blob(tx))).collect();
+    let stub = Arc::new(RpcStub::with_blocks(vec![vec![], vec![], txs]));
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    node.set_network(Arc::new(fuego_sdk::RpcProvider::new("127.0.0.1", stub.serve().await)));
+    let listener = Arc::new(Collect::default());
+    node.add_listener(listener.clone());
+    let mut events = node.subscribe();
+
+    node.sync(Some(3)).await.unwrap();
+    assert_eq!(
+        *listener.0.lock().unwrap(),
+        vec![(1, None), (2, None), (u64::MAX, Some(hashes[0])), (u64::MAX, Some(hashes[1]))]
+    );
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        match event {
+            NodeEvent::BlockReceived(header) => received.push(format!("block {}", header.height)),
+            NodeEvent::TransactionReceived(hash) => received.push(hex::encode(hash)),
+            _ => {}
+        }
+    }
+    assert_eq!(received, vec!["block 1".into(), "block 2".into(), hex::encode(hashes[0]), hex::encode(hashes[1])]);
+}
+
+#[tokio::test]
+async fn test_rpc_provider_broadcasts_wire_bytes() {
+    use fuego_sdk::serialization::{parse_tx, tx_prefix_hash};
+    use fuego_sdk::transaction_builder::BuiltTransaction;
+    use fuego_sdk::TxRejection;
+
+    let built = |prefix| {
+        let serialized = tx_blob(&prefix);
+        BuiltTransaction {
+            tx_hash: fuego_crypto::cn_fast_hash(&serialized),
+            prefix_hash: tx_prefix_hash(&prefix),
+            tx: parse_tx(&serialized).unwrap(),
+            serialized,
+        }
+    };
+    let stub = Arc::new(RpcStub::default());
+    let provider = fuego_sdk::RpcProvider::new("127.0.0.1", stub.clone().serve().await);
+
+    let fresh = built(spend_image([8u8; 32], 400));
+    assert_eq!(provider.broadcast(&fresh).await.unwrap(), fresh.tx_hash);
+    assert_eq!(*stub.submitted.lock().unwrap(), vec![fresh.serialized.clone()]);
+
+    // A fetched transaction keeps its wire encoding and can be relayed.
+    let mut relayed = TxBuilder::new().build();
+    assert!(provider.send_transaction(&relayed).await.is_err());
+    relayed.hash = fresh.tx_hash;
+    relayed.blob = fresh.serialized.clone();
+    assert_eq!(provider.send_transaction(&relayed).await.unwrap(), fresh.tx_hash);
+    assert_eq!(stub.submitted.lock().unwrap().len(), 2);
+
+    // fuegod answers a double spend with a bare "Failed".
+    *stub.send_reply.lock().unwrap() = Some(serde_json::json!({ "status": "Failed" }));
+    match provider.broadcast(&built(spend_image([9u8; 32], 100))).await {
+        Err(SdkError::TxRejected(TxRejection::Invalid(None))) => {}
+        other => panic!("expected a verification failure, got {other:?}"),
+    }
+    assert_eq!(stub.submitted.lock().unwrap().len(), 2);
+}
+
 // ============================================================
 // Node tests
 // ============================================================
@@ -228,6 +954,339 @@ fn test_node_subscribe() {
     let _rx = node.subscribe();
 }
 
+#[tokio::test]
+async fn test_node_syncs_linked_chain() {
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    let chain = MockNode::new(100, 1);
+    node.set_network(chain.clone());
+
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 99);
+    assert_eq!(node.get_block(99).unwrap().unwrap().header.hash, chain.hash(99));
+}
+
+#[tokio::test]
+async fn test_node_downloads_blocks_in_parallel() {
+    use std::sync::atomic::Ordering;
+
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    let chain = MockNode::new(41, 1);
+    chain.block_delay.store(20, Ordering::SeqCst);
+    node.set_network(chain.clone());
+
+    let mut events = node.subscribe();
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 40);
+    assert!(chain.max_in_flight.load(Ordering::SeqCst) > 1);
+
+    // Progress is still reported block by block, in order.
+    let mut heights = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::SyncProgress { current, .. } = event {
+            heights.push(current);
+        }
+    }
+    assert_eq!(heights, (1..=40).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_node_rejects_block_not_linking_to_tip() {
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    node.set_network(MockNode::new(100, 1));
+    node.sync_batch(None, 10).await.unwrap();
+    assert_eq!(node.height(), 10);
+
+    // A node on another chain can't extend ours.
+    node.set_network(MockNode::new(100, 2));
+    let mut events = node.subscribe();
+    let result = node.sync(None).await;
+    assert!(matches!(result, Err(SdkError::InvalidBlock { height: 11, .. })));
+    assert_eq!(node.height(), 10);
+    assert!(node.get_block(11).unwrap().is_none());
+
+    let mut rejected = false;
+    while let Ok(event) = events.try_recv() {
+        rejected |= matches!(event, NodeEvent::Error(ref e) if e.contains("does not link"));
+    }
+    assert!(rejected);
+}
+
+#[tokio::test]
+async fn test_node_enforces_checkpoints() {
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    let chain = MockNode::new(100, 1);
+    node.set_network(chain.clone());
+    node.add_checkpoint(5, [9; 32]);
+
+    let result = node.sync(None).await;
+    assert!(matches!(result, Err(SdkError::InvalidBlock { height: 5, .. })));
+    assert_eq!(node.height(), 4);
+
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    node.set_network(chain.clone());
+    node.add_checkpoints([(5, chain.hash(5)), (50, chain.hash(50))]);
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 99);
+}
+
+#[tokio::test]
+async fn test_node_follows_reorg() {
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    node.set_network(MockNode::new(20, 1));
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 19);
+
+    // Blocks from 15 on were replaced by a longer branch.
+    let branch = MockNode::reorged(30, 2, 15);
+    node.set_network(branch.clone());
+    let mut events = node.subscribe();
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 29);
+    assert_eq!(node.get_block(14).unwrap().unwrap().header.hash, branch.hash(14));
+    assert_eq!(node.get_block(15).unwrap().unwrap().header.hash, branch.hash(15));
+
+    let mut reorgs = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let NodeEvent::Reorg { fork_height, detached } = event {
+            reorgs.push((fork_height, detached));
+        }
+    }
+    assert_eq!(reorgs, vec![(14, 5)]);
+
+    // A shorter branch wins: the orphaned tip is dropped.
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    node.set_network(MockNode::new(30, 1));
+    node.sync(None).await.unwrap();
+    let branch = MockNode::reorged(25, 2, 20);
+    node.set_network(branch.clone());
+    node.sync(None).await.unwrap();
+    assert_eq!(node.height(), 24);
+    assert_eq!(node.get_block(20).unwrap().unwrap().header.hash, branch.hash(20));
+    assert!((25..30).all(|height| node.get_block(height).unwrap().is_none()));
+}
+
+#[tokio::test]
+async fn test_node_does_not_reorg_past_checkpoint() {
+    let mut node = EmbeddedNode::new(NodeConfig::default()).unwrap();
+    let chain = MockNode::new(30, 1);
+    node.set_network(chain.clone());
+    node.add_checkpoint(18, chain.hash(18));
+    node.sync(None).await.unwrap();
+
+    node.set_network(MockNode::reorged(40, 2, 15));
+    let result = node.sync(None).await;
+    assert!(matches!(result, Err(SdkError::InvalidBlock { height: 30, .. })));
+    assert_eq!(node.height(), 29);
+    assert_eq!(node.get_block(20).unwrap().unwrap().header.hash, chain.hash(20));
+}
+
+#[tokio::test]
+async fn test_node_background_sync_fills_status() {
+    let config = NodeConfig { sync_interval_secs: 3600, ..NodeConfig::default() };
+    let mut node = EmbeddedNode::new(config).unwrap();
+    node.set_network(MockNode::new(250, 1));
+    let node = Arc::new(node);
+
+    let service = node.start_sync();
+    let handle = service.handle();
+    for _ in 0..500 {
+        if handle.status().last_sync_time.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    // 250 blocks take three rounds, run back to back.
+    let status = handle.status();
+    assert_eq!((status.current_height, status.target_height), (249, 249));
+    let status = node.sync_status();
+    assert_eq!((status.current_height, status.target_height), (249, 249));
+    assert!(status.last_sync_time.is_some());
+    service.shutdown().await;
+}
+
+/// Sync target counting its rounds; fails while `failing` is set.
+#[derive(Default)]
+struct CountingTarget {
+    rounds: std::sync::atomic::AtomicU64,
+    failing: std::sync::atomic::AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl fuego_sdk::SyncTarget for CountingTarget {
+    async fn sync_round(&self) -> fuego_sdk::Result<fuego_sdk::SyncRound> {
+        let round = self.rounds.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(SdkError::Network("daemon down".into()));
+        }
+        Ok(fuego_sdk::SyncRound { synced: 1, height: round, target: round })
+    }
+}
+
+/// Sync target stuck short of the network tip.
+struct StalledTarget;
+
+#[async_trait::async_trait]
+impl fuego_sdk::SyncTarget for StalledTarget {
+    async fn sync_round(&self) -> fuego_sdk::Result<fuego_sdk::SyncRound> {
+        Ok(fuego_sdk::SyncRound { synced: 0, height: 5, target: 10 })
+    }
+}
+
+#[tokio::test]
+async fn test_sync_service_stalled_round_is_not_synced() {
+    let service = fuego_sdk::SyncService::start(Arc::new(StalledTarget), std::time::Duration::from_secs(3600));
+    let handle = service.handle();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let status = handle.status();
+    assert_eq!((status.current_height, status.target_height), (5, 10));
+    assert!(status.last_sync_time.is_none());
+    service.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_sync_service_pause_resume_stop() {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let target = Arc::new(CountingTarget::default());
+    let service = fuego_sdk::SyncService::start(target.clone(), Duration::from_secs(3600));
+    let handle = service.handle();
+    settle().await;
+    assert_eq!(target.rounds.load(Ordering::SeqCst), 1);
+    assert_eq!(handle.status().current_height, 1);
+
+    handle.sync_now();
+    settle().await;
+    assert_eq!(target.rounds.load(Ordering::SeqCst), 2);
+
+    handle.pause();
+    settle().await;
+    handle.sync_now();
+    settle().await;
+    assert!(handle.is_paused());
+    assert_eq!(target.rounds.load(Ordering::SeqCst), 2);
+
+    target.failing.store(true, Ordering::SeqCst);
+    handle.resume();
+    settle().await;
+    assert_eq!(target.rounds.load(Ordering::SeqCst), 3);
+    assert!(handle.last_error().unwrap().contains("daemon down"));
+    assert_eq!(handle.status().current_height, 2);
+
+    let token = handle.cancellation_token();
+    drop(service);
+    assert!(token.is_cancelled());
+    handle.sync_now();
+    settle().await;
+    assert_eq!(target.rounds.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_header_validator_timestamp_rules() {
+    use fuego_sdk::validation::{HeaderValidator, BLOCK_FUTURE_TIME_LIMIT, TIMESTAMP_CHECK_WINDOW};
+
+    let chain = MockNode::new(1000, 1);
+    let now = 1_600_000_000;
+    let mut validator = HeaderValidator::new(NetworkType::Mainnet);
+    let window = TIMESTAMP_CHECK_WINDOW as u64;
+    for height in 1..=window {
+        validator.validate(&chain.header(height), height, now).unwrap();
+        validator.accept(chain.header(height));
+    }
+
+    let next = window + 1;
+    let mut header = chain.header(next);
+    assert!(validator.validate(&header, next, now).is_ok());
+    assert!(validator.validate(&header, next + 1, now).is_err());
+
+    // Earlier than the median of the last 60 blocks.
+    header.timestamp = chain.header(window / 2 - 1).timestamp;
+    assert!(matches!(
+        validator.validate(&header, next, now),
+        Err(SdkError::InvalidBlock { .. })
+    ));
+
+    header.timestamp = now + BLOCK_FUTURE_TIME_LIMIT + 1;
+    assert!(validator.validate(&header, next, now).is_err());
+    header.timestamp = now + BLOCK_FUTURE_TIME_LIMIT;
+    assert!(validator.validate(&header, next, now).is_ok());
+}
+
+#[test]
+fn test_header_validator_checkpoints() {
+    use fuego_sdk::validation::{checkpoints, parse_checkpoints, HeaderValidator};
+
+    // The hard-coded tables must all decode.
+    for network in [NetworkType::Mainnet, NetworkType::Testnet, NetworkType::Stagenet] {
+        checkpoints(network);
+    }
+
+    let chain = MockNode::new(100, 1);
+    let now = 1_600_000_000;
+    let validator = HeaderValidator::new(NetworkType::Mainnet)
+        .with_checkpoint(5, chain.hash(5))
+        .with_checkpoint(50, [9; 32]);
+    assert!(validator.validate(&chain.header(5), 5, now).is_ok());
+    let result = validator.validate(&chain.header(50), 50, now);
+    assert!(matches!(result, Err(SdkError::InvalidBlock { height: 50, ref reason }) if reason.contains("checkpoint")));
+
+    assert_eq!(validator.last_checkpoint(4), None);
+    assert_eq!(validator.last_checkpoint(49), Some(5));
+    assert_eq!(validator.last_checkpoint(50), Some(50));
+
+    let file = format!("# fuegod checkpoints\n\n5,{}\n 50 , {} \n", hex::encode(chain.hash(5)), hex::encode([9u8; 32]));
+    let parsed = parse_checkpoints(&file).unwrap();
+    assert_eq!(parsed.into_iter().collect::<Vec<_>>(), vec![(5, chain.hash(5)), (50, [9; 32])]);
+    for bad in ["5", "x,00", "5,abcd"] {
+        assert!(matches!(parse_checkpoints(bad), Err(SdkError::Config(_))));
+    }
+}
+
+#[test]
+fn test_tx_rejection_from_response() {
+    use fuego_sdk::TxRejection;
+    use serde_json::json;
+
+    assert_eq!(TxRejection::from_response(&json!({ "status": "OK" })), None);
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed" })),
+        Some(TxRejection::Invalid(None))
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Not relayed" })),
+        Some(TxRejection::NotRelayed)
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed", "double_spend": true })),
+        Some(TxRejection::DoubleSpend)
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed", "fee_too_low": true })),
+        Some(TxRejection::FeeTooLow)
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed", "too_big": true })),
+        Some(TxRejection::TooBig)
+    );
+    // Free-form text is reported, not classified.
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed", "reason": "fee too small" })),
+        Some(TxRejection::Invalid(Some("fee too small".into())))
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "Failed: bad ring signature" })),
+        Some(TxRejection::Invalid(Some("bad ring signature".into())))
+    );
+    assert_eq!(
+        TxRejection::from_response(&json!({ "status": "BUSY" })),
+        Some(TxRejection::Other("BUSY".into()))
+    );
+}
+
 // ============================================================
 // Type tests
 // ============================================================
@@ -270,7 +1329,6 @@ fn test_node_config_default() {
     let config = NodeConfig::default();
     assert_eq!(config.max_peers, 50);
     assert_eq!(config.sync_interval_secs, 30);
-    assert!(!config.enable_seeding);
 }
 
 // ============================================================
@@ -366,6 +1424,66 @@ fn test_wallet_transaction_history_empty() {
     assert!(wallet.get_transactions(10).is_empty());
 }
 
+/// Five history rows across heights 10–40 for the query tests.
+fn history_rows() -> Vec<fuego_sdk::scanner::HistoryEntry> {
+    use fuego_sdk::scanner::{HistoryDirection, HistoryEntry};
+    use fuego_sdk::serialization::PaymentId;
+
+    let row = |tag: u8, height: u64, direction, amount: u64, subaddress: u32| HistoryEntry {
+        tx_hash: [tag; 32],
+        block_height: height,
+        direction,
+        amount,
+        fee: if direction == HistoryDirection::Outgoing { 10 } else { 0 },
+        subaddress,
+        payment_id: (tag == 2).then_some(PaymentId::Short([9u8; 8])),
+    };
+    vec![
+        row(1, 10, HistoryDirection::Incoming, 100, 0),
+        row(2, 20, HistoryDirection::Incoming, 250, 1),
+        row(3, 20, HistoryDirection::Outgoing, 300, 0),
+        row(4, 30, HistoryDirection::Incoming, 400, 1),
+        row(5, 40, HistoryDirection::Outgoing, 500, 0),
+    ]
+}
+
+#[test]
+fn test_history_query_filters_and_pages() {
+    use fuego_sdk::history::HistoryQuery;
+    use fuego_sdk::scanner::HistoryDirection;
+    use fuego_sdk::serialization::PaymentId;
+
+    let rows = history_rows();
+    let heights = |query: HistoryQuery| -> Vec<u64> {
+        query.page(&rows).entries.iter().map(|e| e.block_height).collect()
+    };
+    assert_eq!(heights(HistoryQuery::default()), vec![10, 20, 20, 30, 40]);
+    assert_eq!(heights(HistoryQuery::blocks(20, 11)), vec![20, 20, 30]);
+    assert_eq!(heights(HistoryQuery::blocks(20, 0)), Vec::<u64>::new());
+    assert_eq!(
+        heights(HistoryQuery { direction: Some(HistoryDirection::Outgoing), ..Default::default() }),
+        vec![20, 40]
+    );
+    assert_eq!(
+        heights(HistoryQuery { min_amount: Some(250), max_amount: Some(400), ..Default::default() }),
+        vec![20, 20, 30]
+    );
+    assert_eq!(
+        heights(HistoryQuery { payment_id: Some(PaymentId::Short([9u8; 8])), ..Default::default() }),
+        vec![20]
+    );
+    assert_eq!(heights(HistoryQuery { subaddress: Some(1), ..Default::default() }), vec![20, 30]);
+
+    let page = HistoryQuery { offset: 1, limit: Some(2), ..Default::default() }.page(&rows);
+    assert_eq!(page.total, 5);
+    assert_eq!(page.entries, rows[1..3].to_vec());
+    let latest = HistoryQuery { newest_first: true, limit: Some(2), ..Default::default() }.page(&rows);
+    assert_eq!(latest.entries, vec![rows[4].clone(), rows[3].clone()]);
+    let past_end = HistoryQuery { offset: 9, ..Default::default() }.page(&rows);
+    assert!(past_end.entries.is_empty());
+    assert_eq!(past_end.total, 5);
+}
+
 #[test]
 fn test_wallet_insufficient_funds() {
     let wallet = Wallet::generate().unwrap();
@@ -416,3 +1534,932 @@ fn test_scan_foreign_prefix() {
     assert_eq!(received, 0);
     assert_eq!(spent, 0);
 }
+
+// ============================================================
+// View-only wallet tests
+// ============================================================
+
+/// Seed whose derived spend/view secrets are canonical scalars, so the
+/// full wallet can derive output secrets and key images.
+const SCANNABLE_SEED: [u8; 32] = [207u8; 32];
+
+fn pay_to_wallet(wallet: &Wallet, amount: u64) -> fuego_sdk::serialization::TransactionPrefix {
+    pay_to_address(&wallet.primary_address(), amount)
+}
+
+fn pay_to_address(address: &Address, amount: u64) -> fuego_sdk::serialization::TransactionPrefix {
+    use fuego_sdk::serialization::{build_extra_with_pubkey, OutputTarget, TransactionPrefix, TxOutput};
+
+    let (spend_public, view_public) = fuego_crypto::parse_address(&address.0).unwrap();
+    let mut tx_secret = [0u8; 32];
+    tx_secret[0] = 0x11;
+    let r = fuego_crypto::Keypair::from_secret(tx_secret);
+    let derivation =
+        fuego_crypto::generate_key_derivation(&fuego_crypto::PublicKey(view_public), &r.secret)
+            .unwrap();
+    let output_key = fuego_crypto::derive_public_key(&derivation, 0, &spend_public).unwrap();
+    TransactionPrefix {
+        version: 1,
+        unlock_time: 0,
+        inputs: Vec::new(),
+        outputs: vec![TxOutput { amount, target: OutputTarget::Key(output_key.0) }],
+        extra: build_extra_with_pubkey(&r.public),
+    }
+}
+
+fn spend_image(key_image: [u8; 32], amount: u64) -> fuego_sdk::serialization::TransactionPrefix {
+    use fuego_sdk::serialization::{build_extra_with_pubkey, KeyInput, TransactionPrefix, TxInput};
+
+    let mut tx_secret = [0u8; 32];
+    tx_secret[0] = 0x22;
+    let r = fuego_crypto::Keypair::from_secret(tx_secret);
+    TransactionPrefix {
+        version: 1,
+        unlock_time: 0,
+        inputs: vec![TxInput::Key(KeyInput { amount, offsets: vec![0], key_image })],
+        outputs: Vec::new(),
+        extra: build_extra_with_pubkey(&r.public),
+    }
+}
+
+fn watch_wallet_for(full: &Wallet) -> Wallet {
+    let keys = full.wallet_keys();
+    Wallet::view_only(keys.spend_public, keys.view_secret).unwrap()
+}
+
+#[test]
+fn test_view_only_same_address() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+    assert!(watch.is_view_only());
+    assert!(!full.is_view_only());
+    assert_eq!(watch.primary_address().0, full.primary_address().0);
+    assert_eq!(watch.wallet_keys().spend_secret, [0u8; 32]);
+}
+
+#[test]
+fn test_view_only_tracks_incoming_and_imported_spends() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+
+    let pay = pay_to_wallet(&full, 5000);
+    full.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    let (received, _) = watch.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    assert_eq!(received, 5000);
+    assert_eq!(watch.balance().confirmed, 5000);
+    assert_eq!(watch.get_transactions(10).len(), 1);
+    assert_eq!(watch.utxos()[0].key_image, [0u8; 32]);
+
+    // Without the key image the spend goes unnoticed.
+    let owned = full.utxos()[0].clone();
+    let spend = spend_image(owned.key_image, owned.amount);
+    full.scan_tx_prefix(&[2u8; 32], &spend, 2).unwrap();
+    watch.scan_tx_prefix(&[2u8; 32], &spend, 2).unwrap();
+    assert_eq!(watch.balance().confirmed, 5000);
+
+    // Spent outputs are exported too.
+    let exported = full.export_key_images().unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].key_image, owned.key_image);
+    let import = watch.import_key_images(&exported).unwrap();
+    assert_eq!(import.attached.len(), 1);
+    assert_eq!(import.attached[0].key_image, owned.key_image);
+    assert!(import.spent.is_empty());
+    assert!(watch.import_key_images(&exported).unwrap().attached.is_empty());
+
+    // The daemon reports it spent; the caller records that.
+    let record = watch.mark_spent(&owned.key_image).unwrap();
+    assert_eq!(record.key_image(), owned.key_image);
+    assert_eq!(watch.balance().confirmed, 0);
+}
+
+#[test]
+fn test_view_only_detects_spends_after_import() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+
+    let pay = pay_to_wallet(&full, 700);
+    full.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    watch.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    let owned = full.utxos()[0].clone();
+    watch.import_key_images(&full.export_key_images().unwrap()).unwrap();
+
+    let (_, spent) = watch
+        .scan_tx_prefix(&[2u8; 32], &spend_image(owned.key_image, owned.amount), 2)
+        .unwrap();
+    assert_eq!(spent, 700);
+    assert_eq!(watch.balance().confirmed, 0);
+}
+
+#[test]
+fn test_key_image_import_rejects_bad_signature() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+    let pay = pay_to_wallet(&full, 700);
+    full.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    watch.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+
+    let mut exported = full.export_key_images().unwrap();
+    assert!(exported[0].verify());
+    exported[0].key_image = fuego_crypto::ring::secret_key_to_public_key(&[3u8; 32]);
+    assert!(matches!(watch.import_key_images(&exported), Err(SdkError::Crypto(_))));
+    assert_eq!(watch.utxos()[0].key_image, [0u8; 32]);
+    assert!(watch.export_key_images().is_err());
+}
+
+#[test]
+fn test_view_only_refuses_to_build() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+    watch.scan_tx_prefix(&[1u8; 32], &pay_to_wallet(&full, 5000), 1).unwrap();
+    watch.attach_global_indices(&[1u8; 32], &[42]);
+
+    // Selection only needs amounts and indices; signing needs the spend key.
+    let selected = watch.select_for_send(1000, &mut rand::thread_rng()).unwrap();
+    let result = watch.build_with_selection(
+        &selected,
+        &[(full.primary_address(), 1000)],
+        10,
+        0,
+        &[vec![]],
+        &mut rand::thread_rng(),
+    );
+    assert!(matches!(result, Err(SdkError::Wallet(_))));
+    assert!(watch.try_get_keypair(0).is_err());
+    assert!(watch.try_vault().is_none());
+
+    let dir = tempdir().unwrap();
+    assert!(watch.save(dir.path().join("watch.bin"), b"pass").is_err());
+}
+
+#[test]
+fn test_view_only_builder() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let keys = full.wallet_keys();
+    let watch = WalletBuilder::new()
+        .view_only(keys.spend_public, keys.view_secret)
+        .build()
+        .unwrap();
+    assert!(watch.is_view_only());
+    assert_eq!(watch.primary_address().0, full.primary_address().0);
+
+    let conflicting = WalletBuilder::new()
+        .seed(SCANNABLE_SEED)
+        .view_only(keys.spend_public, keys.view_secret)
+        .build();
+    assert!(conflicting.is_err());
+}
+
+#[test]
+fn test_view_only_rejects_invalid_keys() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let keys = full.wallet_keys();
+    assert!(Wallet::view_only(keys.spend_public, [0xffu8; 32]).is_err());
+}
+
+// ============================================================
+// Pool tests
+// ============================================================
+
+#[test]
+fn test_preview_pool_tx_records_nothing() {
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let pay = pay_to_wallet(&wallet, 5000);
+
+    let effect = wallet.preview_tx_prefix(&[1u8; 32], &pay);
+    assert_eq!(effect.tx_hash, [1u8; 32]);
+    assert_eq!((effect.received, effect.spent), (5000, 0));
+    assert_eq!(effect.received_by, vec![(0, 5000)]);
+    assert_eq!(wallet.balance().confirmed, 0);
+    assert!(wallet.get_transactions(10).is_empty());
+
+    wallet.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    let owned = wallet.utxos()[0].clone();
+    let effect = wallet.preview_tx_prefix(&[2u8; 32], &spend_image(owned.key_image, owned.amount));
+    assert_eq!((effect.received, effect.spent), (0, 5000));
+    assert_eq!(effect.spent_key_images, vec![owned.key_image]);
+    assert_eq!(wallet.balance().confirmed, 5000);
+    assert_eq!(wallet.get_transactions(10).len(), 1);
+}
+
+#[test]
+fn test_pool_changes_lite_messages() {
+    use fuego_sdk::serialization::{
+        parse_kv_document, parse_pool_changes_lite_response, pool_changes_lite_request,
+        write_kv_document, KvValue,
+    };
+    use std::collections::BTreeMap;
+
+    let body = pool_changes_lite_request(&[1u8; 32], &[[2u8; 32], [3u8; 32]]);
+    let request = parse_kv_document(&body).unwrap();
+    assert_eq!(request["tailBlockId"], KvValue::Bytes(vec![1u8; 32]));
+    assert_eq!(request["knownTxsIds"], KvValue::Bytes([[2u8; 32], [3u8; 32]].concat()));
+
+    let mut response = BTreeMap::new();
+    response.insert("status".to_string(), KvValue::Bytes(b"OK".to_vec()));
+    response.insert("isTailBlockActual".to_string(), KvValue::Bool(false));
+    response.insert("deletedTxsIds".to_string(), KvValue::Bytes(vec![3u8; 32]));
+    let parsed = parse_pool_changes_lite_response(&write_kv_document(&response)).unwrap();
+    assert!(!parsed.is_tail_block_actual);
+    assert!(parsed.added_txs.is_empty());
+    assert_eq!(parsed.deleted_tx_ids, vec![[3u8; 32]]);
+
+    response.insert("deletedTxsIds".to_string(), KvValue::Bytes(vec![3u8; 31]));
+    assert!(parse_pool_changes_lite_response(&write_kv_document(&response)).is_err());
+    response.insert("status".to_string(), KvValue::Bytes(b"BUSY".to_vec()));
+    assert!(parse_pool_changes_lite_response(&write_kv_document(&response)).is_err());
+}
+
+// ============================================================
+// Wallet event tests
+// ============================================================
+
+fn drain_events(rx: &mut tokio::sync::broadcast::Receiver<WalletEvent>) -> Vec<WalletEvent> {
+    std::iter::from_fn(|| rx.try_recv().ok()).collect()
+}
+
+#[test]
+fn test_wallet_events_follow_a_payment_until_spent() {
+    use fuego_sdk::events::SPENDABLE_AGE;
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let mut rx = wallet.subscribe();
+
+    wallet.scan_tx_prefix(&[1u8; 32], &pay_to_wallet(&wallet, 5000), 1).unwrap();
+    wallet.set_height(1);
+    assert_eq!(
+        drain_events(&mut rx),
+        vec![
+            WalletEvent::PaymentReceived {
+                tx_hash: [1u8; 32],
+                height: 1,
+                amount: 5000,
+                subaddress: 0,
+                payment_id: None,
+            },
+            WalletEvent::Confirmations { tx_hash: [1u8; 32], confirmations: 1 },
+        ]
+    );
+
+    // Jumping to the unlock height reports the count reached and the unlock.
+    wallet.set_height(SPENDABLE_AGE);
+    assert_eq!(
+        drain_events(&mut rx),
+        vec![
+            WalletEvent::Confirmations { tx_hash: [1u8; 32], confirmations: SPENDABLE_AGE },
+            WalletEvent::OutputUnlocked {
+                tx_hash: [1u8; 32],
+                output_position: 0,
+                amount: 5000,
+                height: SPENDABLE_AGE,
+            },
+        ]
+    );
+    wallet.set_height(SPENDABLE_AGE + 1);
+    assert!(drain_events(&mut rx).is_empty());
+
+    // The tracked send is mined.
+    let owned = wallet.utxos()[0].clone();
+    wallet.track_send(&[2u8; 32], &[owned.key_image]);
+    wallet.scan_tx_prefix(&[2u8; 32], &spend_image(owned.key_image, 5000), 12).unwrap();
+    assert_eq!(
+        drain_events(&mut rx),
+        vec![
+            WalletEvent::OutputSpent {
+                tx_hash: [2u8; 32],
+                key_image: owned.key_image,
+                amount: 5000,
+                height: 12,
+            },
+            WalletEvent::SendConfirmed { tx_hash: [2u8; 32], height: 12 },
+        ]
+    );
+}
+
+#[test]
+fn test_wallet_events_reach_listeners_and_serialize() {
+    struct Collect(std::sync::Mutex<Vec<serde_json::Value>>);
+    impl EventListener for Collect {
+        fn on_wallet_event(&self, event: &WalletEvent) {
+            self.0.lock().unwrap().push(event.to_json());
+        }
+    }
+
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let watch = watch_wallet_for(&full);
+    let listener = Arc::new(Collect(std::sync::Mutex::new(Vec::new())));
+    watch.events().add_listener(listener.clone());
+
+    // A foreign spend is not a send of ours.
+    watch.scan_tx_prefix(&[3u8; 32], &spend_image([9u8; 32], 1), 1).unwrap();
+    assert!(listener.0.lock().unwrap().is_empty());
+
+    watch.scan_tx_prefix(&[1u8; 32], &pay_to_wallet(&full, 700), 2).unwrap();
+    let seen = listener.0.lock().unwrap().clone();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0]["type"], "payment_received");
+    assert_eq!(seen[0]["transactionHash"], hex::encode([1u8; 32]));
+    assert_eq!(seen[0]["amount"], 700);
+    assert_eq!(seen[0]["paymentId"], serde_json::Value::Null);
+}
+
+// ============================================================
+// Cold signing tests
+// ============================================================
+
+fn decoys_for(mixin: usize, salt: u8) -> Vec<fuego_sdk::transaction_builder::DecoyEntry> {
+    (0..mixin)
+        .map(|i| {
+            let mut secret = [0u8; 32];
+            secret[0] = salt;
+            secret[1] = i as u8 + 1;
+            fuego_sdk::transaction_builder::DecoyEntry {
+                global_index: 100 + i as u32,
+                out_key: fuego_crypto::ring::secret_key_to_public_key(&secret),
+            }
+        })
+        .collect()
+}
+
+/// A watch wallet holding one 5000 output, and the unsigned spend of it.
+fn unsigned_spend(full: &Wallet) -> (Wallet, fuego_sdk::unsigned_tx::UnsignedTransaction) {
+    use fuego_sdk::unsigned_tx::{UnsignedInput, UnsignedTransaction};
+
+    let watch = watch_wallet_for(full);
+    watch.scan_tx_prefix(&[1u8; 32], &pay_to_wallet(full, 50_000), 1).unwrap();
+    watch.attach_global_indices(&[1u8; 32], &[42]);
+    let selected = watch.select_for_send(30_000, &mut rand::thread_rng()).unwrap();
+    let unsigned = UnsignedTransaction {
+        inputs: selected.iter().map(|u| UnsignedInput::new(u, &decoys_for(2, 9))).collect(),
+        destinations: vec![(Wallet::from_seed([8u8; 32]).unwrap().primary_address(), 20_000)],
+        fee: 10_000,
+        mixin: 2,
+        unlock_time: 0,
+        extra: Vec::new(),
+    };
+    (watch, unsigned)
+}
+
+#[test]
+fn test_unsigned_tx_round_trip() {
+    use fuego_sdk::unsigned_tx::UnsignedTransaction;
+
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let (_, mut unsigned) = unsigned_spend(&full);
+    unsigned.unlock_time = 1234;
+    unsigned.extra = vec![0xff, 1, 2];
+    let bytes = unsigned.to_bytes();
+    assert_eq!(&bytes[..8], b"FUEGOUTX");
+    assert_eq!(UnsignedTransaction::from_bytes(&bytes).unwrap(), unsigned);
+
+    let mut corrupted = bytes.clone();
+    corrupted[20] ^= 1;
+    assert!(matches!(
+        UnsignedTransaction::from_bytes(&corrupted),
+        Err(SdkError::Serialization(_))
+    ));
+    let mut future = bytes.clone();
+    future[8] = 2;
+    assert!(UnsignedTransaction::from_bytes(&future).is_err());
+    assert!(UnsignedTransaction::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn test_unsigned_tx_rejects_out_of_range_index() {
+    use fuego_sdk::serialization::write_varint;
+    use fuego_sdk::unsigned_tx::UnsignedTransaction;
+
+    // One input, no decoys, whose output position does not fit a u32.
+    let mut file = b"FUEGOUTX".to_vec();
+    file.push(1);
+    for value in [0, 0, 0, 1, 5000] {
+        write_varint(value, &mut file);
+    }
+    file.extend_from_slice(&[0u8; 64]);
+    write_varint(u64::from(u32::MAX) + 1, &mut file);
+    let checksum = fuego_crypto::cn_fast_hash(&file);
+    file.extend_from_slice(&checksum[..4]);
+
+    match UnsignedTransaction::from_bytes(&file) {
+        Err(SdkError::Serialization(msg)) => assert!(msg.contains("out of range"), "{msg}"),
+        other => panic!("expected an out-of-range index, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_cold_sign_and_submit_round_trip() {
+    use fuego_sdk::unsigned_tx::{SignedTransaction, UnsignedTransaction};
+
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let (watch, unsigned) = unsigned_spend(&full);
+
+    // The air-gapped signer has never scanned the chain.
+    let cold = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let file = UnsignedTransaction::from_bytes(&unsigned.to_bytes()).unwrap();
+    let signed = cold.sign_unsigned(&file).unwrap();
+    assert_eq!(signed.built.tx.prefix.inputs.len(), 1);
+    assert_eq!(signed.key_images.len(), 1);
+
+    let decoded = SignedTransaction::from_bytes(&signed.to_bytes()).unwrap();
+    assert_eq!(decoded.built.tx_hash, signed.built.tx_hash);
+    assert_eq!(decoded.built.prefix_hash, signed.built.prefix_hash);
+    assert_eq!(decoded.built.serialized, signed.built.serialized);
+    assert_eq!(decoded.input_key_images(), vec![signed.key_images[0].key_image]);
+
+    // The watch wallet learns the image from the file and sees the spend.
+    watch.import_key_images(&decoded.key_images).unwrap();
+    let (_, spent) = watch
+        .scan_tx_prefix(&decoded.built.tx_hash, &decoded.built.tx.prefix, 2)
+        .unwrap();
+    assert_eq!(spent, 50_000);
+}
+
+#[test]
+fn test_cold_sign_rejects_foreign_outputs() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let (watch, unsigned) = unsigned_spend(&full);
+    let other = Wallet::from_seed([5u8; 32]).unwrap();
+    assert!(matches!(other.sign_unsigned(&unsigned), Err(SdkError::Crypto(_))));
+    assert!(watch.sign_unsigned(&unsigned).is_err());
+}
+
+#[test]
+fn test_signed_tx_rejects_tampering() {
+    use fuego_sdk::unsigned_tx::SignedTransaction;
+
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let (_, unsigned) = unsigned_spend(&full);
+    let mut signed = full.sign_unsigned(&unsigned).unwrap();
+    let mut corrupted = signed.to_bytes();
+    corrupted[12] ^= 1;
+    assert!(SignedTransaction::from_bytes(&corrupted).is_err());
+
+    // A proof for an image the transaction does not spend.
+    signed.key_images[0].key_image = [1u8; 32];
+    assert!(SignedTransaction::from_bytes(&signed.to_bytes()).is_err());
+}
+
+#[test]
+fn test_output_secrets_are_rederived() {
+    use fuego_sdk::serialization::{
+        build_extra_with_pubkey, CommitmentOutputTarget, OutputTarget, TransactionPrefix,
+        TxOutput, AMOUNT_PROOF_LEN, HEAT_TERM,
+    };
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let mut tx_secret = [0u8; 32];
+    tx_secret[0] = 0x33;
+    let r = fuego_crypto::Keypair::from_secret(tx_secret);
+    let derivation = fuego_crypto::generate_key_derivation(
+        &fuego_crypto::PublicKey(wallet.wallet_keys().view_public),
+        &r.secret,
+    )
+    .unwrap();
+    let ck = fuego_crypto::ring::derive_commitment_keys(
+        &fuego_crypto::ring::derive_deposit_secret(&derivation, 0),
+    );
+    let heat = TransactionPrefix {
+        version: 2,
+        unlock_time: 0,
+        inputs: Vec::new(),
+        outputs: vec![TxOutput {
+            amount: 900,
+            target: OutputTarget::Commitment(CommitmentOutputTarget {
+                commit_key: ck.commit_key,
+                term: HEAT_TERM,
+                amount_commitment: [0u8; 32],
+                amount_proof: [0u8; AMOUNT_PROOF_LEN],
+            }),
+        }],
+        extra: build_extra_with_pubkey(&r.public),
+    };
+    wallet.scan_tx_prefix(&[1u8; 32], &heat, 1).unwrap();
+    wallet.scan_tx_prefix(&[2u8; 32], &pay_to_wallet(&wallet, 500), 2).unwrap();
+
+    let entry = wallet.heat_outputs().pop().unwrap();
+    assert_eq!(entry.tx_public_key, r.public);
+    let deposit = wallet.commitment_deposit(&entry, 7).unwrap();
+    assert_eq!(deposit.key_scalar, ck.key_scalar);
+    assert_eq!(deposit.claimed_interest, 7);
+
+    let utxos = wallet.utxos();
+    let inputs = wallet.spendable_outputs(&utxos).unwrap();
+    assert_eq!(
+        fuego_crypto::generate_key_image(
+            &fuego_crypto::PublicKey(inputs[0].output_key),
+            &inputs[0].secret_key
+        )
+        .0,
+        utxos[0].key_image
+    );
+
+    // Rows that do not open with this wallet's keys are refused.
+    let mut foreign = entry.clone();
+    foreign.tx_public_key = r.public;
+    foreign.output_position = 1;
+    assert!(wallet.commitment_deposit(&foreign, 0).is_err());
+    let mut foreign = utxos[0].clone();
+    foreign.output_position = 1;
+    assert!(wallet.spendable_outputs(&[foreign]).is_err());
+    let watch = watch_wallet_for(&wallet);
+    assert!(watch.spendable_outputs(&utxos).is_err());
+}
+
+// ============================================================
+// Subaddress tests
+// ============================================================
+
+#[test]
+fn test_subaddress_outputs_are_detected() {
+    let mut wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    assert_eq!(wallet.new_subaddress().unwrap(), 1);
+    let sub = wallet.subaddress(1).unwrap();
+    assert_ne!(sub, wallet.primary_address());
+    assert_eq!(wallet.subaddresses(), vec![wallet.primary_address(), sub.clone()]);
+
+    wallet.scan_tx_prefix(&[1u8; 32], &pay_to_address(&sub, 700), 1).unwrap();
+    wallet.scan_tx_prefix(&[2u8; 32], &pay_to_wallet(&wallet, 300), 2).unwrap();
+    assert_eq!(wallet.balance().confirmed, 1000);
+    assert_eq!(wallet.subaddress_balances(), vec![300, 700]);
+
+    let utxo = wallet.utxos().into_iter().find(|u| u.amount == 700).unwrap();
+    assert_eq!(utxo.subaddress, 1);
+    let input = wallet.spendable_outputs(std::slice::from_ref(&utxo)).unwrap();
+    assert_eq!(
+        fuego_crypto::ring::secret_key_to_public_key(&input[0].secret_key),
+        utxo.output_key
+    );
+    let history = wallet.get_transactions(10);
+    assert_eq!(history.iter().find(|h| h.amount == 700).unwrap().subaddress, 1);
+    assert_eq!(history.iter().find(|h| h.amount == 300).unwrap().subaddress, 0);
+}
+
+#[test]
+fn test_split_payment_credits_each_subaddress() {
+    use fuego_sdk::serialization::{OutputTarget, TxOutput};
+
+    let mut wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    wallet.new_subaddress().unwrap();
+    // Output 1 of the same transaction pays subaddress 1.
+    let mut pay = pay_to_wallet(&wallet, 300);
+    let (sub_spend, view_public) = fuego_crypto::parse_address(&wallet.subaddress(1).unwrap().0).unwrap();
+    let mut tx_secret = [0u8; 32];
+    tx_secret[0] = 0x11;
+    let derivation = fuego_crypto::generate_key_derivation(
+        &fuego_crypto::PublicKey(view_public),
+        &fuego_crypto::Keypair::from_secret(tx_secret).secret,
+    )
+    .unwrap();
+    let output_key = fuego_crypto::derive_public_key(&derivation, 1, &sub_spend).unwrap();
+    pay.outputs.push(TxOutput { amount: 700, target: OutputTarget::Key(output_key.0) });
+
+    assert_eq!(wallet.preview_tx_prefix(&[1u8; 32], &pay).received_by, vec![(0, 300), (1, 700)]);
+    wallet.scan_tx_prefix(&[1u8; 32], &pay, 1).unwrap();
+    let mut credited: Vec<_> = wallet.get_transactions(10).iter().map(|h| (h.subaddress, h.amount)).collect();
+    credited.sort();
+    assert_eq!(credited, vec![(0, 300), (1, 700)]);
+    assert_eq!(wallet.subaddress_balances(), vec![300, 700]);
+}
+
+#[test]
+fn test_subaddress_needs_issued_count() {
+    let mut issuer = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    issuer.new_subaddress().unwrap();
+    issuer.new_subaddress().unwrap();
+    let payment = pay_to_address(&issuer.subaddress(2).unwrap(), 500);
+
+    // A wallet restored from the seed alone does not know subaddress 2.
+    let mut restored = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    assert!(restored.subaddress(2).is_none());
+    assert_eq!(restored.scan_tx_prefix(&[1u8; 32], &payment, 1).unwrap(), (0, 0));
+    restored.set_subaddress_count(2);
+    assert_eq!(restored.subaddress(2), issuer.subaddress(2));
+    assert_eq!(restored.scan_tx_prefix(&[1u8; 32], &payment, 1).unwrap(), (500, 0));
+    assert_eq!(restored.subaddress_balances(), vec![0, 0, 500]);
+}
+
+#[test]
+fn test_view_only_wallet_cannot_issue_subaddresses() {
+    let full = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let mut watch = watch_wallet_for(&full);
+    assert!(watch.new_subaddress().is_err());
+    assert_eq!(watch.subaddresses(), vec![full.primary_address()]);
+}
+
+// ============================================================
+// Integrated address tests
+// ============================================================
+
+/// Build a 20_000 transfer from a funded wallet to its own integrated
+/// addresses, one destination per payment id.
+fn pay_own_invoices(
+    wallet: &Wallet,
+    payment_ids: &[&[u8]],
+) -> Result<fuego_sdk::transaction_builder::BuiltTransaction, SdkError> {
+    wallet.scan_tx_prefix(&[1u8; 32], &pay_to_wallet(wallet, 50_000), 1).unwrap();
+    wallet.attach_global_indices(&[1u8; 32], &[42]);
+    let keys = wallet.wallet_keys();
+    let destinations: Vec<(Address, u64)> = payment_ids
+        .iter()
+        .map(|id| {
+            let invoice = crypto::make_integrated_address(
+                &PublicKey(keys.spend_public),
+                &PublicKey(keys.view_public),
+                id,
+            )
+            .unwrap();
+            (invoice, 20_000 / payment_ids.len() as u64)
+        })
+        .collect();
+    let selected = wallet.select_for_send(30_000, &mut rand::thread_rng()).unwrap();
+    wallet.build_with_selection(
+        &selected,
+        &destinations,
+        10_000,
+        2,
+        &[decoys_for(2, 9)],
+        &mut rand::thread_rng(),
+    )
+}
+
+/// The decrypted payment id recorded for the incoming side of `tx_hash`.
+fn received_payment_id(
+    wallet: &Wallet,
+    tx_hash: [u8; 32],
+) -> Option<fuego_sdk::serialization::PaymentId> {
+    wallet
+        .get_transactions(10)
+        .into_iter()
+        .find(|h| {
+            h.tx_hash == tx_hash
+                && matches!(h.direction, fuego_sdk::scanner::HistoryDirection::Incoming)
+        })
+        .and_then(|h| h.payment_id)
+}
+
+#[test]
+fn test_encrypted_payment_id_reaches_recipient_history() {
+    use fuego_sdk::serialization::{parse_extra_payment_id, PaymentId};
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let built = pay_own_invoices(&wallet, &[&[0xab; 8]]).unwrap();
+    let prefix = &built.tx.prefix;
+    // Only the encrypted id is published.
+    let on_chain = parse_extra_payment_id(&prefix.extra).unwrap();
+    assert!(matches!(on_chain, PaymentId::Short(_)));
+    assert_ne!(on_chain, PaymentId::Short([0xab; 8]));
+
+    let (received, _) = wallet.scan_tx_prefix(&built.tx_hash, prefix, 2).unwrap();
+    assert_eq!(received, 40_000);
+    assert_eq!(received_payment_id(&wallet, built.tx_hash), Some(PaymentId::Short([0xab; 8])));
+    let history = wallet.get_transactions(10);
+    assert!(history.iter().all(|h| h.tx_hash == built.tx_hash || h.payment_id.is_none()));
+}
+
+#[test]
+fn test_long_payment_id_travels_in_clear() {
+    use fuego_sdk::serialization::{parse_extra_payment_id, PaymentId};
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let built = pay_own_invoices(&wallet, &[&[0xcd; 32]]).unwrap();
+    let prefix = &built.tx.prefix;
+    assert_eq!(parse_extra_payment_id(&prefix.extra), Some(PaymentId::Long([0xcd; 32])));
+    wallet.scan_tx_prefix(&built.tx_hash, prefix, 2).unwrap();
+    assert_eq!(received_payment_id(&wallet, built.tx_hash), Some(PaymentId::Long([0xcd; 32])));
+}
+
+#[test]
+fn test_conflicting_payment_ids_are_rejected() {
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let result = pay_own_invoices(&wallet, &[&[1; 8], &[2; 8]]);
+    assert!(matches!(result, Err(SdkError::Serialization(_))));
+}
+
+// ============================================================
+// Alias tests
+// ============================================================
+
+#[test]
+fn test_alias_rule() {
+    use fuego_sdk::alias::is_valid_alias;
+
+    assert!(is_valid_alias("fuego&me"));
+    assert!(is_valid_alias("12345678"));
+    assert!(!is_valid_alias("fuego"));
+    assert!(!is_valid_alias("fuego&me1"));
+    assert!(!is_valid_alias("Fuego&me"));
+    assert!(!is_valid_alias("fuego_me"));
+}
+
+#[test]
+fn test_alias_extra_round_trip() {
+    use fuego_sdk::alias::{add_alias_info_to_extra, AliasInfo};
+    use fuego_sdk::serialization::{
+        add_payment_id_extra, build_extra_with_pubkey, parse_extra_alias, parse_extra_payment_id,
+        parse_extra_pubkey, PaymentId,
+    };
+
+    let wallet = Wallet::from_seed(SCANNABLE_SEED).unwrap();
+    let info = AliasInfo {
+        alias: "fuego&me".into(),
+        address: wallet.primary_address(),
+        view_key: fuego_crypto::PublicKey(wallet.wallet_keys().view_public),
+    };
+    let mut extra = build_extra_with_pubkey(&[3u8; 32]);
+    add_alias_info_to_extra(&mut extra, &info);
+    add_payment_id_extra(&mut extra, &PaymentId::Long([4u8; 32]));
+
+    assert_eq!(parse_extra_alias(&extra), Some(info));
+    assert_eq!(parse_extra_pubkey(&extra), Some([3u8; 32]));
+    // Fields after the alias stay reachable.
+    assert_eq!(parse_extra_payment_id(&extra), Some(PaymentId::Long([4u8; 32])));
+    assert_eq!(parse_extra_alias(&extra[..extra.len() - 40]), None);
+    assert_eq!(parse_extra_alias(&build_extra_with_pubkey(&[3u8; 32])), None);
+}
+
+// ============================================================
+// HTTP config tests
+// ============================================================
+
+/// What the stand-in SOCKS5 proxy saw on one connection.
+#[derive(Debug, Clone)]
+struct ProxiedRequest {
+    username: String,
+    password: String,
+    host: String,
+    request: String,
+}
+
+/// SOCKS5 proxy that requires username/password auth, records the
+/// credentials and target, and answers every request itself with `{}`.
+async fn spawn_socks_proxy() -> (String, Arc<std::sync::Mutex<Vec<ProxiedRequest>>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn read_string(stream: &mut tokio::net::TcpStream) -> std::io::Result<String> {
+        let len = stream.read_u8().await? as usize;
+        let mut buf = vec![0u8; len];
+        stream.read_exact(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    async fn serve(
+        mut stream: tokio::net::TcpStream,
+        seen: Arc<std::sync::Mutex<Vec<ProxiedRequest>>>,
+    ) -> std::io::Result<()> {
+        let mut head = [0u8; 2];
+        stream.read_exact(&mut head).await?;
+        let mut methods = vec![0u8; head[1] as usize];
+        stream.read_exact(&mut methods).await?;
+        if !methods.contains(&2) {
+            return stream.write_all(&[5, 0xff]).await;
+        }
+        stream.write_all(&[5, 2]).await?;
+
+        stream.read_u8().await?;
+        let username = read_string(&mut stream).await?;
+        let password = read_string(&mut stream).await?;
+        stream.write_all(&[1, 0]).await?;
+
+        let mut request = [0u8; 4];
+        stream.read_exact(&mut request).await?;
+        let host = match request[3] {
+            3 => read_string(&mut stream).await?,
+            1 => {
+                let mut ip = [0u8; 4];
+                stream.read_exact(&mut ip).await?;
+                std::net::Ipv4Addr::from(ip).to_string()
+            }
+            _ => {
+                let mut ip = [0u8; 16];
+                stream.read_exact(&mut ip).await?;
+                std::net::Ipv6Addr::from(ip).to_string()
+            }
+        };
+        stream.read_u16().await?;
+        stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+
+        let mut http = Vec::new();
+        let mut byte = [0u8; 1];
+        while !http.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).await?;
+            http.push(byte[0]);
+        }
+        seen.lock().unwrap().push(ProxiedRequest {
+            username,
+            password,
+            host,
+            request: String::from_utf8_lossy(&http).into_owned(),
+        });
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}")
+            .await
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("socks5h://{}", listener.local_addr().unwrap());
+    let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = seen.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, log.clone()));
+        }
+    });
+    (url, seen)
+}
+
+#[tokio::test]
+async fn test_http_isolates_destinations_by_socks_credentials() {
+    use fuego_sdk::http::{HttpConfig, DEST_DAEMON, DEST_ORDERBOOK};
+
+    let (proxy, seen) = spawn_socks_proxy().await;
+    let http = HttpConfig::tor().with_proxy(proxy);
+    for destination in [DEST_DAEMON, DEST_ORDERBOOK] {
+        let reply = http
+            .client(destination)
+            .unwrap()
+            .get("http://fuegod.invalid:18180/getinfo")
+            .send()
+            .await
+            .unwrap();
+        assert!(reply.status().is_success());
+    }
+
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), 2);
+    // socks5h: the proxy resolves the name, nothing is looked up locally.
+    assert!(seen.iter().all(|r| r.host == "fuegod.invalid"));
+    assert_eq!(seen[0].username, DEST_DAEMON);
+    assert_eq!(seen[1].username, DEST_ORDERBOOK);
+    assert_eq!(seen[0].password, seen[1].password);
+}
+
+#[tokio::test]
+async fn test_http_sends_basic_auth_through_proxy() {
+    use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+
+    let (proxy, seen) = spawn_socks_proxy().await;
+    let http = HttpConfig::default()
+        .with_proxy(proxy)
+        .isolate_destinations(true)
+        .with_basic_auth("rpc", "hunter2");
+    http.client(DEST_DAEMON)
+        .unwrap()
+        .get("http://fuegod.invalid/getinfo")
+        .send()
+        .await
+        .unwrap();
+
+    let request = seen.lock().unwrap()[0].request.to_lowercase();
+    // base64("rpc:hunter2")
+    assert!(request.contains("authorization: basic cnbjomh1bnrlcji="), "{request}");
+}
+
+#[tokio::test]
+async fn test_http_loopback_bypasses_proxy() {
+    use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await;
+        let _ = stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}")
+            .await;
+    });
+
+    // Nothing listens on the proxy port: only a direct connection works.
+    let http = HttpConfig::tor().with_proxy("socks5h://127.0.0.1:1");
+    let reply = http
+        .client(DEST_DAEMON)
+        .unwrap()
+        .get(format!("http://{addr}/getinfo"))
+        .send()
+        .await
+        .unwrap();
+    assert!(reply.status().is_success());
+}
+
+#[test]
+fn test_http_config_errors() {
+    use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+
+    let bad_proxy = HttpConfig::default().with_proxy("not a proxy url");
+    assert!(matches!(bad_proxy.client(DEST_DAEMON), Err(SdkError::Config(_))));
+
+    let bad_ca = HttpConfig::default().with_ca_certificate(b"-----BEGIN CERTIFICATE-----\nnope\n".to_vec());
+    assert!(matches!(bad_ca.client(DEST_DAEMON), Err(SdkError::Config(_))));
+
+    assert_eq!(HttpConfig::default().scheme(), "http");
+    assert_eq!(HttpConfig::default().https_only(true).scheme(), "https");
+}
+
+#[tokio::test]
+async fn test_http_https_only_refuses_plain_http() {
+    use fuego_sdk::http::{HttpConfig, DEST_DAEMON};
+
+    let client = HttpConfig::default().https_only(true).client(DEST_DAEMON).unwrap();
+    assert!(client.get("http://127.0.0.1:1/getinfo").send().await.is_err());
+}
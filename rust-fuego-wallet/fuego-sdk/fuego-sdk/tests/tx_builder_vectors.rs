@@ -88,7 +88,7 @@ fn full_transaction_build() {
 
     let amount = 1_000_000;
     let fee = MINIMUM_FEE;
-    let (selected, found) = select_inputs(&available, amount + fee, DEFAULT_DUST_THRESHOLD, &mut rng);
+    let (selected, found) = select_inputs(&available, |o| o.amount, amount + fee, DEFAULT_DUST_THRESHOLD, &mut rng);
     assert!(found >= amount + fee, "selection must cover amount+fee");
 
     // Recipient keys.
@@ -113,12 +113,14 @@ fn full_transaction_build() {
         amount,
         spend_pub: recv_spend,
         view_pub: recv_view,
+        payment_id: None,
     }];
     for chunk in change_chunks {
         dests.push(BuildDestination {
             amount: chunk,
             spend_pub,
             view_pub,
+            payment_id: None,
         });
     }
     if dust > 0 {
@@ -126,6 +128,7 @@ fn full_transaction_build() {
             amount: dust,
             spend_pub,
             view_pub,
+            payment_id: None,
         });
     }
 
@@ -171,6 +174,7 @@ fn full_transaction_build() {
         let (offsets, image) = match input {
             fuego_sdk::serialization::TxInput::Key(k) => (&k.offsets, &k.key_image),
             fuego_sdk::serialization::TxInput::CommitmentSpend(c) => (&c.offsets, &c.key_image),
+            fuego_sdk::serialization::TxInput::Base(_) => unreachable!("built txs spend key inputs"),
         };
         // reconstruct the ring order used at signing time: decoys + real sorted by index
         let mut ring: Vec<(u32, [u8; 32])> = decoys[i]
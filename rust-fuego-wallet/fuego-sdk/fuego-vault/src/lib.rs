@@ -14,7 +14,84 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 pub mod recovery;
 pub use recovery::RecoveryRequest;
 
-const VAULT_FILE_VERSION: u8 = 1;
+/// v1: Keccak256(passphrase || "fuego-vault-v1") key, no associated data.
+/// v2: Argon2id key; the header (version, KDF params, salt, nonce) is the
+/// AEAD associated data.
+const VAULT_FILE_VERSION: u8 = 2;
+const VAULT_FILE_VERSION_V1: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const V2_HEADER_LEN: usize = 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+/// Largest Argon2 costs accepted from a file header (4 GiB, 64 passes,
+/// 16 lanes), so a crafted file cannot make load allocate or spin without
+/// bound.
+const MAX_KDF_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 64;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters, stored in the v2 vault header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP's Argon2id baseline: 19 MiB, 2 iterations, 1 lane.
+    fn default() -> Self {
+        KdfParams { memory_kib: 19 * 1024, iterations: 2, parallelism: 1 }
+    }
+}
+
+impl KdfParams {
+    /// Argon2id key for `passphrase` and `salt` under these costs.
+    pub fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; 32], std::io::Error> {
+        if self.memory_kib > MAX_KDF_MEMORY_KIB {
+            return Err(invalid_data("Vault KDF memory cost too large"));
+        }
+        if self.iterations > MAX_KDF_ITERATIONS {
+            return Err(invalid_data("Vault KDF iteration count too large"));
+        }
+        if self.parallelism > MAX_KDF_PARALLELISM {
+            return Err(invalid_data("Vault KDF parallelism too large"));
+        }
+        let params = argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| invalid_data(&format!("Invalid vault KDF parameters: {}", e)))?;
+        let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = [0u8; 32];
+        argon
+            .hash_password_into(passphrase, salt, &mut key)
+            .map_err(|e| invalid_data(&format!("Key derivation failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn decryption_failed() -> std::io::Error {
+    invalid_data("Decryption failed (wrong passphrase or corrupted vault)")
+}
+
+/// Parsed v2 header: KDF params, salt and nonce.
+fn parse_v2_header(header: &[u8]) -> (KdfParams, &[u8], &[u8]) {
+    let word = |i: usize| u32::from_le_bytes(header[1 + 4 * i..5 + 4 * i].try_into().unwrap());
+    let params = KdfParams { memory_kib: word(0), iterations: word(1), parallelism: word(2) };
+    let salt = &header[13..13 + SALT_LEN];
+    let nonce = &header[13 + SALT_LEN..];
+    (params, salt, nonce)
+}
+
+/// Write via a sibling temp file and rename, so a crash mid-write never
+/// leaves a truncated vault behind.
+fn write_atomic(path: &PathBuf, data: &[u8]) -> Result<(), std::io::Error> {
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct Vault {
@@ -144,76 +221,115 @@ impl Vault {
         RecoveryRequest::new(old_root, new_public_key, self.recovery_threshold)
     }
 
-    /// Save vault with ChaCha20Poly1305 encryption using passphrase.
+    /// Save vault with ChaCha20Poly1305 encryption under an Argon2id key
+    /// derived from the passphrase, with the default KDF cost.
     pub fn save(&self, path: PathBuf, passphrase: &[u8]) -> Result<(), std::io::Error> {
-        let mut plaintext = bincode::serialize(self).map_err(std::io::Error::other)?;
+        self.save_with_params(path, passphrase, &KdfParams::default())
+    }
 
-        let mut key = [0u8; 32];
-        let mut hasher = Keccak256::new();
-        hasher.update(passphrase);
-        hasher.update(b"fuego-vault-v1");
-        key.copy_from_slice(&hasher.finalize());
+    /// Save in the v2 format with explicit Argon2id cost parameters.
+    pub fn save_with_params(
+        &self,
+        path: PathBuf,
+        passphrase: &[u8],
+        params: &KdfParams,
+    ) -> Result<(), std::io::Error> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let mut header = Vec::with_capacity(V2_HEADER_LEN);
+        header.push(VAULT_FILE_VERSION);
+        header.extend_from_slice(&params.memory_kib.to_le_bytes());
+        header.extend_from_slice(&params.iterations.to_le_bytes());
+        header.extend_from_slice(&params.parallelism.to_le_bytes());
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(nonce.as_slice());
 
+        let mut key = params.derive_key(passphrase, &salt)?;
+        let mut plaintext = bincode::serialize(self).map_err(std::io::Error::other)?;
         let cipher = ChaCha20Poly1305::new((&key).into());
-        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        key.zeroize();
         cipher
-            .encrypt_in_place(&nonce, b"", &mut plaintext)
+            .encrypt_in_place(&nonce, &header, &mut plaintext)
             .map_err(|e| std::io::Error::other(format!("Encryption failed: {}", e)))?;
 
-        let mut output = Vec::with_capacity(1 + 12 + plaintext.len());
-        output.push(VAULT_FILE_VERSION);
-        output.extend_from_slice(nonce.as_slice());
+        let mut output = header;
         output.extend_from_slice(&plaintext);
-
-        key.zeroize();
-
-        fs::write(path, output)
+        write_atomic(&path, &output)
     }
 
-    /// Load vault with ChaCha20Poly1305 decryption using passphrase.
+    /// Load an encrypted vault. v1 files load transparently; the next save
+    /// rewrites them as v2.
     pub fn load(path: PathBuf, passphrase: &[u8]) -> Result<Self, std::io::Error> {
         let data = fs::read(path)?;
-        if data.len() < 13 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Vault file too short",
-            ));
-        }
-
-        let version = data[0];
-        if version != VAULT_FILE_VERSION {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Unsupported vault version: {}", version),
-            ));
-        }
+        let mut plaintext = match data.first() {
+            Some(&VAULT_FILE_VERSION) => Self::decrypt_v2(&data, passphrase)?,
+            Some(&VAULT_FILE_VERSION_V1) => Self::decrypt_v1(&data, passphrase)?,
+            Some(version) => {
+                return Err(invalid_data(&format!("Unsupported vault version: {}", version)))
+            }
+            None => return Err(invalid_data("Vault file too short")),
+        };
+        let vault = bincode::deserialize(&plaintext).map_err(std::io::Error::other);
+        plaintext.zeroize();
+        vault
+    }
 
-        let nonce = &data[1..13];
-        let ciphertext = &data[13..];
+    /// Format version of an encrypted vault file.
+    pub fn file_version(path: PathBuf) -> Result<u8, std::io::Error> {
+        fs::read(path)?
+            .first()
+            .copied()
+            .ok_or_else(|| invalid_data("Vault file too short"))
+    }
 
-        let mut key = [0u8; 32];
-        let mut hasher = Keccak256::new();
-        hasher.update(passphrase);
-        hasher.update(b"fuego-vault-v1");
-        key.copy_from_slice(&hasher.finalize());
+    /// Re-encrypt the vault at `path` under a new passphrase, keeping the
+    /// file's KDF cost (the default for a v1 file, which is upgraded).
+    pub fn change_passphrase(
+        path: PathBuf,
+        old_passphrase: &[u8],
+        new_passphrase: &[u8],
+    ) -> Result<(), std::io::Error> {
+        let data = fs::read(&path)?;
+        let params = if data.first() == Some(&VAULT_FILE_VERSION) && data.len() >= V2_HEADER_LEN {
+            parse_v2_header(&data[..V2_HEADER_LEN]).0
+        } else {
+            KdfParams::default()
+        };
+        let vault = Self::load(path.clone(), old_passphrase)?;
+        vault.save_with_params(path, new_passphrase, &params)
+    }
 
+    fn decrypt_v2(data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        if data.len() < V2_HEADER_LEN {
+            return Err(invalid_data("Vault file too short"));
+        }
+        let (header, ciphertext) = data.split_at(V2_HEADER_LEN);
+        let (params, salt, nonce) = parse_v2_header(header);
+        let mut key = params.derive_key(passphrase, salt)?;
         let cipher = ChaCha20Poly1305::new((&key).into());
-        let nonce_arr: [u8; 12] = nonce.try_into().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid nonce length")
-        })?;
+        key.zeroize();
         let mut plaintext = ciphertext.to_vec();
         cipher
-            .decrypt_in_place(nonce_arr.as_ref().into(), b"", &mut plaintext)
-            .map_err(|_| {
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Decryption failed (wrong passphrase or corrupted vault)",
-                )
-            })?;
+            .decrypt_in_place(nonce.into(), header, &mut plaintext)
+            .map_err(|_| decryption_failed())?;
+        Ok(plaintext)
+    }
 
+    fn decrypt_v1(data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        if data.len() < 1 + NONCE_LEN {
+            return Err(invalid_data("Vault file too short"));
+        }
+        let nonce = &data[1..1 + NONCE_LEN];
+        let mut key = v1_key(passphrase);
+        let cipher = ChaCha20Poly1305::new((&key).into());
         key.zeroize();
-
-        bincode::deserialize(&plaintext).map_err(std::io::Error::other)
+        let mut plaintext = data[1 + NONCE_LEN..].to_vec();
+        cipher
+            .decrypt_in_place(nonce.into(), b"", &mut plaintext)
+            .map_err(|_| decryption_failed())?;
+        Ok(plaintext)
     }
 
     /// Unencrypted save (for FFI backward compatibility).
@@ -229,6 +345,14 @@ impl Vault {
     }
 }
 
+/// The v1 file key: a single Keccak256 of the passphrase.
+fn v1_key(passphrase: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(passphrase);
+    hasher.update(b"fuego-vault-v1");
+    hasher.finalize().into()
+}
+
 // --- Atomic Swap Implementation ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -282,3 +406,81 @@ impl AtomicSwap {
         Ok(vec![0u8; 32])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests do not spend seconds in Argon2.
+    const TEST_PARAMS: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    fn write_v1(vault: &Vault, path: &PathBuf, passphrase: &[u8]) {
+        let mut plaintext = bincode::serialize(vault).unwrap();
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        ChaCha20Poly1305::new((&v1_key(passphrase)).into())
+            .encrypt_in_place(&nonce, b"", &mut plaintext)
+            .unwrap();
+        let mut output = vec![VAULT_FILE_VERSION_V1];
+        output.extend_from_slice(nonce.as_slice());
+        output.extend_from_slice(&plaintext);
+        fs::write(path, output).unwrap();
+    }
+
+    #[test]
+    fn v1_vault_loads_and_upgrades_on_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.bin");
+        let vault = Vault::new([7u8; 32]);
+        write_v1(&vault, &path, b"old");
+
+        let loaded = Vault::load(path.clone(), b"old").unwrap();
+        assert_eq!(loaded.master_seed, [7u8; 32]);
+        assert!(Vault::load(path.clone(), b"wrong").is_err());
+
+        loaded.save_with_params(path.clone(), b"old", &TEST_PARAMS).unwrap();
+        assert_eq!(Vault::file_version(path.clone()).unwrap(), VAULT_FILE_VERSION);
+        assert_eq!(Vault::load(path, b"old").unwrap().master_seed, [7u8; 32]);
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.bin");
+        Vault::new([7u8; 32]).save_with_params(path.clone(), b"pass", &TEST_PARAMS).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert_eq!(parse_v2_header(&data[..V2_HEADER_LEN]).0, TEST_PARAMS);
+
+        // Bumping the iteration count still derives a valid key, but the
+        // header no longer matches the associated data.
+        for offset in [5, 13, 1 + 12 + SALT_LEN] {
+            let mut tampered = data.clone();
+            tampered[offset] ^= 1;
+            fs::write(&path, &tampered).unwrap();
+            assert!(Vault::load(path.clone(), b"pass").is_err());
+        }
+
+        // Out-of-bounds costs are refused before any hashing.
+        for word in 0..3 {
+            let mut huge = data.clone();
+            huge[1 + 4 * word..5 + 4 * word].copy_from_slice(&u32::MAX.to_le_bytes());
+            fs::write(&path, &huge).unwrap();
+            let started = std::time::Instant::now();
+            assert!(Vault::load(path.clone(), b"pass").is_err());
+            assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn change_passphrase_keeps_kdf_params() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.bin");
+        Vault::new([9u8; 32]).save_with_params(path.clone(), b"old", &TEST_PARAMS).unwrap();
+
+        assert!(Vault::change_passphrase(path.clone(), b"wrong", b"new").is_err());
+        Vault::change_passphrase(path.clone(), b"old", b"new").unwrap();
+        assert!(Vault::load(path.clone(), b"old").is_err());
+        assert_eq!(Vault::load(path.clone(), b"new").unwrap().master_seed, [9u8; 32]);
+        let data = fs::read(&path).unwrap();
+        assert_eq!(parse_v2_header(&data[..V2_HEADER_LEN]).0, TEST_PARAMS);
+    }
+}